@@ -0,0 +1,65 @@
+//! [`Arbitrary`] impls producing valid values only, for fuzzing.
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{DataType, DataTypeCode, Device, DeviceType};
+
+const DTYPE_CODES: [DataTypeCode; 7] = [
+    DataTypeCode::Int,
+    DataTypeCode::UInt,
+    DataTypeCode::Float,
+    DataTypeCode::OpaqueHandle,
+    DataTypeCode::Bfloat,
+    DataTypeCode::Complex,
+    DataTypeCode::Bool,
+];
+
+const DEVICE_TYPES: [DeviceType; 14] = [
+    DeviceType::Cpu,
+    DeviceType::Cuda,
+    DeviceType::CudaHost,
+    DeviceType::OpenCl,
+    DeviceType::Vulkan,
+    DeviceType::Metal,
+    DeviceType::Vpi,
+    DeviceType::Rocm,
+    DeviceType::RocmHost,
+    DeviceType::ExtDev,
+    DeviceType::CudaManaged,
+    DeviceType::OneApi,
+    DeviceType::WebGpu,
+    DeviceType::Hexagon,
+];
+
+const BITS: [u8; 4] = [8, 16, 32, 64];
+
+impl<'a> Arbitrary<'a> for DataTypeCode {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&DTYPE_CODES).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for DeviceType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&DEVICE_TYPES).copied()
+    }
+}
+
+impl<'a> Arbitrary<'a> for DataType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            code: u.arbitrary()?,
+            bits: *u.choose(&BITS)?,
+            lanes: u.int_in_range(1..=4)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Device {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self {
+            device_type: u.arbitrary()?,
+            device_id: u.int_in_range(0..=7)?,
+        })
+    }
+}
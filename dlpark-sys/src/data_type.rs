@@ -0,0 +1,271 @@
+use crate::{DataType, DataTypeCode, Unknown};
+
+impl From<(DataTypeCode, u8, u16)> for DataType {
+    fn from(value: (DataTypeCode, u8, u16)) -> Self {
+        Self {
+            code: value.0,
+            bits: value.1,
+            lanes: value.2,
+        }
+    }
+}
+
+impl Default for DataType {
+    fn default() -> Self {
+        // Most deep learning framework create float32 tensor by default.
+        Self::F32
+    }
+}
+
+impl DataType {
+    // Bfloat
+    pub const BF16: Self = Self {
+        code: DataTypeCode::Bfloat,
+        bits: 16,
+        lanes: 1,
+    };
+    // Bool
+    pub const BOOL: Self = Self {
+        code: DataTypeCode::Bool,
+        bits: 8,
+        lanes: 1,
+    };
+    // Float
+    pub const F16: Self = Self {
+        code: DataTypeCode::Float,
+        bits: 16,
+        lanes: 1,
+    };
+    pub const F32: Self = Self {
+        code: DataTypeCode::Float,
+        bits: 32,
+        lanes: 1,
+    };
+    pub const F64: Self = Self {
+        code: DataTypeCode::Float,
+        bits: 64,
+        lanes: 1,
+    };
+    pub const I128: Self = Self {
+        code: DataTypeCode::Int,
+        bits: 128,
+        lanes: 1,
+    };
+    pub const I16: Self = Self {
+        code: DataTypeCode::Int,
+        bits: 16,
+        lanes: 1,
+    };
+    pub const I32: Self = Self {
+        code: DataTypeCode::Int,
+        bits: 32,
+        lanes: 1,
+    };
+    pub const I64: Self = Self {
+        code: DataTypeCode::Int,
+        bits: 64,
+        lanes: 1,
+    };
+    // Int
+    pub const I8: Self = Self {
+        code: DataTypeCode::Int,
+        bits: 8,
+        lanes: 1,
+    };
+    pub const U128: Self = Self {
+        code: DataTypeCode::UInt,
+        bits: 128,
+        lanes: 1,
+    };
+    pub const U16: Self = Self {
+        code: DataTypeCode::UInt,
+        bits: 16,
+        lanes: 1,
+    };
+    pub const U32: Self = Self {
+        code: DataTypeCode::UInt,
+        bits: 32,
+        lanes: 1,
+    };
+    pub const U64: Self = Self {
+        code: DataTypeCode::UInt,
+        bits: 64,
+        lanes: 1,
+    };
+    // Uint
+    pub const U8: Self = Self {
+        code: DataTypeCode::UInt,
+        bits: 8,
+        lanes: 1,
+    };
+
+    /// Calculate `DataType` size as (bits * lanes + 7) // 8
+    pub fn size(&self) -> usize {
+        (self.bits as u32 * self.lanes as u32).div_ceil(8) as usize
+    }
+}
+
+impl TryFrom<u8> for DataTypeCode {
+    type Error = Unknown<u8>;
+
+    fn try_from(raw: u8) -> Result<Self, Unknown<u8>> {
+        let code = match raw {
+            0 => Self::Int,
+            1 => Self::UInt,
+            2 => Self::Float,
+            3 => Self::OpaqueHandle,
+            4 => Self::Bfloat,
+            5 => Self::Complex,
+            6 => Self::Bool,
+            #[cfg(feature = "dlpack-1-1")]
+            7 => Self::Float8E3M4,
+            #[cfg(feature = "dlpack-1-1")]
+            8 => Self::Float8E4M3,
+            #[cfg(feature = "dlpack-1-1")]
+            9 => Self::Float8E4M3B11Fnuz,
+            #[cfg(feature = "dlpack-1-1")]
+            10 => Self::Float8E4M3Fn,
+            #[cfg(feature = "dlpack-1-1")]
+            11 => Self::Float8E4M3Fnuz,
+            #[cfg(feature = "dlpack-1-1")]
+            12 => Self::Float8E5M2,
+            #[cfg(feature = "dlpack-1-1")]
+            13 => Self::Float8E5M2Fnuz,
+            #[cfg(feature = "dlpack-1-1")]
+            14 => Self::Float8E8M0Fnu,
+            #[cfg(feature = "dlpack-1-1")]
+            15 => Self::Float6E2M3Fn,
+            #[cfg(feature = "dlpack-1-1")]
+            16 => Self::Float6E3M2Fn,
+            #[cfg(feature = "dlpack-1-1")]
+            17 => Self::Float4E2M1Fn,
+            _ => return Err(Unknown(raw)),
+        };
+        Ok(code)
+    }
+}
+
+impl TryFrom<u32> for DataTypeCode {
+    type Error = Unknown<u32>;
+
+    fn try_from(raw: u32) -> Result<Self, Unknown<u32>> {
+        u8::try_from(raw)
+            .ok()
+            .and_then(|code| Self::try_from(code).ok())
+            .ok_or(Unknown(raw))
+    }
+}
+
+impl TryFrom<i32> for DataTypeCode {
+    type Error = Unknown<i32>;
+
+    fn try_from(raw: i32) -> Result<Self, Unknown<i32>> {
+        u8::try_from(raw)
+            .ok()
+            .and_then(|code| Self::try_from(code).ok())
+            .ok_or(Unknown(raw))
+    }
+}
+
+/// Why [`DataTypeBuilder::build`] or [`DataType::validate`] rejected a data
+/// type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InvalidDataType {
+    ZeroBits,
+    ZeroLanes,
+    /// The type code only comes in other widths, e.g. bfloat16.
+    Bits {
+        code: DataTypeCode,
+        bits: u8,
+    },
+}
+
+impl core::fmt::Display for InvalidDataType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ZeroBits => write!(f, "data type with zero bits"),
+            Self::ZeroLanes => write!(f, "data type with zero lanes"),
+            Self::Bits { code, bits } => write!(f, "{code:?} elements can't have {bits} bits"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidDataType {}
+
+impl DataType {
+    /// Builder of a validated data type, `float32` unless changed.
+    pub const fn builder() -> DataTypeBuilder {
+        DataTypeBuilder(Self::F32)
+    }
+
+    /// Check that the bits and lanes are non-zero and the bits legal for the
+    /// type code: 16 for bfloat, 1 or 8 for bool, an even number for
+    /// complex and the width in the name for the fp8, fp6 and fp4 types.
+    pub const fn validate(&self) -> Result<(), InvalidDataType> {
+        if self.bits == 0 {
+            return Err(InvalidDataType::ZeroBits);
+        }
+        if self.lanes == 0 {
+            return Err(InvalidDataType::ZeroLanes);
+        }
+        let legal = match self.code {
+            DataTypeCode::Int
+            | DataTypeCode::UInt
+            | DataTypeCode::Float
+            | DataTypeCode::OpaqueHandle => true,
+            DataTypeCode::Bfloat => self.bits == 16,
+            DataTypeCode::Bool => matches!(self.bits, 1 | 8),
+            DataTypeCode::Complex => self.bits.is_multiple_of(2),
+            #[cfg(feature = "dlpack-1-1")]
+            DataTypeCode::Float8E3M4
+            | DataTypeCode::Float8E4M3
+            | DataTypeCode::Float8E4M3B11Fnuz
+            | DataTypeCode::Float8E4M3Fn
+            | DataTypeCode::Float8E4M3Fnuz
+            | DataTypeCode::Float8E5M2
+            | DataTypeCode::Float8E5M2Fnuz
+            | DataTypeCode::Float8E8M0Fnu => self.bits == 8,
+            #[cfg(feature = "dlpack-1-1")]
+            DataTypeCode::Float6E2M3Fn | DataTypeCode::Float6E3M2Fn => self.bits == 6,
+            #[cfg(feature = "dlpack-1-1")]
+            DataTypeCode::Float4E2M1Fn => self.bits == 4,
+        };
+        if !legal {
+            return Err(InvalidDataType::Bits {
+                code: self.code,
+                bits: self.bits,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builder of a [`DataType`], made by [`DataType::builder`], e.g.
+/// `DataType::builder().code(DataTypeCode::Float).bits(16).lanes(4).build()`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DataTypeBuilder(DataType);
+
+impl DataTypeBuilder {
+    pub const fn code(mut self, code: DataTypeCode) -> Self {
+        self.0.code = code;
+        self
+    }
+
+    pub const fn bits(mut self, bits: u8) -> Self {
+        self.0.bits = bits;
+        self
+    }
+
+    pub const fn lanes(mut self, lanes: u16) -> Self {
+        self.0.lanes = lanes;
+        self
+    }
+
+    /// The data type, if it passes [`DataType::validate`].
+    pub const fn build(self) -> Result<DataType, InvalidDataType> {
+        match self.0.validate() {
+            Ok(()) => Ok(self.0),
+            Err(err) => Err(err),
+        }
+    }
+}
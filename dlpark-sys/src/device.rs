@@ -0,0 +1,73 @@
+use crate::{Device, DeviceType, Unknown};
+
+impl From<(DeviceType, i32)> for Device {
+    fn from(value: (DeviceType, i32)) -> Self {
+        Self {
+            device_type: value.0,
+            device_id: value.1,
+        }
+    }
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self {
+            device_type: DeviceType::Cpu,
+            device_id: 0,
+        }
+    }
+}
+
+impl Device {
+    /// CPU device with `device_id = 0`.
+    pub const CPU: Self = Self {
+        device_type: DeviceType::Cpu,
+        device_id: 0,
+    };
+
+    /// Create CUDA device.
+    pub fn cuda(index: usize) -> Self {
+        Self {
+            device_type: DeviceType::Cuda,
+            device_id: index as i32,
+        }
+    }
+}
+
+impl TryFrom<i32> for DeviceType {
+    type Error = Unknown<i32>;
+
+    fn try_from(raw: i32) -> Result<Self, Unknown<i32>> {
+        let device_type = match raw {
+            1 => Self::Cpu,
+            2 => Self::Cuda,
+            3 => Self::CudaHost,
+            4 => Self::OpenCl,
+            7 => Self::Vulkan,
+            8 => Self::Metal,
+            9 => Self::Vpi,
+            10 => Self::Rocm,
+            11 => Self::RocmHost,
+            12 => Self::ExtDev,
+            13 => Self::CudaManaged,
+            14 => Self::OneApi,
+            15 => Self::WebGpu,
+            16 => Self::Hexagon,
+            #[cfg(feature = "dlpack-1-1")]
+            17 => Self::Maia,
+            _ => return Err(Unknown(raw)),
+        };
+        Ok(device_type)
+    }
+}
+
+impl TryFrom<u32> for DeviceType {
+    type Error = Unknown<u32>;
+
+    fn try_from(raw: u32) -> Result<Self, Unknown<u32>> {
+        i32::try_from(raw)
+            .ok()
+            .and_then(|code| Self::try_from(code).ok())
+            .ok_or(Unknown(raw))
+    }
+}
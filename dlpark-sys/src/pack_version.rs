@@ -0,0 +1,39 @@
+use crate::{
+    PackVersion, DLPACK_FLAG_BITMASK_READ_ONLY, DLPACK_MAJOR_VERSION, DLPACK_MINOR_VERSION,
+};
+
+impl Default for PackVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl PackVersion {
+    /// The version these definitions were compiled against.
+    pub const CURRENT: Self = Self {
+        major: DLPACK_MAJOR_VERSION,
+        minor: DLPACK_MINOR_VERSION,
+    };
+
+    /// Whether a tensor of this version can be read with these definitions.
+    /// Minor versions only add to the ABI, so just the major has to match.
+    pub fn is_compatible(&self) -> bool {
+        self.major == DLPACK_MAJOR_VERSION
+    }
+
+    /// Flags both this version and these definitions know, others have to
+    /// be ignored.
+    pub fn supported_flags(&self) -> u64 {
+        if !self.is_compatible() {
+            return 0;
+        }
+        #[allow(unused_mut)]
+        let mut flags = DLPACK_FLAG_BITMASK_READ_ONLY;
+        #[cfg(feature = "dlpack-1-1")]
+        if self.minor >= 1 {
+            flags |= crate::DLPACK_FLAG_BITMASK_IS_COPIED
+                | crate::DLPACK_FLAG_BITMASK_IS_SUBBYTE_TYPE_PADDED;
+        }
+        flags
+    }
+}
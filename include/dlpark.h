@@ -0,0 +1,101 @@
+#ifndef DLPARK_H
+#define DLPARK_H
+
+#include <stdint.h>
+#include <stddef.h>
+#include <dlpack/dlpack.h>
+
+// Direction of [`Allocator::copy`].
+typedef enum DLParkCopyKind {
+  DLParkCopyKind_HostToDevice = 0,
+  DLParkCopyKind_DeviceToHost = 1,
+  DLParkCopyKind_DeviceToDevice = 2,
+} DLParkCopyKind;
+
+// C-compatible vtable of a device backend. Every callback gets `ctx` as its
+// first argument and must be safe to call from any thread. Only
+// `memory_info` may be NULL.
+typedef struct DLParkAllocator {
+  void *ctx;
+  // Allocate `size` bytes aligned to `align` on device `device_id`.
+  // Returns NULL on failure.
+  void *(*alloc)(void *ctx, int32_t device_id, uintptr_t size, uintptr_t align);
+  // Free a block returned by `alloc` with the same arguments.
+  void (*free)(void *ctx, int32_t device_id, void *ptr, uintptr_t size, uintptr_t align);
+  // Copy `size` bytes from `src` to `dst`, where the device side is on
+  // device `device_id`. Returns 0 on success.
+  int32_t (*copy)(void *ctx,
+                  int32_t device_id,
+                  void *dst,
+                  const void *src,
+                  uintptr_t size,
+                  enum DLParkCopyKind kind);
+  // Write the free and total bytes of device `device_id`. Returns 0 on
+  // success. May be NULL if the backend can't tell.
+  int32_t (*memory_info)(void *ctx, int32_t device_id, uintptr_t *free, uintptr_t *total);
+} DLParkAllocator;
+
+// Copy `len` bytes at `data` into a new contiguous CPU tensor of `dtype`
+// with the `ndim` dims at `shape`. Returns NULL if `len` doesn't match them.
+// Free the tensor with `dlpark_tensor_free`.
+//
+// # Safety
+// `data` must be readable for `len` bytes and `shape` for `ndim` dims.
+DLManagedTensor *dlpark_tensor_from_buffer(const void *data,
+                                           uintptr_t len,
+                                           DLDataType dtype,
+                                           const int64_t *shape,
+                                           int32_t ndim);
+
+// Register the memory management of a device backend for every device of
+// `device_type`, see `dlpark::plugin`. The vtable is copied. Returns 0 on
+// success, or -1 if `device_type` is unknown, or `allocator` or one of its
+// `alloc`, `free` and `copy` callbacks is NULL.
+//
+// # Safety
+// The callbacks must behave as documented on the vtable, and its `ctx` must
+// stay valid as long as tensors allocated with it are alive.
+int32_t dlpark_register_allocator(int32_t device_type, const struct DLParkAllocator *allocator);
+
+// Call the deleter of `tensor`, which may come from any producer. Does
+// nothing if `tensor` is NULL.
+//
+// # Safety
+// `tensor` must not be used afterwards.
+void dlpark_tensor_free(DLManagedTensor *tensor);
+
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+int32_t dlpark_tensor_ndim(const DLManagedTensor *tensor);
+
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+const int64_t *dlpark_tensor_shape(const DLManagedTensor *tensor);
+
+// Strides in elements, or NULL if the tensor is row-major contiguous.
+//
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+const int64_t *dlpark_tensor_strides(const DLManagedTensor *tensor);
+
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+DLDataType dlpark_tensor_dtype(const DLManagedTensor *tensor);
+
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+DLDevice dlpark_tensor_device(const DLManagedTensor *tensor);
+
+// Pointer to the first element, with the byte offset applied.
+//
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+void *dlpark_tensor_data(const DLManagedTensor *tensor);
+
+// Number of elements, or -1 if a dim is negative or the count overflows.
+//
+// # Safety
+// `tensor` must be a valid DLManagedTensor.
+int64_t dlpark_tensor_num_elements(const DLManagedTensor *tensor);
+
+#endif  /* DLPARK_H */
@@ -0,0 +1,92 @@
+package dlpark;
+
+import java.lang.ref.Cleaner;
+import java.nio.ByteBuffer;
+
+/**
+ * A DLPack tensor owned by the JVM. Its deleter runs on {@link #close()}, or
+ * when the object is unreachable otherwise.
+ *
+ * <p>The native methods are implemented by the {@code java} module of the
+ * dlpark crate, built with the {@code jni} feature.
+ */
+public final class ManagedTensor implements AutoCloseable {
+    private static final Cleaner CLEANER = Cleaner.create();
+
+    private static final class State implements Runnable {
+        private long handle;
+
+        State(long handle) {
+            this.handle = handle;
+        }
+
+        @Override
+        public void run() {
+            if (handle != 0) {
+                free(handle);
+                handle = 0;
+            }
+        }
+    }
+
+    private final State state;
+    private final Cleaner.Cleanable cleanable;
+
+    /** Take ownership of the {@code DLManagedTensor*} {@code handle}. */
+    public ManagedTensor(long handle) {
+        state = new State(handle);
+        cleanable = CLEANER.register(this, state);
+    }
+
+    private long handle() {
+        if (state.handle == 0) {
+            throw new IllegalStateException("tensor is closed");
+        }
+        return state.handle;
+    }
+
+    public long[] shape() {
+        return shape(handle());
+    }
+
+    /** Type code, bits and lanes of the dtype. */
+    public int[] dtype() {
+        return dtype(handle());
+    }
+
+    /** Device type and id. */
+    public int[] device() {
+        return device(handle());
+    }
+
+    /**
+     * Direct buffer over the data of a contiguous CPU tensor, in native byte
+     * order. Only valid until the tensor is closed.
+     */
+    public ByteBuffer buffer() {
+        return buffer(handle()).order(java.nio.ByteOrder.nativeOrder());
+    }
+
+    /** Give up ownership, returning the {@code DLManagedTensor*}. */
+    public long release() {
+        long handle = handle();
+        state.handle = 0;
+        cleanable.clean();
+        return handle;
+    }
+
+    @Override
+    public void close() {
+        cleanable.clean();
+    }
+
+    private static native long[] shape(long handle);
+
+    private static native int[] dtype(long handle);
+
+    private static native int[] device(long handle);
+
+    private static native ByteBuffer buffer(long handle);
+
+    private static native void free(long handle);
+}
@@ -0,0 +1,154 @@
+//! Allocator of the buffers of [`OwnedTensor`]s, which factories like
+//! [`ManagerCtx::zeros`], copies like [`ManagedTensor::to_layout`] and
+//! deserializers produce. Replace it with [`set_global`] to use jemalloc
+//! arenas, hugepages or pools.
+//!
+//! [`ManagedTensor::to_contiguous`] returns a `Vec`, which can only come from
+//! the global Rust allocator, [`ManagedTensor::to_layout_in`] with
+//! `Order::C` is its equivalent going through any [`TensorAllocator`].
+//!
+//! [`OwnedTensor`]: crate::OwnedTensor
+//! [`ManagerCtx::zeros`]: crate::ManagerCtx::zeros
+//! [`ManagedTensor::to_layout`]: crate::ManagedTensor::to_layout
+//! [`ManagedTensor::to_layout_in`]: crate::ManagedTensor::to_layout_in
+//! [`ManagedTensor::to_contiguous`]: crate::ManagedTensor::to_contiguous
+
+use alloc::alloc::{alloc, alloc_zeroed, dealloc, Layout};
+use core::{
+    cell::UnsafeCell,
+    hint,
+    ptr::NonNull,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Source of tensor buffers.
+///
+/// # Safety
+/// Blocks must be valid for `layout` and stay valid until deallocated, like
+/// with `GlobalAlloc`.
+pub unsafe trait TensorAllocator: Sync {
+    /// Allocate a block of `layout`, which has a non-zero size, or return
+    /// `None` on failure. Its contents are uninitialized.
+    fn allocate_aligned(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// Same as [`TensorAllocator::allocate_aligned`], with the block filled
+    /// with zeros.
+    fn allocate_zeroed(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let block = self.allocate_aligned(layout)?;
+        unsafe { block.as_ptr().write_bytes(0, layout.size()) };
+        Some(block)
+    }
+
+    /// # Safety
+    /// `block` must have been allocated by this allocator with `layout`, and
+    /// must not be used afterwards.
+    unsafe fn deallocate(&self, block: NonNull<u8>, layout: Layout);
+}
+
+/// The global Rust allocator, used unless another one is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct System;
+
+unsafe impl TensorAllocator for System {
+    fn allocate_aligned(&self, layout: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { alloc(layout) })
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { alloc_zeroed(layout) })
+    }
+
+    unsafe fn deallocate(&self, block: NonNull<u8>, layout: Layout) {
+        dealloc(block.as_ptr(), layout);
+    }
+}
+
+/// The allocator set last, behind a spin lock since a `&dyn` is too wide
+/// for an atomic. It is held only to copy the reference.
+struct Global {
+    locked: AtomicBool,
+    allocator: UnsafeCell<&'static dyn TensorAllocator>,
+}
+
+// The allocator is only accessed with the lock held.
+unsafe impl Sync for Global {}
+
+impl Global {
+    fn with<T>(&self, f: impl FnOnce(&mut &'static dyn TensorAllocator) -> T) -> T {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+        let result = f(unsafe { &mut *self.allocator.get() });
+        self.locked.store(false, Ordering::Release);
+        result
+    }
+}
+
+static GLOBAL: Global = Global {
+    locked: AtomicBool::new(false),
+    allocator: UnsafeCell::new(&System),
+};
+
+/// Allocate the buffers of new tensors with `allocator`. Tensors allocated
+/// before are still freed by the allocator they came from.
+pub fn set_global(allocator: &'static dyn TensorAllocator) {
+    GLOBAL.with(|global| *global = allocator);
+}
+
+/// The allocator of new tensors, [`System`] unless set.
+pub fn global() -> &'static dyn TensorAllocator {
+    GLOBAL.with(|global| *global)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::{
+        ffi::DataType, fixtures::transposed, prelude::*, ManagedTensor, Order, OwnedTensor,
+    };
+
+    struct Counting(AtomicUsize);
+
+    unsafe impl TensorAllocator for Counting {
+        fn allocate_aligned(&self, layout: Layout) -> Option<NonNull<u8>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            System.allocate_aligned(layout)
+        }
+
+        unsafe fn deallocate(&self, block: NonNull<u8>, layout: Layout) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+            System.deallocate(block, layout);
+        }
+    }
+
+    #[test]
+    fn custom_allocator() {
+        static COUNTING: Counting = Counting(AtomicUsize::new(0));
+        let tensor = OwnedTensor::new_zeroed_in(&[2, 3], DataType::F32, &COUNTING).unwrap();
+        assert_eq!(COUNTING.0.load(Ordering::SeqCst), 1);
+        assert!(tensor.as_bytes().iter().all(|&b| b == 0));
+        drop(tensor);
+        assert_eq!(COUNTING.0.load(Ordering::SeqCst), 0);
+
+        // Other tests may allocate while it is set, but also free.
+        set_global(&COUNTING);
+        let tensor = OwnedTensor::new_zeroed(&[4], DataType::U8).unwrap();
+        set_global(&System);
+        assert!(COUNTING.0.load(Ordering::SeqCst) >= 1);
+        drop(tensor);
+
+        static COPIES: Counting = Counting(AtomicUsize::new(0));
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        let copy = tensor.to_layout_in(Order::C, &COPIES).unwrap();
+        assert_eq!(COPIES.0.load(Ordering::SeqCst), 1);
+        assert_eq!(copy.as_slice::<i32>(), [0, 3, 1, 4, 2, 5]);
+        drop(copy);
+        assert_eq!(COPIES.0.load(Ordering::SeqCst), 0);
+    }
+}
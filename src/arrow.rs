@@ -0,0 +1,312 @@
+//! Storing named tensors in an [Arrow IPC](https://arrow.apache.org/docs/format/Columnar.html#serialization-and-interprocess-communication-ipc)
+//! stream.
+//!
+//! Every tensor becomes a single-row `FixedSizeList` column, tagged as the
+//! canonical `arrow.fixed_shape_tensor` extension type so other Arrow
+//! implementations see its shape. The exact shape is also kept in the
+//! `dlpark:shape` field metadata.
+//!
+//! When reading streams written elsewhere, columns without `dlpark:shape`
+//! become tensors with the number of rows as leading dimension: a fixed shape
+//! tensor column of shape `s` becomes `[rows, s...]`, any other fixed size
+//! list column becomes `[rows, list_size]` and a primitive column `[rows]`.
+//! Rows of all record batches are concatenated.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use arrow_array::{
+    make_array, Array, ArrayRef, BooleanArray, FixedSizeListArray, RecordBatch, RecordBatchOptions,
+};
+use arrow_buffer::{BooleanBuffer, Buffer};
+use arrow_data::ArrayData;
+use arrow_ipc::{reader::StreamReader, writer::StreamWriter};
+use arrow_schema::{ArrowError, DataType as ArrowDataType, Field, Schema};
+
+use crate::{
+    ffi::{DataType, DataTypeCode},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    ManagedTensor, OwnedTensor,
+};
+
+pub(crate) const SHAPE_KEY: &str = "dlpark:shape";
+const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+const EXTENSION_METADATA_KEY: &str = "ARROW:extension:metadata";
+const FIXED_SHAPE_TENSOR: &str = "arrow.fixed_shape_tensor";
+
+pub(crate) fn to_io_error(err: ArrowError) -> io::Error {
+    match err {
+        ArrowError::IoError(_, err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+pub(crate) fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Map a DLPack dtype to an Arrow value type, if there is one.
+pub fn to_arrow_data_type(dtype: DataType) -> Option<ArrowDataType> {
+    if dtype.lanes != 1 {
+        return None;
+    }
+    let dtype = match (dtype.code, dtype.bits) {
+        (DataTypeCode::Bool, 8) => ArrowDataType::Boolean,
+        (DataTypeCode::Int, 8) => ArrowDataType::Int8,
+        (DataTypeCode::Int, 16) => ArrowDataType::Int16,
+        (DataTypeCode::Int, 32) => ArrowDataType::Int32,
+        (DataTypeCode::Int, 64) => ArrowDataType::Int64,
+        (DataTypeCode::UInt, 8) => ArrowDataType::UInt8,
+        (DataTypeCode::UInt, 16) => ArrowDataType::UInt16,
+        (DataTypeCode::UInt, 32) => ArrowDataType::UInt32,
+        (DataTypeCode::UInt, 64) => ArrowDataType::UInt64,
+        (DataTypeCode::Float, 16) => ArrowDataType::Float16,
+        (DataTypeCode::Float, 32) => ArrowDataType::Float32,
+        (DataTypeCode::Float, 64) => ArrowDataType::Float64,
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+/// Map an Arrow value type to a DLPack dtype, if there is one.
+pub fn from_arrow_data_type(dtype: &ArrowDataType) -> Option<DataType> {
+    let dtype = match dtype {
+        ArrowDataType::Boolean => DataType::BOOL,
+        ArrowDataType::Int8 => DataType::I8,
+        ArrowDataType::Int16 => DataType::I16,
+        ArrowDataType::Int32 => DataType::I32,
+        ArrowDataType::Int64 => DataType::I64,
+        ArrowDataType::UInt8 => DataType::U8,
+        ArrowDataType::UInt16 => DataType::U16,
+        ArrowDataType::UInt32 => DataType::U32,
+        ArrowDataType::UInt64 => DataType::U64,
+        ArrowDataType::Float16 => DataType::F16,
+        ArrowDataType::Float32 => DataType::F32,
+        ArrowDataType::Float64 => DataType::F64,
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+fn format_shape(shape: &[i64]) -> String {
+    let dims: Vec<String> = shape.iter().map(i64::to_string).collect();
+    format!("[{}]", dims.join(","))
+}
+
+/// Parse a shape such as `[2,3]`, also accepting the JSON object of the fixed
+/// shape tensor extension.
+fn parse_shape(text: &str) -> Option<Vec<i64>> {
+    let text = match text.find("\"shape\"") {
+        Some(start) => &text[start + "\"shape\"".len()..],
+        None => text,
+    };
+    let start = text.find('[')?;
+    let end = start + text[start..].find(']')?;
+    let dims = text[start + 1..end].trim();
+    if dims.is_empty() {
+        return Some(Vec::new());
+    }
+    dims.split(',')
+        .map(|dim| dim.trim().parse().ok().filter(|&dim| dim >= 0))
+        .collect()
+}
+
+fn tensor_column(name: &str, tensor: &ManagedTensor) -> io::Result<(Field, ArrayRef)> {
+    let dtype = tensor.dtype();
+    let value_type = to_arrow_data_type(dtype).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("dtype {dtype:?} has no arrow equivalent"),
+        )
+    })?;
+    let len = tensor.num_elements();
+    let size = i32::try_from(len).map_err(|_| invalid_data("tensor is too large for arrow"))?;
+    let data = tensor.to_contiguous_bytes();
+    let values: ArrayRef = if value_type == ArrowDataType::Boolean {
+        let bits = BooleanBuffer::from_iter(data.iter().map(|&b| b != 0));
+        Arc::new(BooleanArray::new(bits, None))
+    } else {
+        // Copying into an Arrow buffer gives the alignment Arrow requires.
+        let data = ArrayData::builder(value_type.clone())
+            .len(len)
+            .add_buffer(Buffer::from_slice_ref(&data))
+            .build()
+            .map_err(to_io_error)?;
+        make_array(data)
+    };
+    let item = Arc::new(Field::new("item", value_type, false));
+    let column = FixedSizeListArray::new(item, size, values, None);
+    let shape = format_shape(tensor.shape());
+    let field = Field::new(name, column.data_type().clone(), false).with_metadata(HashMap::from([
+        (SHAPE_KEY.to_string(), shape.clone()),
+        (
+            EXTENSION_NAME_KEY.to_string(),
+            FIXED_SHAPE_TENSOR.to_string(),
+        ),
+        (
+            EXTENSION_METADATA_KEY.to_string(),
+            format!("{{\"shape\":{shape}}}"),
+        ),
+    ]));
+    Ok((field, Arc::new(column)))
+}
+
+/// Single-row record batch with a column for each named CPU tensor.
+pub(crate) fn tensor_batch<'a, I>(tensors: I) -> io::Result<RecordBatch>
+where
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    let (fields, columns): (Vec<_>, Vec<_>) = tensors
+        .into_iter()
+        .map(|(name, tensor)| tensor_column(name, tensor))
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let schema = Arc::new(Schema::new(fields));
+    let options = RecordBatchOptions::new().with_row_count(Some(1));
+    RecordBatch::try_new_with_options(schema, columns, &options).map_err(to_io_error)
+}
+
+/// Write named CPU tensors as an Arrow IPC stream holding a single record
+/// batch.
+pub fn write_arrow_ipc<'a, W, I>(writer: W, tensors: I) -> io::Result<W>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    let batch = tensor_batch(tensors)?;
+    let mut writer = StreamWriter::try_new(writer, &batch.schema()).map_err(to_io_error)?;
+    writer.write(&batch).map_err(to_io_error)?;
+    writer.finish().map_err(to_io_error)?;
+    writer.into_inner().map_err(to_io_error)
+}
+
+/// Append the values of a primitive or boolean array to `out`.
+pub(crate) fn append_values(values: &dyn Array, out: &mut Vec<u8>) -> io::Result<()> {
+    if values.null_count() > 0 {
+        return Err(invalid_data("arrow arrays with nulls can't become tensors"));
+    }
+    if let Some(bools) = values.as_any().downcast_ref::<BooleanArray>() {
+        out.extend(bools.values().iter().map(u8::from));
+        return Ok(());
+    }
+    let data = values.to_data();
+    let itemsize = data.data_type().primitive_width().unwrap_or(0);
+    let start = data.offset() * itemsize;
+    out.extend_from_slice(&data.buffers()[0][start..start + data.len() * itemsize]);
+    Ok(())
+}
+
+/// Shape of a column with `rows` rows, and its flat values.
+pub(crate) fn column_values(field: &Field, column: &dyn Array) -> io::Result<(Vec<i64>, ArrayRef)> {
+    let rows = column.len() as i64;
+    let Some(list) = column.as_any().downcast_ref::<FixedSizeListArray>() else {
+        return Ok((vec![rows], make_array(column.to_data())));
+    };
+    if list.null_count() > 0 {
+        return Err(invalid_data("arrow arrays with nulls can't become tensors"));
+    }
+    let size = list.value_length() as usize;
+    let values = list
+        .values()
+        .slice(list.value_offset(0) as usize, list.len() * size);
+    let metadata = field.metadata();
+    let shape = if let Some(shape) = metadata.get(SHAPE_KEY) {
+        parse_shape(shape).ok_or_else(|| invalid_data("invalid dlpark:shape metadata"))?
+    } else if metadata.get(EXTENSION_NAME_KEY).map(String::as_str) == Some(FIXED_SHAPE_TENSOR) {
+        let element = metadata
+            .get(EXTENSION_METADATA_KEY)
+            .and_then(|meta| parse_shape(meta))
+            .ok_or_else(|| invalid_data("invalid fixed shape tensor metadata"))?;
+        [vec![rows], element].concat()
+    } else {
+        vec![rows, size as i64]
+    };
+    Ok((shape, values))
+}
+
+/// DLPack dtype of the values of every column of `schema`.
+pub(crate) fn column_dtypes(schema: &Schema) -> io::Result<Vec<DataType>> {
+    schema
+        .fields()
+        .iter()
+        .map(|field| {
+            let value_type = match field.data_type() {
+                ArrowDataType::FixedSizeList(item, _) => item.data_type(),
+                dtype => dtype,
+            };
+            from_arrow_data_type(value_type).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported arrow type {value_type}"),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Read every column of an Arrow IPC stream into a new CPU tensor, keyed by
+/// column name.
+pub fn read_arrow_ipc<R: Read>(reader: R) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let reader = StreamReader::try_new(reader, None).map_err(to_io_error)?;
+    let schema = reader.schema();
+    let dtypes = column_dtypes(&schema)?;
+
+    let mut data = vec![Vec::new(); dtypes.len()];
+    let mut shapes = vec![vec![0]; dtypes.len()];
+    let mut rows = 0;
+    for batch in reader {
+        let batch = batch.map_err(to_io_error)?;
+        for (i, field) in schema.fields().iter().enumerate() {
+            let (shape, values) = column_values(field, batch.column(i))?;
+            append_values(&values, &mut data[i])?;
+            shapes[i] = shape;
+        }
+        rows += batch.num_rows() as i64;
+    }
+
+    let mut tensors = BTreeMap::new();
+    for (((field, dtype), data), mut shape) in
+        schema.fields().iter().zip(dtypes).zip(data).zip(shapes)
+    {
+        if !field.metadata().contains_key(SHAPE_KEY) {
+            shape[0] = rows;
+        }
+        let tensor = OwnedTensor::from_bytes(&data, &shape, dtype).ok_or_else(|| {
+            invalid_data(format!("column {} doesn't match its shape", field.name()))
+        })?;
+        tensors.insert(
+            field.name().clone(),
+            ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        );
+    }
+    Ok(tensors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let a = ManagedTensor::from_dlpack(vec![1.0f32, 2.0, 3.0, 4.0].into_dlpack());
+        let b = ManagedTensor::from_dlpack(vec![true, false, true].into_dlpack());
+        let buf = write_arrow_ipc(Vec::new(), [("a", &a), ("b", &b)]).unwrap();
+        let tensors = read_arrow_ipc(buf.as_slice()).unwrap();
+        assert_eq!(tensors["a"].shape(), &[4]);
+        assert_eq!(tensors["a"].as_slice::<f32>(), &[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(tensors["b"].dtype(), DataType::BOOL);
+        assert_eq!(tensors["b"].as_slice::<u8>(), &[1, 0, 1]);
+    }
+
+    #[test]
+    fn parse_shapes() {
+        assert_eq!(parse_shape("[2,3]"), Some(vec![2, 3]));
+        assert_eq!(parse_shape("[]"), Some(vec![]));
+        assert_eq!(parse_shape("{\"shape\": [4, 5]}"), Some(vec![4, 5]));
+        assert_eq!(parse_shape("[-1]"), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! Waveforms as `[channels, samples]` tensors, converted from and to the
+//! interleaved or planar PCM buffers of audio decoders such as hound or
+//! symphonia. Samples keep their type, e.g. `i16` stays `i16`.
+
+use alloc::vec::Vec;
+
+use crate::{
+    tensor::traits::{InferDtype, TensorView},
+    LayoutError, ManagedTensor, ManagerCtx, OwnedTensor, Result,
+};
+
+impl ManagerCtx<OwnedTensor> {
+    /// Tensor of interleaved frames of `channels` samples each. Returns
+    /// `None` if `channels` is zero or doesn't divide the number of samples.
+    pub fn from_interleaved<A: InferDtype + Copy>(samples: &[A], channels: usize) -> Option<Self> {
+        if channels == 0 || !samples.len().is_multiple_of(channels) {
+            return None;
+        }
+        let frames = samples.len() / channels;
+        OwnedTensor::new_with(&[channels as i64, frames as i64], |out: &mut [A]| {
+            for (frame, samples) in samples.chunks_exact(channels).enumerate() {
+                for (channel, &sample) in samples.iter().enumerate() {
+                    out[channel * frames + frame] = sample;
+                }
+            }
+        })
+        .map(Self::new)
+    }
+
+    /// Tensor of one plane of samples per channel. Returns `None` if the
+    /// planes have different lengths.
+    pub fn from_planar<A, P>(planes: &[P]) -> Option<Self>
+    where
+        A: InferDtype + Copy,
+        P: AsRef<[A]>,
+    {
+        let frames = planes.first().map_or(0, |plane| plane.as_ref().len());
+        if planes.iter().any(|plane| plane.as_ref().len() != frames) {
+            return None;
+        }
+        OwnedTensor::new_with(&[planes.len() as i64, frames as i64], |out: &mut [A]| {
+            for (plane, out) in planes.iter().zip(out.chunks_exact_mut(frames.max(1))) {
+                out.copy_from_slice(plane.as_ref());
+            }
+        })
+        .map(Self::new)
+    }
+
+    /// Tensor of the planes of a buffer decoded by symphonia. 24-bit samples
+    /// are widened to `i32` or `u32`, keeping their values.
+    #[cfg(feature = "symphonia")]
+    pub fn from_audio_buffer(buffer: &symphonia_core::audio::AudioBufferRef) -> Self {
+        use symphonia_core::audio::AudioBufferRef;
+
+        fn planar<A: InferDtype + Copy, S: Copy>(
+            planes: &[&[S]],
+            f: impl Fn(S) -> A,
+        ) -> ManagerCtx<OwnedTensor> {
+            let planes: Vec<Vec<A>> = planes
+                .iter()
+                .map(|plane| plane.iter().map(|&s| f(s)).collect())
+                .collect();
+            ManagerCtx::from_planar(&planes).expect("audio planes have the same length")
+        }
+
+        let tensor = match buffer {
+            AudioBufferRef::U8(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::U16(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::U24(buf) => Some(planar(buf.planes().planes(), |s| s.inner())),
+            AudioBufferRef::U32(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::S8(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::S16(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::S24(buf) => Some(planar(buf.planes().planes(), |s| s.inner())),
+            AudioBufferRef::S32(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::F32(buf) => Self::from_planar(buf.planes().planes()),
+            AudioBufferRef::F64(buf) => Self::from_planar(buf.planes().planes()),
+        };
+        tensor.expect("audio planes have the same length")
+    }
+}
+
+impl ManagedTensor {
+    fn check_waveform<A: InferDtype>(&self) -> Result<(usize, usize)> {
+        self.check_cpu_dtype::<A>()?;
+        match *self.shape() {
+            [channels, frames] => Ok((channels as usize, frames as usize)),
+            _ => Err(LayoutError::NdimMismatch {
+                expected: 2,
+                found: self.ndim(),
+            }
+            .into()),
+        }
+    }
+
+    /// Samples of a `[channels, samples]` CPU tensor as interleaved frames.
+    pub fn to_interleaved<A: InferDtype + Copy>(&self) -> Result<Vec<A>> {
+        let (channels, frames) = self.check_waveform::<A>()?;
+        let planar = self.to_contiguous::<A>();
+        let mut samples = Vec::with_capacity(planar.len());
+        for frame in 0..frames {
+            samples.extend((0..channels).map(|channel| planar[channel * frames + frame]));
+        }
+        Ok(samples)
+    }
+
+    /// Samples of a `[channels, samples]` CPU tensor as one plane per
+    /// channel.
+    pub fn to_planar<A: InferDtype + Copy>(&self) -> Result<Vec<Vec<A>>> {
+        let (channels, frames) = self.check_waveform::<A>()?;
+        let planar = self.to_contiguous::<A>();
+        if frames == 0 {
+            return Ok(alloc::vec![Vec::new(); channels]);
+        }
+        Ok(planar.chunks_exact(frames).map(<[A]>::to_vec).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, Error};
+
+    #[test]
+    fn waveforms() {
+        let interleaved = [1i16, -1, 2, -2, 3, -3];
+        let tensor = ManagerCtx::from_interleaved(&interleaved, 2).unwrap();
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.as_slice::<i16>(), &[1, 2, 3, -1, -2, -3]);
+        assert_eq!(tensor.to_interleaved::<i16>().unwrap(), interleaved);
+        assert_eq!(
+            tensor.to_planar::<i16>().unwrap(),
+            vec![vec![1, 2, 3], vec![-1, -2, -3]]
+        );
+        assert!(ManagerCtx::from_interleaved(&interleaved, 4).is_none());
+
+        let planes = vec![vec![0.5f32, 0.25], vec![-0.5, -0.25]];
+        let tensor = ManagerCtx::from_planar(&planes).unwrap();
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(
+            tensor.to_interleaved::<f32>().unwrap(),
+            [0.5, -0.5, 0.25, -0.25]
+        );
+        assert!(ManagerCtx::from_planar(&[&[1u8][..], &[]]).is_none());
+        assert!(matches!(
+            tensor.to_planar::<i16>(),
+            Err(Error::DtypeMismatch { .. })
+        ));
+
+        let empty = ManagerCtx::from_planar::<u8, Vec<u8>>(&[vec![], vec![]]).unwrap();
+        let empty = ManagedTensor::from_dlpack(empty.into_dlpack());
+        assert_eq!(empty.to_planar::<u8>().unwrap(), vec![Vec::<u8>::new(); 2]);
+    }
+
+    #[cfg(feature = "symphonia")]
+    #[test]
+    fn symphonia_buffer() {
+        use std::borrow::Cow;
+
+        use symphonia_core::{
+            audio::{AudioBuffer, AudioBufferRef, Channels, Signal, SignalSpec},
+            sample::i24,
+        };
+
+        let spec = SignalSpec::new(16000, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+        let mut buffer = AudioBuffer::<i24>::new(4, spec);
+        buffer.render_reserved(Some(3));
+        buffer.chan_mut(1)[2] = i24(-7);
+        let buffer = AudioBufferRef::S24(Cow::Owned(buffer));
+        let tensor =
+            ManagedTensor::from_dlpack(ManagerCtx::from_audio_buffer(&buffer).into_dlpack());
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.as_slice::<i32>(), &[0, 0, 0, 0, 0, -7]);
+    }
+}
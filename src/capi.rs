@@ -0,0 +1,228 @@
+//! C API for using this crate as the DLPack producer and consumer of C and
+//! C++ programs. The header `include/dlpark.h` is generated from this module
+//! with `cbindgen --config cbindgen.toml --output include/dlpark.h`, and the
+//! library built with `cargo rustc --release --features capi --crate-type
+//! staticlib` (or `cdylib`).
+//!
+//! Functions taking a tensor require a valid, non-deleted DLManagedTensor,
+//! unless stated otherwise.
+
+use std::{ffi::c_void, ptr};
+
+use crate::{
+    ffi::{DLManagedTensor, DLTensor, DataType, Device, DeviceType},
+    plugin::{self, Allocator},
+    tensor::traits::{IntoDLPack, TensorView},
+    utils::catch_ffi_panic,
+    OwnedTensor,
+};
+
+unsafe fn dl_tensor<'a>(tensor: *const DLManagedTensor) -> &'a DLTensor {
+    &(*tensor).dl_tensor
+}
+
+/// Copy `len` bytes at `data` into a new contiguous CPU tensor of `dtype`
+/// with the `ndim` dims at `shape`. Returns NULL if `len` doesn't match them.
+/// Free the tensor with `dlpark_tensor_free`.
+///
+/// # Safety
+/// `data` must be readable for `len` bytes and `shape` for `ndim` dims.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_from_buffer(
+    data: *const c_void,
+    len: usize,
+    dtype: DataType,
+    shape: *const i64,
+    ndim: i32,
+) -> *mut DLManagedTensor {
+    let Ok(ndim) = usize::try_from(ndim) else {
+        return ptr::null_mut();
+    };
+    if (data.is_null() && len > 0) || (shape.is_null() && ndim > 0) {
+        return ptr::null_mut();
+    }
+    let mut tensor = ptr::null_mut();
+    catch_ffi_panic("dlpark_tensor_from_buffer", || {
+        let shape: &[i64] = if ndim == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(shape, ndim)
+        };
+        let bytes: &[u8] = if len == 0 {
+            &[]
+        } else {
+            std::slice::from_raw_parts(data.cast(), len)
+        };
+        if let Some(owned) = OwnedTensor::from_bytes(bytes, shape, dtype) {
+            tensor = owned.into_dlpack().as_ptr();
+        }
+    });
+    tensor
+}
+
+/// Register the memory management of a device backend for every device of
+/// `device_type`, see `dlpark::plugin`. The vtable is copied. Returns 0 on
+/// success, or -1 if `device_type` is unknown, or `allocator` or one of its
+/// `alloc`, `free` and `copy` callbacks is NULL.
+///
+/// # Safety
+/// The callbacks must behave as documented on the vtable, and its `ctx` must
+/// stay valid as long as tensors allocated with it are alive.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_register_allocator(
+    device_type: i32,
+    allocator: *const Allocator,
+) -> i32 {
+    match (DeviceType::try_from(device_type), allocator.as_ref()) {
+        (Ok(device_type), Some(allocator)) if allocator.is_complete() => {
+            plugin::register_allocator(device_type, *allocator);
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// Call the deleter of `tensor`, which may come from any producer. Does
+/// nothing if `tensor` is NULL.
+///
+/// # Safety
+/// `tensor` must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_free(tensor: *mut DLManagedTensor) {
+    if tensor.is_null() {
+        return;
+    }
+    if let Some(deleter) = (*tensor).deleter {
+        deleter(tensor);
+    }
+}
+
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_ndim(tensor: *const DLManagedTensor) -> i32 {
+    dl_tensor(tensor).ndim
+}
+
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_shape(tensor: *const DLManagedTensor) -> *const i64 {
+    dl_tensor(tensor).shape
+}
+
+/// Strides in elements, or NULL if the tensor is row-major contiguous.
+///
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_strides(tensor: *const DLManagedTensor) -> *const i64 {
+    dl_tensor(tensor).strides
+}
+
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_dtype(tensor: *const DLManagedTensor) -> DataType {
+    dl_tensor(tensor).dtype
+}
+
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_device(tensor: *const DLManagedTensor) -> Device {
+    dl_tensor(tensor).device
+}
+
+/// Pointer to the first element, with the byte offset applied.
+///
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_data(tensor: *const DLManagedTensor) -> *mut c_void {
+    let tensor = dl_tensor(tensor);
+    tensor
+        .data
+        .cast::<u8>()
+        .wrapping_add(tensor.byte_offset as usize)
+        .cast()
+}
+
+/// Number of elements, or -1 if a dim is negative or the count overflows.
+///
+/// # Safety
+/// `tensor` must be a valid DLManagedTensor.
+#[no_mangle]
+pub unsafe extern "C" fn dlpark_tensor_num_elements(tensor: *const DLManagedTensor) -> i64 {
+    dl_tensor(tensor)
+        .checked_num_elements()
+        .and_then(|n| i64::try_from(n).ok())
+        .unwrap_or(-1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_buffer() {
+        let data = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let shape = [2i64, 3];
+        unsafe {
+            let tensor = dlpark_tensor_from_buffer(
+                data.as_ptr().cast(),
+                24,
+                DataType::F32,
+                shape.as_ptr(),
+                2,
+            );
+            assert!(!tensor.is_null());
+            assert_eq!(dlpark_tensor_ndim(tensor), 2);
+            assert_eq!(
+                std::slice::from_raw_parts(dlpark_tensor_shape(tensor), 2),
+                shape
+            );
+            assert_eq!(
+                std::slice::from_raw_parts(dlpark_tensor_strides(tensor), 2),
+                [3, 1]
+            );
+            assert_eq!(dlpark_tensor_dtype(tensor), DataType::F32);
+            assert_eq!(dlpark_tensor_device(tensor), Device::CPU);
+            assert_eq!(dlpark_tensor_num_elements(tensor), 6);
+            let values = std::slice::from_raw_parts(dlpark_tensor_data(tensor).cast::<f32>(), 6);
+            assert_eq!(values, data);
+            dlpark_tensor_free(tensor);
+
+            let tensor = dlpark_tensor_from_buffer(
+                data.as_ptr().cast(),
+                20,
+                DataType::F32,
+                shape.as_ptr(),
+                2,
+            );
+            assert!(tensor.is_null());
+            dlpark_tensor_free(tensor);
+        }
+    }
+
+    #[test]
+    fn incomplete_allocator() {
+        let allocator = Allocator {
+            ctx: ptr::null_mut(),
+            alloc: None,
+            free: None,
+            copy: None,
+            memory_info: None,
+        };
+        unsafe {
+            assert_eq!(
+                dlpark_register_allocator(DeviceType::Hexagon as i32, &allocator),
+                -1
+            );
+            assert_eq!(
+                dlpark_register_allocator(DeviceType::Hexagon as i32, ptr::null()),
+                -1
+            );
+        }
+    }
+}
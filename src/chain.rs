@@ -0,0 +1,452 @@
+//! Views derived from another tensor without copying. The manager context of
+//! an exported view owns a handle to its parent, which the view's deleter
+//! drops, so the data lives as long as the last view of it. Views of views
+//! chain the same way, whichever side of the Python boundary deletes them.
+//!
+//! [`ManagedTensor::chunks`] borrows views of consecutive rows instead, to
+//! stream through tensors larger than RAM, such as memory-mapped ones,
+//! without reading them at once.
+
+use alloc::{vec, vec::Vec};
+use core::{
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Deref, Range},
+    ptr::NonNull,
+};
+
+use crate::{
+    ffi::{DataType, Device},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView, ToTensor},
+    utils::{byte_span, make_contiguous_strides},
+    LayoutError, ManagedTensor, ManagerCtx, Ownership, Result, ShapeAndStrides,
+};
+
+/// Tensor viewing the data of its parent `P` with another layout, keeping
+/// the parent alive until it is dropped.
+#[derive(Debug)]
+pub struct ChainedManager<P> {
+    parent: P,
+    shape: Vec<i64>,
+    strides: Vec<i64>,
+    byte_offset: u64,
+}
+
+impl<P: TensorView> ChainedManager<P> {
+    /// View the data of `parent` with `shape` and `strides`, starting
+    /// `byte_offset` bytes after its data pointer like the parent itself.
+    ///
+    /// # Safety
+    /// Every element of the view must be an element of `parent`.
+    pub unsafe fn new(parent: P, shape: &[i64], strides: &[i64], byte_offset: u64) -> Result<Self> {
+        if shape.len() != strides.len() {
+            return Err(LayoutError::StridesLength {
+                ndim: shape.len(),
+                strides: strides.len(),
+            }
+            .into());
+        }
+        Ok(Self {
+            parent,
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            byte_offset,
+        })
+    }
+
+    pub fn parent(&self) -> &P {
+        &self.parent
+    }
+
+    pub fn into_parent(self) -> P {
+        self.parent
+    }
+}
+
+impl<P: TensorView> ToTensor for ChainedManager<P> {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.parent.data_ptr()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        self.byte_offset
+    }
+
+    fn device(&self) -> Device {
+        self.parent.device()
+    }
+
+    fn dtype(&self) -> DataType {
+        self.parent.dtype()
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_with_strides(&self.shape, &self.strides)
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        self.parent.flags()
+    }
+}
+
+impl ManagedTensor {
+    /// View with reordered axes, axis `i` of the view being axis `axes[i]` of
+    /// this tensor.
+    pub fn permute(self, axes: &[usize]) -> Result<Self> {
+        let ndim = self.ndim();
+        if axes.len() != ndim {
+            return Err(LayoutError::NdimMismatch {
+                expected: ndim,
+                found: axes.len(),
+            }
+            .into());
+        }
+        let mut seen = vec![false; ndim];
+        for &axis in axes {
+            if axis >= ndim || seen[axis] {
+                return Err(LayoutError::InvalidAxis { axis, ndim }.into());
+            }
+            seen[axis] = true;
+        }
+        let strides = self.strides_or_contiguous();
+        let shape: Vec<_> = axes.iter().map(|&axis| self.shape()[axis]).collect();
+        let strides: Vec<_> = axes.iter().map(|&axis| strides[axis]).collect();
+        let byte_offset = self.byte_offset();
+        self.chain(&shape, &strides, byte_offset)
+    }
+
+    /// View of the elements in `range` along `axis`.
+    pub fn narrow(self, axis: usize, range: Range<i64>) -> Result<Self> {
+        let ndim = self.ndim();
+        let Some(&dim) = self.shape().get(axis) else {
+            return Err(LayoutError::InvalidAxis { axis, ndim }.into());
+        };
+        if range.start < 0 || range.start > range.end || range.end > dim {
+            return Err(LayoutError::OutOfRange {
+                axis,
+                start: range.start,
+                end: range.end,
+                dim,
+            }
+            .into());
+        }
+        let strides = self.strides_or_contiguous().into_owned();
+        let mut shape = self.shape().to_vec();
+        shape[axis] = range.end - range.start;
+        let offset = range.start * strides[axis] * self.dtype().size() as i64;
+        let byte_offset = (self.byte_offset() as i64)
+            .checked_add(offset)
+            .and_then(|offset| u64::try_from(offset).ok())
+            .ok_or(LayoutError::NegativeOffset)?;
+        self.chain(&shape, &strides, byte_offset)
+    }
+
+    /// View with `axis` reversed through a negative stride, like numpy's
+    /// `[::-1]`.
+    pub fn flip(self, axis: usize) -> Result<Self> {
+        let strides = self.strides_or_contiguous();
+        let layout = ShapeAndStrides::new_borrowed(self.shape(), Some(&strides));
+        let (flipped, offset) = layout.flip(axis)?;
+        let byte_offset = (self.byte_offset() as i64)
+            .checked_add(offset * self.dtype().size() as i64)
+            .and_then(|offset| u64::try_from(offset).ok())
+            .ok_or(LayoutError::NegativeOffset)?;
+        let (shape, strides) = (
+            flipped.shape().to_vec(),
+            flipped.strides().unwrap().to_vec(),
+        );
+        self.chain(&shape, &strides, byte_offset)
+    }
+
+    /// View of a row-major contiguous tensor with another shape of as many
+    /// elements.
+    pub fn reshape(self, shape: &[i64]) -> Result<Self> {
+        if !self.is_contiguous() {
+            return Err(LayoutError::NotContiguous.into());
+        }
+        let num_elements = shape.iter().try_fold(1usize, |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        });
+        if num_elements != Some(self.num_elements()) {
+            return Err(LayoutError::ReshapeMismatch {
+                num_elements: self.num_elements(),
+            }
+            .into());
+        }
+        let strides = make_contiguous_strides(shape);
+        let byte_offset = self.byte_offset();
+        self.chain(shape, &strides, byte_offset)
+    }
+
+    /// View of the diagonal of the first two axes, which becomes the last
+    /// axis, like numpy's `diagonal()`.
+    pub fn diagonal(self) -> Result<Self> {
+        let ndim = self.ndim();
+        if ndim < 2 {
+            return Err(LayoutError::NdimMismatch {
+                expected: 2,
+                found: ndim,
+            }
+            .into());
+        }
+        let (shape, strides) = (self.shape(), self.strides_or_contiguous());
+        let mut diag_shape = shape[2..].to_vec();
+        diag_shape.push(shape[0].min(shape[1]));
+        let mut diag_strides = strides[2..].to_vec();
+        diag_strides.push(strides[0] + strides[1]);
+        let byte_offset = self.byte_offset();
+        self.chain(&diag_shape, &diag_strides, byte_offset)
+    }
+
+    /// View with any `shape` and `strides` in elements, starting
+    /// `byte_offset` bytes after the data pointer, e.g. overlapping windows
+    /// for frame stacking.
+    ///
+    /// # Safety
+    /// Every element of the view must be an element of this tensor, see
+    /// [`ManagedTensor::try_as_strided`] for a checked version.
+    pub unsafe fn as_strided(
+        self,
+        shape: &[i64],
+        strides: &[i64],
+        byte_offset: u64,
+    ) -> Result<Self> {
+        let chained = ChainedManager::new(self, shape, strides, byte_offset)?;
+        Ok(Self::from_dlpack(ManagerCtx::new(chained).into_dlpack()))
+    }
+
+    /// Same as [`ManagedTensor::as_strided`], but checks that the view stays
+    /// within the bytes spanned by this tensor.
+    pub fn try_as_strided(self, shape: &[i64], strides: &[i64], byte_offset: u64) -> Result<Self> {
+        let size = self.dtype().size();
+        let parent = byte_span(self.shape(), Some(&self.strides_or_contiguous()), size);
+        let view = byte_span(shape, Some(strides), size);
+        let within = |span: Option<core::ops::Range<i64>>, offset: u64| {
+            let span = span?;
+            let offset = i64::try_from(offset).ok()?;
+            Some(span.start.checked_add(offset)?..span.end.checked_add(offset)?)
+        };
+        match (
+            within(parent, self.byte_offset()),
+            within(view, byte_offset),
+        ) {
+            (Some(_), Some(view)) if view.is_empty() => {}
+            (Some(parent), Some(view)) if parent.start <= view.start && view.end <= parent.end => {}
+            _ => return Err(LayoutError::OutsideParent.into()),
+        }
+        // Checked above, and the lengths are checked by `as_strided`.
+        unsafe { self.as_strided(shape, strides, byte_offset) }
+    }
+
+    /// Export a view of this tensor and import it again.
+    fn chain(self, shape: &[i64], strides: &[i64], byte_offset: u64) -> Result<Self> {
+        // The callers only derive views within this tensor.
+        let chained = unsafe { ChainedManager::new(self, shape, strides, byte_offset)? };
+        Ok(Self::from_dlpack(ManagerCtx::new(chained).into_dlpack()))
+    }
+}
+
+/// View of consecutive indices of the first axis of a tensor, which it
+/// borrows.
+#[derive(Debug)]
+pub struct TensorChunk<'a> {
+    tensor: ManagedTensor,
+    parent: PhantomData<&'a ManagedTensor>,
+}
+
+impl Deref for TensorChunk<'_> {
+    type Target = ManagedTensor;
+
+    fn deref(&self) -> &ManagedTensor {
+        &self.tensor
+    }
+}
+
+/// Iterator of [`ManagedTensor::chunks`].
+#[derive(Debug)]
+pub struct Chunks<'a> {
+    parent: &'a ManagedTensor,
+    rows: i64,
+    start: i64,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = TensorChunk<'a>;
+
+    fn next(&mut self) -> Option<TensorChunk<'a>> {
+        let dim = self.parent.shape()[0];
+        if self.start >= dim {
+            return None;
+        }
+        let end = dim.min(self.start.saturating_add(self.rows));
+        // The parent outlives the chunk, which never deletes it.
+        let handle = NonNull::new(self.parent.as_ptr()).unwrap();
+        let tensor = ManagedTensor::new(handle, Ownership::Borrowed)
+            .narrow(0, self.start..end)
+            .expect("chunk within the first axis");
+        self.start = end;
+        Some(TensorChunk {
+            tensor,
+            parent: PhantomData,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.parent.shape()[0] - self.start).max(0) as usize;
+        let len = left.div_ceil(self.rows as usize);
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Chunks<'_> {}
+
+impl FusedIterator for Chunks<'_> {}
+
+impl ManagedTensor {
+    /// Iterator over views of `rows` indices of the first axis at a time, the
+    /// last one possibly shorter, e.g. batches of a memory-mapped dataset
+    /// larger than RAM, whose pages are only read when a chunk is.
+    ///
+    /// # Panics
+    /// If `rows` is zero or the tensor is 0-d.
+    pub fn chunks(&self, rows: usize) -> Chunks<'_> {
+        assert!(rows != 0, "chunk size must be non-zero");
+        assert!(self.ndim() != 0, "a 0-d tensor has no rows");
+        Chunks {
+            parent: self,
+            rows: i64::try_from(rows).unwrap_or(i64::MAX),
+            start: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::Error;
+
+    struct Flagged(Vec<i32>, Arc<AtomicBool>);
+
+    impl Drop for Flagged {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::SeqCst);
+        }
+    }
+
+    impl ToTensor for Flagged {
+        fn data_ptr(&self) -> *mut std::ffi::c_void {
+            self.0.as_ptr() as *mut std::ffi::c_void
+        }
+
+        fn byte_offset(&self) -> u64 {
+            0
+        }
+
+        fn device(&self) -> Device {
+            Device::CPU
+        }
+
+        fn dtype(&self) -> DataType {
+            DataType::I32
+        }
+
+        fn shape_and_strides(&self) -> ShapeAndStrides {
+            ShapeAndStrides::new_contiguous(&[2, 3])
+        }
+    }
+
+    #[test]
+    fn chained_views() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let tensor =
+            ManagedTensor::from(ManagerCtx::new(Flagged((0..6).collect(), dropped.clone())));
+
+        let view = tensor.permute(&[1, 0]).unwrap();
+        assert_eq!(view.shape(), &[3, 2]);
+        assert_eq!(view.to_contiguous::<i32>(), [0, 3, 1, 4, 2, 5]);
+        let view = view.narrow(0, 1..3).unwrap();
+        assert_eq!(view.to_contiguous::<i32>(), [1, 4, 2, 5]);
+        assert_eq!(view.byte_offset(), 4);
+        assert!(!dropped.load(Ordering::SeqCst));
+
+        // Only deleting the last view deletes the original tensor.
+        let view = ManagedTensor::from_dlpack(view.into_dlpack());
+        drop(view);
+        assert!(dropped.load(Ordering::SeqCst));
+
+        let tensor = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack());
+        let view = tensor.reshape(&[2, 3]).unwrap().flip(1).unwrap();
+        assert_eq!(view.strides(), Some([3, -1].as_slice()));
+        assert_eq!(view.byte_offset(), 8);
+        assert_eq!(view.to_contiguous::<i32>(), [2, 1, 0, 5, 4, 3]);
+        let view = view.flip(0).unwrap().flip(1).unwrap();
+        assert_eq!(view.to_contiguous::<i32>(), [3, 4, 5, 0, 1, 2]);
+
+        let tensor = ManagedTensor::from_dlpack((0i32..9).collect::<Vec<_>>().into_dlpack());
+        let view = tensor.reshape(&[3, 3]).unwrap().diagonal().unwrap();
+        assert_eq!(view.to_contiguous::<i32>(), [0, 4, 8]);
+        // Overlapping windows of 3 frames.
+        let view = view.try_as_strided(&[2, 2], &[3, 2], 4).unwrap();
+        assert_eq!(view.to_contiguous::<i32>(), [1, 3, 4, 6]);
+        assert!(matches!(
+            view.try_as_strided(&[3], &[4], 4),
+            Err(Error::Layout(LayoutError::OutsideParent))
+        ));
+
+        let tensor = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack());
+        let view = tensor.reshape(&[3, 2]).unwrap();
+        assert_eq!(view.shape(), &[3, 2]);
+        assert!(matches!(
+            view.permute(&[1, 0]).unwrap().reshape(&[6]),
+            Err(Error::Layout(LayoutError::NotContiguous))
+        ));
+        // To a scalar and back.
+        let tensor = ManagedTensor::from_dlpack(vec![7i32].into_dlpack());
+        let scalar = tensor.reshape(&[]).unwrap();
+        assert_eq!(scalar.ndim(), 0);
+        assert_eq!(scalar.reshape(&[1, 1]).unwrap().as_slice::<i32>(), &[7]);
+
+        let tensor = ManagedTensor::from_dlpack(vec![0i32; 6].into_dlpack());
+        assert!(matches!(
+            tensor.reshape(&[2, 3]).unwrap().permute(&[0, 0]),
+            Err(Error::Layout(LayoutError::InvalidAxis { axis: 0, ndim: 2 }))
+        ));
+        let tensor = ManagedTensor::from_dlpack(vec![0i32; 6].into_dlpack());
+        assert!(matches!(
+            tensor.narrow(0, 4..7),
+            Err(Error::Layout(LayoutError::OutOfRange { .. }))
+        ));
+    }
+
+    #[test]
+    fn chunks() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let tensor =
+            ManagedTensor::from(ManagerCtx::new(Flagged((0..6).collect(), dropped.clone())));
+        let chunks = tensor.chunks(1);
+        assert_eq!(chunks.len(), 2);
+        let rows: Vec<_> = chunks.map(|chunk| chunk.to_contiguous::<i32>()).collect();
+        assert_eq!(rows, [[0, 1, 2], [3, 4, 5]]);
+        // Dropping the chunks leaves the tensor alone.
+        assert!(!dropped.load(Ordering::SeqCst));
+
+        let tensor = tensor.permute(&[1, 0]).unwrap();
+        let mut chunks = tensor.chunks(2);
+        assert_eq!(chunks.len(), 2);
+        let chunk = chunks.next().unwrap();
+        assert_eq!(chunk.shape(), &[2, 2]);
+        assert_eq!(chunk.to_contiguous::<i32>(), [0, 3, 1, 4]);
+        let chunk = chunks.next().unwrap();
+        assert_eq!(chunk.shape(), &[1, 2]);
+        assert_eq!(chunk.to_contiguous::<i32>(), [2, 5]);
+        assert!(chunks.next().is_none());
+        drop(tensor);
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
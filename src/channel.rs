@@ -0,0 +1,192 @@
+//! Bounded channels moving tensors between async tasks, for pipelines whose
+//! stages run on a tokio runtime. Enabled by the `tokio` feature.
+//!
+//! Some deleters must run on a particular thread, e.g. with the GIL held or a
+//! CUDA context current. Tensors sent through a channel made by
+//! [`channel_with_returns`] go back to its [`ReturnQueue`] when dropped,
+//! whether or not they were received, and are deleted wherever the queue is
+//! drained.
+
+use core::{mem::ManuallyDrop, ops::Deref};
+
+use tokio::sync::mpsc;
+
+use crate::{ManagedTensor, SendTensor};
+
+/// Sending half of a tensor channel, which can be cloned.
+#[derive(Debug, Clone)]
+pub struct TensorSender {
+    tx: mpsc::Sender<ReceivedTensor>,
+    returns: Option<mpsc::UnboundedSender<SendTensor>>,
+}
+
+/// Receiving half of a tensor channel.
+#[derive(Debug)]
+pub struct TensorReceiver(mpsc::Receiver<ReceivedTensor>);
+
+/// Tensors dropped after being sent through a channel made by
+/// [`channel_with_returns`], waiting to be deleted.
+#[derive(Debug)]
+pub struct ReturnQueue(mpsc::UnboundedReceiver<SendTensor>);
+
+/// Channel holding up to `capacity` tensors, senders wait while it is full.
+/// Deleters run wherever the tensors are dropped.
+pub fn channel(capacity: usize) -> (TensorSender, TensorReceiver) {
+    let (tx, rx) = mpsc::channel(capacity);
+    (TensorSender { tx, returns: None }, TensorReceiver(rx))
+}
+
+/// Same as [`channel`], but dropped tensors are deleted by the returned
+/// [`ReturnQueue`].
+pub fn channel_with_returns(capacity: usize) -> (TensorSender, TensorReceiver, ReturnQueue) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let (returns, queue) = mpsc::unbounded_channel();
+    let sender = TensorSender {
+        tx,
+        returns: Some(returns),
+    };
+    (sender, TensorReceiver(rx), ReturnQueue(queue))
+}
+
+impl TensorSender {
+    /// Send `tensor`, waiting for room in the channel. Gives the tensor back
+    /// if the receiver is gone.
+    pub async fn send(&self, tensor: SendTensor) -> Result<(), SendTensor> {
+        self.tx
+            .send(self.wrap(tensor))
+            .await
+            .map_err(|err| err.0.into_inner())
+    }
+
+    /// Send `tensor` if there is room in the channel, giving it back
+    /// otherwise.
+    pub fn try_send(&self, tensor: SendTensor) -> Result<(), SendTensor> {
+        self.tx
+            .try_send(self.wrap(tensor))
+            .map_err(|err| match err {
+                mpsc::error::TrySendError::Full(tensor)
+                | mpsc::error::TrySendError::Closed(tensor) => tensor.into_inner(),
+            })
+    }
+
+    fn wrap(&self, tensor: SendTensor) -> ReceivedTensor {
+        ReceivedTensor {
+            tensor: ManuallyDrop::new(tensor),
+            returns: self.returns.clone(),
+        }
+    }
+}
+
+impl TensorReceiver {
+    /// Wait for the next tensor, or `None` once every sender is gone and the
+    /// channel is empty.
+    pub async fn recv(&mut self) -> Option<ReceivedTensor> {
+        self.0.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Option<ReceivedTensor> {
+        self.0.try_recv().ok()
+    }
+}
+
+/// Tensor that went through a channel. If the channel has a [`ReturnQueue`]
+/// it is sent back there when dropped, unless the queue is gone, in which
+/// case it is deleted in place.
+#[derive(Debug)]
+pub struct ReceivedTensor {
+    tensor: ManuallyDrop<SendTensor>,
+    returns: Option<mpsc::UnboundedSender<SendTensor>>,
+}
+
+impl ReceivedTensor {
+    /// Take the tensor out, so that it is no longer sent back when dropped.
+    pub fn into_inner(self) -> SendTensor {
+        let mut this = ManuallyDrop::new(self);
+        drop(this.returns.take());
+        unsafe { ManuallyDrop::take(&mut this.tensor) }
+    }
+}
+
+impl Deref for ReceivedTensor {
+    type Target = ManagedTensor;
+
+    fn deref(&self) -> &ManagedTensor {
+        &self.tensor
+    }
+}
+
+impl Drop for ReceivedTensor {
+    fn drop(&mut self) {
+        let tensor = unsafe { ManuallyDrop::take(&mut self.tensor) };
+        if let Some(returns) = &self.returns {
+            // Deleted in place if the queue is gone.
+            let _ = returns.send(tensor);
+        }
+    }
+}
+
+impl ReturnQueue {
+    /// Delete returned tensors as they come, until every sender and tensor
+    /// sent through the channel is gone. Spawn or await this where the
+    /// deleters have to run.
+    pub async fn run(mut self) {
+        while let Some(tensor) = self.0.recv().await {
+            drop(tensor);
+        }
+    }
+
+    /// Delete the tensors returned so far without waiting, returning how
+    /// many there were, e.g. from a thread polling while it holds the GIL.
+    pub fn delete_pending(&mut self) -> usize {
+        let mut deleted = 0;
+        while let Ok(tensor) = self.0.try_recv() {
+            drop(tensor);
+            deleted += 1;
+        }
+        deleted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn tensor(len: usize) -> SendTensor {
+        unsafe { SendTensor::new(ManagedTensor::from_dlpack(vec![0f32; len].into_dlpack())) }
+    }
+
+    #[test]
+    fn pipeline() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (tx, mut rx) = channel(1);
+            tx.send(tensor(3)).await.unwrap();
+            // Full until received.
+            assert!(tx.try_send(tensor(1)).is_err());
+            assert_eq!(rx.recv().await.unwrap().shape(), &[3]);
+            drop(tx);
+            assert!(rx.recv().await.is_none());
+
+            let (tx, mut rx, mut returns) = channel_with_returns(4);
+            tx.send(tensor(2)).await.unwrap();
+            tx.send(tensor(5)).await.unwrap();
+            let received = rx.recv().await.unwrap();
+            // Dropped on another thread, returned here.
+            thread::spawn(move || drop(received)).join().unwrap();
+            assert_eq!(returns.delete_pending(), 1);
+            let kept = rx.recv().await.unwrap().into_inner();
+            assert_eq!(kept.shape(), &[5]);
+            assert_eq!(returns.delete_pending(), 0);
+
+            // Tensors still in the channel are returned too.
+            tx.send(tensor(1)).await.unwrap();
+            drop((tx, rx));
+            returns.run().await;
+        });
+    }
+}
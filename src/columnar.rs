@@ -0,0 +1,234 @@
+//! Loading numeric columns of CSV and Parquet files into CPU tensors,
+//! through Arrow record batches. Enabled by the `csv` and `parquet` features.
+//!
+//! Every column becomes a 1-D tensor of its rows, and a selection of columns
+//! of the same type a 2-D `[rows, columns]` tensor. Columns without a DLPack
+//! dtype, such as strings or dates, are skipped, and columns with nulls can't
+//! become tensors.
+
+#[cfg(feature = "csv")]
+use std::io::{Read, Seek};
+use std::{collections::BTreeMap, io};
+
+use arrow_array::RecordBatch;
+use arrow_schema::{ArrowError, Schema};
+
+use crate::{
+    arrow::{append_values, from_arrow_data_type, invalid_data, to_io_error},
+    ffi::DataType,
+    tensor::traits::{FromDLPack, IntoDLPack},
+    ManagedTensor, OwnedTensor,
+};
+
+/// Concatenated values of column `index` of `batches`.
+fn column_data(batches: &[RecordBatch], index: usize) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    for batch in batches {
+        append_values(batch.column(index), &mut data)?;
+    }
+    Ok(data)
+}
+
+fn new_tensor(data: &[u8], shape: &[i64], dtype: DataType) -> io::Result<ManagedTensor> {
+    let tensor = OwnedTensor::from_bytes(data, shape, dtype)
+        .ok_or_else(|| invalid_data("columns don't match their number of rows"))?;
+    Ok(ManagedTensor::from_dlpack(tensor.into_dlpack()))
+}
+
+/// A 1-D tensor for every numeric column, keyed by column name.
+fn column_tensors(
+    schema: &Schema,
+    batches: &[RecordBatch],
+) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let rows = batches.iter().map(RecordBatch::num_rows).sum::<usize>() as i64;
+    let mut tensors = BTreeMap::new();
+    for (i, field) in schema.fields().iter().enumerate() {
+        let Some(dtype) = from_arrow_data_type(field.data_type()) else {
+            continue;
+        };
+        let data = column_data(batches, i)?;
+        tensors.insert(field.name().clone(), new_tensor(&data, &[rows], dtype)?);
+    }
+    Ok(tensors)
+}
+
+/// A `[rows, columns.len()]` tensor of the named columns, which must share a
+/// numeric type.
+fn column_matrix(
+    schema: &Schema,
+    batches: &[RecordBatch],
+    columns: &[&str],
+) -> io::Result<ManagedTensor> {
+    let mut dtype = None;
+    let mut data = Vec::with_capacity(columns.len());
+    for &name in columns {
+        let (index, field) = schema.column_with_name(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no column named {name}"))
+        })?;
+        let column_dtype = from_arrow_data_type(field.data_type()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "column {name} has unsupported arrow type {}",
+                    field.data_type()
+                ),
+            )
+        })?;
+        if dtype.is_some_and(|dtype| dtype != column_dtype) {
+            return Err(invalid_data(format!(
+                "column {name} doesn't have the type of {}",
+                columns[0]
+            )));
+        }
+        dtype = Some(column_dtype);
+        data.push(column_data(batches, index)?);
+    }
+    let Some(dtype) = dtype else {
+        return Err(invalid_data("no columns selected"));
+    };
+
+    let rows = batches.iter().map(RecordBatch::num_rows).sum::<usize>();
+    let itemsize = dtype.size();
+    let mut matrix = Vec::with_capacity(rows * itemsize * columns.len());
+    for row in 0..rows {
+        for column in &data {
+            matrix.extend_from_slice(&column[row * itemsize..(row + 1) * itemsize]);
+        }
+    }
+    new_tensor(&matrix, &[rows as i64, columns.len() as i64], dtype)
+}
+
+#[cfg(feature = "csv")]
+fn csv_batches<R: Read + Seek>(mut reader: R) -> io::Result<(Schema, Vec<RecordBatch>)> {
+    use arrow_csv::reader::{Format, ReaderBuilder};
+
+    let format = Format::default().with_header(true);
+    let (schema, _) = format
+        .infer_schema(&mut reader, None)
+        .map_err(to_io_error)?;
+    reader.rewind()?;
+    let batches = ReaderBuilder::new(schema.clone().into())
+        .with_format(format)
+        .build(reader)
+        .map_err(to_io_error)?
+        .collect::<Result<Vec<_>, ArrowError>>()
+        .map_err(to_io_error)?;
+    Ok((schema, batches))
+}
+
+/// Read every numeric column of a CSV file with a header row into a 1-D
+/// tensor, keyed by column name. Column types are inferred from the values:
+/// integers become `i64`, decimals `f64` and `true`/`false` bools.
+#[cfg(feature = "csv")]
+pub fn read_csv_columns<R: Read + Seek>(reader: R) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let (schema, batches) = csv_batches(reader)?;
+    column_tensors(&schema, &batches)
+}
+
+/// Read the named columns of a CSV file with a header row into a
+/// `[rows, columns.len()]` tensor. The columns must have the same inferred
+/// type.
+#[cfg(feature = "csv")]
+pub fn read_csv_matrix<R: Read + Seek>(reader: R, columns: &[&str]) -> io::Result<ManagedTensor> {
+    let (schema, batches) = csv_batches(reader)?;
+    column_matrix(&schema, &batches, columns)
+}
+
+#[cfg(feature = "parquet")]
+fn parquet_batches<R>(reader: R) -> io::Result<(Schema, Vec<RecordBatch>)>
+where
+    R: parquet::file::reader::ChunkReader + 'static,
+{
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    let builder = ParquetRecordBatchReaderBuilder::try_new(reader)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let schema = builder.schema().as_ref().clone();
+    let batches = builder
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        .collect::<Result<Vec<_>, ArrowError>>()
+        .map_err(to_io_error)?;
+    Ok((schema, batches))
+}
+
+/// Read every numeric column of a Parquet file into a 1-D tensor, keyed by
+/// column name. `reader` is usually a [`std::fs::File`].
+#[cfg(feature = "parquet")]
+pub fn read_parquet_columns<R>(reader: R) -> io::Result<BTreeMap<String, ManagedTensor>>
+where
+    R: parquet::file::reader::ChunkReader + 'static,
+{
+    let (schema, batches) = parquet_batches(reader)?;
+    column_tensors(&schema, &batches)
+}
+
+/// Read the named columns of a Parquet file into a `[rows, columns.len()]`
+/// tensor. The columns must have the same type.
+#[cfg(feature = "parquet")]
+pub fn read_parquet_matrix<R>(reader: R, columns: &[&str]) -> io::Result<ManagedTensor>
+where
+    R: parquet::file::reader::ChunkReader + 'static,
+{
+    let (schema, batches) = parquet_batches(reader)?;
+    column_matrix(&schema, &batches, columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::traits::TensorView;
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv() {
+        let text = "id,x,y,name\n1,0.5,1.5,a\n2,2.5,3.5,b\n3,4.5,5.5,c\n";
+        let tensors = read_csv_columns(io::Cursor::new(text)).unwrap();
+        assert_eq!(tensors.len(), 3);
+        assert_eq!(tensors["id"].dtype(), DataType::I64);
+        assert_eq!(tensors["id"].as_slice::<i64>(), &[1, 2, 3]);
+        assert_eq!(tensors["y"].as_slice::<f64>(), &[1.5, 3.5, 5.5]);
+
+        let matrix = read_csv_matrix(io::Cursor::new(text), &["x", "y"]).unwrap();
+        assert_eq!(matrix.shape(), &[3, 2]);
+        assert_eq!(matrix.as_slice::<f64>(), &[0.5, 1.5, 2.5, 3.5, 4.5, 5.5]);
+        let err = read_csv_matrix(io::Cursor::new(text), &["x", "id"]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_csv_matrix(io::Cursor::new(text), &["z"]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = read_csv_matrix(io::Cursor::new(text), &["name"]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet() {
+        use std::sync::Arc;
+
+        use arrow_array::{Float32Array, Int32Array};
+        use parquet::arrow::ArrowWriter;
+
+        let a: Arc<dyn arrow_array::Array> = Arc::new(Float32Array::from(vec![1.0, 2.0, 3.0]));
+        let b: Arc<dyn arrow_array::Array> = Arc::new(Float32Array::from(vec![4.0, 5.0, 6.0]));
+        let c: Arc<dyn arrow_array::Array> = Arc::new(Int32Array::from(vec![7, 8, 9]));
+        let batch = RecordBatch::try_from_iter([("a", a), ("b", b), ("c", c)]).unwrap();
+        let path = std::env::temp_dir().join(format!("dlpark-{}.parquet", std::process::id()));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        // Two row groups, which are concatenated.
+        writer.write(&batch).unwrap();
+        writer.flush().unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let tensors = read_parquet_columns(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(tensors["c"].as_slice::<i32>(), &[7, 8, 9, 7, 8, 9]);
+        let matrix = read_parquet_matrix(std::fs::File::open(&path).unwrap(), &["b", "a"]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(matrix.shape(), &[6, 2]);
+        assert_eq!(
+            &matrix.as_slice::<f32>()[..6],
+            &[4.0, 1.0, 5.0, 2.0, 6.0, 3.0]
+        );
+    }
+}
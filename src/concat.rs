@@ -0,0 +1,161 @@
+//! Joining CPU tensors into a new [`OwnedTensor`], e.g. to assemble a batch
+//! from tensors received one by one.
+
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{
+    error::invariant_violated,
+    ffi::{Device, DeviceType},
+    tensor::traits::TensorView,
+    Error, LayoutError, ManagedTensor, OwnedTensor, Result,
+};
+
+/// Join `tensors` along an existing `axis`. All other dims, the dtype and
+/// the number of dims must match.
+pub fn concat(tensors: &[&ManagedTensor], axis: usize) -> Result<OwnedTensor> {
+    join(tensors, axis, false)
+}
+
+/// Join `tensors` of the same shape along a new `axis`, e.g. `0` to make a
+/// batch.
+pub fn stack(tensors: &[&ManagedTensor], axis: usize) -> Result<OwnedTensor> {
+    join(tensors, axis, true)
+}
+
+fn join(tensors: &[&ManagedTensor], axis: usize, new_axis: bool) -> Result<OwnedTensor> {
+    let first = tensors.first().ok_or(LayoutError::NoTensors)?;
+    let (ndim, dtype) = (first.ndim(), first.dtype());
+    let out_ndim = ndim + new_axis as usize;
+    if axis >= out_ndim {
+        return Err(LayoutError::InvalidAxis {
+            axis,
+            ndim: out_ndim,
+        }
+        .into());
+    }
+    let mut shape = first.shape().to_vec();
+    if new_axis {
+        shape.insert(axis, 0);
+    } else {
+        shape[axis] = 0;
+    }
+    for tensor in tensors {
+        if tensor.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: tensor.device(),
+            });
+        }
+        if tensor.dtype() != dtype {
+            return Err(Error::DtypeMismatch {
+                expected: dtype,
+                found: tensor.dtype(),
+            });
+        }
+        tensor.check_supported()?;
+        if tensor.ndim() != ndim {
+            return Err(LayoutError::NdimMismatch {
+                expected: ndim,
+                found: tensor.ndim(),
+            }
+            .into());
+        }
+        for (i, (&dim, &expected)) in tensor.shape().iter().zip(first.shape()).enumerate() {
+            if dim != expected && (new_axis || i != axis) {
+                return Err(LayoutError::ShapeMismatch {
+                    axis: i,
+                    expected,
+                    found: dim,
+                }
+                .into());
+            }
+        }
+        shape[axis] += if new_axis { 1 } else { tensor.shape()[axis] };
+    }
+
+    let mut out = OwnedTensor::new_zeroed(&shape, dtype)
+        .ok_or_else(|| invariant_violated("invalid shape of joined tensors"))?;
+    if out.as_bytes().is_empty() {
+        return Ok(out);
+    }
+    // Every input contributes one contiguous chunk per index of the axes
+    // before `axis`.
+    let outer = shape[..axis].iter().product::<i64>() as usize;
+    let inputs: Vec<Cow<[u8]>> = tensors.iter().map(|t| t.to_contiguous_bytes()).collect();
+    let mut dst = out.as_bytes_mut();
+    for i in 0..outer {
+        for input in &inputs {
+            let len = input.len() / outer;
+            let (chunk, rest) = dst.split_at_mut(len);
+            chunk.copy_from_slice(&input[i * len..][..len]);
+            dst = rest;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn join_tensors() {
+        let a = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap();
+        let b = ManagedTensor::from_dlpack(vec![6i32, 7, 8, 9, 10, 11].into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap();
+
+        let joined = ManagedTensor::from_dlpack(concat(&[&a, &b], 0).unwrap().into_dlpack());
+        assert_eq!(joined.shape(), &[4, 3]);
+        assert_eq!(joined.as_slice::<i32>(), (0..12).collect::<Vec<_>>());
+
+        let joined = ManagedTensor::from_dlpack(concat(&[&a, &b], 1).unwrap().into_dlpack());
+        assert_eq!(joined.shape(), &[2, 6]);
+        assert_eq!(
+            joined.as_slice::<i32>(),
+            [0, 1, 2, 6, 7, 8, 3, 4, 5, 9, 10, 11]
+        );
+
+        let joined = ManagedTensor::from_dlpack(stack(&[&a, &b], 0).unwrap().into_dlpack());
+        assert_eq!(joined.shape(), &[2, 2, 3]);
+        assert_eq!(joined.as_slice::<i32>(), (0..12).collect::<Vec<_>>());
+
+        // Strided inputs are copied following their strides.
+        let t = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap()
+            .permute(&[1, 0])
+            .unwrap();
+        let joined = ManagedTensor::from_dlpack(stack(&[&t, &t], 2).unwrap().into_dlpack());
+        assert_eq!(joined.shape(), &[3, 2, 2]);
+        assert_eq!(
+            joined.as_slice::<i32>(),
+            [0, 0, 3, 3, 1, 1, 4, 4, 2, 2, 5, 5]
+        );
+
+        assert!(matches!(
+            stack(&[&a, &t], 0),
+            Err(Error::Layout(LayoutError::ShapeMismatch {
+                axis: 0,
+                expected: 2,
+                found: 3
+            }))
+        ));
+        assert!(matches!(
+            concat(&[&a, &b], 2),
+            Err(Error::Layout(LayoutError::InvalidAxis { axis: 2, ndim: 2 }))
+        ));
+        let f = ManagedTensor::from_dlpack(vec![0f32; 6].into_dlpack());
+        assert!(matches!(
+            concat(&[&a, &f], 0),
+            Err(Error::DtypeMismatch { .. })
+        ));
+        assert!(matches!(
+            stack(&[], 0),
+            Err(Error::Layout(LayoutError::NoTensors))
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+//! Sharing CUDA tensors between processes through CUDA IPC memory handles.
+//!
+//! This mirrors how PyTorch's multiprocessing shares GPU storage: the producer
+//! calls [`ManagedTensor::to_cuda_ipc`] to get a [`CudaIpcHandle`] for the
+//! allocation backing a tensor and sends it to another process, which maps the
+//! same device memory with [`CudaIpcTensor::open`]. No data is copied.
+//!
+//! The producer must keep its tensor alive until every consumer has dropped
+//! its [`CudaIpcTensor`]. A handle cannot be opened by the process that
+//! created it.
+
+use std::{
+    ffi::c_void,
+    io::{self, Read, Write},
+    ptr,
+};
+
+use cudarc::driver::sys::{self, CUcontext, CUdevice, CUdeviceptr, CUipcMemHandle};
+
+use crate::{
+    endian::Endian,
+    ffi::{DataType, Device, DeviceType},
+    tensor::traits::{TensorView, ToTensor},
+    wire::WireHeader,
+    ManagedTensor, ShapeAndStrides,
+};
+
+/// Size of an opaque CUDA IPC memory handle.
+pub const CUDA_IPC_HANDLE_SIZE: usize = 64;
+
+pub(crate) fn check(result: sys::CUresult) -> io::Result<()> {
+    result.result().map_err(io::Error::other)
+}
+
+/// Retain the primary context of `device_id` and make it current for the
+/// duration of `f`.
+pub(crate) unsafe fn with_device<T>(
+    device_id: i32,
+    f: impl FnOnce(CUdevice) -> io::Result<T>,
+) -> io::Result<T> {
+    if !sys::is_culib_present() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "cuda driver library not found",
+        ));
+    }
+    check(sys::cuInit(0))?;
+    let mut device = 0;
+    check(sys::cuDeviceGet(&mut device, device_id))?;
+    let mut ctx = ptr::null_mut();
+    check(sys::cuDevicePrimaryCtxRetain(&mut ctx, device))?;
+    let result = check(sys::cuCtxPushCurrent_v2(ctx)).and_then(|_| {
+        let result = f(device);
+        let mut popped: CUcontext = ptr::null_mut();
+        sys::cuCtxPopCurrent_v2(&mut popped);
+        result
+    });
+    sys::cuDevicePrimaryCtxRelease_v2(device);
+    result
+}
+
+/// Everything another process needs to map a CUDA tensor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CudaIpcHandle {
+    /// Opaque handle of the allocation containing the tensor.
+    pub handle: [u8; CUDA_IPC_HANDLE_SIZE],
+    /// Offset in bytes of the first element from the start of the allocation.
+    pub offset: u64,
+    pub device: Device,
+    pub dtype: DataType,
+    pub shape: Vec<i64>,
+    pub strides: Option<Vec<i64>>,
+}
+
+impl CudaIpcHandle {
+    /// Encode the handle and offset followed by a [wire](crate::wire) header
+    /// without payload.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.handle)?;
+        writer.write_all(&self.offset.to_le_bytes())?;
+        WireHeader {
+            flags: 0,
+            dtype: self.dtype,
+            device: self.device,
+            shape: self.shape.clone(),
+            strides: self.strides.clone(),
+            axis_names: None,
+            endian: Endian::NATIVE,
+            compressed: false,
+            checksum: false,
+            payload_len: 0,
+        }
+        .write_to(writer)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut handle = [0u8; CUDA_IPC_HANDLE_SIZE];
+        reader.read_exact(&mut handle)?;
+        let mut offset = [0u8; 8];
+        reader.read_exact(&mut offset)?;
+        let header = WireHeader::read_from(reader)?;
+        if header.device.device_type != DeviceType::Cuda {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "cuda ipc handle for a non-cuda device",
+            ));
+        }
+        Ok(Self {
+            handle,
+            offset: u64::from_le_bytes(offset),
+            device: header.device,
+            dtype: header.dtype,
+            shape: header.shape,
+            strides: header.strides,
+        })
+    }
+}
+
+impl ManagedTensor {
+    /// Get an IPC handle for the device memory of a CUDA tensor. The tensor
+    /// must stay alive while other processes use the handle.
+    pub fn to_cuda_ipc(&self) -> io::Result<CudaIpcHandle> {
+        let device = self.device();
+        if device.device_type != DeviceType::Cuda {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cuda tensors can be shared through cuda ipc",
+            ));
+        }
+        let dptr = self.data_ptr().addr() as CUdeviceptr + self.byte_offset();
+        let (handle, base) = unsafe {
+            with_device(device.device_id, |_| {
+                let mut base = 0;
+                let mut size = 0;
+                check(sys::cuMemGetAddressRange_v2(&mut base, &mut size, dptr))?;
+                let mut handle: CUipcMemHandle = std::mem::zeroed();
+                check(sys::cuIpcGetMemHandle(&mut handle, base))?;
+                Ok((handle, base))
+            })?
+        };
+        Ok(CudaIpcHandle {
+            handle: handle.reserved.map(|b| b as u8),
+            offset: dptr - base,
+            device,
+            dtype: self.dtype(),
+            shape: self.shape().to_vec(),
+            strides: self.strides().map(<[i64]>::to_vec),
+        })
+    }
+}
+
+/// A CUDA tensor mapped from another process, closed again when dropped.
+#[derive(Debug)]
+pub struct CudaIpcTensor {
+    base: CUdeviceptr,
+    device: CUdevice,
+    handle: CudaIpcHandle,
+}
+
+unsafe impl Send for CudaIpcTensor {}
+unsafe impl Sync for CudaIpcTensor {}
+
+impl CudaIpcTensor {
+    /// Map the allocation referred to by `handle` into this process.
+    pub fn open(handle: &CudaIpcHandle) -> io::Result<Self> {
+        let mut raw: CUipcMemHandle = unsafe { std::mem::zeroed() };
+        raw.reserved = handle.handle.map(|b| b as _);
+        let (base, device) = unsafe {
+            with_device(handle.device.device_id, |device| {
+                let mut base = 0;
+                check(sys::cuIpcOpenMemHandle_v2(
+                    &mut base,
+                    raw,
+                    sys::CUipcMem_flags::CU_IPC_MEM_LAZY_ENABLE_PEER_ACCESS as u32,
+                ))?;
+                // Keep the context alive for as long as the mapping.
+                let mut ctx = ptr::null_mut();
+                if let Err(err) = check(sys::cuDevicePrimaryCtxRetain(&mut ctx, device)) {
+                    sys::cuIpcCloseMemHandle(base);
+                    return Err(err);
+                }
+                Ok((base, device))
+            })?
+        };
+        Ok(Self {
+            base,
+            device,
+            handle: handle.clone(),
+        })
+    }
+
+    pub fn handle(&self) -> &CudaIpcHandle {
+        &self.handle
+    }
+}
+
+impl Drop for CudaIpcTensor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = with_device(self.handle.device.device_id, |_| {
+                check(sys::cuIpcCloseMemHandle(self.base))
+            });
+            sys::cuDevicePrimaryCtxRelease_v2(self.device);
+        }
+    }
+}
+
+impl ToTensor for CudaIpcTensor {
+    fn data_ptr(&self) -> *mut c_void {
+        // Only ever dereferenced by the device.
+        ptr::without_provenance_mut(self.base as usize)
+    }
+
+    fn byte_offset(&self) -> u64 {
+        self.handle.offset
+    }
+
+    fn device(&self) -> Device {
+        self.handle.device
+    }
+
+    fn dtype(&self) -> DataType {
+        self.handle.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        match &self.handle.strides {
+            Some(strides) => ShapeAndStrides::new_with_strides(&self.handle.shape, strides),
+            None => ShapeAndStrides::new_contiguous_with_strides(&self.handle.shape),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handle_round_trip() {
+        let handle = CudaIpcHandle {
+            handle: [7; CUDA_IPC_HANDLE_SIZE],
+            offset: 256,
+            device: Device::cuda(1),
+            dtype: DataType::F32,
+            shape: vec![2, 3],
+            strides: Some(vec![1, 2]),
+        };
+        let mut buf = Vec::new();
+        handle.write_to(&mut buf).unwrap();
+        assert_eq!(
+            CudaIpcHandle::read_from(&mut buf.as_slice()).unwrap(),
+            handle
+        );
+    }
+}
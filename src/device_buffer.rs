@@ -0,0 +1,139 @@
+//! Integration point for accelerator crates: implement [`DeviceBuffer`] for
+//! a device allocation, and export it through a [`BufferTensor`], without a
+//! dedicated feature in this crate.
+
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+use crate::{
+    ffi::{DataType, Device},
+    tensor::traits::ToTensor,
+    validate::ValidationError,
+    ShapeAndStrides,
+};
+
+/// Memory on some device, kept alive for as long as the value is.
+pub trait DeviceBuffer {
+    /// Start of the buffer, in the address space of its device.
+    fn device_ptr(&self) -> *mut c_void;
+    /// Size of the buffer in bytes.
+    fn byte_len(&self) -> usize;
+    fn device(&self) -> Device;
+}
+
+/// Row-major tensor over a [`DeviceBuffer`], which it owns, so that the
+/// buffer is dropped once the exported tensor is deleted. Buffers don't know
+/// their shape and dtype, so they get a wrapper rather than a blanket
+/// [`ToTensor`] impl, which would also overlap with the existing ones.
+#[derive(Debug)]
+pub struct BufferTensor<B> {
+    buffer: B,
+    dtype: DataType,
+    shape: Vec<i64>,
+}
+
+impl<B: DeviceBuffer> BufferTensor<B> {
+    /// View the start of `buffer` as elements of `shape` and `dtype`, which
+    /// must fit in it.
+    pub fn new(buffer: B, shape: &[i64], dtype: DataType) -> Result<Self, ValidationError> {
+        let mut len = dtype.size();
+        for (axis, &dim) in shape.iter().enumerate() {
+            let dim =
+                usize::try_from(dim).map_err(|_| ValidationError::NegativeDim { axis, dim })?;
+            len = len
+                .checked_mul(dim)
+                .ok_or(ValidationError::TooManyElements)?;
+        }
+        if len > buffer.byte_len() {
+            return Err(ValidationError::OutOfBounds {
+                buffer_len: buffer.byte_len(),
+            });
+        }
+        Ok(Self {
+            buffer,
+            dtype,
+            shape: shape.to_vec(),
+        })
+    }
+
+    pub fn buffer(&self) -> &B {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> B {
+        self.buffer
+    }
+}
+
+impl<B: DeviceBuffer> ToTensor for BufferTensor<B> {
+    fn data_ptr(&self) -> *mut c_void {
+        self.buffer.device_ptr()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        self.buffer.device()
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::prelude::*;
+
+    /// Stand-in for the allocation of a GPU crate.
+    struct FakeBuffer(Vec<u8>, Rc<Cell<bool>>);
+
+    impl DeviceBuffer for FakeBuffer {
+        fn device_ptr(&self) -> *mut c_void {
+            self.0.as_ptr().cast_mut().cast()
+        }
+
+        fn byte_len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn device(&self) -> Device {
+            Device::cuda(1)
+        }
+    }
+
+    impl Drop for FakeBuffer {
+        fn drop(&mut self) {
+            self.1.set(true);
+        }
+    }
+
+    #[test]
+    fn device_buffer_export() {
+        let freed = Rc::new(Cell::new(false));
+        let buffer = FakeBuffer(vec![0; 32], freed.clone());
+        let ptr = buffer.device_ptr();
+        let tensor = BufferTensor::new(buffer, &[2, 3], DataType::F32).unwrap();
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.device(), Device::cuda(1));
+        assert_eq!(tensor.data_ptr(), ptr);
+        assert_eq!(tensor.shape(), &[2, 3]);
+        drop(tensor);
+        assert!(freed.get());
+
+        let buffer = FakeBuffer(vec![0; 16], freed.clone());
+        assert!(matches!(
+            BufferTensor::new(buffer, &[2, 3], DataType::F32),
+            Err(ValidationError::OutOfBounds { buffer_len: 16 })
+        ));
+    }
+}
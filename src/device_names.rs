@@ -0,0 +1,113 @@
+//! Human-readable names of device types, shown by [`DisplayDevice`] in tensor
+//! summaries, errors and tracing events. The device types of DLPack have
+//! built-in names, and with the `std` feature vendor extensions can register
+//! theirs, e.g. when their backend is loaded, so that logs from
+//! heterogeneous clusters name every device.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::{collections::BTreeMap, sync::RwLock};
+
+use crate::ffi::{Device, DeviceType};
+
+#[cfg(feature = "std")]
+static NAMES: RwLock<BTreeMap<i32, &'static str>> = RwLock::new(BTreeMap::new());
+
+/// Name `device_type` in logs from now on, returning the name it replaces.
+/// Built-in names can be replaced too.
+#[cfg(feature = "std")]
+pub fn register_device_name(device_type: i32, name: &'static str) -> Option<&'static str> {
+    let previous = NAMES
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(device_type, name);
+    previous.or_else(|| builtin_name(device_type))
+}
+
+/// Name of a raw device type, registered or built-in, or `None` if it has
+/// neither.
+pub fn device_type_name(device_type: i32) -> Option<&'static str> {
+    #[cfg(feature = "std")]
+    if let Some(&name) = NAMES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&device_type)
+    {
+        return Some(name);
+    }
+    builtin_name(device_type)
+}
+
+fn builtin_name(device_type: i32) -> Option<&'static str> {
+    let name = match DeviceType::try_from(device_type).ok()? {
+        DeviceType::Cpu => "cpu",
+        DeviceType::Cuda => "cuda",
+        DeviceType::CudaHost => "cuda_host",
+        DeviceType::OpenCl => "opencl",
+        DeviceType::Vulkan => "vulkan",
+        DeviceType::Metal => "metal",
+        DeviceType::Vpi => "vpi",
+        DeviceType::Rocm => "rocm",
+        DeviceType::RocmHost => "rocm_host",
+        DeviceType::ExtDev => "ext_dev",
+        DeviceType::CudaManaged => "cuda_managed",
+        DeviceType::OneApi => "oneapi",
+        DeviceType::WebGpu => "webgpu",
+        DeviceType::Hexagon => "hexagon",
+        #[cfg(feature = "dlpack-1-1")]
+        DeviceType::Maia => "maia",
+    };
+    Some(name)
+}
+
+/// Name of a raw device type, falling back to `device<id>`.
+pub(crate) struct TypeName(pub(crate) i32);
+
+impl fmt::Display for TypeName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match device_type_name(self.0) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "device{}", self.0),
+        }
+    }
+}
+
+/// Displays a device as `<name>:<id>`, e.g. `cuda:0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayDevice(pub Device);
+
+impl fmt::Display for DisplayDevice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            TypeName(self.0.device_type as i32),
+            self.0.device_id
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(DisplayDevice(Device::cuda(1)).to_string(), "cuda:1");
+        assert_eq!(device_type_name(5), None);
+        assert_eq!(TypeName(5).to_string(), "device5");
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(register_device_name(5, "npu"), None);
+            assert_eq!(TypeName(5).to_string(), "npu");
+            assert_eq!(register_device_name(5, "npu2"), Some("npu"));
+            // A device type no other test uses.
+            assert_eq!(register_device_name(12, "fpga"), Some("ext_dev"));
+            let device = Device {
+                device_type: DeviceType::ExtDev,
+                device_id: 3,
+            };
+            assert_eq!(DisplayDevice(device).to_string(), "fpga:3");
+        }
+    }
+}
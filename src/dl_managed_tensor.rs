@@ -1,7 +1,7 @@
 use crate::{ffi, tensor::traits::TensorView};
 
 impl TensorView for ffi::DLManagedTensor {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
         self.dl_tensor.data_ptr()
     }
 
@@ -6,7 +6,7 @@ use crate::{
 // TODO: DLManagedTensor may be deprecated in the future.
 // FIXME: it's unsafe to access it when not initialized
 impl TensorView for ffi::DLManagedTensorVersioned {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
         self.dl_tensor.data_ptr()
     }
 
@@ -33,4 +33,8 @@ impl TensorView for ffi::DLManagedTensorVersioned {
     fn ndim(&self) -> usize {
         self.dl_tensor.ndim()
     }
+
+    fn flags(&self) -> u64 {
+        self.flags
+    }
 }
@@ -1,19 +1,25 @@
 use crate::{ffi, tensor::traits::TensorView};
 
 impl TensorView for ffi::DLTensor {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
         self.data
     }
 
     fn shape(&self) -> &[i64] {
-        unsafe { std::slice::from_raw_parts(self.shape, self.ndim()) }
+        // Scalars may have a NULL shape.
+        if self.ndim == 0 {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.shape, self.ndim()) }
     }
 
     fn strides(&self) -> Option<&[i64]> {
         if self.strides.is_null() {
             None
+        } else if self.ndim == 0 {
+            Some(&[])
         } else {
-            Some(unsafe { std::slice::from_raw_parts(self.strides, self.ndim()) })
+            Some(unsafe { core::slice::from_raw_parts(self.strides, self.ndim()) })
         }
     }
 
@@ -0,0 +1,65 @@
+use crate::ffi::{DataType, DataTypeCode};
+
+/// Byte order of serialized tensor data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    /// Byte order of the host.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Self = Self::Little;
+    /// Byte order of the host.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Self = Self::Big;
+
+    pub fn is_native(self) -> bool {
+        self == Self::NATIVE
+    }
+}
+
+/// Reverse the byte order of every scalar in `data`, which holds row-major
+/// elements of `dtype`. Complex numbers and vector lanes are swapped per
+/// component.
+pub fn swap_byte_order(data: &mut [u8], dtype: DataType) {
+    let bits = match dtype.code {
+        DataTypeCode::Complex => dtype.bits / 2,
+        _ => dtype.bits,
+    };
+    if bits <= 8 || bits % 8 != 0 {
+        return;
+    }
+    for scalar in data.chunks_exact_mut(bits as usize / 8) {
+        scalar.reverse();
+    }
+}
+
+/// Convert `data` from `from` byte order into `to` byte order in place.
+pub fn convert_byte_order(data: &mut [u8], dtype: DataType, from: Endian, to: Endian) {
+    if from != to {
+        swap_byte_order(data, dtype);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_scalars() {
+        let mut data = 0x0102_0304u32.to_le_bytes();
+        swap_byte_order(&mut data, DataType::U32);
+        assert_eq!(u32::from_be_bytes(data), 0x0102_0304);
+
+        // Complex numbers are swapped per component.
+        let mut data = [1, 2, 3, 4, 5, 6, 7, 8];
+        swap_byte_order(&mut data, (DataTypeCode::Complex, 64, 1).into());
+        assert_eq!(data, [4, 3, 2, 1, 8, 7, 6, 5]);
+
+        let mut data = [1, 2];
+        swap_byte_order(&mut data, DataType::U8);
+        assert_eq!(data, [1, 2]);
+    }
+}
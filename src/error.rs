@@ -0,0 +1,266 @@
+use core::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{
+    device_names::{DisplayDevice, TypeName},
+    ffi::{DataType, Device, InvalidDataType},
+    validate::{UnsupportedLayout, ValidationError},
+    LayoutError,
+};
+
+/// Errors of the fallible APIs of this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Layout(LayoutError),
+    DtypeMismatch {
+        expected: DataType,
+        found: DataType,
+    },
+    DeviceMismatch {
+        expected: Device,
+        found: Device,
+    },
+    /// The data isn't aligned to this many bytes as the element type requires.
+    Misaligned {
+        align: usize,
+    },
+    /// An alignment asked for isn't a power of two.
+    InvalidAlignment(usize),
+    /// No `plugin::Allocator` is registered for the device type.
+    NoAllocator(Device),
+    /// The tensor was exported read-only, see `TensorView::is_read_only`.
+    ReadOnly,
+    /// A Python object is not a usable DLPack capsule.
+    Capsule(&'static str),
+    Validation(ValidationError),
+    DataType(InvalidDataType),
+    UnsupportedLayout(UnsupportedLayout),
+    /// An internal invariant of this crate was broken, returned instead of
+    /// panicking under [`InvariantPolicy::Error`].
+    Invariant(&'static str),
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// What the fallible APIs of this crate do when they find one of its internal
+/// invariants broken, i.e. a bug: panic, to catch it early during
+/// development, or return [`Error::Invariant`], to keep a long-running
+/// service, such as one embedding Python, up.
+///
+/// Infallible APIs have no error to return, and panic whatever the policy
+/// when their caller breaks a documented precondition, e.g.
+/// [`ShapeAndStrides::new_with_strides`](crate::ShapeAndStrides::new_with_strides),
+/// [`ManagedTensor::as_slice`](crate::ManagedTensor::as_slice),
+/// [`ManagedTensor::to_contiguous`](crate::ManagedTensor::to_contiguous) and
+/// [`ManagedTensor::new`](crate::ManagedTensor::new) with the `defensive`
+/// feature. Services should call their fallible counterparts instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantPolicy {
+    Panic,
+    Error,
+}
+
+static INVARIANT_POLICY: AtomicU8 = AtomicU8::new(if cfg!(debug_assertions) {
+    InvariantPolicy::Panic as u8
+} else {
+    InvariantPolicy::Error as u8
+});
+
+impl InvariantPolicy {
+    /// The policy of the process, [`InvariantPolicy::Panic`] in debug builds
+    /// and [`InvariantPolicy::Error`] in release builds unless set.
+    pub fn get() -> Self {
+        if INVARIANT_POLICY.load(Ordering::Relaxed) == Self::Panic as u8 {
+            Self::Panic
+        } else {
+            Self::Error
+        }
+    }
+
+    /// Make this the policy of the process.
+    pub fn set(self) {
+        INVARIANT_POLICY.store(self as u8, Ordering::Relaxed);
+    }
+
+    fn report(self, msg: &'static str) -> Error {
+        match self {
+            Self::Panic => panic!("{msg}"),
+            Self::Error => Error::Invariant(msg),
+        }
+    }
+}
+
+/// Report the broken invariant `msg` according to the [`InvariantPolicy`],
+/// panicking or returning the error to propagate.
+pub(crate) fn invariant_violated(msg: &'static str) -> Error {
+    InvariantPolicy::get().report(msg)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Layout(err) => err.fmt(f),
+            Self::DtypeMismatch { expected, found } => {
+                write!(f, "expected dtype {expected:?}, found {found:?}")
+            }
+            Self::DeviceMismatch { expected, found } => {
+                write!(
+                    f,
+                    "expected device {}, found {}",
+                    DisplayDevice(*expected),
+                    DisplayDevice(*found)
+                )
+            }
+            Self::Misaligned { align } => write!(f, "data is not aligned to {align} bytes"),
+            Self::InvalidAlignment(align) => {
+                write!(f, "alignment {align} is not a power of two")
+            }
+            Self::NoAllocator(device) => {
+                write!(
+                    f,
+                    "no allocator registered for {}",
+                    TypeName(device.device_type as i32)
+                )
+            }
+            Self::ReadOnly => write!(f, "tensor is read-only"),
+            Self::Capsule(msg) => write!(f, "invalid capsule: {msg}"),
+            Self::Validation(err) => err.fmt(f),
+            Self::DataType(err) => err.fmt(f),
+            Self::UnsupportedLayout(err) => err.fmt(f),
+            Self::Invariant(msg) => write!(f, "broken invariant: {msg}"),
+            #[cfg(feature = "std")]
+            Self::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Layout(err) => Some(err),
+            Self::Validation(err) => Some(err),
+            Self::DataType(err) => Some(err),
+            Self::UnsupportedLayout(err) => Some(err),
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<LayoutError> for Error {
+    fn from(err: LayoutError) -> Self {
+        Self::Layout(err)
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(err: ValidationError) -> Self {
+        Self::Validation(err)
+    }
+}
+
+impl From<InvalidDataType> for Error {
+    fn from(err: InvalidDataType) -> Self {
+        Self::DataType(err)
+    }
+}
+
+impl From<UnsupportedLayout> for Error {
+    fn from(err: UnsupportedLayout) -> Self {
+        Self::UnsupportedLayout(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            Error::DeviceMismatch { .. } | Error::NoAllocator(_) | Error::UnsupportedLayout(_) => {
+                io::Error::new(io::ErrorKind::Unsupported, err)
+            }
+            Error::ReadOnly => io::Error::new(io::ErrorKind::PermissionDenied, err),
+            Error::Invariant(_) => io::Error::other(err),
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(feature = "pyo3")]
+impl From<Error> for pyo3::PyErr {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err.into(),
+            Error::DtypeMismatch { .. } | Error::Capsule(_) => {
+                pyo3::exceptions::PyTypeError::new_err(err.to_string())
+            }
+            Error::Invariant(_) => pyo3::exceptions::PyRuntimeError::new_err(err.to_string()),
+            err => pyo3::exceptions::PyValueError::new_err(err.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "napi")]
+impl From<Error> for napi::Error {
+    fn from(err: Error) -> Self {
+        napi::Error::from_reason(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let err = Error::from(ValidationError::NullShape);
+        assert!(matches!(err, Error::Validation(ValidationError::NullShape)));
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(io::Error::from(err).kind(), io::ErrorKind::InvalidData);
+
+            let err = Error::from(io::Error::new(io::ErrorKind::UnexpectedEof, "eof"));
+            assert_eq!(io::Error::from(err).kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        let err = Error::DtypeMismatch {
+            expected: DataType::F32,
+            found: DataType::I64,
+        };
+        assert!(err.to_string().starts_with("expected dtype"));
+    }
+
+    #[test]
+    fn invariant_policy() {
+        // No test sets the policy of the process, which tests share.
+        let default = if cfg!(debug_assertions) {
+            InvariantPolicy::Panic
+        } else {
+            InvariantPolicy::Error
+        };
+        assert_eq!(InvariantPolicy::get(), default);
+        let err = InvariantPolicy::Error.report("shape overflows");
+        assert!(matches!(err, Error::Invariant("shape overflows")));
+        assert_eq!(err.to_string(), "broken invariant: shape overflows");
+        #[cfg(feature = "std")]
+        assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Other);
+        assert!(
+            std::panic::catch_unwind(|| InvariantPolicy::Panic.report("shape overflows")).is_err()
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Tensors shared by the unit tests.
+
+// Each feature set only uses some of them.
+#![allow(dead_code)]
+
+use crate::{
+    ffi::{DataType, Device},
+    tensor::traits::InferDtype,
+    ShapeAndStrides, ToTensor,
+};
+
+/// `data` viewed with `shape` and `strides`, in elements, on the CPU unless
+/// another device is claimed.
+pub(crate) struct Strided<A> {
+    data: Vec<A>,
+    shape: Vec<i64>,
+    strides: Vec<i64>,
+    device: Device,
+}
+
+impl<A> Strided<A> {
+    pub(crate) fn new(data: Vec<A>, shape: &[i64], strides: &[i64]) -> Self {
+        Self {
+            data,
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            device: Device::CPU,
+        }
+    }
+
+    /// Claim the data is on `device`, for the checks of APIs that don't
+    /// support it.
+    pub(crate) fn on(self, device: Device) -> Self {
+        Self { device, ..self }
+    }
+}
+
+/// `data` as a contiguous 1-D tensor on a GPU.
+pub(crate) fn on_gpu<A>(data: Vec<A>) -> Strided<A> {
+    let len = data.len() as i64;
+    Strided::new(data, &[len], &[1]).on(Device::cuda(0))
+}
+
+/// A [2, 3] tensor viewed as its [3, 2] transpose.
+pub(crate) fn transposed<A>(data: Vec<A>) -> Strided<A> {
+    Strided::new(data, &[3, 2], &[1, 3])
+}
+
+impl<A: InferDtype> ToTensor for Strided<A> {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.data.as_ptr() as *mut std::ffi::c_void
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        self.device
+    }
+
+    fn dtype(&self) -> DataType {
+        A::infer_dtype()
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_with_strides(&self.shape, &self.strides)
+    }
+}
@@ -0,0 +1,268 @@
+//! Tensors as FlatBuffers, following the schema in `include/dlpark.fbs`, for
+//! latency-sensitive systems that view received or memory-mapped messages
+//! in place. Enabled by the `flatbuffers` feature.
+//!
+//! Decoding only verifies the message and copies the shape and strides, a
+//! [`FlatTensor`] exports its payload without copying it. The payload is
+//! aligned to 64 bytes from the start of the message, so it is aligned for
+//! any dtype as long as the message is.
+
+use std::{io, ops::Range};
+
+use flatbuffers::{
+    FlatBufferBuilder, Follow, ForwardsUOffset, InvalidFlatbuffer, Push, PushAlignment, Table,
+    VOffsetT, Vector, Verifiable, Verifier,
+};
+
+use crate::{
+    endian::Endian,
+    ffi::{DataType, DataTypeCode, Device, DeviceType},
+    tensor::traits::{TensorView, ToTensor},
+    utils::byte_span,
+    ManagedTensor, ShapeAndStrides,
+};
+
+pub const FLATBUFFER_IDENTIFIER: &str = "DLFB";
+
+const VT_DTYPE_CODE: VOffsetT = 4;
+const VT_DTYPE_BITS: VOffsetT = 6;
+const VT_DTYPE_LANES: VOffsetT = 8;
+const VT_DEVICE_TYPE: VOffsetT = 10;
+const VT_DEVICE_ID: VOffsetT = 12;
+const VT_SHAPE: VOffsetT = 14;
+const VT_STRIDES: VOffsetT = 16;
+const VT_PAYLOAD: VOffsetT = 18;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Payload byte pushed with the `force_align` of the schema.
+#[repr(transparent)]
+struct PayloadByte(u8);
+
+impl Push for PayloadByte {
+    type Output = u8;
+
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        dst[0] = self.0;
+    }
+
+    fn alignment() -> PushAlignment {
+        PushAlignment::new(64)
+    }
+}
+
+/// The `Tensor` table, as flatc would generate it.
+struct TensorTable<'a>(Table<'a>);
+
+impl<'a> Follow<'a> for TensorTable<'a> {
+    type Inner = Self;
+
+    unsafe fn follow(buf: &'a [u8], loc: usize) -> Self {
+        Self(Table::new(buf, loc))
+    }
+}
+
+impl Verifiable for TensorTable<'_> {
+    fn run_verifier(v: &mut Verifier, pos: usize) -> Result<(), InvalidFlatbuffer> {
+        v.visit_table(pos)?
+            .visit_field::<u8>("dtype_code", VT_DTYPE_CODE, false)?
+            .visit_field::<u8>("dtype_bits", VT_DTYPE_BITS, false)?
+            .visit_field::<u16>("dtype_lanes", VT_DTYPE_LANES, false)?
+            .visit_field::<i32>("device_type", VT_DEVICE_TYPE, false)?
+            .visit_field::<i32>("device_id", VT_DEVICE_ID, false)?
+            .visit_field::<ForwardsUOffset<Vector<i64>>>("shape", VT_SHAPE, false)?
+            .visit_field::<ForwardsUOffset<Vector<i64>>>("strides", VT_STRIDES, false)?
+            .visit_field::<ForwardsUOffset<Vector<u8>>>("payload", VT_PAYLOAD, false)?
+            .finish();
+        Ok(())
+    }
+}
+
+impl<'a> TensorTable<'a> {
+    // Safe since the table was verified.
+    fn scalar<T: Follow<'a, Inner = T> + 'a>(&self, slot: VOffsetT, default: T) -> T {
+        unsafe { self.0.get::<T>(slot, None) }.unwrap_or(default)
+    }
+
+    fn vector<T: Follow<'a> + 'a>(&self, slot: VOffsetT) -> Option<Vector<'a, T>> {
+        unsafe { self.0.get::<ForwardsUOffset<Vector<'a, T>>>(slot, None) }
+    }
+}
+
+impl ManagedTensor {
+    /// Encode a CPU tensor as a FlatBuffer. The payload is always written in
+    /// row-major order.
+    pub fn to_flatbuffer(&self) -> io::Result<Vec<u8>> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be encoded",
+            ));
+        }
+        if !Endian::Little.is_native() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "flatbuffer payloads are little-endian",
+            ));
+        }
+        self.check_supported()?;
+        let data = self.to_contiguous_bytes();
+        // Same layout as `u8`, which `PayloadByte` wraps.
+        let data = unsafe { &*(&*data as *const [u8] as *const [PayloadByte]) };
+        let mut builder = FlatBufferBuilder::with_capacity(data.len() + 256);
+        let payload = builder.create_vector(data);
+        let shape = builder.create_vector(self.shape());
+        let table = builder.start_table();
+        let dtype = self.dtype();
+        let device = self.device();
+        builder.push_slot_always(VT_PAYLOAD, payload);
+        builder.push_slot_always(VT_SHAPE, shape);
+        builder.push_slot(VT_DEVICE_ID, device.device_id, 0);
+        builder.push_slot(VT_DEVICE_TYPE, device.device_type as i32, 1);
+        builder.push_slot(VT_DTYPE_LANES, dtype.lanes, 1);
+        builder.push_slot(VT_DTYPE_BITS, dtype.bits, 0);
+        builder.push_slot(VT_DTYPE_CODE, dtype.code as u8, 0);
+        let table = builder.end_table(table);
+        builder.finish(table, Some(FLATBUFFER_IDENTIFIER));
+        let (buf, start) = builder.collapse();
+        Ok(buf[start..].to_vec())
+    }
+}
+
+/// CPU tensor over the payload of a FlatBuffer held by `B`, e.g. a `Vec<u8>`
+/// or a memory map, which is dropped once the exported tensor is deleted.
+#[derive(Debug)]
+pub struct FlatTensor<B> {
+    buffer: B,
+    payload: Range<usize>,
+    dtype: DataType,
+    source_device: Device,
+    shape: Vec<i64>,
+    strides: Option<Vec<i64>>,
+}
+
+impl<B: AsRef<[u8]>> FlatTensor<B> {
+    /// Verify the FlatBuffer in `buffer`, and that its payload holds every
+    /// element reachable through its shape and strides.
+    pub fn new(buffer: B) -> io::Result<Self> {
+        if !Endian::Little.is_native() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "flatbuffer payloads are little-endian",
+            ));
+        }
+        let buf = buffer.as_ref();
+        if buf.len() < 8 || !flatbuffers::buffer_has_identifier(buf, FLATBUFFER_IDENTIFIER, false) {
+            return Err(invalid_data("not a dlpark flatbuffer"));
+        }
+        let table = flatbuffers::root::<TensorTable>(buf)
+            .map_err(|err| invalid_data(&format!("invalid flatbuffer: {err}")))?;
+        let code = DataTypeCode::try_from(table.scalar::<u8>(VT_DTYPE_CODE, 0))
+            .map_err(|_| invalid_data("unknown dtype code"))?;
+        let dtype = DataType::builder()
+            .code(code)
+            .bits(table.scalar(VT_DTYPE_BITS, 0))
+            .lanes(table.scalar(VT_DTYPE_LANES, 1))
+            .build()
+            .map_err(|err| invalid_data(&format!("invalid dtype: {err}")))?;
+        let source_device = Device {
+            device_type: DeviceType::try_from(table.scalar::<i32>(VT_DEVICE_TYPE, 1))
+                .map_err(|_| invalid_data("unknown device type"))?,
+            device_id: table.scalar(VT_DEVICE_ID, 0),
+        };
+        let shape: Vec<i64> = table
+            .vector::<i64>(VT_SHAPE)
+            .map_or_else(Vec::new, |shape| shape.iter().collect());
+        let strides: Option<Vec<i64>> = table
+            .vector::<i64>(VT_STRIDES)
+            .map(|strides| strides.iter().collect());
+        if strides.as_ref().is_some_and(|s| s.len() != shape.len()) {
+            return Err(invalid_data("strides don't match the shape"));
+        }
+        let payload = table.vector::<u8>(VT_PAYLOAD).map_or(0..0, |payload| {
+            let start = payload.bytes().as_ptr() as usize - buf.as_ptr() as usize;
+            start..start + payload.len()
+        });
+        let span = byte_span(&shape, strides.as_deref(), dtype.size())
+            .ok_or_else(|| invalid_data("invalid shape or strides"))?;
+        if span.start < 0 || span.end as u64 > payload.len() as u64 {
+            return Err(invalid_data("payload is too short for shape and strides"));
+        }
+        Ok(Self {
+            buffer,
+            payload,
+            dtype,
+            source_device,
+            shape,
+            strides,
+        })
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[self.payload.clone()]
+    }
+
+    /// Device the tensor was encoded from.
+    pub fn source_device(&self) -> Device {
+        self.source_device
+    }
+
+    pub fn into_inner(self) -> B {
+        self.buffer
+    }
+}
+
+impl<B: AsRef<[u8]>> ToTensor for FlatTensor<B> {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.payload().as_ptr().cast_mut().cast()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        match &self.strides {
+            Some(strides) => ShapeAndStrides::new_with_strides(&self.shape, strides),
+            None => ShapeAndStrides::new_contiguous_with_strides(&self.shape),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn view_in_place() {
+        let v: Vec<f32> = (0..6).map(|x| x as f32).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let buf = tensor.to_flatbuffer().unwrap();
+        let flat = FlatTensor::new(&buf[..]).unwrap();
+        assert_eq!(
+            (flat.payload().as_ptr() as usize - buf.as_ptr() as usize) % 64,
+            0
+        );
+        assert_eq!(flat.source_device(), Device::CPU);
+
+        let flat = FlatTensor::new(buf).unwrap();
+        let payload = flat.payload().as_ptr();
+        let tensor = ManagedTensor::from_dlpack(ManagerCtx::new(flat).into_dlpack());
+        assert_eq!(tensor.first_byte().cast_const(), payload);
+        assert_eq!(tensor.shape(), &[6]);
+        assert_eq!(tensor.as_slice::<f32>(), &v[..]);
+
+        assert!(FlatTensor::new(b"DLFB").is_err());
+    }
+}
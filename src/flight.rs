@@ -0,0 +1,233 @@
+//! Streaming batches of named tensors as [Arrow Flight](https://arrow.apache.org/docs/format/Flight.html)
+//! `FlightData` messages, e.g. from `DoGet` and `DoPut` handlers, for bulk
+//! transfer of datasets and activations between services.
+//!
+//! Each batch becomes one record batch laid out as by
+//! [`write_arrow_ipc`](crate::arrow::write_arrow_ipc), preceded by a schema
+//! message for the first one. All batches of a stream must have the same
+//! names, dtypes and shapes.
+
+use std::{collections::BTreeMap, io, sync::Arc};
+
+use arrow_array::RecordBatch;
+use arrow_buffer::Buffer;
+use arrow_ipc::{
+    convert::fb_to_schema,
+    reader::read_record_batch,
+    root_as_message,
+    writer::{DictionaryTracker, EncodedData, IpcDataGenerator, IpcWriteOptions},
+    MessageHeader,
+};
+use arrow_schema::SchemaRef;
+
+use crate::{
+    arrow::{
+        append_values, column_dtypes, column_values, invalid_data, tensor_batch, to_io_error,
+        SHAPE_KEY,
+    },
+    ffi::DataType,
+    tensor::traits::{FromDLPack, IntoDLPack},
+    ManagedTensor, OwnedTensor,
+};
+
+/// The data fields of the Flight `FlightData` message, with the tags of
+/// `Flight.proto`. Descriptors are left to the caller.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct FlightData {
+    /// Arrow IPC message header.
+    #[prost(bytes = "vec", tag = "2")]
+    pub data_header: Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub app_metadata: Vec<u8>,
+    /// Arrow IPC message body.
+    #[prost(bytes = "vec", tag = "1000")]
+    pub data_body: Vec<u8>,
+}
+
+impl From<EncodedData> for FlightData {
+    fn from(data: EncodedData) -> Self {
+        Self {
+            data_header: data.ipc_message,
+            app_metadata: Vec::new(),
+            data_body: data.arrow_data,
+        }
+    }
+}
+
+/// Encoder of a stream of tensor batches.
+#[derive(Debug)]
+pub struct FlightEncoder {
+    schema: Option<SchemaRef>,
+    generator: IpcDataGenerator,
+    tracker: DictionaryTracker,
+    options: IpcWriteOptions,
+}
+
+impl Default for FlightEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlightEncoder {
+    pub fn new() -> Self {
+        Self {
+            schema: None,
+            generator: IpcDataGenerator::default(),
+            tracker: DictionaryTracker::new(false),
+            options: IpcWriteOptions::default(),
+        }
+    }
+
+    /// Encode a batch of named CPU tensors, returning the messages to send
+    /// for it.
+    pub fn encode<'a, I>(&mut self, tensors: I) -> io::Result<Vec<FlightData>>
+    where
+        I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+    {
+        let batch = tensor_batch(tensors)?;
+        let mut messages = Vec::new();
+        match &self.schema {
+            Some(schema) if *schema != batch.schema() => {
+                return Err(invalid_data(
+                    "tensor batch doesn't match the first one of the stream",
+                ));
+            }
+            Some(_) => {}
+            None => {
+                let schema = batch.schema();
+                messages.push(
+                    self.generator
+                        .schema_to_bytes_with_dictionary_tracker(
+                            &schema,
+                            &mut self.tracker,
+                            &self.options,
+                        )
+                        .into(),
+                );
+                self.schema = Some(schema);
+            }
+        }
+        let (dictionaries, batch) = self
+            .generator
+            .encoded_batch(&batch, &mut self.tracker, &self.options)
+            .map_err(to_io_error)?;
+        messages.extend(dictionaries.into_iter().map(FlightData::from));
+        messages.push(batch.into());
+        Ok(messages)
+    }
+}
+
+/// Decoder of a stream of tensor batches, which also reads record batches
+/// of primitive and fixed size list columns written elsewhere, with the
+/// number of rows as leading dimension.
+#[derive(Debug, Default)]
+pub struct FlightDecoder {
+    schema: Option<(SchemaRef, Vec<DataType>)>,
+}
+
+impl FlightDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode the next message of the stream, returning the tensors of its
+    /// batch, or `None` for the schema message.
+    pub fn decode(
+        &mut self,
+        data: &FlightData,
+    ) -> io::Result<Option<BTreeMap<String, ManagedTensor>>> {
+        let message = root_as_message(&data.data_header)
+            .map_err(|err| invalid_data(format!("invalid arrow ipc message: {err}")))?;
+        match message.header_type() {
+            MessageHeader::Schema => {
+                let schema = message
+                    .header_as_schema()
+                    .ok_or_else(|| invalid_data("invalid arrow schema message"))?;
+                let schema = Arc::new(fb_to_schema(schema));
+                let dtypes = column_dtypes(&schema)?;
+                self.schema = Some((schema, dtypes));
+                Ok(None)
+            }
+            MessageHeader::RecordBatch => {
+                let (schema, dtypes) = self
+                    .schema
+                    .as_ref()
+                    .ok_or_else(|| invalid_data("record batch before the schema"))?;
+                let batch = message
+                    .header_as_record_batch()
+                    .ok_or_else(|| invalid_data("invalid arrow record batch message"))?;
+                let batch = read_record_batch(
+                    &Buffer::from(data.data_body.as_slice()),
+                    batch,
+                    schema.clone(),
+                    &Default::default(),
+                    None,
+                    &message.version(),
+                )
+                .map_err(to_io_error)?;
+                batch_tensors(&batch, dtypes).map(Some)
+            }
+            header => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported arrow ipc message {header:?}"),
+            )),
+        }
+    }
+}
+
+fn batch_tensors(
+    batch: &RecordBatch,
+    dtypes: &[DataType],
+) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let schema = batch.schema();
+    let mut tensors = BTreeMap::new();
+    for ((field, column), &dtype) in schema.fields().iter().zip(batch.columns()).zip(dtypes) {
+        let (mut shape, values) = column_values(field, column)?;
+        if !field.metadata().contains_key(SHAPE_KEY) {
+            shape[0] = batch.num_rows() as i64;
+        }
+        let mut data = Vec::new();
+        append_values(&values, &mut data)?;
+        let tensor = OwnedTensor::from_bytes(&data, &shape, dtype).ok_or_else(|| {
+            invalid_data(format!("column {} doesn't match its shape", field.name()))
+        })?;
+        tensors.insert(
+            field.name().clone(),
+            ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        );
+    }
+    Ok(tensors)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn stream_batches() {
+        let mut encoder = FlightEncoder::new();
+        let mut decoder = FlightDecoder::new();
+        let mut batches = Vec::new();
+        for step in 0..3 {
+            let x = ManagedTensor::from_dlpack(vec![step as f32; 4].into_dlpack());
+            let y = ManagedTensor::from_dlpack(vec![step as i64].into_dlpack());
+            let messages = encoder.encode([("x", &x), ("y", &y)]).unwrap();
+            assert_eq!(messages.len(), if step == 0 { 2 } else { 1 });
+            for message in messages {
+                let message = FlightData::decode(message.encode_to_vec().as_slice()).unwrap();
+                batches.extend(decoder.decode(&message).unwrap());
+            }
+        }
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[2]["x"].shape(), &[4]);
+        assert_eq!(batches[2]["x"].as_slice::<f32>(), &[2.0; 4]);
+        assert_eq!(batches[1]["y"].as_slice::<i64>(), &[1]);
+
+        let z = ManagedTensor::from_dlpack(vec![0u8; 2].into_dlpack());
+        assert!(encoder.encode([("z", &z)]).is_err());
+    }
+}
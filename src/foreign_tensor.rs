@@ -0,0 +1,184 @@
+//! Exports of buffers owned by other libraries, e.g. decoded frames or driver
+//! buffers, which are freed with their own function.
+
+use alloc::vec::Vec;
+use core::ffi::c_void;
+
+use crate::{
+    ffi::{DataType, Device},
+    tensor::traits::ToTensor,
+    ManagerCtx, ShapeAndStrides,
+};
+
+/// Row-major tensor over a buffer that `deleter` frees once the tensor is
+/// dropped, made by [`ManagerCtx::from_raw_parts`].
+pub struct ForeignTensor<F: FnOnce()> {
+    data: *mut c_void,
+    shape: Vec<i64>,
+    dtype: DataType,
+    device: Device,
+    deleter: Option<F>,
+}
+
+impl<F: FnOnce()> ManagerCtx<ForeignTensor<F>> {
+    /// Export the buffer at `data`, calling `deleter` when the consumer
+    /// deletes the tensor, or when this is dropped without being exported.
+    ///
+    /// # Safety
+    /// `data` must point to row-major elements of `shape` and `dtype` on
+    /// `device`, valid until `deleter` is called.
+    pub unsafe fn from_raw_parts(
+        data: *mut c_void,
+        shape: &[i64],
+        dtype: DataType,
+        device: Device,
+        deleter: F,
+    ) -> Self {
+        Self::new(ForeignTensor {
+            data,
+            shape: shape.to_vec(),
+            dtype,
+            device,
+            deleter: Some(deleter),
+        })
+    }
+}
+
+impl<F: FnOnce()> Drop for ForeignTensor<F> {
+    fn drop(&mut self) {
+        if let Some(deleter) = self.deleter.take() {
+            deleter();
+        }
+    }
+}
+
+impl<F: FnOnce()> ToTensor for ForeignTensor<F> {
+    fn data_ptr(&self) -> *mut c_void {
+        self.data
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        self.device
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+/// Row-major CPU tensor over a buffer allocated with `malloc`, e.g. by a C
+/// library, which is freed with `libc::free`. Enabled by the `malloc`
+/// feature.
+#[cfg(feature = "malloc")]
+#[derive(Debug)]
+pub struct MallocTensor {
+    data: *mut c_void,
+    shape: Vec<i64>,
+    dtype: DataType,
+}
+
+#[cfg(feature = "malloc")]
+impl ManagerCtx<MallocTensor> {
+    /// Export the buffer at `data`, freeing it with `libc::free` when the
+    /// consumer deletes the tensor, or when this is dropped without being
+    /// exported.
+    ///
+    /// # Safety
+    /// `data` must be NULL or returned by `malloc` and friends, and hold
+    /// row-major elements of `shape` and `dtype`. Nothing else may free it.
+    pub unsafe fn from_malloc(data: *mut c_void, shape: &[i64], dtype: DataType) -> Self {
+        Self::new(MallocTensor {
+            data,
+            shape: shape.to_vec(),
+            dtype,
+        })
+    }
+}
+
+#[cfg(feature = "malloc")]
+impl Drop for MallocTensor {
+    fn drop(&mut self) {
+        unsafe { libc::free(self.data) };
+    }
+}
+
+#[cfg(feature = "malloc")]
+impl ToTensor for MallocTensor {
+    fn data_ptr(&self) -> *mut c_void {
+        self.data
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use crate::prelude::*;
+
+    #[test]
+    fn native_deleter() {
+        let freed = Cell::new(false);
+        let data = Box::into_raw(Box::new([1.0f32, 2.0, 3.0, 4.0]));
+        let ctx = unsafe {
+            ManagerCtx::from_raw_parts(data.cast(), &[2, 2], DataType::F32, Device::CPU, || {
+                drop(Box::from_raw(data));
+                freed.set(true);
+            })
+        };
+        let tensor = ManagedTensor::from_dlpack(ctx.into_dlpack());
+        assert_eq!(tensor.shape(), &[2, 2]);
+        assert_eq!(tensor.as_slice::<f32>(), [1.0, 2.0, 3.0, 4.0]);
+        assert!(!freed.get());
+        drop(tensor);
+        assert!(freed.get());
+
+        // Dropped without being exported.
+        let freed = Cell::new(false);
+        drop(unsafe {
+            ManagerCtx::from_raw_parts(
+                core::ptr::null_mut(),
+                &[0],
+                DataType::U8,
+                Device::CPU,
+                || freed.set(true),
+            )
+        });
+        assert!(freed.get());
+    }
+
+    #[cfg(feature = "malloc")]
+    #[test]
+    fn malloc_buffer() {
+        let tensor = unsafe {
+            let data = libc::malloc(3 * 4).cast::<i32>();
+            data.copy_from_nonoverlapping([7, 8, 9].as_ptr(), 3);
+            ManagerCtx::from_malloc(data.cast(), &[3], DataType::I32)
+        };
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.as_slice::<i32>(), [7, 8, 9]);
+    }
+}
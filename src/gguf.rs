@@ -0,0 +1,571 @@
+//! Loading [GGUF](https://github.com/ggml-org/ggml/blob/master/docs/gguf.md)
+//! model files, for Rust LLM tooling handing weights to Python.
+//!
+//! Tensors of plain types get their DLPack dtype. Quantized tensors become
+//! `u8` tensors of their raw blocks, with the blocks of a row along the last
+//! axis, and their [`GgmlType`] tells how to decode them. GGUF stores dims
+//! innermost first, [`GgufTensor::shape`] has them in row-major order.
+
+use std::{collections::BTreeMap, fs::File, io, ops::Range, path::Path, sync::Arc};
+
+use memmap2::Mmap;
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, Device},
+    tensor::traits::{FromDLPack, IntoDLPack, ToTensor},
+    ManagedTensor, OwnedTensor, ShapeAndStrides,
+};
+
+const MAGIC: &[u8; 4] = b"GGUF";
+const DEFAULT_ALIGNMENT: u64 = 32;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Element types of ggml tensors, with their GGUF ids.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GgmlType {
+    F32     = 0,
+    F16     = 1,
+    Q4_0    = 2,
+    Q4_1    = 3,
+    Q5_0    = 6,
+    Q5_1    = 7,
+    Q8_0    = 8,
+    Q8_1    = 9,
+    Q2_K    = 10,
+    Q3_K    = 11,
+    Q4_K    = 12,
+    Q5_K    = 13,
+    Q6_K    = 14,
+    Q8_K    = 15,
+    IQ2_XXS = 16,
+    IQ2_XS  = 17,
+    IQ3_XXS = 18,
+    IQ1_S   = 19,
+    IQ4_NL  = 20,
+    IQ3_S   = 21,
+    IQ2_S   = 22,
+    IQ4_XS  = 23,
+    I8      = 24,
+    I16     = 25,
+    I32     = 26,
+    I64     = 27,
+    F64     = 28,
+    IQ1_M   = 29,
+    BF16    = 30,
+    TQ1_0   = 34,
+    TQ2_0   = 35,
+}
+
+impl GgmlType {
+    pub fn from_id(id: u32) -> Option<Self> {
+        use GgmlType::*;
+        let ty = match id {
+            0 => F32,
+            1 => F16,
+            2 => Q4_0,
+            3 => Q4_1,
+            6 => Q5_0,
+            7 => Q5_1,
+            8 => Q8_0,
+            9 => Q8_1,
+            10 => Q2_K,
+            11 => Q3_K,
+            12 => Q4_K,
+            13 => Q5_K,
+            14 => Q6_K,
+            15 => Q8_K,
+            16 => IQ2_XXS,
+            17 => IQ2_XS,
+            18 => IQ3_XXS,
+            19 => IQ1_S,
+            20 => IQ4_NL,
+            21 => IQ3_S,
+            22 => IQ2_S,
+            23 => IQ4_XS,
+            24 => I8,
+            25 => I16,
+            26 => I32,
+            27 => I64,
+            28 => F64,
+            29 => IQ1_M,
+            30 => BF16,
+            34 => TQ1_0,
+            35 => TQ2_0,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    /// Number of elements per block, 1 for plain types.
+    pub fn block_size(self) -> usize {
+        use GgmlType::*;
+        match self {
+            F32 | F16 | BF16 | F64 | I8 | I16 | I32 | I64 => 1,
+            Q4_0 | Q4_1 | Q5_0 | Q5_1 | Q8_0 | Q8_1 | IQ4_NL => 32,
+            _ => 256,
+        }
+    }
+
+    /// Number of bytes per block.
+    pub fn type_size(self) -> usize {
+        use GgmlType::*;
+        match self {
+            I8 => 1,
+            F16 | BF16 | I16 => 2,
+            F32 | I32 => 4,
+            F64 | I64 => 8,
+            Q4_0 | IQ4_NL => 18,
+            Q4_1 => 20,
+            Q5_0 => 22,
+            Q5_1 => 24,
+            Q8_0 => 34,
+            Q8_1 => 36,
+            Q2_K => 84,
+            Q3_K | IQ3_S => 110,
+            Q4_K => 144,
+            Q5_K => 176,
+            Q6_K => 210,
+            Q8_K => 292,
+            IQ2_XXS | TQ2_0 => 66,
+            IQ2_XS => 74,
+            IQ3_XXS => 98,
+            IQ1_S => 50,
+            IQ2_S => 82,
+            IQ4_XS => 136,
+            IQ1_M => 56,
+            TQ1_0 => 54,
+        }
+    }
+
+    pub fn is_quantized(self) -> bool {
+        self.block_size() > 1
+    }
+
+    /// DLPack dtype of the tensors of this type, `u8` for quantized ones.
+    pub fn dtype(self) -> DataType {
+        use GgmlType::*;
+        match self {
+            F32 => DataType::F32,
+            F16 => DataType::F16,
+            BF16 => DataType::BF16,
+            F64 => DataType::F64,
+            I8 => DataType::I8,
+            I16 => DataType::I16,
+            I32 => DataType::I32,
+            I64 => DataType::I64,
+            _ => DataType::U8,
+        }
+    }
+}
+
+/// Value of a GGUF metadata key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GgufValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<GgufValue>),
+}
+
+impl GgufValue {
+    /// The value of unsigned integer keys such as `general.alignment`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Self::U8(v) => Some(v.into()),
+            Self::U16(v) => Some(v.into()),
+            Self::U32(v) => Some(v.into()),
+            Self::U64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// A tensor of a GGUF file.
+#[derive(Debug)]
+pub struct GgufTensor {
+    pub ggml_type: GgmlType,
+    /// Shape in elements, row-major. For quantized tensors the last dim of
+    /// [`GgufTensor::tensor`] counts bytes of blocks instead.
+    pub shape: Vec<i64>,
+    pub tensor: ManagedTensor,
+}
+
+/// The metadata and tensors of a GGUF file.
+#[derive(Debug)]
+pub struct Gguf {
+    pub version: u32,
+    pub metadata: BTreeMap<String, GgufValue>,
+    pub tensors: BTreeMap<String, GgufTensor>,
+}
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn bytes(&mut self, len: u64) -> io::Result<&'a [u8]> {
+        let len = usize::try_from(len).map_err(|_| invalid_data("gguf length overflows"))?;
+        let bytes = self
+            .buf
+            .get(self.pos..)
+            .and_then(|rest| rest.get(..len))
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        Ok(self.bytes(N as u64)?.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u64()?;
+        String::from_utf8(self.bytes(len)?.to_vec())
+            .map_err(|_| invalid_data("gguf string isn't utf-8"))
+    }
+
+    fn value(&mut self, ty: u32) -> io::Result<GgufValue> {
+        let value = match ty {
+            0 => GgufValue::U8(u8::from_le_bytes(self.array()?)),
+            1 => GgufValue::I8(i8::from_le_bytes(self.array()?)),
+            2 => GgufValue::U16(u16::from_le_bytes(self.array()?)),
+            3 => GgufValue::I16(i16::from_le_bytes(self.array()?)),
+            4 => GgufValue::U32(self.u32()?),
+            5 => GgufValue::I32(i32::from_le_bytes(self.array()?)),
+            6 => GgufValue::F32(f32::from_le_bytes(self.array()?)),
+            7 => GgufValue::Bool(self.array::<1>()?[0] != 0),
+            8 => GgufValue::String(self.string()?),
+            9 => {
+                let ty = self.u32()?;
+                let len = self.u64()?;
+                // Every value takes at least a byte, which bounds allocations.
+                if len > (self.buf.len() - self.pos) as u64 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                let values = (0..len)
+                    .map(|_| self.value(ty))
+                    .collect::<io::Result<_>>()?;
+                GgufValue::Array(values)
+            }
+            10 => GgufValue::U64(self.u64()?),
+            11 => GgufValue::I64(i64::from_le_bytes(self.array()?)),
+            12 => GgufValue::F64(f64::from_le_bytes(self.array()?)),
+            _ => return Err(invalid_data("unknown gguf value type")),
+        };
+        Ok(value)
+    }
+}
+
+/// A tensor as described in the header, before its data is located.
+struct TensorInfo {
+    ggml_type: GgmlType,
+    shape: Vec<i64>,
+    /// Shape of the exported tensor, in bytes of blocks along the last axis
+    /// for quantized types.
+    storage_shape: Vec<i64>,
+    data: Range<usize>,
+}
+
+fn tensor_info(cursor: &mut Cursor) -> io::Result<(String, TensorInfo)> {
+    let name = cursor.string()?;
+    let ndim = cursor.u32()?;
+    if ndim > 8 {
+        return Err(invalid_data("too many dimensions in gguf tensor"));
+    }
+    let mut shape = (0..ndim)
+        .map(|_| {
+            let dim = cursor.u64()?;
+            i64::try_from(dim).map_err(|_| invalid_data("gguf dim overflows"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    shape.reverse();
+    let id = cursor.u32()?;
+    let ggml_type = GgmlType::from_id(id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported ggml type {id}"),
+        )
+    })?;
+    let offset = cursor.u64()?;
+
+    let mut storage_shape = shape.clone();
+    if let Some(last) = storage_shape.last_mut() {
+        let block_size = ggml_type.block_size() as i64;
+        if *last % block_size != 0 {
+            return Err(invalid_data("gguf row isn't a whole number of blocks"));
+        }
+        if ggml_type.is_quantized() {
+            *last = *last / block_size * ggml_type.type_size() as i64;
+        }
+    }
+    let len = storage_shape
+        .iter()
+        .try_fold(ggml_type.dtype().size(), |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        })
+        .ok_or_else(|| invalid_data("gguf tensor is too large"))?;
+    let start = usize::try_from(offset).map_err(|_| invalid_data("gguf offset overflows"))?;
+    let data = start
+        ..start
+            .checked_add(len)
+            .ok_or_else(|| invalid_data("gguf offset overflows"))?;
+    let info = TensorInfo {
+        ggml_type,
+        shape,
+        storage_shape,
+        data,
+    };
+    Ok((name, info))
+}
+
+/// Parse the header, returning the version, metadata and tensors with their
+/// data ranges relative to the start of `buf`.
+#[allow(clippy::type_complexity)]
+fn parse(buf: &[u8]) -> io::Result<(u32, BTreeMap<String, GgufValue>, Vec<(String, TensorInfo)>)> {
+    let mut cursor = Cursor { buf, pos: 0 };
+    if &cursor.array::<4>()? != MAGIC {
+        return Err(invalid_data("not a gguf file"));
+    }
+    let version = cursor.u32()?;
+    if !(2..=3).contains(&version) {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported gguf version {version}"),
+        ));
+    }
+    let tensor_count = cursor.u64()?;
+    let kv_count = cursor.u64()?;
+    let mut metadata = BTreeMap::new();
+    for _ in 0..kv_count {
+        let key = cursor.string()?;
+        let ty = cursor.u32()?;
+        metadata.insert(key, cursor.value(ty)?);
+    }
+    // Every tensor info takes at least 24 bytes.
+    if tensor_count > (buf.len() / 24) as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut tensors = (0..tensor_count)
+        .map(|_| tensor_info(&mut cursor))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let alignment = match metadata.get("general.alignment") {
+        Some(value) => value
+            .as_u64()
+            .filter(|&a| a > 0)
+            .ok_or_else(|| invalid_data("invalid general.alignment"))?,
+        None => DEFAULT_ALIGNMENT,
+    };
+    let data_start = (cursor.pos as u64).div_ceil(alignment) * alignment;
+    for (_, info) in &mut tensors {
+        let data = &mut info.data;
+        *data = usize::try_from(data_start)
+            .ok()
+            .and_then(|start| Some(start.checked_add(data.start)?..start.checked_add(data.end)?))
+            .filter(|data| data.end <= buf.len())
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+    }
+    Ok((version, metadata, tensors))
+}
+
+/// Copy every tensor of a GGUF buffer into new CPU tensors.
+pub fn read_gguf(buffer: &[u8]) -> io::Result<Gguf> {
+    let (version, metadata, infos) = parse(buffer)?;
+    let mut tensors = BTreeMap::new();
+    for (name, info) in infos {
+        let dtype = info.ggml_type.dtype();
+        let mut tensor = OwnedTensor::from_bytes(&buffer[info.data], &info.storage_shape, dtype)
+            .ok_or_else(|| invalid_data("invalid gguf shape"))?;
+        if !info.ggml_type.is_quantized() {
+            convert_byte_order(tensor.as_bytes_mut(), dtype, Endian::Little, Endian::NATIVE);
+        }
+        let tensor = GgufTensor {
+            ggml_type: info.ggml_type,
+            shape: info.shape,
+            tensor: ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        };
+        tensors.insert(name, tensor);
+    }
+    Ok(Gguf {
+        version,
+        metadata,
+        tensors,
+    })
+}
+
+/// Load every tensor of a GGUF file into new CPU tensors.
+pub fn load_gguf<P: AsRef<Path>>(path: P) -> io::Result<Gguf> {
+    read_gguf(&std::fs::read(path)?)
+}
+
+/// A tensor borrowing its data from a memory-mapped file, keeping the mapping
+/// alive.
+struct MappedTensor {
+    mmap: Arc<Mmap>,
+    offset: usize,
+    shape: Vec<i64>,
+    dtype: DataType,
+}
+
+impl ToTensor for MappedTensor {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.mmap.as_ptr() as *mut std::ffi::c_void
+    }
+
+    fn byte_offset(&self) -> u64 {
+        self.offset as u64
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        crate::ffi::DLPACK_FLAG_BITMASK_READ_ONLY
+    }
+}
+
+/// Memory-map a GGUF file and expose every tensor without copying. The
+/// mapping is released once all returned tensors are dropped.
+///
+/// The tensors are backed by read-only pages, consumers must not write to
+/// them. Since GGUF data is little-endian, this fails with
+/// [`io::ErrorKind::Unsupported`] on big-endian hosts.
+///
+/// # Safety
+/// The file must not be modified or truncated while the tensors are alive,
+/// see [`Mmap::map`].
+pub unsafe fn mmap_gguf<P: AsRef<Path>>(path: P) -> io::Result<Gguf> {
+    if !Endian::Little.is_native() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zero-copy loading requires a little-endian host",
+        ));
+    }
+    let mmap = Arc::new(Mmap::map(&File::open(path)?)?);
+    let (version, metadata, infos) = parse(&mmap)?;
+    let mut tensors = BTreeMap::new();
+    for (name, info) in infos {
+        let tensor = MappedTensor {
+            mmap: mmap.clone(),
+            offset: info.data.start,
+            shape: info.storage_shape,
+            dtype: info.ggml_type.dtype(),
+        };
+        let tensor = GgufTensor {
+            ggml_type: info.ggml_type,
+            shape: info.shape,
+            tensor: ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        };
+        tensors.insert(name, tensor);
+    }
+    Ok(Gguf {
+        version,
+        metadata,
+        tensors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::traits::TensorView;
+
+    fn string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend((s.len() as u64).to_le_bytes());
+        buf.extend(s.as_bytes());
+    }
+
+    fn tensor_info(buf: &mut Vec<u8>, name: &str, dims: &[u64], ty: u32, offset: u64) {
+        string(buf, name);
+        buf.extend((dims.len() as u32).to_le_bytes());
+        for dim in dims {
+            buf.extend(dim.to_le_bytes());
+        }
+        buf.extend(ty.to_le_bytes());
+        buf.extend(offset.to_le_bytes());
+    }
+
+    #[test]
+    fn load() {
+        let mut buf = b"GGUF".to_vec();
+        buf.extend(3u32.to_le_bytes());
+        buf.extend(2u64.to_le_bytes());
+        buf.extend(2u64.to_le_bytes());
+        string(&mut buf, "general.name");
+        buf.extend(8u32.to_le_bytes());
+        string(&mut buf, "tiny");
+        string(&mut buf, "general.alignment");
+        buf.extend(4u32.to_le_bytes());
+        buf.extend(16u32.to_le_bytes());
+        // Innermost dim first.
+        tensor_info(&mut buf, "w", &[3, 2], 0, 0);
+        tensor_info(&mut buf, "q", &[64, 1], 8, 32);
+        buf.resize(buf.len().div_ceil(16) * 16, 0);
+        buf.extend((0..6).flat_map(|x| (x as f32).to_le_bytes()));
+        buf.extend([0; 8]);
+        buf.extend((0..68).map(|x| x as u8));
+
+        let gguf = read_gguf(&buf).unwrap();
+        assert_eq!(gguf.version, 3);
+        assert_eq!(gguf.metadata["general.name"].as_str(), Some("tiny"));
+        let w = &gguf.tensors["w"];
+        assert_eq!(w.ggml_type, GgmlType::F32);
+        assert_eq!(w.tensor.shape(), &[2, 3]);
+        assert_eq!(w.tensor.as_slice::<f32>(), &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        let q = &gguf.tensors["q"];
+        assert_eq!(q.ggml_type, GgmlType::Q8_0);
+        assert_eq!(q.shape, vec![1, 64]);
+        assert_eq!(q.tensor.shape(), &[1, 68]);
+        assert_eq!(q.tensor.as_slice::<u8>()[67], 67);
+
+        let path = std::env::temp_dir().join(format!("dlpark-{}.gguf", std::process::id()));
+        std::fs::write(&path, &buf).unwrap();
+        let gguf = unsafe { mmap_gguf(&path) }.unwrap();
+        assert_eq!(gguf.tensors["q"].tensor.as_slice::<u8>()[..2], [0, 1]);
+        drop(gguf);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(read_gguf(&buf[..buf.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! gRPC support for tensors, encoded as [wire](crate::wire) messages inside
+//! a protobuf message. Enabled by the `tonic` feature.
+//!
+//! Services declare tensors in their `.proto` files as
+//!
+//! ```proto
+//! message Tensor {
+//!   bytes wire = 1;
+//! }
+//! ```
+//!
+//! and either embed [`TensorMessage`] in their own prost messages, or use
+//! [`TensorCodec`] for methods taking and returning a single tensor.
+
+use std::io;
+
+use bytes::{Buf, BufMut};
+use prost::{
+    encoding::{encode_key, encode_varint, WireType},
+    Message,
+};
+use tonic::{
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    Status,
+};
+
+use crate::{ManagedTensor, SendTensor};
+
+/// Protobuf message holding a tensor encoded with
+/// [`ManagedTensor::encode_to`].
+#[derive(Clone, PartialEq, Message)]
+pub struct TensorMessage {
+    #[prost(bytes = "vec", tag = "1")]
+    pub wire: Vec<u8>,
+}
+
+impl ManagedTensor {
+    /// Encode a CPU tensor into a [`TensorMessage`].
+    pub fn to_message(&self) -> io::Result<TensorMessage> {
+        let mut wire = Vec::new();
+        self.encode_to(&mut wire)?;
+        Ok(TensorMessage { wire })
+    }
+
+    /// Decode a [`TensorMessage`] into a new CPU tensor.
+    pub fn from_message(message: &TensorMessage) -> io::Result<Self> {
+        Self::decode_from(&message.wire[..])
+    }
+}
+
+/// Write `tensor` as an encoded [`TensorMessage`], without building one.
+fn encode_message(tensor: &ManagedTensor, buf: &mut impl BufMut) -> io::Result<()> {
+    let mut wire = Vec::new();
+    tensor.encode_to(&mut wire)?;
+    encode_key(1, WireType::LengthDelimited, buf);
+    encode_varint(wire.len() as u64, buf);
+    buf.put_slice(&wire);
+    Ok(())
+}
+
+fn decode_message(buf: impl Buf) -> io::Result<ManagedTensor> {
+    let message = TensorMessage::decode(buf).map_err(io::Error::other)?;
+    ManagedTensor::from_message(&message)
+}
+
+/// [`Codec`] for gRPC methods whose requests and responses are single
+/// tensors, sent as [`TensorMessage`]s. Only CPU tensors can be encoded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TensorCodec;
+
+impl Codec for TensorCodec {
+    type Decode = SendTensor;
+    type Decoder = TensorDecoder;
+    type Encode = SendTensor;
+    type Encoder = TensorEncoder;
+
+    fn encoder(&mut self) -> TensorEncoder {
+        TensorEncoder
+    }
+
+    fn decoder(&mut self) -> TensorDecoder {
+        TensorDecoder
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TensorEncoder;
+
+impl Encoder for TensorEncoder {
+    type Error = Status;
+    type Item = SendTensor;
+
+    fn encode(&mut self, item: SendTensor, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        encode_message(&item, dst).map_err(|err| Status::invalid_argument(err.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TensorDecoder;
+
+impl Decoder for TensorDecoder {
+    type Error = Status;
+    type Item = SendTensor;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<SendTensor>, Status> {
+        let tensor =
+            decode_message(src).map_err(|err| Status::invalid_argument(err.to_string()))?;
+        // Decoded tensors own their data, and are deleted by any thread.
+        Ok(Some(unsafe { SendTensor::new(tensor) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn message_round_trip() {
+        let tensor = ManagedTensor::from_dlpack(vec![1i32, 2, 3].into_dlpack());
+        let message = tensor.to_message().unwrap();
+        let mut buf = Vec::new();
+        encode_message(&tensor, &mut buf).unwrap();
+        assert_eq!(buf, message.encode_to_vec());
+
+        let decoded = decode_message(&buf[..]).unwrap();
+        assert_eq!(decoded.shape(), &[3]);
+        assert_eq!(decoded.as_slice::<i32>(), &[1, 2, 3]);
+        assert!(decode_message(&b"\x0a\x01x"[..]).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Registry of the tensors exported by this crate, catching deleters that run
+//! twice and tensors that are used after being deleted. Enabled by the
+//! `debug-guards` feature, it takes a global lock on every export, deletion
+//! and access.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    sync::Mutex,
+};
+
+use crate::ffi;
+
+/// Deleted addresses remembered at most, the oldest are forgotten first.
+const MAX_DELETED: usize = 1 << 16;
+
+struct Registry {
+    live: BTreeSet<usize>,
+    /// Deleted addresses, by the sequence number of their deletion.
+    deleted: BTreeMap<usize, u64>,
+    /// Deletions in the order they happened, stale entries included.
+    order: VecDeque<(usize, u64)>,
+    next: u64,
+}
+
+impl Registry {
+    fn delete(&mut self, addr: usize) {
+        let seq = self.next;
+        self.next += 1;
+        self.deleted.insert(addr, seq);
+        self.order.push_back((addr, seq));
+        while self.deleted.len() > MAX_DELETED {
+            let Some((addr, seq)) = self.order.pop_front() else {
+                break;
+            };
+            if self.deleted.get(&addr) == Some(&seq) {
+                self.deleted.remove(&addr);
+            }
+        }
+        // Entries for addresses exported again pile up otherwise.
+        if self.order.len() > 2 * MAX_DELETED {
+            let deleted = &self.deleted;
+            self.order
+                .retain(|(addr, seq)| deleted.get(addr) == Some(seq));
+        }
+    }
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    live: BTreeSet::new(),
+    deleted: BTreeMap::new(),
+    order: VecDeque::new(),
+    next: 0,
+});
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    f(&mut REGISTRY.lock().unwrap_or_else(|err| err.into_inner()))
+}
+
+/// Record that `managed` was handed out. Its address may be reused from a
+/// deleted tensor.
+pub(crate) fn exported(managed: *const ffi::DLManagedTensor) {
+    with_registry(|registry| {
+        registry.live.insert(managed as usize);
+        registry.deleted.remove(&(managed as usize));
+    });
+}
+
+/// Record that `managed` was deleted, aborting if it wasn't live. Called from
+/// deleters, which must not unwind.
+pub(crate) fn deleted(managed: *const ffi::DLManagedTensor) {
+    let was_live = with_registry(|registry| {
+        registry.delete(managed as usize);
+        registry.live.remove(&(managed as usize))
+    });
+    if !was_live {
+        eprintln!("dlpark: deleter of {managed:p} called twice or on a foreign tensor");
+        std::process::abort();
+    }
+}
+
+/// Panic if `managed` was exported by this crate and deleted since. Only its
+/// address is looked at, as the memory behind it may have been freed, so a
+/// tensor of another producer reusing the address of a recently deleted
+/// export is reported too.
+pub(crate) fn check_alive(managed: *const ffi::DLManagedTensor) {
+    let deleted = with_registry(|registry| registry.deleted.contains_key(&(managed as usize)));
+    assert!(!deleted, "tensor {managed:p} used after being deleted");
+}
+
+/// Whether `managed` was exported by this crate and not deleted yet.
+pub fn is_live(managed: *const ffi::DLManagedTensor) -> bool {
+    with_registry(|registry| registry.live.contains(&(managed as usize)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, ManagedTensor};
+
+    #[test]
+    fn track_exports() {
+        let dlpack = vec![1u8, 2, 3].into_dlpack();
+        assert!(is_live(dlpack.as_ptr()));
+        let tensor = ManagedTensor::from_dlpack(dlpack);
+        assert_eq!(tensor.shape(), &[3]);
+        drop(tensor);
+        assert!(!is_live(dlpack.as_ptr()));
+    }
+
+    #[test]
+    #[should_panic(expected = "used after being deleted")]
+    fn use_after_delete() {
+        let dlpack = vec![1u8, 2, 3].into_dlpack();
+        drop(ManagedTensor::from_dlpack(dlpack));
+        // Simulates a consumer keeping the tensor after deleting it.
+        let tensor = std::mem::ManuallyDrop::new(ManagedTensor::from_dlpack(dlpack));
+        tensor.shape();
+    }
+
+    #[test]
+    fn foreign_tensors() {
+        let mut foreign = ffi::DLManagedTensor {
+            dl_tensor: ffi::DLTensor {
+                data: std::ptr::null_mut(),
+                device: Device::CPU,
+                ndim: 0,
+                dtype: DataType::U8,
+                shape: std::ptr::null_mut(),
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: std::ptr::null_mut(),
+            deleter: None,
+        };
+        let foreign = std::ptr::addr_of_mut!(foreign);
+        check_alive(foreign);
+        // As if an export deleted at the same address came before it.
+        with_registry(|registry| registry.delete(foreign as usize));
+        let reused = std::panic::catch_unwind(|| check_alive(foreign));
+        assert!(reused.is_err());
+
+        // Odd addresses are never those of real tensors.
+        for addr in (1..).step_by(2).take(MAX_DELETED + 1) {
+            with_registry(|registry| registry.delete(addr));
+        }
+        with_registry(|registry| {
+            assert!(registry.deleted.len() <= MAX_DELETED);
+            assert!(registry.order.len() <= 2 * MAX_DELETED + 1);
+        });
+    }
+}
@@ -0,0 +1,422 @@
+//! Reading and writing [HDF5](https://www.hdfgroup.org/solutions/hdf5/)
+//! datasets, through the HDF5 C library.
+//!
+//! [`read_hdf5`] reads a whole dataset into a new CPU tensor, and
+//! [`read_hdf5_slice`] a hyperslab of it given as one [`Slice`] per leading
+//! axis, like `dataset[0:10:2, 5:]` in h5py. [`ManagedTensor::save_hdf5`]
+//! writes a CPU tensor as a new dataset. Integer and floating point datasets
+//! are supported; the library converts their byte order to the native one.
+
+use std::{ffi::CString, io, ops::Range, path::Path, ptr};
+
+use hdf5_metno_sys::{
+    h5::H5open,
+    h5d::{H5Dclose, H5Dcreate2, H5Dget_space, H5Dget_type, H5Dopen2, H5Dread, H5Dwrite},
+    h5e::{H5Eset_auto2, H5E_DEFAULT},
+    h5f::{H5Fclose, H5Fcreate, H5Fopen, H5F_ACC_EXCL, H5F_ACC_RDONLY, H5F_ACC_RDWR},
+    h5i::hid_t,
+    h5p::H5P_DEFAULT,
+    h5s::{
+        H5S_seloper_t, H5Sclose, H5Screate_simple, H5Sget_simple_extent_dims,
+        H5Sget_simple_extent_ndims, H5Sselect_hyperslab, H5S_ALL,
+    },
+    h5t::{
+        H5T_class_t, H5T_sign_t, H5Tclose, H5Tget_class, H5Tget_sign, H5Tget_size,
+        H5T_NATIVE_DOUBLE, H5T_NATIVE_FLOAT, H5T_NATIVE_INT16, H5T_NATIVE_INT32, H5T_NATIVE_INT64,
+        H5T_NATIVE_INT8, H5T_NATIVE_UINT16, H5T_NATIVE_UINT32, H5T_NATIVE_UINT64, H5T_NATIVE_UINT8,
+    },
+    LOCK,
+};
+
+use crate::{
+    ffi::{DataType, DataTypeCode, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    ManagedTensor, OwnedTensor,
+};
+
+fn unsupported(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, msg)
+}
+
+fn invalid_input(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+fn check(status: i32, call: &str) -> io::Result<()> {
+    if status < 0 {
+        return Err(io::Error::other(format!("{call} failed")));
+    }
+    Ok(())
+}
+
+/// The indices `start, start + step, ...` below `stop` along one axis, like
+/// `start:stop:step` in Python. `stop` is clipped to the length of the axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slice {
+    pub start: u64,
+    pub stop: u64,
+    pub step: u64,
+}
+
+impl Slice {
+    pub fn new(range: Range<u64>, step: u64) -> Self {
+        Self {
+            start: range.start,
+            stop: range.end,
+            step,
+        }
+    }
+
+    /// Number of indices selected from an axis of length `dim`.
+    fn count(&self, dim: u64) -> u64 {
+        let stop = self.stop.min(dim);
+        stop.saturating_sub(self.start).div_ceil(self.step)
+    }
+}
+
+impl From<Range<u64>> for Slice {
+    fn from(range: Range<u64>) -> Self {
+        Self::new(range, 1)
+    }
+}
+
+/// An HDF5 identifier, closed once dropped.
+struct Handle {
+    id: hid_t,
+    close: unsafe extern "C" fn(hid_t) -> i32,
+}
+
+impl Handle {
+    fn new(id: hid_t, close: unsafe extern "C" fn(hid_t) -> i32, call: &str) -> io::Result<Self> {
+        if id < 0 {
+            return Err(io::Error::other(format!("{call} failed")));
+        }
+        Ok(Self { id, close })
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { (self.close)(self.id) };
+    }
+}
+
+/// Initialize the library, which the native type ids need, and turn off its
+/// printing of error stacks. Must be called with [`LOCK`] held.
+fn init() -> io::Result<()> {
+    unsafe {
+        check(H5open(), "H5open")?;
+        check(
+            H5Eset_auto2(H5E_DEFAULT, None, ptr::null_mut()),
+            "H5Eset_auto2",
+        )
+    }
+}
+
+/// The native HDF5 type of `dtype`, if there is one. Must be called after
+/// [`init`].
+fn native_type(dtype: DataType) -> Option<hid_t> {
+    let id = match dtype {
+        DataType::I8 => &H5T_NATIVE_INT8,
+        DataType::I16 => &H5T_NATIVE_INT16,
+        DataType::I32 => &H5T_NATIVE_INT32,
+        DataType::I64 => &H5T_NATIVE_INT64,
+        DataType::U8 => &H5T_NATIVE_UINT8,
+        DataType::U16 => &H5T_NATIVE_UINT16,
+        DataType::U32 => &H5T_NATIVE_UINT32,
+        DataType::U64 => &H5T_NATIVE_UINT64,
+        DataType::F32 => &H5T_NATIVE_FLOAT,
+        DataType::F64 => &H5T_NATIVE_DOUBLE,
+        _ => return None,
+    };
+    Some(**id)
+}
+
+/// The dtype of the values of the file type `type_id`, if supported.
+fn dataset_dtype(type_id: hid_t) -> Option<DataType> {
+    let (class, size) = unsafe { (H5Tget_class(type_id), H5Tget_size(type_id)) };
+    let dtype = match (class, size) {
+        (H5T_class_t::H5T_INTEGER, 1 | 2 | 4 | 8) => {
+            let bits = size as u8 * 8;
+            match unsafe { H5Tget_sign(type_id) } {
+                H5T_sign_t::H5T_SGN_2 => (DataTypeCode::Int, bits, 1).into(),
+                H5T_sign_t::H5T_SGN_NONE => (DataTypeCode::UInt, bits, 1).into(),
+                _ => return None,
+            }
+        }
+        (H5T_class_t::H5T_FLOAT, 4) => DataType::F32,
+        (H5T_class_t::H5T_FLOAT, 8) => DataType::F64,
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+fn c_string(s: &str) -> io::Result<CString> {
+    CString::new(s).map_err(|_| invalid_input("name contains a nul byte"))
+}
+
+fn c_path(path: &Path) -> io::Result<CString> {
+    c_string(
+        path.to_str()
+            .ok_or_else(|| invalid_input("path is not utf-8"))?,
+    )
+}
+
+/// Read the dataset `name` of the HDF5 file at `path` into a new CPU tensor.
+pub fn read_hdf5<P: AsRef<Path>>(path: P, name: &str) -> io::Result<ManagedTensor> {
+    read_hdf5_slice(path, name, &[])
+}
+
+/// Read a hyperslab of the dataset `name` of the HDF5 file at `path` into a
+/// new CPU tensor. `slices` select the indices along the leading axes, the
+/// remaining axes are read whole.
+pub fn read_hdf5_slice<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    slices: &[Slice],
+) -> io::Result<ManagedTensor> {
+    let path = c_path(path.as_ref())?;
+    let name = c_string(name)?;
+    let _lock = LOCK.lock();
+    init()?;
+    unsafe {
+        let file = Handle::new(
+            H5Fopen(path.as_ptr(), H5F_ACC_RDONLY, H5P_DEFAULT),
+            H5Fclose,
+            "H5Fopen",
+        )?;
+        let dataset = Handle::new(
+            H5Dopen2(file.id, name.as_ptr(), H5P_DEFAULT),
+            H5Dclose,
+            "H5Dopen2",
+        )?;
+        let file_type = Handle::new(H5Dget_type(dataset.id), H5Tclose, "H5Dget_type")?;
+        let dtype =
+            dataset_dtype(file_type.id).ok_or_else(|| unsupported("unsupported hdf5 datatype"))?;
+        let mem_type =
+            native_type(dtype).ok_or_else(|| unsupported("unsupported hdf5 datatype"))?;
+        let file_space = Handle::new(H5Dget_space(dataset.id), H5Sclose, "H5Dget_space")?;
+
+        let rank = H5Sget_simple_extent_ndims(file_space.id);
+        if rank < 0 {
+            return Err(io::Error::other("H5Sget_simple_extent_ndims failed"));
+        }
+        let mut dims = vec![0u64; rank as usize];
+        if H5Sget_simple_extent_dims(file_space.id, dims.as_mut_ptr(), ptr::null_mut()) < 0 {
+            return Err(io::Error::other("H5Sget_simple_extent_dims failed"));
+        }
+        if slices.len() > dims.len() {
+            return Err(invalid_input("more slices than axes"));
+        }
+        if slices.iter().any(|slice| slice.step == 0) {
+            return Err(invalid_input("slice step must be positive"));
+        }
+
+        let mut start = vec![0; dims.len()];
+        let mut stride = vec![1; dims.len()];
+        let mut count = dims.clone();
+        for (axis, slice) in slices.iter().enumerate() {
+            count[axis] = slice.count(dims[axis]);
+            start[axis] = if count[axis] == 0 { 0 } else { slice.start };
+            stride[axis] = slice.step;
+        }
+        let shape = count
+            .iter()
+            .map(|&n| i64::try_from(n))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid shape"))?;
+        let mut tensor = OwnedTensor::try_new_zeroed(&shape, dtype)?;
+        if count.contains(&0) {
+            return Ok(ManagedTensor::from_dlpack(tensor.into_dlpack()));
+        }
+
+        // Without slices the whole dataset is read, which also covers scalar
+        // datasets that hyperslabs can't select from.
+        let mut mem_space = None;
+        if !slices.is_empty() {
+            check(
+                H5Sselect_hyperslab(
+                    file_space.id,
+                    H5S_seloper_t::H5S_SELECT_SET,
+                    start.as_ptr(),
+                    stride.as_ptr(),
+                    count.as_ptr(),
+                    ptr::null(),
+                ),
+                "H5Sselect_hyperslab",
+            )?;
+            mem_space = Some(Handle::new(
+                H5Screate_simple(rank, count.as_ptr(), ptr::null()),
+                H5Sclose,
+                "H5Screate_simple",
+            )?);
+        }
+        let (mem_space_id, file_space_id) = match &mem_space {
+            Some(mem_space) => (mem_space.id, file_space.id),
+            None => (H5S_ALL, H5S_ALL),
+        };
+        check(
+            H5Dread(
+                dataset.id,
+                mem_type,
+                mem_space_id,
+                file_space_id,
+                H5P_DEFAULT,
+                tensor.as_bytes_mut().as_mut_ptr().cast(),
+            ),
+            "H5Dread",
+        )?;
+        Ok(ManagedTensor::from_dlpack(tensor.into_dlpack()))
+    }
+}
+
+impl ManagedTensor {
+    /// Write a CPU tensor as the new dataset `name` of the HDF5 file at
+    /// `path`, which is created if it doesn't exist. Groups on the way to the
+    /// dataset must already exist.
+    pub fn save_hdf5<P: AsRef<Path>>(&self, path: P, name: &str) -> io::Result<()> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(unsupported("only cpu tensors can be written"));
+        }
+        self.check_supported()?;
+        let path = path.as_ref();
+        let exists = path.exists();
+        let path = c_path(path)?;
+        let name = c_string(name)?;
+        let dims: Vec<u64> = self.shape().iter().map(|&d| d as u64).collect();
+        let bytes = self.to_contiguous_bytes();
+        let _lock = LOCK.lock();
+        init()?;
+        let mem_type = native_type(self.dtype()).ok_or_else(|| unsupported("unsupported dtype"))?;
+        unsafe {
+            let file = if exists {
+                Handle::new(
+                    H5Fopen(path.as_ptr(), H5F_ACC_RDWR, H5P_DEFAULT),
+                    H5Fclose,
+                    "H5Fopen",
+                )?
+            } else {
+                Handle::new(
+                    H5Fcreate(path.as_ptr(), H5F_ACC_EXCL, H5P_DEFAULT, H5P_DEFAULT),
+                    H5Fclose,
+                    "H5Fcreate",
+                )?
+            };
+            let space = Handle::new(
+                H5Screate_simple(dims.len() as i32, dims.as_ptr(), ptr::null()),
+                H5Sclose,
+                "H5Screate_simple",
+            )?;
+            let dataset = Handle::new(
+                H5Dcreate2(
+                    file.id,
+                    name.as_ptr(),
+                    mem_type,
+                    space.id,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                ),
+                H5Dclose,
+                "H5Dcreate2",
+            )?;
+            check(
+                H5Dwrite(
+                    dataset.id,
+                    mem_type,
+                    H5S_ALL,
+                    H5S_ALL,
+                    H5P_DEFAULT,
+                    bytes.as_ptr().cast(),
+                ),
+                "H5Dwrite",
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ffi::Device,
+        fixtures::{transposed, Strided},
+    };
+
+    fn temp_file(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("dlpark-{name}-{}.h5", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_file("round-trip");
+        let v: Vec<i32> = (0..12).collect();
+        ManagedTensor::from_dlpack(Strided::new(v.clone(), &[3, 4], &[4, 1]).into_dlpack())
+            .save_hdf5(&path, "x")
+            .unwrap();
+        // Strided tensors are written in row-major order.
+        ManagedTensor::from_dlpack(transposed(vec![0.5f64, 1.0, 1.5, 2.0, 2.5, 3.0]).into_dlpack())
+            .save_hdf5(&path, "y")
+            .unwrap();
+
+        let x = read_hdf5(&path, "x").unwrap();
+        assert_eq!(x.shape(), &[3, 4]);
+        assert_eq!(x.dtype(), DataType::I32);
+        assert_eq!(x.as_slice::<i32>(), &v[..]);
+        let y = read_hdf5(&path, "y").unwrap();
+        assert_eq!(y.shape(), &[3, 2]);
+        assert_eq!(y.as_slice::<f64>(), &[0.5, 2.0, 1.0, 2.5, 1.5, 3.0]);
+
+        ManagedTensor::from_dlpack(Strided::new(vec![7i64], &[], &[]).into_dlpack())
+            .save_hdf5(&path, "z")
+            .unwrap();
+        let z = read_hdf5(&path, "z").unwrap();
+        assert!(z.shape().is_empty());
+        assert_eq!(z.as_slice::<i64>(), &[7]);
+
+        // The datasets exist already.
+        let tensor = ManagedTensor::from_dlpack(vec![1u8].into_dlpack());
+        assert!(tensor.save_hdf5(&path, "x").is_err());
+        assert!(read_hdf5(&path, "missing").is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn slices() {
+        let path = temp_file("slices");
+        let v: Vec<u16> = (0..12).collect();
+        ManagedTensor::from_dlpack(Strided::new(v, &[3, 4], &[4, 1]).into_dlpack())
+            .save_hdf5(&path, "x")
+            .unwrap();
+
+        // x[1:, ::2]
+        let tensor =
+            read_hdf5_slice(&path, "x", &[Slice::from(1..3), Slice::new(0..4, 2)]).unwrap();
+        assert_eq!(tensor.shape(), &[2, 2]);
+        assert_eq!(tensor.as_slice::<u16>(), &[4, 6, 8, 10]);
+        // x[1:10], clipped like in Python.
+        let tensor = read_hdf5_slice(&path, "x", &[Slice::from(1..10)]).unwrap();
+        assert_eq!(tensor.shape(), &[2, 4]);
+        let tensor = read_hdf5_slice(&path, "x", &[Slice::from(5..10)]).unwrap();
+        assert_eq!(tensor.shape(), &[0, 4]);
+
+        let err = read_hdf5_slice(&path, "x", &[Slice::new(0..3, 0)]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = read_hdf5_slice(&path, "x", &[Slice::from(0..1); 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn gpu_tensor() {
+        let tensor = ManagedTensor::from_dlpack(
+            Strided::new(vec![1.0f32], &[1], &[1])
+                .on(Device::cuda(0))
+                .into_dlpack(),
+        );
+        let err = tensor.save_hdf5(temp_file("gpu"), "x").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
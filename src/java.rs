@@ -0,0 +1,145 @@
+//! JNI bridge to the `dlpark.ManagedTensor` Java class in `java/`, for JVM
+//! and Android apps embedding Rust inference. Enabled by the `jni` feature.
+//!
+//! The Java object owns a `DLManagedTensor*` handle and runs its deleter when
+//! closed, or through a `Cleaner` once it is unreachable.
+
+use std::{mem::ManuallyDrop, ptr::NonNull};
+
+use jni::{
+    objects::{JClass, JObject, JValue},
+    sys::{jintArray, jlong, jlongArray, jobject},
+    JNIEnv,
+};
+
+use crate::{
+    ffi::{self, DeviceType},
+    tensor::traits::TensorView,
+    ManagedTensor, Ownership,
+};
+
+const CLASS: &str = "dlpark/ManagedTensor";
+
+impl ManagedTensor {
+    /// Wrap into a `dlpark.ManagedTensor` Java object owning this tensor.
+    pub fn into_java<'local>(
+        self,
+        env: &mut JNIEnv<'local>,
+    ) -> jni::errors::Result<JObject<'local>> {
+        let handle = self.into_inner();
+        env.new_object(CLASS, "(J)V", &[JValue::Long(handle.as_ptr() as jlong)])
+            .inspect_err(|_| drop(ManagedTensor::new(handle, Ownership::Owned)))
+    }
+
+    /// Take the tensor owned by a `dlpark.ManagedTensor` Java object, which
+    /// is closed afterwards.
+    ///
+    /// # Safety
+    /// The handle of `obj` must be a valid `DLManagedTensor*`.
+    pub unsafe fn from_java(env: &mut JNIEnv, obj: &JObject) -> jni::errors::Result<Self> {
+        let handle = env.call_method(obj, "release", "()J", &[])?.j()?;
+        let handle = NonNull::new(handle as *mut ffi::DLManagedTensor)
+            .ok_or(jni::errors::Error::NullPtr("dlpark.ManagedTensor handle"))?;
+        Ok(Self::new(handle, Ownership::Owned))
+    }
+}
+
+/// Borrow the tensor of a handle, which the Java side checks to be non-zero.
+unsafe fn borrow(handle: jlong) -> ManuallyDrop<ManagedTensor> {
+    ManuallyDrop::new(ManagedTensor::new(
+        NonNull::new_unchecked(handle as *mut ffi::DLManagedTensor),
+        Ownership::Borrowed,
+    ))
+}
+
+/// Throw an `IllegalStateException` unless one is pending already, and
+/// return NULL.
+fn throw(env: &mut JNIEnv, err: impl std::fmt::Display) -> jobject {
+    if !matches!(env.exception_check(), Ok(true)) {
+        let _ = env.throw_new("java/lang/IllegalStateException", err.to_string());
+    }
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "system" fn Java_dlpark_ManagedTensor_shape(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jlongArray {
+    let tensor = unsafe { borrow(handle) };
+    let shape = tensor.shape();
+    let array = env.new_long_array(shape.len() as i32).and_then(|array| {
+        env.set_long_array_region(&array, 0, shape)?;
+        Ok(array)
+    });
+    match array {
+        Ok(array) => array.into_raw(),
+        Err(err) => throw(&mut env, err),
+    }
+}
+
+fn new_int_array(env: &mut JNIEnv, values: &[i32]) -> jintArray {
+    let array = env.new_int_array(values.len() as i32).and_then(|array| {
+        env.set_int_array_region(&array, 0, values)?;
+        Ok(array)
+    });
+    match array {
+        Ok(array) => array.into_raw(),
+        Err(err) => throw(env, err),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_dlpark_ManagedTensor_dtype(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jintArray {
+    let dtype = unsafe { borrow(handle) }.dtype();
+    new_int_array(
+        &mut env,
+        &[dtype.code as i32, dtype.bits as i32, dtype.lanes as i32],
+    )
+}
+
+#[no_mangle]
+pub extern "system" fn Java_dlpark_ManagedTensor_device(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jintArray {
+    let device = unsafe { borrow(handle) }.device();
+    new_int_array(&mut env, &[device.device_type as i32, device.device_id])
+}
+
+#[no_mangle]
+pub extern "system" fn Java_dlpark_ManagedTensor_buffer(
+    mut env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jobject {
+    let tensor = unsafe { borrow(handle) };
+    if tensor.device().device_type != DeviceType::Cpu {
+        return throw(&mut env, "tensor is not on the cpu");
+    }
+    if !tensor.is_contiguous() {
+        return throw(&mut env, "tensor is not contiguous");
+    }
+    let len = tensor.data_size();
+    // The data pointer of an empty tensor may be NULL, which JNI rejects.
+    let data = if len == 0 {
+        NonNull::dangling().as_ptr()
+    } else {
+        tensor.first_byte()
+    };
+    match unsafe { env.new_direct_byte_buffer(data, len) } {
+        Ok(buffer) => buffer.into_raw(),
+        Err(err) => throw(&mut env, err),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_dlpark_ManagedTensor_free(_env: JNIEnv, _class: JClass, handle: jlong) {
+    drop(ManuallyDrop::into_inner(unsafe { borrow(handle) }));
+}
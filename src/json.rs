@@ -0,0 +1,154 @@
+//! JSON encoding of small CPU tensors, for HTTP APIs that can't carry binary
+//! payloads. Enabled by the `json` feature.
+//!
+//! A tensor is an object such as
+//! `{"dtype": "float32", "shape": [2, 3], "data_b64": "..."}`, where
+//! `data_b64` is the standard base64 of the row-major little-endian data.
+//! Dtypes are named by code and bits, e.g. `int8`, `uint16`, `bfloat16` or
+//! `complex64`, plus `bool`, with `x<lanes>` appended for vector types.
+//! Every byte is inflated by a third, so prefer the [wire](crate::wire)
+//! format for anything large.
+
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    ManagedTensor, OwnedTensor,
+};
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Name of `dtype` in JSON tensors, or `None` for opaque handles.
+pub fn dtype_name(dtype: DataType) -> Option<String> {
+    let code = match dtype.code {
+        DataTypeCode::Bool if dtype.bits == 8 => "bool",
+        DataTypeCode::Int => "int",
+        DataTypeCode::UInt => "uint",
+        DataTypeCode::Float => "float",
+        DataTypeCode::Bfloat => "bfloat",
+        DataTypeCode::Complex => "complex",
+        _ => return None,
+    };
+    let mut name = code.to_string();
+    if dtype.code != DataTypeCode::Bool {
+        name += &dtype.bits.to_string();
+    }
+    if dtype.lanes != 1 {
+        name += &format!("x{}", dtype.lanes);
+    }
+    Some(name)
+}
+
+/// Parse a dtype named by [`dtype_name`].
+pub fn parse_dtype_name(name: &str) -> Option<DataType> {
+    let (name, lanes) = match name.split_once('x') {
+        Some((name, lanes)) => (name, lanes.parse().ok()?),
+        None => (name, 1),
+    };
+    let builder = DataType::builder().lanes(lanes);
+    if name == "bool" {
+        return builder.code(DataTypeCode::Bool).bits(8).build().ok();
+    }
+    let digits = name.find(|c: char| c.is_ascii_digit())?;
+    let code = match &name[..digits] {
+        "int" => DataTypeCode::Int,
+        "uint" => DataTypeCode::UInt,
+        "float" => DataTypeCode::Float,
+        "bfloat" => DataTypeCode::Bfloat,
+        "complex" => DataTypeCode::Complex,
+        _ => return None,
+    };
+    let bits = name[digits..].parse().ok()?;
+    builder.code(code).bits(bits).build().ok()
+}
+
+impl ManagedTensor {
+    /// Encode a CPU tensor as a JSON object with its `dtype`, `shape` and
+    /// base64 `data_b64`.
+    pub fn to_json_value(&self) -> io::Result<Value> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be encoded",
+            ));
+        }
+        let dtype = dtype_name(self.dtype()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("dtype {:?} has no json name", self.dtype()),
+            )
+        })?;
+        let mut data = self.to_contiguous_bytes().into_owned();
+        convert_byte_order(&mut data, self.dtype(), Endian::NATIVE, Endian::Little);
+        Ok(json!({
+            "dtype": dtype,
+            "shape": self.shape(),
+            "data_b64": STANDARD.encode(data),
+        }))
+    }
+
+    /// Decode a JSON object made by [`ManagedTensor::to_json_value`] into a
+    /// new CPU tensor.
+    pub fn from_json_value(value: &Value) -> io::Result<Self> {
+        let field = |key| {
+            value
+                .get(key)
+                .ok_or_else(|| invalid_data(&format!("missing `{key}` in json tensor")))
+        };
+        let dtype = field("dtype")?
+            .as_str()
+            .and_then(parse_dtype_name)
+            .ok_or_else(|| invalid_data("invalid dtype in json tensor"))?;
+        let shape: Vec<i64> = field("shape")?
+            .as_array()
+            .and_then(|dims| dims.iter().map(Value::as_i64).collect())
+            .ok_or_else(|| invalid_data("invalid shape in json tensor"))?;
+        let mut data = field("data_b64")?
+            .as_str()
+            .and_then(|data| STANDARD.decode(data).ok())
+            .ok_or_else(|| invalid_data("invalid base64 data in json tensor"))?;
+        convert_byte_order(&mut data, dtype, Endian::Little, Endian::NATIVE);
+        let tensor = OwnedTensor::from_bytes(&data, &shape, dtype)
+            .ok_or_else(|| invalid_data("json tensor data doesn't match its shape"))?;
+        Ok(Self::from_dlpack(tensor.into_dlpack()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let tensor = ManagedTensor::from_dlpack(vec![1.5f32, -2.0, 0.25].into_dlpack());
+        let value = tensor.to_json_value().unwrap();
+        assert_eq!(
+            value,
+            json!({"dtype": "float32", "shape": [3], "data_b64": "AADAPwAAAMAAAIA+"})
+        );
+        let decoded = ManagedTensor::from_json_value(&value).unwrap();
+        assert_eq!(decoded.dtype(), DataType::F32);
+        assert_eq!(decoded.as_slice::<f32>(), &[1.5, -2.0, 0.25]);
+
+        for dtype in [
+            DataType::BOOL,
+            DataType::U16,
+            DataType::BF16,
+            (DataTypeCode::Float, 32, 4).into(),
+        ] {
+            assert_eq!(parse_dtype_name(&dtype_name(dtype).unwrap()), Some(dtype));
+        }
+        assert_eq!(parse_dtype_name("float"), None);
+        assert_eq!(parse_dtype_name("int8x0"), None);
+
+        let value = json!({"dtype": "int32", "shape": [2], "data_b64": "AQAAAA=="});
+        assert!(ManagedTensor::from_json_value(&value).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Negotiating between the layout a consumer needs and the one a tensor has,
+//! so data is only copied when it has to be.
+
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+use crate::{
+    allocator::{self, TensorAllocator},
+    ffi::{DataType, DataTypeCode, Device, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    utils::copy_strided_bytes,
+    validate::ValidationError,
+    Error, ManagedTensor, OwnedTensor, Result,
+};
+
+/// Requirements of a consumer on the tensors it accepts. Fields left as
+/// `None` accept anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestedLayout {
+    pub device: Option<Device>,
+    pub dtype: Option<DataType>,
+    /// Require row-major contiguous data.
+    pub contiguous: bool,
+}
+
+/// What has to happen to a tensor to satisfy a [`RequestedLayout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// The tensor can be used as is.
+    View,
+    /// The data has to be copied into row-major order.
+    Compact,
+    /// The data has to be converted to another dtype.
+    Cast(DataType),
+    /// The data has to be moved to another device.
+    Transfer(Device),
+}
+
+/// Physical order of the elements of a compact tensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Row-major, the last axis varies fastest, as in C and NumPy.
+    C,
+    /// Column-major, the first axis varies fastest, as in Fortran, LAPACK
+    /// and Julia.
+    F,
+}
+
+impl RequestedLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn device(self, device: Device) -> Self {
+        Self {
+            device: Some(device),
+            ..self
+        }
+    }
+
+    pub fn dtype(self, dtype: DataType) -> Self {
+        Self {
+            dtype: Some(dtype),
+            ..self
+        }
+    }
+
+    pub fn contiguous(self, contiguous: bool) -> Self {
+        Self { contiguous, ..self }
+    }
+
+    /// Decide the cheapest way to make `tensor` satisfy this layout. A
+    /// transfer or cast takes precedence over compacting, since either one
+    /// produces a contiguous copy anyway.
+    pub fn conversion<T: TensorView + ?Sized>(&self, tensor: &T) -> Conversion {
+        match (self.device, self.dtype) {
+            (Some(device), _) if device != tensor.device() => Conversion::Transfer(device),
+            (_, Some(dtype)) if dtype != tensor.dtype() => Conversion::Cast(dtype),
+            _ if self.contiguous && !tensor.is_contiguous() => Conversion::Compact,
+            _ => Conversion::View,
+        }
+    }
+}
+
+/// Element of a dtype [`ManagedTensor::require`] can cast, widened.
+#[derive(Clone, Copy)]
+enum Scalar {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+/// Whether elements of `dtype` can be cast, i.e. it is one of the usual
+/// numeric types or bool.
+fn castable(dtype: DataType) -> bool {
+    dtype.lanes == 1
+        && matches!(
+            (dtype.code, dtype.bits),
+            (DataTypeCode::Int | DataTypeCode::UInt, 8 | 16 | 32 | 64)
+                | (DataTypeCode::Float, 32 | 64)
+                | (DataTypeCode::Bool, 8)
+        )
+}
+
+fn read_scalar(bytes: &[u8], code: DataTypeCode) -> Scalar {
+    macro_rules! ne {
+        ($ty:ty) => {
+            <$ty>::from_ne_bytes(bytes.try_into().unwrap())
+        };
+    }
+    match (code, bytes.len()) {
+        (DataTypeCode::Int, 1) => Scalar::Int(ne!(i8).into()),
+        (DataTypeCode::Int, 2) => Scalar::Int(ne!(i16).into()),
+        (DataTypeCode::Int, 4) => Scalar::Int(ne!(i32).into()),
+        (DataTypeCode::Int, _) => Scalar::Int(ne!(i64)),
+        (DataTypeCode::Float, 4) => Scalar::Float(ne!(f32).into()),
+        (DataTypeCode::Float, _) => Scalar::Float(ne!(f64)),
+        (_, 1) => Scalar::UInt(ne!(u8).into()),
+        (_, 2) => Scalar::UInt(ne!(u16).into()),
+        (_, 4) => Scalar::UInt(ne!(u32).into()),
+        (..) => Scalar::UInt(ne!(u64)),
+    }
+}
+
+/// Write `value` converted as by `as`, or to 0 or 1 for bools.
+fn write_scalar(value: Scalar, code: DataTypeCode, out: &mut [u8]) {
+    macro_rules! put {
+        ($ty:ty) => {
+            out.copy_from_slice(
+                &match value {
+                    Scalar::Int(v) => v as $ty,
+                    Scalar::UInt(v) => v as $ty,
+                    Scalar::Float(v) => v as $ty,
+                }
+                .to_ne_bytes(),
+            )
+        };
+    }
+    match (code, out.len()) {
+        (DataTypeCode::Bool, _) => {
+            out[0] = match value {
+                Scalar::Int(v) => v != 0,
+                Scalar::UInt(v) => v != 0,
+                Scalar::Float(v) => v != 0.0,
+            } as u8
+        }
+        (DataTypeCode::Int, 1) => put!(i8),
+        (DataTypeCode::Int, 2) => put!(i16),
+        (DataTypeCode::Int, 4) => put!(i32),
+        (DataTypeCode::Int, _) => put!(i64),
+        (DataTypeCode::Float, 4) => put!(f32),
+        (DataTypeCode::Float, _) => put!(f64),
+        (_, 1) => put!(u8),
+        (_, 2) => put!(u16),
+        (_, 4) => put!(u32),
+        (..) => put!(u64),
+    }
+}
+
+impl ManagedTensor {
+    /// Return this tensor if it already satisfies `layout`, without copying,
+    /// or a contiguous copy of it compacted or cast to satisfy it otherwise,
+    /// together with whether a copy was made, as the `IS_COPIED` flag of
+    /// DLPack tells. Casts convert as Rust's `as` does and are supported
+    /// between the usual numeric types and bool, on the CPU only.
+    pub fn require(self, layout: &RequestedLayout) -> Result<(Self, bool)> {
+        match layout.conversion(&self) {
+            Conversion::View => Ok((self, false)),
+            Conversion::Compact => Ok((self.into_layout(layout)?, true)),
+            Conversion::Cast(dtype)
+                if self.device().device_type == DeviceType::Cpu
+                    && castable(dtype)
+                    && castable(self.dtype()) =>
+            {
+                self.check_supported()?;
+                let mut owned = OwnedTensor::new_zeroed(self.shape(), dtype)
+                    .ok_or(ValidationError::TooManyElements)?;
+                let src = self.to_contiguous_bytes();
+                let elements = src.chunks_exact(self.dtype().size());
+                let out = owned.as_bytes_mut().chunks_exact_mut(dtype.size());
+                for (element, out) in elements.zip(out) {
+                    write_scalar(read_scalar(element, self.dtype().code), dtype.code, out);
+                }
+                Ok((Self::from_dlpack(owned.into_dlpack()), true))
+            }
+            // Left to `into_layout` to report.
+            _ => self.into_layout(layout).map(|tensor| (tensor, false)),
+        }
+    }
+
+    /// Return this tensor if it already satisfies `layout`, or a compacted
+    /// copy of it if it only lacks contiguity. Transfers and casts are left to
+    /// the caller.
+    pub fn into_layout(self, layout: &RequestedLayout) -> Result<Self> {
+        match layout.conversion(&self) {
+            Conversion::View => Ok(self),
+            Conversion::Compact if self.device().device_type == DeviceType::Cpu => {
+                let owned = OwnedTensor::from_bytes(
+                    &self.to_contiguous_bytes(),
+                    self.shape(),
+                    self.dtype(),
+                )
+                .ok_or(ValidationError::TooManyElements)?;
+                Ok(Self::from_dlpack(owned.into_dlpack()))
+            }
+            Conversion::Compact => Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: self.device(),
+            }),
+            Conversion::Cast(dtype) => Err(Error::DtypeMismatch {
+                expected: dtype,
+                found: self.dtype(),
+            }),
+            Conversion::Transfer(device) => Err(Error::DeviceMismatch {
+                expected: device,
+                found: self.device(),
+            }),
+        }
+    }
+
+    /// Copy this CPU tensor into a new buffer with its elements in `order`,
+    /// e.g. [`Order::F`] for column-major consumers. The copy has the same
+    /// shape, with strides describing the new order.
+    pub fn to_layout(&self, order: Order) -> Result<Self> {
+        self.to_layout_in(order, allocator::global())
+    }
+
+    /// Same as [`ManagedTensor::to_layout`], with the buffer allocated by
+    /// `allocator` instead of the global one.
+    pub fn to_layout_in(
+        &self,
+        order: Order,
+        allocator: &'static dyn TensorAllocator,
+    ) -> Result<Self> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: self.device(),
+            });
+        }
+        self.check_supported()?;
+        let mut shape = self.shape().to_vec();
+        let mut strides = self.strides_or_contiguous().into_owned();
+        // Column-major data is the row-major data of the reversed axes.
+        if order == Order::F {
+            shape.reverse();
+            strides.reverse();
+        }
+        let mut owned = OwnedTensor::new_zeroed_in(&shape, self.dtype(), allocator)
+            .ok_or(ValidationError::TooManyElements)?;
+        let bytes = owned.as_bytes_mut();
+        if !bytes.is_empty() {
+            unsafe {
+                let dst = core::slice::from_raw_parts_mut(
+                    bytes.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+                    bytes.len(),
+                );
+                copy_strided_bytes(
+                    self.first_byte(),
+                    &shape,
+                    &strides,
+                    self.dtype().size(),
+                    dst,
+                );
+            }
+        }
+        let tensor = Self::from_dlpack(owned.into_dlpack());
+        match order {
+            Order::C => Ok(tensor),
+            Order::F => tensor.permute(&(0..shape.len()).rev().collect::<Vec<_>>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ffi::DataType, fixtures::transposed};
+
+    #[test]
+    fn negotiate() {
+        let tensor = ManagedTensor::from_dlpack(
+            transposed((0..6).map(|x| x as f32).collect()).into_dlpack(),
+        );
+        let layout = RequestedLayout::new().device(Device::CPU);
+        assert_eq!(layout.conversion(&tensor), Conversion::View);
+        assert_eq!(
+            layout.dtype(DataType::F64).conversion(&tensor),
+            Conversion::Cast(DataType::F64)
+        );
+        assert_eq!(
+            layout.device(Device::cuda(0)).conversion(&tensor),
+            Conversion::Transfer(Device::cuda(0))
+        );
+
+        let layout = layout.contiguous(true);
+        assert_eq!(layout.conversion(&tensor), Conversion::Compact);
+        let tensor = tensor.into_layout(&layout).unwrap();
+        assert!(tensor.is_contiguous());
+        assert_eq!(tensor.as_slice::<f32>(), &[0.0, 3.0, 1.0, 4.0, 2.0, 5.0]);
+        let ptr = tensor.data_ptr();
+        let tensor = tensor.into_layout(&layout).unwrap();
+        assert_eq!(tensor.data_ptr(), ptr);
+        assert!(matches!(
+            tensor.into_layout(&layout.dtype(DataType::F64)),
+            Err(Error::DtypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn require() {
+        let tensor = ManagedTensor::from_dlpack(
+            transposed((0..6).map(|x| x as f32 - 2.5).collect()).into_dlpack(),
+        );
+        let ptr = tensor.data_ptr();
+        let (tensor, copied) = tensor.require(&RequestedLayout::new()).unwrap();
+        assert!(!copied);
+        assert_eq!(tensor.data_ptr(), ptr);
+
+        let (cast, copied) = tensor
+            .require(&RequestedLayout::new().dtype(DataType::I16))
+            .unwrap();
+        assert!(copied);
+        assert_eq!(cast.dtype(), DataType::I16);
+        assert_eq!(cast.as_slice::<i16>(), &[-2, 0, -1, 1, 0, 2]);
+        let (cast, _) = cast
+            .require(&RequestedLayout::new().dtype(DataType::BOOL))
+            .unwrap();
+        assert_eq!(cast.as_slice::<u8>(), &[1, 0, 1, 1, 0, 1]);
+        assert!(matches!(
+            cast.require(&RequestedLayout::new().dtype(DataType::F16)),
+            Err(Error::DtypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reorder() {
+        // [[0, 1, 2], [3, 4, 5]]
+        let tensor = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap();
+        let fortran = tensor.to_layout(Order::F).unwrap();
+        assert_eq!(fortran.shape(), &[2, 3]);
+        assert_eq!(fortran.strides(), Some([1, 2].as_slice()));
+        let data = unsafe { std::slice::from_raw_parts(fortran.data_ptr().cast::<i32>(), 6) };
+        assert_eq!(data, [0, 3, 1, 4, 2, 5]);
+
+        let back = fortran.to_layout(Order::C).unwrap();
+        assert!(back.is_contiguous());
+        assert_eq!(back.as_slice::<i32>(), [0, 1, 2, 3, 4, 5]);
+    }
+}
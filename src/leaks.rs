@@ -0,0 +1,198 @@
+//! Tracking of the tensors exported by this crate that haven't been deleted
+//! yet, e.g. to find capsules leaked on the Python side of a long-running
+//! service. Enabled by the `leak-tracking` feature.
+//!
+//! Exports can also be labeled, e.g. with the name of the request or model
+//! that produced them, and looked up by label as [`WeakExport`]s, which
+//! don't keep them alive.
+
+use std::{
+    collections::BTreeMap,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::ffi::{self, DataType, Device};
+
+/// A tensor exported by this crate whose deleter hasn't run yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveExport {
+    pub ptr: *const ffi::DLManagedTensor,
+    pub device: Device,
+    pub dtype: DataType,
+    pub shape: Vec<i64>,
+    /// Time since the tensor was exported.
+    pub age: Duration,
+    /// Label given with [`label_export`], if any.
+    pub label: Option<String>,
+}
+
+/// A handle to an export that doesn't keep it alive, from [`label_export`]
+/// or [`find_exports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeakExport {
+    addr: usize,
+    /// Tells apart exports at the same address.
+    id: u64,
+}
+
+impl WeakExport {
+    /// Snapshot of the export if it wasn't deleted yet, `None` otherwise,
+    /// even if another tensor was exported at the same address since.
+    pub fn upgrade(&self) -> Option<LiveExport> {
+        let now = Instant::now();
+        with_live(|live| {
+            live.get(&self.addr)
+                .filter(|entry| entry.id == self.id)
+                .map(|entry| entry.snapshot(now))
+        })
+    }
+
+    pub fn is_live(&self) -> bool {
+        with_live(|live| {
+            live.get(&self.addr)
+                .is_some_and(|entry| entry.id == self.id)
+        })
+    }
+}
+
+struct Entry {
+    id: u64,
+    ptr: *const ffi::DLManagedTensor,
+    device: Device,
+    dtype: DataType,
+    shape: Vec<i64>,
+    exported_at: Instant,
+    label: Option<String>,
+}
+
+impl Entry {
+    fn weak(&self) -> WeakExport {
+        WeakExport {
+            addr: self.ptr as usize,
+            id: self.id,
+        }
+    }
+
+    fn snapshot(&self, now: Instant) -> LiveExport {
+        LiveExport {
+            ptr: self.ptr,
+            device: self.device,
+            dtype: self.dtype,
+            shape: self.shape.clone(),
+            age: now.saturating_duration_since(self.exported_at),
+            label: self.label.clone(),
+        }
+    }
+}
+
+// Only used to report the pointers.
+unsafe impl Send for Entry {}
+
+static LIVE: Mutex<BTreeMap<usize, Entry>> = Mutex::new(BTreeMap::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn with_live<T>(f: impl FnOnce(&mut BTreeMap<usize, Entry>) -> T) -> T {
+    f(&mut LIVE.lock().unwrap_or_else(|err| err.into_inner()))
+}
+
+pub(crate) fn exported(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    use crate::tensor::traits::TensorView;
+
+    let entry = Entry {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        ptr: managed,
+        device: tensor.device,
+        dtype: tensor.dtype,
+        shape: tensor.shape().to_vec(),
+        exported_at: Instant::now(),
+        label: None,
+    };
+    with_live(|live| live.insert(managed as usize, entry));
+}
+
+pub(crate) fn deleted(managed: *const ffi::DLManagedTensor) {
+    with_live(|live| live.remove(&(managed as usize)));
+}
+
+/// Number of exported tensors that haven't been deleted yet.
+pub fn live_export_count() -> usize {
+    with_live(|live| live.len())
+}
+
+/// Label the tensor `managed` exported by this crate, replacing any previous
+/// label. The label goes away with the tensor. Returns `None` if `managed`
+/// isn't a live export of this crate.
+pub fn label_export(
+    managed: NonNull<ffi::DLManagedTensor>,
+    label: impl Into<String>,
+) -> Option<WeakExport> {
+    with_live(|live| {
+        let entry = live.get_mut(&(managed.as_ptr() as usize))?;
+        entry.label = Some(label.into());
+        Some(entry.weak())
+    })
+}
+
+/// Handles to the live exports labeled `label`, oldest first.
+pub fn find_exports(label: &str) -> Vec<WeakExport> {
+    let mut exports: Vec<_> = with_live(|live| {
+        live.values()
+            .filter(|entry| entry.label.as_deref() == Some(label))
+            .map(|entry| (entry.exported_at, entry.weak()))
+            .collect()
+    });
+    exports.sort_by_key(|&(exported_at, _)| exported_at);
+    exports.into_iter().map(|(_, weak)| weak).collect()
+}
+
+/// Snapshot of every exported tensor that hasn't been deleted yet, oldest
+/// first.
+pub fn dump_live_exports() -> Vec<LiveExport> {
+    let now = Instant::now();
+    let mut exports: Vec<_> =
+        with_live(|live| live.values().map(|entry| entry.snapshot(now)).collect());
+    exports.sort_by_key(|export| std::cmp::Reverse(export.age));
+    exports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, ManagedTensor};
+
+    #[test]
+    fn live_exports() {
+        let dlpack = vec![0f32; 6].into_dlpack();
+        let export = dump_live_exports()
+            .into_iter()
+            .find(|export| export.ptr == dlpack.as_ptr())
+            .unwrap();
+        assert_eq!(export.dtype, DataType::F32);
+        assert_eq!(export.shape, [6]);
+        assert!(live_export_count() >= 1);
+
+        assert_eq!(export.label, None);
+        let weak = label_export(dlpack, "embeddings").unwrap();
+        assert_eq!(find_exports("embeddings"), [weak]);
+        let labeled = weak.upgrade().unwrap();
+        assert_eq!(labeled.ptr, dlpack.as_ptr());
+        assert_eq!(labeled.device, Device::CPU);
+        assert_eq!(labeled.label.as_deref(), Some("embeddings"));
+
+        drop(ManagedTensor::from_dlpack(dlpack));
+        assert!(find_exports("embeddings").is_empty());
+        assert!(!weak.is_live());
+        // Likely at the same address, reused from the pool.
+        let again = vec![0f32; 6].into_dlpack();
+        assert!(weak.upgrade().is_none());
+        drop(ManagedTensor::from_dlpack(again));
+        assert!(dump_live_exports()
+            .iter()
+            .all(|export| export.ptr != dlpack.as_ptr()));
+    }
+}
@@ -1,29 +1,141 @@
-mod data_type;
-mod device;
+// Without `std` only the core remains: the raw bindings, exporting with
+// `ManagerCtx` and importing as `ManagedTensor`, on top of `alloc`.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod audio;
+mod chain;
+mod concat;
+mod device_buffer;
 mod dl_managed_tensor;
+#[cfg(feature = "dlpack-1-0")]
 mod dl_managed_tensor_versioned;
 mod dl_tensor;
+mod endian;
+mod error;
+mod foreign_tensor;
 mod manager_ctx;
-mod pack_version;
+mod map;
+mod owned_tensor;
+mod reduce;
 mod shape_and_strides;
+mod static_tensor;
 mod tensor;
 
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "rayon")]
+mod parallel;
 #[cfg(feature = "pyo3")]
 mod python;
+#[cfg(feature = "rand")]
+mod random;
+#[cfg(feature = "std")]
+mod tensor_dict;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "zerocopy")]
+mod zero_copy;
+
+#[cfg(feature = "debug-guards")]
+pub mod guards;
+#[cfg(feature = "leak-tracking")]
+pub mod leaks;
 
-/// Raw bindings for DLPack.
+pub mod allocator;
+pub mod device_names;
+/// Raw bindings for DLPack, re-exported from `dlpark-sys`.
 pub mod ffi;
+pub mod layout;
+pub mod pool;
 pub mod utils;
+pub mod validate;
+
+#[cfg(feature = "std")]
+pub mod npy;
+#[cfg(feature = "std")]
+pub mod plugin;
+#[cfg(feature = "std")]
+pub mod wire;
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "tokio")]
+pub mod channel;
+#[cfg(any(feature = "csv", feature = "parquet"))]
+pub mod columnar;
+#[cfg(feature = "cudarc")]
+pub mod cuda_ipc;
+#[cfg(feature = "flatbuffers")]
+pub mod flatbuf;
+#[cfg(feature = "arrow-flight")]
+pub mod flight;
+#[cfg(feature = "gguf")]
+pub mod gguf;
+#[cfg(feature = "tonic")]
+pub mod grpc;
+#[cfg(feature = "hdf5")]
+pub mod hdf5;
+#[cfg(feature = "jni")]
+pub mod java;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "nifti")]
+pub mod nifti;
+#[cfg(feature = "napi")]
+pub mod node;
+#[cfg(feature = "npz")]
+pub mod npz;
+#[cfg(all(feature = "numa", target_os = "linux"))]
+pub mod numa;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+#[cfg(feature = "cudarc")]
+pub mod pinned;
+#[cfg(feature = "safetensors")]
+pub mod safetensors;
+#[cfg(all(feature = "shm", unix))]
+pub mod shm;
+#[cfg(feature = "test-support")]
+pub mod testing;
+#[cfg(feature = "wasm-bindgen")]
+pub mod wasm;
+#[cfg(feature = "zarr")]
+pub mod zarr;
 
 /// Imports the structs and traits for you to implement [`IntoDLPack`] and
 /// [`FromDLPack`].
 pub mod prelude;
 
+#[cfg(feature = "malloc")]
+pub use crate::foreign_tensor::MallocTensor;
+#[cfg(feature = "rayon")]
+pub use crate::parallel::{parallel_copy_threshold, set_parallel_copy_threshold};
+#[cfg(feature = "pyo3")]
+pub use crate::python::{dlpack_device, PyBytesTensor, Stream, TensorBuffer};
+#[cfg(feature = "std")]
+pub use crate::tensor_dict::TensorDict;
+#[cfg(feature = "zerocopy")]
+pub use crate::zero_copy::BytesTensor;
 pub use crate::{
+    chain::{ChainedManager, Chunks, TensorChunk},
+    concat::{concat, stack},
+    device_buffer::{BufferTensor, DeviceBuffer},
+    endian::{convert_byte_order, swap_byte_order, Endian},
+    error::{Error, InvariantPolicy, Result},
+    foreign_tensor::ForeignTensor,
+    layout::{Conversion, Order, RequestedLayout},
     manager_ctx::ManagerCtx,
-    shape_and_strides::ShapeAndStrides,
+    owned_tensor::{ArangeElement, OwnedTensor, OWNED_TENSOR_ALIGNMENT},
+    shape_and_strides::{LayoutError, ShapeAndStrides, MAX_INLINE_NDIM},
+    static_tensor::StaticTensor,
     tensor::{
+        sync::{SendTensor, SyncTensorView},
         traits::{DLPack, FromDLPack, InferDtype, IntoDLPack, TensorView, ToTensor},
-        ManagedTensor,
+        typed::{Tensor, TypedIntoIter, TypedIter, TypedTensor},
+        ManagedTensor, Ownership,
     },
 };
@@ -1,18 +1,173 @@
-use std::ptr::NonNull;
+use alloc::{alloc::Layout, boxed::Box, string::String, vec::Vec};
+use core::{
+    any::TypeId,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+};
 
 use crate::{
-    ffi,
+    ffi, pool,
     prelude::ToTensor,
     tensor::traits::{IntoDLPack, TensorView},
-    ShapeAndStrides,
+    utils::catch_ffi_panic,
+    LayoutError, Result, ShapeAndStrides,
 };
 
-unsafe extern "C" fn deleter_fn<T>(dl_managed_tensor: *mut ffi::DLManagedTensor) {
-    // Reconstruct pointer and destroy it.
-    let ctx = (*dl_managed_tensor).manager_ctx as *mut T;
-    // https://doc.rust-lang.org/std/boxed/struct.Box.html#method.into_raw
-    // Use from_raw to clean it.
-    unsafe { let _ = Box::from_raw(ctx); };
+/// The single allocation backing an exported tensor. The shape, followed by
+/// the strides if there are any, is stored right after it.
+#[repr(C)]
+struct Exported<T> {
+    // First field, so the block and its DLManagedTensor share an address.
+    tensor: ffi::DLManagedTensor,
+    // Before `inner`, so it is at the same offset whatever `T` is.
+    header: Header,
+    inner: T,
+}
+
+/// The fields of an [`Exported`] block that don't depend on its `T`.
+#[repr(C)]
+struct Header {
+    /// Drops the block and frees it.
+    release: unsafe fn(*mut ffi::DLManagedTensor),
+    /// The `T` of the block, for [`reclaim`] to check.
+    type_id: TypeId,
+    #[cfg(feature = "dlpack-1-0")]
+    flags: u64,
+    ndim: usize,
+    has_strides: bool,
+    axis_names: Option<Box<[String]>>,
+}
+
+impl<T> Exported<T> {
+    /// Layout of the block and the offset of the shape within it.
+    fn layout(ndim: usize, has_strides: bool) -> (Layout, usize) {
+        let dims = if has_strides { ndim * 2 } else { ndim };
+        let (layout, offset) = Layout::new::<Self>()
+            .extend(Layout::array::<i64>(dims).expect("too many dimensions"))
+            .expect("too many dimensions");
+        (layout.pad_to_align(), offset)
+    }
+}
+
+/// The deleter of every block, which isn't generic so that blocks can be
+/// told apart from tensors of other producers by it.
+///
+/// Calling it twice on the same tensor is undefined behavior, as for any
+/// DLPack deleter; the `debug-guards` feature catches it.
+unsafe extern "C" fn deleter_fn(dl_managed_tensor: *mut ffi::DLManagedTensor) {
+    #[cfg(feature = "debug-guards")]
+    crate::guards::deleted(dl_managed_tensor);
+    #[cfg(feature = "leak-tracking")]
+    crate::leaks::deleted(dl_managed_tensor);
+    let block = (*dl_managed_tensor).manager_ctx as *mut Exported<()>;
+    #[cfg(feature = "tracing")]
+    crate::trace::deleted(dl_managed_tensor, &(*dl_managed_tensor).dl_tensor);
+    ((*block).header.release)(dl_managed_tensor);
+}
+
+/// The block was allocated in `into_dl_managed_tensor`, drop it in place and
+/// give it back to the pool with the same layout.
+unsafe fn release<T>(dl_managed_tensor: *mut ffi::DLManagedTensor) {
+    let block = (*dl_managed_tensor).manager_ctx as *mut Exported<T>;
+    let (layout, _) = Exported::<T>::layout((*block).header.ndim, (*block).header.has_strides);
+    unsafe {
+        // A panicking drop of the tensor is reported and the block freed anyway.
+        catch_ffi_panic("DLManagedTensor deleter", || ptr::drop_in_place(block));
+        pool::dealloc(NonNull::new_unchecked(block.cast()), layout);
+    }
+}
+
+/// The `TypeId` of `T` with its lifetimes erased, as `TypeId::of` only takes
+/// `'static` types and exported ones don't have to be.
+fn type_id<T>() -> TypeId {
+    trait Erased {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T> Erased for PhantomData<T> {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let erased: &dyn Erased = &PhantomData::<T>;
+    // Lifetimes don't exist at runtime, extending them to get the id is fine.
+    unsafe { core::mem::transmute::<&dyn Erased, &'static dyn Erased>(erased) }.type_id()
+}
+
+/// Whether `managed` was exported by [`ManagerCtx`].
+///
+/// # Safety
+/// `managed` must point to a valid DLManagedTensor.
+#[allow(unpredictable_function_pointer_comparisons)]
+pub(crate) unsafe fn is_exported(managed: *const ffi::DLManagedTensor) -> bool {
+    (*managed).deleter == Some(deleter_fn as _)
+}
+
+/// Axis names given to `managed` with [`ManagerCtx::with_axis_names`], if it
+/// was exported by one.
+///
+/// # Safety
+/// `managed` must point to a valid DLManagedTensor, which must outlive the
+/// names.
+pub(crate) unsafe fn axis_names<'a>(
+    managed: NonNull<ffi::DLManagedTensor>,
+) -> Option<&'a [String]> {
+    if !is_exported(managed.as_ptr()) {
+        return None;
+    }
+    let block = managed.as_ref().manager_ctx as *const Exported<()>;
+    (*block).header.axis_names.as_deref()
+}
+
+/// Flags given to `managed` by [`ToTensor::flags`], or 0 if it wasn't
+/// exported by [`ManagerCtx`].
+///
+/// # Safety
+/// `managed` must point to a valid DLManagedTensor.
+#[cfg(feature = "dlpack-1-0")]
+pub(crate) unsafe fn flags(managed: NonNull<ffi::DLManagedTensor>) -> u64 {
+    if !is_exported(managed.as_ptr()) {
+        return 0;
+    }
+    let block = managed.as_ref().manager_ctx as *const Exported<()>;
+    (*block).header.flags
+}
+
+/// Take back the `T` that `managed` was exported from by [`ManagerCtx`],
+/// freeing the rest of the allocation without running the deleter. Returns
+/// `None`, leaving `managed` untouched, if it wasn't exported from a `T`.
+///
+/// # Safety
+/// `managed` must point to a valid DLManagedTensor, which must not be used
+/// again if `Some` is returned.
+pub(crate) unsafe fn reclaim<T>(managed: NonNull<ffi::DLManagedTensor>) -> Option<T> {
+    let managed = managed.as_ptr();
+    if !is_exported(managed) {
+        return None;
+    }
+    let block = (*managed).manager_ctx as *mut Exported<T>;
+    if (*block).header.type_id != type_id::<T>() {
+        return None;
+    }
+    #[cfg(feature = "debug-guards")]
+    crate::guards::deleted(managed);
+    #[cfg(feature = "leak-tracking")]
+    crate::leaks::deleted(managed);
+    #[cfg(feature = "tracing")]
+    crate::trace::reclaimed(managed, &(*managed).dl_tensor);
+    let (layout, _) = Exported::<T>::layout((*block).header.ndim, (*block).header.has_strides);
+    unsafe {
+        ptr::drop_in_place(ptr::addr_of_mut!((*block).header));
+        let inner = ptr::read(ptr::addr_of!((*block).inner));
+        pool::dealloc(NonNull::new_unchecked(block.cast()), layout);
+        Some(inner)
+    }
 }
 
 // TODO: should be ManagerCtx<T, M> where M is one of DLManagedTensor and
@@ -21,8 +176,7 @@ unsafe extern "C" fn deleter_fn<T>(dl_managed_tensor: *mut ffi::DLManagedTensor)
 pub struct ManagerCtx<T> {
     inner: T,
     shape_and_strides: ShapeAndStrides,
-    // The ctx should hold DLManagedTensor, so that the tensor can be freed.
-    tensor: Option<ffi::DLManagedTensor>,
+    axis_names: Option<Box<[String]>>,
 }
 
 impl<T> ManagerCtx<T>
@@ -34,34 +188,114 @@ where
         Self {
             inner,
             shape_and_strides,
-            tensor: None,
+            axis_names: None,
         }
     }
 
+    /// Export every tensor of `iter`, e.g. all outputs of a pipeline step, in
+    /// one pass. The caller becomes responsible for calling their deleters.
+    pub fn export_batch<I>(iter: I) -> Vec<NonNull<ffi::DLManagedTensor>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let iter = iter.into_iter();
+        let mut dlpacks = Vec::with_capacity(iter.size_hint().0);
+        dlpacks.extend(iter.map(|inner| Self::new(inner).into_dl_managed_tensor()));
+        dlpacks
+    }
+
+    /// Export NULL strides if the tensor is row-major contiguous, leaving
+    /// consumers to compute them when needed.
+    pub fn lazy_strides(self) -> Self {
+        Self {
+            shape_and_strides: self.shape_and_strides.into_lazy(),
+            ..self
+        }
+    }
+
+    /// Label the axes, e.g. `["batch", "channel", "height", "width"]`, for
+    /// consumers to check with [`ManagedTensor::axis_names`]. Fails unless
+    /// there is one name per axis.
+    ///
+    /// [`ManagedTensor::axis_names`]: crate::ManagedTensor::axis_names
+    pub fn with_axis_names<I>(self, names: I) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let names: Box<[String]> = names.into_iter().map(Into::into).collect();
+        if names.len() != self.shape_and_strides.len() {
+            return Err(LayoutError::NdimMismatch {
+                expected: self.shape_and_strides.len(),
+                found: names.len(),
+            }
+            .into());
+        }
+        Ok(Self {
+            axis_names: Some(names),
+            ..self
+        })
+    }
+
+    pub fn axis_names(&self) -> Option<&[String]> {
+        self.axis_names.as_deref()
+    }
+
+    /// Move the tensor, its shape and strides and the DLManagedTensor into a
+    /// single heap allocation, taken from and returned to the [`pool`] of the
+    /// current thread.
     pub(crate) fn into_dl_managed_tensor(self) -> NonNull<ffi::DLManagedTensor> {
-        // Move self to heap and get it's pointer.
-        // We leak the data here and let deleter handle its memmory.
-        let ctx = Box::leak(Box::new(self));
-        let tensor: ffi::DLManagedTensor = ffi::DLManagedTensor {
-            dl_tensor: ctx.make_dl_tensor(),
-            manager_ctx: ctx as *mut Self as *mut std::ffi::c_void,
-            deleter: Some(deleter_fn::<Self>),
-        };
-        // Hold the data so it can be dropped when ctx dropped.
-        ctx.tensor = Some(tensor);
-        // Take the address of DLManagedTensor
-        NonNull::from(ctx.tensor.as_ref().unwrap())
-    }
-
-    fn make_dl_tensor(&self) -> ffi::DLTensor {
-        ffi::DLTensor {
-            data: self.inner.data_ptr(),
-            device: self.inner.device(),
-            ndim: self.shape_and_strides.ndim(),
-            dtype: self.inner.dtype(),
-            shape: self.shape_and_strides.shape_ptr(),
-            strides: self.shape_and_strides.strides_ptr(),
-            byte_offset: self.inner.byte_offset(),
+        let Self {
+            inner,
+            shape_and_strides,
+            axis_names,
+        } = self;
+        let ndim = shape_and_strides.len();
+        let strides = shape_and_strides.strides();
+        let (layout, offset) = Exported::<T>::layout(ndim, strides.is_some());
+        unsafe {
+            let block = pool::alloc(layout).as_ptr() as *mut Exported<T>;
+            let shape = block.cast::<u8>().add(offset).cast::<i64>();
+            ptr::copy_nonoverlapping(shape_and_strides.shape().as_ptr(), shape, ndim);
+            let strides = match strides {
+                Some(strides) => {
+                    ptr::copy_nonoverlapping(strides.as_ptr(), shape.add(ndim), ndim);
+                    shape.add(ndim)
+                }
+                None => ptr::null_mut(),
+            };
+            ptr::addr_of_mut!((*block).header).write(Header {
+                release: release::<T>,
+                type_id: type_id::<T>(),
+                #[cfg(feature = "dlpack-1-0")]
+                flags: inner.flags(),
+                ndim,
+                has_strides: !strides.is_null(),
+                axis_names,
+            });
+            // Move the tensor first, its data may live inside of it.
+            ptr::addr_of_mut!((*block).inner).write(inner);
+            let inner = &(*block).inner;
+            ptr::addr_of_mut!((*block).tensor).write(ffi::DLManagedTensor {
+                dl_tensor: ffi::DLTensor {
+                    data: inner.data_ptr(),
+                    device: inner.device(),
+                    ndim: ndim as i32,
+                    dtype: inner.dtype(),
+                    shape,
+                    strides,
+                    byte_offset: inner.byte_offset(),
+                },
+                manager_ctx: block.cast(),
+                deleter: Some(deleter_fn),
+            });
+            #[cfg(feature = "debug-guards")]
+            crate::guards::exported(block.cast());
+            #[cfg(feature = "leak-tracking")]
+            crate::leaks::exported(block.cast(), &(*block).tensor.dl_tensor);
+            #[cfg(feature = "tracing")]
+            crate::trace::exported(block.cast(), &(*block).tensor.dl_tensor);
+            NonNull::new_unchecked(block.cast())
         }
     }
 }
@@ -79,7 +313,7 @@ impl<T> TensorView for ManagerCtx<T>
 where
     T: ToTensor,
 {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
         self.inner.data_ptr()
     }
 
@@ -106,6 +340,11 @@ where
     fn dtype(&self) -> ffi::DataType {
         self.inner.dtype()
     }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        self.inner.flags()
+    }
 }
 
 // It's hard and unsafe to recover T from dlpack ptr.
@@ -118,3 +357,173 @@ where
         self.into_dl_managed_tensor()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::{
+        ffi::{DataType, Device},
+        tensor::traits::FromDLPack,
+        ManagedTensor,
+    };
+
+    struct Counted(Vec<i32>, Arc<AtomicUsize>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.1.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl ToTensor for Counted {
+        fn data_ptr(&self) -> *mut std::ffi::c_void {
+            self.0.as_ptr() as *mut std::ffi::c_void
+        }
+
+        fn shape_and_strides(&self) -> ShapeAndStrides {
+            ShapeAndStrides::new_with_strides(&[3, 2], &[1, 3])
+        }
+
+        fn device(&self) -> Device {
+            Device::CPU
+        }
+
+        fn dtype(&self) -> DataType {
+            DataType::I32
+        }
+
+        fn byte_offset(&self) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn single_allocation_export() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let dlpack = Counted((0..6).collect(), drops.clone()).into_dlpack();
+        unsafe {
+            let managed = dlpack.as_ref();
+            assert_eq!(managed.manager_ctx, dlpack.as_ptr().cast());
+            assert_eq!(managed.dl_tensor.shape.add(2), managed.dl_tensor.strides);
+        }
+        let tensor = ManagedTensor::from_dlpack(dlpack);
+        assert_eq!(tensor.shape(), &[3, 2]);
+        assert_eq!(tensor.strides(), Some([1, 3].as_slice()));
+        assert_eq!(tensor.to_contiguous::<i32>(), &[0, 3, 1, 4, 2, 5]);
+        drop(tensor);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn axis_names() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let ctx = ManagerCtx::new(Counted((0..6).collect(), drops.clone()));
+        assert!(matches!(
+            ctx.with_axis_names(["rows"]),
+            Err(crate::Error::Layout(LayoutError::NdimMismatch {
+                expected: 2,
+                found: 1
+            }))
+        ));
+        let ctx = ManagerCtx::new(Counted((0..6).collect(), drops.clone()))
+            .with_axis_names(["height", "width"])
+            .unwrap();
+        let tensor = ManagedTensor::from(ctx);
+        assert_eq!(tensor.axis_names().unwrap(), ["height", "width"]);
+        drop(tensor);
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+        let tensor = ManagedTensor::from_dlpack(vec![0i32; 3].into_dlpack());
+        assert_eq!(tensor.axis_names(), None);
+    }
+
+    #[test]
+    fn lazy_strides_export() {
+        let v: Vec<i64> = vec![0; 6];
+        let ctx = ManagerCtx::new(v.clone());
+        assert_eq!(ctx.strides(), None);
+        let ctx = ManagerCtx::new(v).lazy_strides();
+        assert_eq!(ctx.strides(), None);
+        let tensor = ManagedTensor::from(ctx);
+        assert!(tensor.dl_tensor().strides.is_null());
+        assert_eq!(tensor.strides_or_contiguous(), [1].as_slice());
+    }
+
+    // The pool only exists with `std`.
+    #[cfg(feature = "std")]
+    #[test]
+    fn pooled_export() {
+        pool::clear();
+        let first = 1u32.into_dlpack();
+        drop(ManagedTensor::from_dlpack(first));
+        let second = ManagedTensor::from_dlpack(2u32.into_dlpack());
+        assert_eq!(second.as_ptr(), first.as_ptr());
+        assert_eq!(second.as_slice::<u32>(), &[2]);
+    }
+
+    #[test]
+    fn batch_export() {
+        let dlpacks = ManagerCtx::export_batch((0..3).map(|i| vec![i as f32; i + 1]));
+        let tensors: Vec<_> = dlpacks
+            .into_iter()
+            .map(ManagedTensor::from_dlpack)
+            .collect();
+        assert_eq!(tensors.len(), 3);
+        for (i, tensor) in tensors.iter().enumerate() {
+            assert_eq!(tensor.as_slice::<f32>(), vec![i as f32; i + 1]);
+        }
+    }
+
+    // Panics can only be caught in deleters with `std`.
+    #[cfg(feature = "std")]
+    #[test]
+    fn panicking_drop() {
+        struct PanicOnDrop(Vec<i32>);
+
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                panic!("dropped");
+            }
+        }
+
+        impl ToTensor for PanicOnDrop {
+            fn data_ptr(&self) -> *mut std::ffi::c_void {
+                self.0.as_ptr() as *mut std::ffi::c_void
+            }
+
+            fn shape_and_strides(&self) -> ShapeAndStrides {
+                ShapeAndStrides::new_1d(self.0.len())
+            }
+
+            fn device(&self) -> Device {
+                Device::CPU
+            }
+
+            fn dtype(&self) -> DataType {
+                DataType::I32
+            }
+
+            fn byte_offset(&self) -> u64 {
+                0
+            }
+        }
+
+        // Would abort the process if the panic crossed the deleter.
+        let tensor = ManagedTensor::from_dlpack(PanicOnDrop(vec![1, 2]).into_dlpack());
+        assert_eq!(tensor.as_slice::<i32>(), &[1, 2]);
+        drop(tensor);
+    }
+
+    #[test]
+    fn scalar_export() {
+        // The data of a scalar lives inside the block.
+        let tensor = ManagedTensor::from_dlpack(42u64.into_dlpack());
+        assert_eq!(tensor.ndim(), 0);
+        assert_eq!(tensor.as_slice::<u64>(), &[42]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Elementwise maps over CPU tensors, following strides, e.g. to normalize
+//! or scale data right where it is exchanged.
+
+use crate::{
+    error::invariant_violated,
+    ffi::{Device, DeviceType},
+    tensor::traits::{InferDtype, TensorView},
+    utils::for_each_offset,
+    Error, ManagedTensor, OwnedTensor, Result,
+};
+
+impl ManagedTensor {
+    /// Replace every element of `A` with `f` of it. Elements reached more
+    /// than once, e.g. through zero strides, are mapped more than once. The
+    /// data doesn't have to be aligned for `A`. Fails with
+    /// [`Error::ReadOnly`] if the tensor is known to be read-only.
+    ///
+    /// # Safety
+    /// The data must be writable, e.g. not memory-mapped read-only by another
+    /// producer, and not aliased by anything reading or writing it meanwhile.
+    pub unsafe fn map_inplace<A: InferDtype + Copy>(
+        &mut self,
+        mut f: impl FnMut(A) -> A,
+    ) -> Result<()> {
+        self.check_cpu_dtype::<A>()?;
+        #[cfg(feature = "dlpack-1-0")]
+        if self.is_read_only() {
+            return Err(Error::ReadOnly);
+        }
+        let first = self.first_byte().cast::<A>();
+        for_each_offset(
+            self.shape(),
+            &self.strides_or_contiguous(),
+            |offset| unsafe {
+                let element = first.offset(offset);
+                element.write_unaligned(f(element.read_unaligned()));
+            },
+        );
+        Ok(())
+    }
+
+    /// New row-major tensor of `f` of every element of `A`, e.g. to convert
+    /// `u8` pixels into normalized `f32`. The data doesn't have to be aligned
+    /// for `A`.
+    pub fn map_into<A, U>(&self, mut f: impl FnMut(A) -> U) -> Result<OwnedTensor>
+    where
+        A: InferDtype + Copy,
+        U: InferDtype,
+    {
+        self.check_cpu_dtype::<A>()?;
+        let first = self.first_byte().cast::<A>().cast_const();
+        let strides = self.strides_or_contiguous();
+        let tensor = OwnedTensor::new_with(self.shape(), |elements: &mut [U]| {
+            let mut elements = elements.iter_mut();
+            for_each_offset(self.shape(), &strides, |offset| {
+                let element = unsafe { first.offset(offset).read_unaligned() };
+                // Both have as many elements as the shape.
+                *elements.next().unwrap() = f(element);
+            });
+        });
+        tensor.ok_or_else(|| invariant_violated("invalid shape of a tensor"))
+    }
+
+    pub(crate) fn check_cpu_dtype<A: InferDtype>(&self) -> Result<()> {
+        if self.dtype() != A::infer_dtype() {
+            return Err(Error::DtypeMismatch {
+                expected: A::infer_dtype(),
+                found: self.dtype(),
+            });
+        }
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: self.device(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, Error};
+
+    #[test]
+    fn map_elements() {
+        let mut tensor =
+            ManagedTensor::from_dlpack(vec![0u8, 51, 102, 153, 204, 255].into_dlpack())
+                .reshape(&[2, 3])
+                .unwrap()
+                .permute(&[1, 0])
+                .unwrap();
+        let normalized = tensor.map_into(|x: u8| x as f32 / 255.0).unwrap();
+        assert_eq!(normalized.shape(), &[3, 2]);
+        let normalized = ManagedTensor::from_dlpack(normalized.into_dlpack());
+        assert_eq!(normalized.as_slice::<f32>(), [0.0, 0.6, 0.2, 0.8, 0.4, 1.0]);
+
+        unsafe { tensor.map_inplace(|x: u8| x / 51) }.unwrap();
+        assert_eq!(tensor.to_contiguous::<u8>(), [0, 3, 1, 4, 2, 5]);
+        assert!(matches!(
+            unsafe { tensor.map_inplace(|x: f32| x) },
+            Err(Error::DtypeMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    #[test]
+    fn read_only() {
+        static TABLE: [u8; 6] = [1, 2, 3, 4, 5, 6];
+        let ctx = ManagerCtx::from_static(&TABLE, &[6]).unwrap();
+        let mut tensor = ManagedTensor::from_dlpack(ctx.into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap();
+        assert!(tensor.is_read_only());
+        assert!(matches!(
+            unsafe { tensor.map_inplace(|x: u8| x + 1) },
+            Err(Error::ReadOnly)
+        ));
+        assert_eq!(tensor.to_contiguous::<u8>(), TABLE);
+    }
+}
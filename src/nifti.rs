@@ -0,0 +1,393 @@
+//! Reading and writing single-file NIfTI-1 (`.nii` and `.nii.gz`) volumes,
+//! for medical imaging pipelines. Enabled by the `nifti` feature.
+//!
+//! Volumes become tensors indexed `[x, y, z, t, ...]` like nibabel's arrays,
+//! so the voxel data, stored with `x` varying fastest, is reordered into
+//! row-major order. The affine maps voxel indices to world coordinates,
+//! taken from the sform, the qform, or the voxel sizes, in that order of
+//! preference. Gzipped volumes are told apart by their magic bytes when
+//! read, and by their `.gz` extension when saved.
+//!
+//! See the [format spec](https://nifti.nimh.nih.gov/nifti-1/).
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, DeviceType},
+    npy::fortran_to_c,
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    ManagedTensor, OwnedTensor,
+};
+
+const HEADER_SIZE: usize = 348;
+/// Header plus the extension flag, where the data of files written here starts.
+const DATA_OFFSET: usize = 352;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+pub type Affine = [[f64; 4]; 4];
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Map a NIfTI datatype code to a DLPack dtype, if there is one.
+pub fn from_nifti_datatype(code: i16) -> Option<DataType> {
+    let dtype = match code {
+        2 => DataType::U8,
+        4 => DataType::I16,
+        8 => DataType::I32,
+        16 => DataType::F32,
+        32 => (DataTypeCode::Complex, 64, 1).into(),
+        64 => DataType::F64,
+        256 => DataType::I8,
+        512 => DataType::U16,
+        768 => DataType::U32,
+        1024 => DataType::I64,
+        1280 => DataType::U64,
+        1792 => (DataTypeCode::Complex, 128, 1).into(),
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+/// Map a DLPack dtype to a NIfTI datatype code, if there is one.
+pub fn to_nifti_datatype(dtype: DataType) -> Option<i16> {
+    let code = match (dtype.code, dtype.bits, dtype.lanes) {
+        (DataTypeCode::UInt, 8, 1) => 2,
+        (DataTypeCode::Int, 16, 1) => 4,
+        (DataTypeCode::Int, 32, 1) => 8,
+        (DataTypeCode::Float, 32, 1) => 16,
+        (DataTypeCode::Complex, 64, 1) => 32,
+        (DataTypeCode::Float, 64, 1) => 64,
+        (DataTypeCode::Int, 8, 1) => 256,
+        (DataTypeCode::UInt, 16, 1) => 512,
+        (DataTypeCode::UInt, 32, 1) => 768,
+        (DataTypeCode::Int, 64, 1) => 1024,
+        (DataTypeCode::UInt, 64, 1) => 1280,
+        (DataTypeCode::Complex, 128, 1) => 1792,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// A NIfTI volume with the metadata needed to interpret it.
+#[derive(Debug)]
+pub struct NiftiVolume {
+    pub tensor: ManagedTensor,
+    pub affine: Affine,
+    /// Scaling of stored values, `slope * x + inter`, not applied to the
+    /// tensor. A slope of zero means no scaling.
+    pub scl_slope: f32,
+    pub scl_inter: f32,
+}
+
+struct Header<'a> {
+    buf: &'a [u8; HEADER_SIZE],
+    endian: Endian,
+}
+
+impl Header<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> [u8; N] {
+        let mut bytes: [u8; N] = self.buf[offset..offset + N].try_into().unwrap();
+        if !self.endian.is_native() {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    fn i16(&self, offset: usize) -> i16 {
+        i16::from_ne_bytes(self.bytes(offset))
+    }
+
+    fn f32(&self, offset: usize) -> f32 {
+        f32::from_ne_bytes(self.bytes(offset))
+    }
+
+    fn f64s<const N: usize>(&self, offset: usize) -> [f64; N] {
+        core::array::from_fn(|i| self.f32(offset + 4 * i) as f64)
+    }
+
+    fn affine(&self) -> Affine {
+        let pixdim: [f64; 8] = self.f64s(76);
+        if self.i16(254) > 0 {
+            let [x, y, z] = [280, 296, 312].map(|offset| self.f64s(offset));
+            return [x, y, z, [0.0, 0.0, 0.0, 1.0]];
+        }
+        let mut affine = [[0.0; 4]; 4];
+        affine[3][3] = 1.0;
+        if self.i16(252) <= 0 {
+            for axis in 0..3 {
+                affine[axis][axis] = pixdim[axis + 1];
+            }
+            return affine;
+        }
+        let [b, c, d, x, y, z]: [f64; 6] = self.f64s(256);
+        let a = (1.0 - (b * b + c * c + d * d)).max(0.0).sqrt();
+        let rotation = [
+            [
+                a * a + b * b - c * c - d * d,
+                2.0 * (b * c - a * d),
+                2.0 * (b * d + a * c),
+            ],
+            [
+                2.0 * (b * c + a * d),
+                a * a + c * c - b * b - d * d,
+                2.0 * (c * d - a * b),
+            ],
+            [
+                2.0 * (b * d - a * c),
+                2.0 * (c * d + a * b),
+                a * a + d * d - c * c - b * b,
+            ],
+        ];
+        let qfac = if pixdim[0] < 0.0 { -1.0 } else { 1.0 };
+        let scale = [pixdim[1], pixdim[2], pixdim[3] * qfac];
+        for ((row, rotation), offset) in affine.iter_mut().zip(rotation).zip([x, y, z]) {
+            for axis in 0..3 {
+                row[axis] = rotation[axis] * scale[axis];
+            }
+            row[3] = offset;
+        }
+        affine
+    }
+}
+
+/// Read a single-file NIfTI-1 volume, gzipped or not.
+pub fn read_nifti<R: Read>(mut reader: R) -> io::Result<NiftiVolume> {
+    let mut magic = [0; 2];
+    reader.read_exact(&mut magic)?;
+    let reader = magic.as_slice().chain(reader);
+    if magic == GZIP_MAGIC {
+        read_volume(GzDecoder::new(reader))
+    } else {
+        read_volume(reader)
+    }
+}
+
+fn read_volume<R: Read>(mut reader: R) -> io::Result<NiftiVolume> {
+    let mut buf = [0; HEADER_SIZE];
+    reader.read_exact(&mut buf)?;
+    let endian = match i32::from_le_bytes(buf[..4].try_into().unwrap()) {
+        348 => Endian::Little,
+        _ if i32::from_be_bytes(buf[..4].try_into().unwrap()) == 348 => Endian::Big,
+        _ => return Err(invalid_data("not a nifti-1 file")),
+    };
+    if &buf[344..348] != b"n+1\0" {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "only single-file nifti-1 volumes are supported",
+        ));
+    }
+    let header = Header { buf: &buf, endian };
+    let ndim = header.i16(40);
+    if !(1..=7).contains(&ndim) {
+        return Err(invalid_data("invalid number of dimensions in nifti header"));
+    }
+    let shape: Vec<i64> = (0..ndim as usize)
+        .map(|axis| header.i16(42 + 2 * axis) as i64)
+        .collect();
+    let code = header.i16(70);
+    let dtype = from_nifti_datatype(code).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported nifti datatype {code}"),
+        )
+    })?;
+    let vox_offset = header.f32(108);
+    if vox_offset.is_nan() || vox_offset < HEADER_SIZE as f32 {
+        return Err(invalid_data("invalid vox_offset in nifti header"));
+    }
+    // Skip the extensions.
+    io::copy(
+        &mut reader.by_ref().take(vox_offset as u64 - HEADER_SIZE as u64),
+        &mut io::sink(),
+    )?;
+
+    let mut data = OwnedTensor::new_zeroed(&shape, dtype)
+        .ok_or_else(|| invalid_data("invalid dimensions in nifti header"))?;
+    reader.read_exact(data.as_bytes_mut())?;
+    convert_byte_order(data.as_bytes_mut(), dtype, endian, Endian::NATIVE);
+    if shape.len() > 1 {
+        let reordered = fortran_to_c(data.as_bytes(), &shape, dtype.size());
+        data.as_bytes_mut().copy_from_slice(&reordered);
+    }
+    Ok(NiftiVolume {
+        tensor: ManagedTensor::from_dlpack(data.into_dlpack()),
+        affine: header.affine(),
+        scl_slope: header.f32(112),
+        scl_inter: header.f32(116),
+    })
+}
+
+/// Load a single-file NIfTI-1 volume, gzipped or not.
+pub fn load_nifti<P: AsRef<Path>>(path: P) -> io::Result<NiftiVolume> {
+    read_nifti(BufReader::new(File::open(path)?))
+}
+
+impl ManagedTensor {
+    /// Write a CPU tensor indexed `[x, y, z, t, ...]` as a single-file
+    /// NIfTI-1 volume, in native byte order, with `affine` as its sform.
+    pub fn write_nifti<W: Write>(&self, mut writer: W, affine: &Affine) -> io::Result<()> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be written",
+            ));
+        }
+        let dtype = self.dtype();
+        let code = to_nifti_datatype(dtype).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("dtype {dtype:?} has no nifti equivalent"),
+            )
+        })?;
+        let shape = self.shape();
+        if !(1..=7).contains(&shape.len()) || shape.iter().any(|&dim| dim > i16::MAX as i64) {
+            return Err(invalid_data("shape doesn't fit in a nifti header"));
+        }
+
+        let mut buf = [0u8; DATA_OFFSET];
+        let mut put = |offset: usize, bytes: &[u8]| {
+            buf[offset..offset + bytes.len()].copy_from_slice(bytes);
+        };
+        put(0, &348i32.to_ne_bytes());
+        put(38, b"r");
+        put(40, &(shape.len() as i16).to_ne_bytes());
+        for (axis, &dim) in shape.iter().enumerate() {
+            put(42 + 2 * axis, &(dim as i16).to_ne_bytes());
+        }
+        put(70, &code.to_ne_bytes());
+        put(72, &(dtype.bits as i16).to_ne_bytes());
+        put(76, &1f32.to_ne_bytes());
+        for axis in 0..3 {
+            let size = affine[..3]
+                .iter()
+                .map(|row| row[axis].powi(2))
+                .sum::<f64>()
+                .sqrt();
+            put(80 + 4 * axis, &(size as f32).to_ne_bytes());
+        }
+        put(108, &(DATA_OFFSET as f32).to_ne_bytes());
+        put(112, &1f32.to_ne_bytes());
+        // Scanner-based anatomical coordinates.
+        put(254, &1i16.to_ne_bytes());
+        for (i, row) in affine[..3].iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                put(280 + 16 * i + 4 * j, &(value as f32).to_ne_bytes());
+            }
+        }
+        put(344, b"n+1\0");
+        writer.write_all(&buf)?;
+
+        let data = self.to_contiguous_bytes();
+        if shape.len() > 1 {
+            // Row-major data of a shape is column-major data of its reverse.
+            let reversed: Vec<i64> = shape.iter().rev().copied().collect();
+            writer.write_all(&fortran_to_c(&data, &reversed, dtype.size()))
+        } else {
+            writer.write_all(&data)
+        }
+    }
+
+    /// Save a CPU tensor indexed `[x, y, z, t, ...]` as a `.nii` file, or a
+    /// gzipped one if `path` ends with `.gz`.
+    pub fn save_nifti<P: AsRef<Path>>(&self, path: P, affine: &Affine) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+            self.write_nifti(&mut encoder, affine)?;
+            encoder.finish()?;
+        } else {
+            self.write_nifti(&mut writer, affine)?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let v: Vec<i16> = (0..24).collect();
+        let bytes: Vec<u8> = v.iter().flat_map(|x| x.to_ne_bytes()).collect();
+        let tensor = OwnedTensor::from_bytes(&bytes, &[2, 3, 4], DataType::I16).unwrap();
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        let affine = [
+            [0.0, 0.0, 2.0, -10.0],
+            [1.0, 0.0, 0.0, 5.0],
+            [0.0, 1.5, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        let mut buf = Vec::new();
+        tensor.write_nifti(&mut buf, &affine).unwrap();
+        assert_eq!(buf.len(), DATA_OFFSET + 48);
+        // x varies fastest on disk.
+        assert_eq!(i16::from_ne_bytes([buf[354], buf[355]]), 12);
+
+        let volume = read_nifti(buf.as_slice()).unwrap();
+        assert_eq!(volume.tensor.shape(), &[2, 3, 4]);
+        assert_eq!(volume.tensor.as_slice::<i16>(), &v[..]);
+        assert_eq!(volume.affine, affine);
+        assert_eq!((volume.scl_slope, volume.scl_inter), (1.0, 0.0));
+
+        // Switch to a qform rotating x and y, with 2mm voxels.
+        buf[254..256].copy_from_slice(&0i16.to_ne_bytes());
+        buf[252..254].copy_from_slice(&1i16.to_ne_bytes());
+        for (i, value) in [2.0f32, 2.0, 2.0].iter().enumerate() {
+            buf[80 + 4 * i..84 + 4 * i].copy_from_slice(&value.to_ne_bytes());
+        }
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        for (i, value) in [0.0f32, 0.0, half, 1.0, 2.0, 3.0].iter().enumerate() {
+            buf[256 + 4 * i..260 + 4 * i].copy_from_slice(&value.to_ne_bytes());
+        }
+        let affine = read_nifti(buf.as_slice()).unwrap().affine;
+        let expected = [
+            [0.0, -2.0, 0.0, 1.0],
+            [2.0, 0.0, 0.0, 2.0],
+            [0.0, 0.0, 2.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        for (row, expected) in affine.iter().zip(expected) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-6);
+            }
+        }
+
+        assert!(read_nifti(&buf[..DATA_OFFSET + 2]).is_err());
+    }
+
+    #[test]
+    fn gzipped() {
+        let path = std::env::temp_dir().join(format!("dlpark-{}.nii.gz", std::process::id()));
+        let tensor = ManagedTensor::from_dlpack(vec![1.5f64, -2.0, 0.25].into_dlpack());
+        let affine = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        tensor.save_nifti(&path, &affine).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap()[..2], GZIP_MAGIC);
+        let volume = load_nifti(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(volume.tensor.as_slice::<f64>(), [1.5, -2.0, 0.25]);
+        assert_eq!(volume.affine, affine);
+
+        for code in (0..=2048).step_by(2) {
+            if let Some(dtype) = from_nifti_datatype(code) {
+                assert_eq!(to_nifti_datatype(dtype), Some(code));
+            }
+        }
+        assert_eq!(to_nifti_datatype(DataType::BOOL), None);
+    }
+}
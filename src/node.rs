@@ -0,0 +1,113 @@
+//! Exchange of CPU tensors with Node.js through `ArrayBuffer`s, without
+//! copying. Enabled by the `napi` feature, for native addons built with
+//! napi-rs.
+//!
+//! JS values may only be touched on the JS thread, so tensors exported from
+//! an [`ArrayBufferTensor`] must be deleted there too.
+
+use std::ffi::c_void;
+
+use napi::{sys, Env, JsArrayBuffer, JsArrayBufferValue, Ref};
+
+use crate::{
+    ffi::{DataType, Device, DeviceType},
+    tensor::traits::TensorView,
+    Error, LayoutError, ManagedTensor, ShapeAndStrides, ToTensor,
+};
+
+impl ManagedTensor {
+    /// Hand the data of this CPU tensor to JS as an external `ArrayBuffer`.
+    /// The tensor is deleted when the buffer is garbage collected.
+    pub fn into_array_buffer(self, env: &Env) -> napi::Result<JsArrayBufferValue> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: self.device(),
+            }
+            .into());
+        }
+        if !self.is_contiguous() {
+            return Err(Error::from(LayoutError::NotContiguous).into());
+        }
+        self.check_supported().map_err(Error::from)?;
+        let len = self.data_size();
+        if len == 0 {
+            // The data pointer of an empty tensor may be NULL, which napi
+            // rejects for external buffers.
+            return env.create_arraybuffer(0);
+        }
+        let data = self.first_byte();
+        unsafe {
+            env.create_arraybuffer_with_borrowed_data(data, len, self, |tensor, _| drop(tensor))
+        }
+    }
+}
+
+/// CPU tensor over the data of a JS `ArrayBuffer`, keeping the buffer alive
+/// until the tensor is dropped, which must happen on the JS thread.
+pub struct ArrayBufferTensor {
+    buffer: Ref<JsArrayBufferValue>,
+    env: sys::napi_env,
+    dtype: DataType,
+    shape: Vec<i64>,
+}
+
+impl ArrayBufferTensor {
+    /// View `buffer` as a row-major tensor of `shape` and `dtype`, failing if
+    /// its length doesn't match them.
+    pub fn new(
+        env: &Env,
+        buffer: JsArrayBuffer,
+        shape: &[i64],
+        dtype: DataType,
+    ) -> napi::Result<Self> {
+        let len = shape.iter().try_fold(dtype.size(), |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        });
+        let buffer = buffer.into_value()?;
+        if len != Some(buffer.len()) {
+            return Err(napi::Error::from_reason(
+                "ArrayBuffer length doesn't match shape and dtype",
+            ));
+        }
+        Ok(Self {
+            buffer: buffer.into_raw().into_ref()?,
+            env: env.raw(),
+            dtype,
+            shape: shape.to_vec(),
+        })
+    }
+}
+
+impl Drop for ArrayBufferTensor {
+    fn drop(&mut self) {
+        // Only fails if the env is being torn down, which frees the buffer.
+        let _ = self.buffer.unref(Env::from(self.env));
+    }
+}
+
+impl ToTensor for ArrayBufferTensor {
+    fn data_ptr(&self) -> *mut c_void {
+        if self.buffer.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            self.buffer.as_ptr().cast_mut().cast()
+        }
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
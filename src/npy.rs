@@ -0,0 +1,513 @@
+//! Reading and writing the NumPy `.npy` format.
+//!
+//! See the [format spec](https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html).
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    utils::copy_strided_bytes,
+    ManagedTensor, OwnedTensor,
+};
+#[cfg(feature = "mmap")]
+use crate::{ffi::Device, tensor::traits::ToTensor, ShapeAndStrides};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+/// Total header length, including magic and version, is padded to a multiple
+/// of this.
+const HEADER_ALIGNMENT: usize = 64;
+
+/// The dictionary stored in a `.npy` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpyHeader {
+    pub dtype: DataType,
+    /// Byte order of the data.
+    pub endian: Endian,
+    pub fortran_order: bool,
+    pub shape: Vec<i64>,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Format a dtype as a numpy type string such as `<f4`.
+pub fn dtype_to_descr(dtype: DataType, endian: Endian) -> Option<String> {
+    if dtype.lanes != 1 {
+        return None;
+    }
+    let kind = match dtype.code {
+        DataTypeCode::Int => 'i',
+        DataTypeCode::UInt => 'u',
+        DataTypeCode::Float => 'f',
+        DataTypeCode::Complex => 'c',
+        DataTypeCode::Bool => 'b',
+        DataTypeCode::Bfloat | DataTypeCode::OpaqueHandle => return None,
+        #[cfg(feature = "dlpack-1-1")]
+        _ => return None,
+    };
+    let itemsize = dtype.size();
+    let endian = match endian {
+        _ if itemsize == 1 => '|',
+        Endian::Little => '<',
+        Endian::Big => '>',
+    };
+    Some(format!("{endian}{kind}{itemsize}"))
+}
+
+/// Parse a numpy type string such as `<f4`.
+pub fn descr_to_dtype(descr: &str) -> Option<(DataType, Endian)> {
+    let mut chars = descr.chars();
+    let endian = match chars.next()? {
+        '<' => Endian::Little,
+        '>' => Endian::Big,
+        '=' | '|' => Endian::NATIVE,
+        _ => return None,
+    };
+    let kind = chars.next()?;
+    let itemsize: u8 = chars.as_str().parse().ok()?;
+    if itemsize == 0 {
+        return None;
+    }
+    let code = match kind {
+        'i' => DataTypeCode::Int,
+        'u' => DataTypeCode::UInt,
+        'f' => DataTypeCode::Float,
+        'c' => DataTypeCode::Complex,
+        'b' => DataTypeCode::Bool,
+        _ => return None,
+    };
+    Some(((code, itemsize.checked_mul(8)?, 1).into(), endian))
+}
+
+/// Extract the raw text following `'key':` in the header dict.
+fn dict_value<'a>(dict: &'a str, key: &str) -> io::Result<&'a str> {
+    let pattern = format!("'{key}':");
+    let start = dict
+        .find(&pattern)
+        .ok_or_else(|| invalid_data("missing key in npy header"))?;
+    Ok(dict[start + pattern.len()..].trim_start())
+}
+
+impl NpyHeader {
+    fn parse(dict: &str) -> io::Result<Self> {
+        let descr = dict_value(dict, "descr")?;
+        let descr = descr
+            .strip_prefix('\'')
+            .and_then(|s| s.split('\'').next())
+            .ok_or_else(|| invalid_data("invalid descr in npy header"))?;
+        let (dtype, endian) = descr_to_dtype(descr).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported npy descr {descr}"),
+            )
+        })?;
+
+        let fortran_order = dict_value(dict, "fortran_order")?.starts_with("True");
+
+        let shape = dict_value(dict, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|s| s.split(')').next())
+            .ok_or_else(|| invalid_data("invalid shape in npy header"))?;
+        let shape = shape
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid_data("invalid shape in npy header"))?;
+
+        Ok(Self {
+            dtype,
+            endian,
+            fortran_order,
+            shape,
+        })
+    }
+
+    /// Read the magic string, version and header dict, leaving `reader` at
+    /// the start of the data.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut preamble = [0u8; 8];
+        reader.read_exact(&mut preamble)?;
+        if &preamble[..6] != MAGIC {
+            return Err(invalid_data("not a npy file"));
+        }
+        let header_len = match preamble[6] {
+            1 => {
+                let mut buf = [0u8; 2];
+                reader.read_exact(&mut buf)?;
+                u16::from_le_bytes(buf) as usize
+            }
+            2 | 3 => {
+                let mut buf = [0u8; 4];
+                reader.read_exact(&mut buf)?;
+                u32::from_le_bytes(buf) as usize
+            }
+            _ => return Err(invalid_data("unsupported npy version")),
+        };
+        let mut dict = vec![0u8; header_len];
+        reader.read_exact(&mut dict)?;
+        let dict = std::str::from_utf8(&dict).map_err(|_| invalid_data("invalid npy header"))?;
+        Self::parse(dict)
+    }
+
+    /// Write the magic string, version and padded header dict.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let descr = dtype_to_descr(self.dtype, self.endian).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("dtype {:?} has no npy equivalent", self.dtype),
+            )
+        })?;
+        let shape = match self.shape.as_slice() {
+            [dim] => format!("({dim},)"),
+            shape => format!(
+                "({})",
+                shape
+                    .iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let fortran_order = if self.fortran_order { "True" } else { "False" };
+        let mut dict =
+            format!("{{'descr': '{descr}', 'fortran_order': {fortran_order}, 'shape': {shape}, }}");
+
+        // Version 1 stores the header length in a u16, version 2 in a u32.
+        let padding = |len_size: usize| {
+            let unpadded = MAGIC.len() + 2 + len_size + dict.len() + 1;
+            (HEADER_ALIGNMENT - unpadded % HEADER_ALIGNMENT) % HEADER_ALIGNMENT
+        };
+        let (version, padding) = if dict.len() + 1 + padding(2) <= u16::MAX as usize {
+            (1u8, padding(2))
+        } else {
+            (2u8, padding(4))
+        };
+        dict.extend(std::iter::repeat_n(' ', padding));
+        dict.push('\n');
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[version, 0])?;
+        if version == 1 {
+            writer.write_all(&(dict.len() as u16).to_le_bytes())?;
+        } else {
+            writer.write_all(&(dict.len() as u32).to_le_bytes())?;
+        }
+        writer.write_all(dict.as_bytes())
+    }
+}
+
+/// Column-major strides of `shape`, in elements. They can only overflow when
+/// a later dimension is zero, i.e. for an empty array, and then saturate.
+fn fortran_strides(shape: &[i64]) -> Vec<i64> {
+    let mut strides = vec![1i64; shape.len()];
+    for i in 1..shape.len() {
+        strides[i] = strides[i - 1].saturating_mul(shape[i - 1]);
+    }
+    strides
+}
+
+/// Reorder column-major `data` into row-major order.
+pub(crate) fn fortran_to_c(data: &[u8], shape: &[i64], itemsize: usize) -> Vec<u8> {
+    let strides = fortran_strides(shape);
+    let mut buf = Vec::with_capacity(data.len());
+    unsafe {
+        copy_strided_bytes(
+            data.as_ptr(),
+            shape,
+            &strides,
+            itemsize,
+            buf.spare_capacity_mut(),
+        );
+        buf.set_len(data.len());
+    }
+    buf
+}
+
+impl ManagedTensor {
+    /// Read a `.npy` stream into a new CPU tensor. Fortran-ordered arrays are
+    /// reordered into row-major order, and non-native byte order is converted.
+    pub fn from_npy<R: Read>(mut reader: R) -> io::Result<Self> {
+        let header = NpyHeader::read_from(&mut reader)?;
+        // The shape may be forged, it is only trusted as far as data follows.
+        let mut tensor = OwnedTensor::read_from(&mut reader, &header.shape, header.dtype)?;
+        convert_byte_order(
+            tensor.as_bytes_mut(),
+            header.dtype,
+            header.endian,
+            Endian::NATIVE,
+        );
+        if header.fortran_order && header.shape.len() > 1 {
+            let data = fortran_to_c(tensor.as_bytes(), &header.shape, header.dtype.size());
+            tensor.as_bytes_mut().copy_from_slice(&data);
+        }
+        Ok(Self::from_dlpack(tensor.into_dlpack()))
+    }
+
+    /// Load a `.npy` file into a new CPU tensor.
+    pub fn load_npy<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_npy(BufReader::new(File::open(path)?))
+    }
+
+    /// Write a CPU tensor as a row-major `.npy` stream.
+    pub fn write_npy<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be written",
+            ));
+        }
+        self.check_supported()?;
+        let header = NpyHeader {
+            dtype: self.dtype(),
+            endian: Endian::NATIVE,
+            fortran_order: false,
+            shape: self.shape().to_vec(),
+        };
+        header.write_to(&mut writer)?;
+        writer.write_all(&self.to_contiguous_bytes())
+    }
+
+    /// Save a CPU tensor as a `.npy` file.
+    pub fn save_npy<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_npy(&mut writer)?;
+        writer.flush()
+    }
+}
+
+/// A tensor over the data of a memory-mapped `.npy` file, unmapping it once
+/// dropped.
+#[cfg(feature = "mmap")]
+struct MappedNpy {
+    mmap: memmap2::Mmap,
+    offset: usize,
+    header: NpyHeader,
+}
+
+#[cfg(feature = "mmap")]
+impl ToTensor for MappedNpy {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.mmap.as_ptr() as *mut std::ffi::c_void
+    }
+
+    fn byte_offset(&self) -> u64 {
+        self.offset as u64
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.header.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        let shape = &self.header.shape;
+        if !self.header.fortran_order {
+            return ShapeAndStrides::new_contiguous_with_strides(shape);
+        }
+        ShapeAndStrides::new_with_strides(shape, &fortran_strides(shape))
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        crate::ffi::DLPACK_FLAG_BITMASK_READ_ONLY
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ManagedTensor {
+    /// Memory-map a `.npy` file and expose its data without copying it, the
+    /// file is unmapped once the tensor is deleted. Fortran-ordered arrays
+    /// get column-major strides. Arrays larger than RAM can be read a few
+    /// rows at a time with [`ManagedTensor::chunks`].
+    ///
+    /// The tensor is backed by read-only pages, consumers must not write to
+    /// them. Fails with [`io::ErrorKind::Unsupported`] if the data isn't in
+    /// native byte order.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated while the tensor is alive,
+    /// see [`memmap2::Mmap::map`].
+    pub unsafe fn mmap_npy<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mmap = memmap2::Mmap::map(&File::open(path)?)?;
+        let mut data = &mmap[..];
+        let header = NpyHeader::read_from(&mut data)?;
+        if !header.endian.is_native() && header.dtype.size() > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "zero-copy loading requires data in native byte order",
+            ));
+        }
+        let len = header
+            .shape
+            .iter()
+            .try_fold(header.dtype.size(), |acc, &dim| {
+                acc.checked_mul(usize::try_from(dim).ok()?)
+            })
+            .ok_or_else(|| invalid_data("invalid shape in npy header"))?;
+        if len > data.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let tensor = MappedNpy {
+            offset: mmap.len() - data.len(),
+            mmap,
+            header,
+        };
+        Ok(Self::from_dlpack(tensor.into_dlpack()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::on_gpu;
+
+    #[test]
+    fn round_trip() {
+        let v: Vec<i16> = (0..12).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let mut buf = Vec::new();
+        tensor.write_npy(&mut buf).unwrap();
+        assert_eq!(&buf[..6], MAGIC);
+        // Data starts at an aligned offset.
+        assert_eq!((buf.len() - 24) % HEADER_ALIGNMENT, 0);
+
+        let tensor = ManagedTensor::from_npy(buf.as_slice()).unwrap();
+        assert_eq!(tensor.shape(), &[12]);
+        assert_eq!(tensor.dtype(), DataType::I16);
+        assert_eq!(tensor.as_slice::<i16>(), &v[..]);
+    }
+
+    #[test]
+    fn parse_header() {
+        let header =
+            NpyHeader::parse("{'descr': '|u1', 'fortran_order': False, 'shape': (2, 3), }")
+                .unwrap();
+        assert_eq!(header.dtype, DataType::U8);
+        assert!(!header.fortran_order);
+        assert_eq!(header.shape, vec![2, 3]);
+
+        let header =
+            NpyHeader::parse("{'descr': '<f8', 'fortran_order': True, 'shape': (), }").unwrap();
+        assert_eq!(header.dtype, DataType::F64);
+        assert_eq!(header.endian, Endian::Little);
+        assert!(header.fortran_order);
+        assert!(header.shape.is_empty());
+    }
+
+    #[test]
+    fn fortran_order() {
+        let header = NpyHeader {
+            dtype: DataType::U8,
+            endian: Endian::NATIVE,
+            fortran_order: true,
+            shape: vec![2, 3],
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        // Column-major [[0, 1, 2], [3, 4, 5]].
+        buf.extend([0, 3, 1, 4, 2, 5]);
+        let tensor = ManagedTensor::from_npy(buf.as_slice()).unwrap();
+        assert_eq!(tensor.as_slice::<u8>(), &[0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn big_endian() {
+        let header = NpyHeader {
+            dtype: DataType::I16,
+            endian: Endian::Big,
+            fortran_order: false,
+            shape: vec![2],
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf).contains("'>i2'"));
+        buf.extend([0x01, 0x02, 0xff, 0xfe]);
+        let tensor = ManagedTensor::from_npy(buf.as_slice()).unwrap();
+        assert_eq!(tensor.as_slice::<i16>(), &[0x0102, -2]);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mmap() {
+        let path = std::env::temp_dir().join(format!("dlpark-mmap-{}.npy", std::process::id()));
+        let header = NpyHeader {
+            dtype: DataType::I32,
+            endian: Endian::NATIVE,
+            fortran_order: true,
+            shape: vec![2, 3],
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        buf.extend([0i32, 3, 1, 4, 2, 5].iter().flat_map(|x| x.to_ne_bytes()));
+        std::fs::write(&path, &buf).unwrap();
+
+        let tensor = unsafe { ManagedTensor::mmap_npy(&path) }.unwrap();
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.strides(), Some(&[1, 2][..]));
+        assert_eq!(tensor.byte_offset() as usize, buf.len() - 24);
+        assert_eq!(tensor.to_contiguous::<i32>(), vec![0, 1, 2, 3, 4, 5]);
+        #[cfg(feature = "dlpack-1-0")]
+        assert!(tensor.is_read_only());
+
+        std::fs::write(&path, &buf[..buf.len() - 4]).unwrap();
+        assert!(unsafe { ManagedTensor::mmap_npy(&path) }.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn forged_shape() {
+        let header = NpyHeader {
+            dtype: DataType::F64,
+            endian: Endian::NATIVE,
+            fortran_order: false,
+            shape: vec![1 << 20, 1 << 20],
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        buf.extend([0; 16]);
+        let err = ManagedTensor::from_npy(buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Empty, but the leading dims overflow column-major strides.
+        let header = NpyHeader {
+            dtype: DataType::U8,
+            endian: Endian::NATIVE,
+            fortran_order: true,
+            shape: vec![1 << 31, 1 << 31, 2, 0],
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        let tensor = ManagedTensor::from_npy(buf.as_slice()).unwrap();
+        assert_eq!(tensor.shape(), &header.shape[..]);
+
+        #[cfg(feature = "mmap")]
+        {
+            let path =
+                std::env::temp_dir().join(format!("dlpark-forged-{}.npy", std::process::id()));
+            std::fs::write(&path, &buf).unwrap();
+            let tensor = unsafe { ManagedTensor::mmap_npy(&path) }.unwrap();
+            assert_eq!(tensor.num_elements(), 0);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn gpu_tensor() {
+        let tensor = ManagedTensor::from_dlpack(on_gpu(vec![1.0f32, 2.0]).into_dlpack());
+        let err = tensor.write_npy(Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
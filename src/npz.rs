@@ -0,0 +1,97 @@
+//! Reading and writing NumPy `.npz` archives, i.e. zip files holding one
+//! `.npy` entry per named array.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::ManagedTensor;
+
+/// Read every array of a `.npz` archive, keyed by name without the `.npy`
+/// suffix.
+pub fn read_npz<R: Read + Seek>(reader: R) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let mut archive = ZipArchive::new(reader)?;
+    let mut tensors = BTreeMap::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let name = entry.name();
+        let name = name.strip_suffix(".npy").unwrap_or(name).to_string();
+        tensors.insert(name, ManagedTensor::from_npy(entry)?);
+    }
+    Ok(tensors)
+}
+
+/// Load every array of a `.npz` file.
+pub fn load_npz<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    read_npz(BufReader::new(File::open(path)?))
+}
+
+fn write_entries<'a, W, I>(writer: W, tensors: I, method: CompressionMethod) -> io::Result<W>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    let options = SimpleFileOptions::default()
+        .compression_method(method)
+        .large_file(true);
+    let mut zip = ZipWriter::new(writer);
+    for (name, tensor) in tensors {
+        zip.start_file(format!("{name}.npy"), options)?;
+        tensor.write_npy(&mut zip)?;
+    }
+    Ok(zip.finish()?)
+}
+
+/// Write named CPU tensors as an uncompressed archive, like `numpy.savez`.
+pub fn write_npz<'a, W, I>(writer: W, tensors: I) -> io::Result<W>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    write_entries(writer, tensors, CompressionMethod::Stored)
+}
+
+/// Write named CPU tensors as a deflate-compressed archive, like
+/// `numpy.savez_compressed`.
+pub fn write_npz_compressed<'a, W, I>(writer: W, tensors: I) -> io::Result<W>
+where
+    W: Write + Seek,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    write_entries(writer, tensors, CompressionMethod::Deflated)
+}
+
+/// Save named CPU tensors as an uncompressed `.npz` file.
+pub fn save_npz<'a, P, I>(path: P, tensors: I) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    write_npz(BufWriter::new(File::create(path)?), tensors)?.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn round_trip() {
+        let a = ManagedTensor::from_dlpack(vec![1.0f32, 2.0, 3.0].into_dlpack());
+        let b = ManagedTensor::from_dlpack(vec![4u8; 16].into_dlpack());
+        for write in [write_npz, write_npz_compressed] {
+            let buf = write(Cursor::new(Vec::new()), [("a", &a), ("b", &b)]).unwrap();
+            let tensors = read_npz(Cursor::new(buf.into_inner())).unwrap();
+            assert_eq!(tensors.len(), 2);
+            assert_eq!(tensors["a"].as_slice::<f32>(), &[1.0, 2.0, 3.0]);
+            assert_eq!(tensors["b"].as_slice::<u8>(), &[4; 16]);
+        }
+    }
+}
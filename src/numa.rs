@@ -0,0 +1,88 @@
+//! NUMA placement of [`OwnedTensor`] buffers, so that on multi-socket
+//! servers exported tensors land near the threads consuming them. Enabled by
+//! the `numa` feature, on Linux.
+
+use std::io;
+
+use crate::{ffi::DataType, OwnedTensor};
+
+const MPOL_BIND: libc::c_long = 2;
+const MPOL_INTERLEAVE: libc::c_long = 3;
+const MPOL_MF_MOVE: libc::c_ulong = 1 << 1;
+
+/// Where the pages of a buffer are placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumaPolicy {
+    /// On this node only.
+    Bind(u32),
+    /// Round-robin over the nodes whose bits are set, e.g. `0b11` for nodes
+    /// 0 and 1.
+    Interleave(u64),
+}
+
+impl OwnedTensor {
+    /// Allocate a zero-filled tensor whose pages are placed by `policy`.
+    /// Nodes above 63 aren't supported.
+    pub fn new_zeroed_on(shape: &[i64], dtype: DataType, policy: NumaPolicy) -> io::Result<Self> {
+        let (mode, nodes) = match policy {
+            NumaPolicy::Bind(node) if node < u64::BITS => (MPOL_BIND, 1u64 << node),
+            NumaPolicy::Interleave(nodes) if nodes != 0 => (MPOL_INTERLEAVE, nodes),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid numa nodes",
+                ))
+            }
+        };
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let mut tensor = OwnedTensor::new_uninit(shape, dtype, page)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid shape"))?;
+        let (ptr, size) = tensor.allocation();
+        if size == 0 {
+            return Ok(tensor);
+        }
+        unsafe {
+            // The kernel reads one bit less than `maxnode`.
+            let maxnode = u64::BITS as libc::c_ulong + 1;
+            let ret = libc::syscall(
+                libc::SYS_mbind,
+                ptr,
+                size,
+                mode,
+                &nodes as *const u64,
+                maxnode,
+                MPOL_MF_MOVE,
+            );
+            if ret != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Pages are placed when first touched, which moved ones were
+            // already.
+            ptr.write_bytes(0, size);
+        }
+        Ok(tensor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placed_allocation() {
+        match OwnedTensor::new_zeroed_on(&[1024, 3], DataType::F32, NumaPolicy::Bind(0)) {
+            Ok(tensor) => {
+                assert_eq!(tensor.shape(), &[1024, 3]);
+                assert!(tensor.as_bytes().iter().all(|&b| b == 0));
+            }
+            // Containers may forbid setting memory policies.
+            Err(err) => eprintln!("skipping numa placement: {err}"),
+        }
+        let empty = OwnedTensor::new_zeroed_on(&[0], DataType::F32, NumaPolicy::Interleave(1));
+        assert!(empty.unwrap().as_bytes().is_empty());
+        for policy in [NumaPolicy::Bind(64), NumaPolicy::Interleave(0)] {
+            let err = OwnedTensor::new_zeroed_on(&[1], DataType::F32, policy).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Conversion between [`ManagedTensor`] and ONNX `TensorProto`.
+//!
+//! Only dense tensors are supported. Exported tensors always carry their data
+//! in `raw_data`, while imports also accept the typed `*_data` fields.
+
+use std::io;
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    ManagedTensor, OwnedTensor,
+};
+
+/// ONNX `TensorProto.DataType` values.
+pub mod data_type {
+    pub const UNDEFINED: i32 = 0;
+    pub const FLOAT: i32 = 1;
+    pub const UINT8: i32 = 2;
+    pub const INT8: i32 = 3;
+    pub const UINT16: i32 = 4;
+    pub const INT16: i32 = 5;
+    pub const INT32: i32 = 6;
+    pub const INT64: i32 = 7;
+    pub const STRING: i32 = 8;
+    pub const BOOL: i32 = 9;
+    pub const FLOAT16: i32 = 10;
+    pub const DOUBLE: i32 = 11;
+    pub const UINT32: i32 = 12;
+    pub const UINT64: i32 = 13;
+    pub const COMPLEX64: i32 = 14;
+    pub const COMPLEX128: i32 = 15;
+    pub const BFLOAT16: i32 = 16;
+}
+
+/// The subset of ONNX `TensorProto` describing dense tensors. Field tags match
+/// `onnx.proto`, so it can be decoded from and encoded into ONNX files with
+/// [`prost::Message`].
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    pub dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    pub data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    pub float_data: Vec<f32>,
+    #[prost(int32, repeated, tag = "5")]
+    pub int32_data: Vec<i32>,
+    #[prost(int64, repeated, tag = "7")]
+    pub int64_data: Vec<i64>,
+    #[prost(string, tag = "8")]
+    pub name: String,
+    #[prost(bytes = "vec", tag = "9")]
+    pub raw_data: Vec<u8>,
+    #[prost(double, repeated, tag = "10")]
+    pub double_data: Vec<f64>,
+    #[prost(uint64, repeated, tag = "11")]
+    pub uint64_data: Vec<u64>,
+}
+
+/// Map a DLPack dtype to its ONNX data type, if there is one.
+pub fn to_onnx_data_type(dtype: DataType) -> Option<i32> {
+    if dtype.lanes != 1 {
+        return None;
+    }
+    let onnx_type = match (dtype.code, dtype.bits) {
+        (DataTypeCode::Float, 16) => data_type::FLOAT16,
+        (DataTypeCode::Float, 32) => data_type::FLOAT,
+        (DataTypeCode::Float, 64) => data_type::DOUBLE,
+        (DataTypeCode::Bfloat, 16) => data_type::BFLOAT16,
+        (DataTypeCode::Int, 8) => data_type::INT8,
+        (DataTypeCode::Int, 16) => data_type::INT16,
+        (DataTypeCode::Int, 32) => data_type::INT32,
+        (DataTypeCode::Int, 64) => data_type::INT64,
+        (DataTypeCode::UInt, 8) => data_type::UINT8,
+        (DataTypeCode::UInt, 16) => data_type::UINT16,
+        (DataTypeCode::UInt, 32) => data_type::UINT32,
+        (DataTypeCode::UInt, 64) => data_type::UINT64,
+        (DataTypeCode::Bool, 8) => data_type::BOOL,
+        (DataTypeCode::Complex, 64) => data_type::COMPLEX64,
+        (DataTypeCode::Complex, 128) => data_type::COMPLEX128,
+        _ => return None,
+    };
+    Some(onnx_type)
+}
+
+/// Map an ONNX data type to a DLPack dtype, if there is one.
+pub fn from_onnx_data_type(onnx_type: i32) -> Option<DataType> {
+    let dtype = match onnx_type {
+        data_type::FLOAT16 => DataType::F16,
+        data_type::FLOAT => DataType::F32,
+        data_type::DOUBLE => DataType::F64,
+        data_type::BFLOAT16 => DataType::BF16,
+        data_type::INT8 => DataType::I8,
+        data_type::INT16 => DataType::I16,
+        data_type::INT32 => DataType::I32,
+        data_type::INT64 => DataType::I64,
+        data_type::UINT8 => DataType::U8,
+        data_type::UINT16 => DataType::U16,
+        data_type::UINT32 => DataType::U32,
+        data_type::UINT64 => DataType::U64,
+        data_type::BOOL => DataType::BOOL,
+        data_type::COMPLEX64 => (DataTypeCode::Complex, 64, 1).into(),
+        data_type::COMPLEX128 => (DataTypeCode::Complex, 128, 1).into(),
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl TensorProto {
+    /// Row-major little-endian payload, gathered from whichever data field is
+    /// populated.
+    fn payload(&self, itemsize: usize) -> Vec<u8> {
+        if !self.raw_data.is_empty() {
+            return self.raw_data.clone();
+        }
+        // Types narrower than 32 bits are stored bitwise in `int32_data`.
+        let truncate = |v: &i32| v.to_le_bytes()[..itemsize].to_vec();
+        match self.data_type {
+            data_type::FLOAT | data_type::COMPLEX64 => self
+                .float_data
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            data_type::DOUBLE | data_type::COMPLEX128 => self
+                .double_data
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            data_type::INT64 => self
+                .int64_data
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            data_type::UINT32 => self
+                .uint64_data
+                .iter()
+                .flat_map(|&v| (v as u32).to_le_bytes())
+                .collect(),
+            data_type::UINT64 => self
+                .uint64_data
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect(),
+            _ => self.int32_data.iter().flat_map(truncate).collect(),
+        }
+    }
+}
+
+impl ManagedTensor {
+    /// Encode a CPU tensor as an ONNX `TensorProto` named `name`.
+    ///
+    /// ONNX stores `raw_data` in little-endian order, data is converted on
+    /// big-endian hosts.
+    pub fn to_onnx(&self, name: &str) -> io::Result<TensorProto> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be encoded",
+            ));
+        }
+        let data_type = to_onnx_data_type(self.dtype()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("dtype {:?} has no onnx equivalent", self.dtype()),
+            )
+        })?;
+        let mut raw_data = self.to_contiguous_bytes().into_owned();
+        convert_byte_order(&mut raw_data, self.dtype(), Endian::NATIVE, Endian::Little);
+        Ok(TensorProto {
+            dims: self.shape().to_vec(),
+            data_type,
+            name: name.to_string(),
+            raw_data,
+            ..Default::default()
+        })
+    }
+
+    /// Decode a dense ONNX `TensorProto` into a new CPU tensor.
+    pub fn from_onnx(proto: &TensorProto) -> io::Result<Self> {
+        let dtype = from_onnx_data_type(proto.data_type).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported onnx data type {}", proto.data_type),
+            )
+        })?;
+        let mut payload = proto.payload(dtype.size());
+        convert_byte_order(&mut payload, dtype, Endian::Little, Endian::NATIVE);
+        let tensor = OwnedTensor::from_bytes(&payload, &proto.dims, dtype)
+            .ok_or_else(|| invalid_data("onnx tensor data doesn't match its dims"))?;
+        Ok(Self::from_dlpack(tensor.into_dlpack()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+    use crate::fixtures::on_gpu;
+
+    #[test]
+    fn round_trip() {
+        let v: Vec<f32> = (0..6).map(|x| x as f32).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let proto = tensor.to_onnx("weight").unwrap();
+        assert_eq!(proto.data_type, data_type::FLOAT);
+
+        let decoded = TensorProto::decode(proto.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, proto);
+        let tensor = ManagedTensor::from_onnx(&decoded).unwrap();
+        assert_eq!(tensor.shape(), &[6]);
+        assert_eq!(tensor.as_slice::<f32>(), &v[..]);
+    }
+
+    #[test]
+    fn typed_fields() {
+        let proto = TensorProto {
+            dims: vec![2, 2],
+            data_type: data_type::INT8,
+            int32_data: vec![1, -1, 2, -2],
+            ..Default::default()
+        };
+        let tensor = ManagedTensor::from_onnx(&proto).unwrap();
+        assert_eq!(tensor.dtype(), DataType::I8);
+        assert_eq!(tensor.as_slice::<i8>(), &[1, -1, 2, -2]);
+
+        let proto = TensorProto {
+            dims: vec![3],
+            ..proto
+        };
+        assert!(ManagedTensor::from_onnx(&proto).is_err());
+    }
+
+    #[test]
+    fn gpu_tensor() {
+        let tensor = ManagedTensor::from_dlpack(on_gpu(vec![1.0f32, 2.0]).into_dlpack());
+        let err = tensor.to_onnx("gpu").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
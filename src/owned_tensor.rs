@@ -0,0 +1,530 @@
+use alloc::{
+    alloc::{handle_alloc_error, Layout},
+    vec,
+    vec::Vec,
+};
+use core::{cmp::Ordering, fmt, ptr::NonNull};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::{
+    allocator::{self, TensorAllocator},
+    ffi::{DataType, DataTypeCode, Device},
+    tensor::traits::{InferDtype, ToTensor},
+    ManagerCtx, ShapeAndStrides,
+};
+
+/// Alignment of buffers allocated by [`OwnedTensor`], enough for any dtype and
+/// for common SIMD kernels.
+pub const OWNED_TENSOR_ALIGNMENT: usize = 64;
+
+/// Payloads read by [`OwnedTensor::read_from`] up to this size are allocated
+/// upfront.
+#[cfg(feature = "std")]
+const PROBE_LEN: usize = 64 << 20;
+
+/// Size in bytes of a contiguous tensor, `None` if `shape` has negative dims
+/// or the size overflows.
+pub(crate) fn byte_len(shape: &[i64], dtype: DataType) -> Option<usize> {
+    let len = shape.iter().try_fold(dtype.size(), |acc, &dim| {
+        acc.checked_mul(usize::try_from(dim).ok()?)
+    })?;
+    // Larger sizes are invalid layouts.
+    (len <= isize::MAX as usize).then_some(len)
+}
+
+/// Type-erased contiguous CPU tensor owning its data.
+///
+/// This is what deserializers and copying operations produce when the dtype
+/// is only known at runtime. Buffers come from the [`allocator`] set when they
+/// are allocated.
+pub struct OwnedTensor {
+    ptr: NonNull<u8>,
+    len: usize,
+    /// Layout of the allocation, of size zero if nothing was allocated.
+    layout: Layout,
+    allocator: &'static dyn TensorAllocator,
+    dtype: DataType,
+    shape: Vec<i64>,
+}
+
+// The buffer is uniquely owned.
+unsafe impl Send for OwnedTensor {}
+unsafe impl Sync for OwnedTensor {}
+
+impl OwnedTensor {
+    /// Allocate a zero-filled tensor. Returns `None` if `shape` has negative
+    /// dims or its size overflows.
+    pub fn new_zeroed(shape: &[i64], dtype: DataType) -> Option<Self> {
+        Self::new_zeroed_in(shape, dtype, allocator::global())
+    }
+
+    /// Same as [`OwnedTensor::new_zeroed`], with the buffer allocated by
+    /// `allocator` instead of the global one.
+    pub fn new_zeroed_in(
+        shape: &[i64],
+        dtype: DataType,
+        allocator: &'static dyn TensorAllocator,
+    ) -> Option<Self> {
+        Self::allocate(shape, dtype, OWNED_TENSOR_ALIGNMENT, true, allocator)
+    }
+
+    /// Allocate a tensor whose buffer is aligned to `align` and padded to a
+    /// multiple of it, e.g. to whole pages. Its bytes are uninitialized.
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    pub(crate) fn new_uninit(shape: &[i64], dtype: DataType, align: usize) -> Option<Self> {
+        Self::allocate(shape, dtype, align, false, allocator::global())
+    }
+
+    fn allocate(
+        shape: &[i64],
+        dtype: DataType,
+        align: usize,
+        zeroed: bool,
+        allocator: &'static dyn TensorAllocator,
+    ) -> Option<Self> {
+        match Self::try_allocate(shape, dtype, align, zeroed, allocator) {
+            Ok(tensor) => Some(tensor),
+            Err(None) => None,
+            Err(Some(layout)) => handle_alloc_error(layout),
+        }
+    }
+
+    /// Same as [`OwnedTensor::allocate`], returning the layout that couldn't
+    /// be allocated instead of aborting, or `None` for an invalid shape.
+    fn try_allocate(
+        shape: &[i64],
+        dtype: DataType,
+        align: usize,
+        zeroed: bool,
+        allocator: &'static dyn TensorAllocator,
+    ) -> Result<Self, Option<Layout>> {
+        let len = byte_len(shape, dtype).ok_or(None)?;
+        let layout = Layout::from_size_align(len, align)
+            .map_err(|_| None)?
+            .pad_to_align();
+        let ptr = if len == 0 {
+            NonNull::dangling()
+        } else {
+            let ptr = if zeroed {
+                allocator.allocate_zeroed(layout)
+            } else {
+                allocator.allocate_aligned(layout)
+            };
+            ptr.ok_or(Some(layout))?
+        };
+        Ok(Self {
+            ptr,
+            len,
+            layout,
+            allocator,
+            dtype,
+            shape: shape.to_vec(),
+        })
+    }
+
+    /// Same as [`OwnedTensor::new_zeroed`], for shapes read from untrusted
+    /// input: an invalid shape is `InvalidData` and one too large to allocate
+    /// `OutOfMemory`, instead of aborting the process.
+    #[cfg(feature = "std")]
+    pub(crate) fn try_new_zeroed(shape: &[i64], dtype: DataType) -> io::Result<Self> {
+        Self::try_allocate(
+            shape,
+            dtype,
+            OWNED_TENSOR_ALIGNMENT,
+            true,
+            allocator::global(),
+        )
+        .map_err(|layout| match layout {
+            None => io::Error::new(io::ErrorKind::InvalidData, "invalid shape"),
+            Some(_) => io::Error::new(io::ErrorKind::OutOfMemory, "tensor is too large"),
+        })
+    }
+
+    /// Read a row-major payload of `shape` and `dtype` from `reader` into a
+    /// new tensor, for shapes read from untrusted input as with
+    /// [`OwnedTensor::try_new_zeroed`]. Payloads over [`PROBE_LEN`] are only
+    /// allocated once that much of them was read, so a forged shape can't
+    /// claim more memory than the stream is actually sending.
+    #[cfg(feature = "std")]
+    pub(crate) fn read_from<R: Read>(
+        reader: &mut R,
+        shape: &[i64],
+        dtype: DataType,
+    ) -> io::Result<Self> {
+        let len = byte_len(shape, dtype)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid shape"))?;
+        if len <= PROBE_LEN {
+            let mut tensor = Self::try_new_zeroed(shape, dtype)?;
+            reader.read_exact(tensor.as_bytes_mut())?;
+            return Ok(tensor);
+        }
+        let mut head = vec![0; PROBE_LEN];
+        reader.read_exact(&mut head)?;
+        let mut tensor = Self::try_new_zeroed(shape, dtype)?;
+        let bytes = tensor.as_bytes_mut();
+        bytes[..PROBE_LEN].copy_from_slice(&head);
+        drop(head);
+        reader.read_exact(&mut bytes[PROBE_LEN..])?;
+        Ok(tensor)
+    }
+
+    /// The whole allocation, see [`OwnedTensor::new_uninit`].
+    #[cfg(all(feature = "numa", target_os = "linux"))]
+    pub(crate) fn allocation(&mut self) -> (*mut u8, usize) {
+        (self.ptr.as_ptr(), self.layout.size())
+    }
+
+    /// Copy row-major `bytes` into a new tensor. Returns `None` if the length
+    /// of `bytes` doesn't match `shape` and `dtype`.
+    pub fn from_bytes(bytes: &[u8], shape: &[i64], dtype: DataType) -> Option<Self> {
+        Self::from_bytes_aligned(bytes, shape, dtype, OWNED_TENSOR_ALIGNMENT)
+    }
+
+    /// Same as [`OwnedTensor::from_bytes`], with the buffer aligned to
+    /// `align`, a power of two, and padded to a multiple of it.
+    pub(crate) fn from_bytes_aligned(
+        bytes: &[u8],
+        shape: &[i64],
+        dtype: DataType,
+        align: usize,
+    ) -> Option<Self> {
+        // Before allocating, the shape may be anything.
+        if byte_len(shape, dtype)? != bytes.len() {
+            return None;
+        }
+        let mut tensor = Self::allocate(shape, dtype, align, true, allocator::global())?;
+        tensor.as_bytes_mut().copy_from_slice(bytes);
+        Some(tensor)
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// New tensor of `A` whose elements are written by `fill`.
+    pub(crate) fn new_with<A: InferDtype>(
+        shape: &[i64],
+        fill: impl FnOnce(&mut [A]),
+    ) -> Option<Self> {
+        let tensor = Self::new_zeroed(shape, A::infer_dtype())?;
+        let len = tensor.len / core::mem::size_of::<A>().max(1);
+        // The buffer is aligned to `OWNED_TENSOR_ALIGNMENT`, unless it is
+        // empty and the pointer dangling.
+        if len == 0 {
+            fill(&mut []);
+        } else {
+            fill(unsafe { core::slice::from_raw_parts_mut(tensor.ptr.as_ptr().cast::<A>(), len) });
+        }
+        Some(tensor)
+    }
+}
+
+/// Factories of filled CPU tensors, ready to export. They return `None` if
+/// `shape` has negative dims or its size overflows.
+impl ManagerCtx<OwnedTensor> {
+    pub fn zeros(shape: &[i64], dtype: DataType) -> Option<Self> {
+        OwnedTensor::new_zeroed(shape, dtype).map(Self::new)
+    }
+
+    /// Also `None` if `dtype` has no known encoding of one, e.g. opaque
+    /// handles or sub-byte types.
+    pub fn ones(shape: &[i64], dtype: DataType) -> Option<Self> {
+        let one = one_bytes(dtype)?;
+        let mut tensor = OwnedTensor::new_zeroed(shape, dtype)?;
+        for element in tensor.as_bytes_mut().chunks_exact_mut(one.len()) {
+            element.copy_from_slice(&one);
+        }
+        Some(Self::new(tensor))
+    }
+
+    pub fn full<A: InferDtype + Copy>(shape: &[i64], value: A) -> Option<Self> {
+        OwnedTensor::new_with(shape, |elements| elements.fill(value)).map(Self::new)
+    }
+
+    /// 1-d tensor of `start`, `start + step`, ... up to `stop` excluded, or
+    /// down to it if `step` is negative. `None` if `step` is zero or NaN, or
+    /// the length isn't finite or doesn't fit in `usize`.
+    pub fn arange<A: ArangeElement>(start: A, stop: A, step: A) -> Option<Self> {
+        let len = A::arange_len(start, stop, step)?;
+        let len = i64::try_from(len).ok()?;
+        OwnedTensor::new_with(&[len], |elements| {
+            for (i, element) in elements.iter_mut().enumerate() {
+                *element = A::arange_nth(start, step, i);
+            }
+        })
+        .map(Self::new)
+    }
+}
+
+/// Element types of [`ManagerCtx::arange`]. Its length is computed upfront,
+/// so that it is bounded whatever the arguments.
+pub trait ArangeElement: InferDtype + Copy {
+    /// Number of elements from `start` to `stop` by `step`, as documented in
+    /// [`ManagerCtx::arange`].
+    fn arange_len(start: Self, stop: Self, step: Self) -> Option<usize>;
+
+    /// `start + i * step`, for `i` below the length.
+    fn arange_nth(start: Self, step: Self, i: usize) -> Self;
+}
+
+// The elements lie between `start` and `stop`, so wrapping arithmetic is
+// exact for them.
+macro_rules! impl_arange_signed {
+    ($($t:ty),*) => {$(
+        impl ArangeElement for $t {
+            fn arange_len(start: Self, stop: Self, step: Self) -> Option<usize> {
+                let empty = match step.cmp(&0) {
+                    Ordering::Equal => return None,
+                    Ordering::Greater => start >= stop,
+                    Ordering::Less => start <= stop,
+                };
+                if empty {
+                    return Some(0);
+                }
+                usize::try_from(stop.abs_diff(start).div_ceil(step.unsigned_abs())).ok()
+            }
+
+            fn arange_nth(start: Self, step: Self, i: usize) -> Self {
+                start.wrapping_add((i as Self).wrapping_mul(step))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_arange_unsigned {
+    ($($t:ty),*) => {$(
+        impl ArangeElement for $t {
+            fn arange_len(start: Self, stop: Self, step: Self) -> Option<usize> {
+                if step == 0 {
+                    return None;
+                }
+                usize::try_from(stop.saturating_sub(start).div_ceil(step)).ok()
+            }
+
+            fn arange_nth(start: Self, step: Self, i: usize) -> Self {
+                start.wrapping_add((i as Self).wrapping_mul(step))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_arange_float {
+    ($($t:ty),*) => {$(
+        impl ArangeElement for $t {
+            fn arange_len(start: Self, stop: Self, step: Self) -> Option<usize> {
+                if step == 0.0 || step.is_nan() {
+                    return None;
+                }
+                let len = (stop - start) / step;
+                if len.is_nan() || len >= usize::MAX as Self {
+                    return None;
+                }
+                if len <= 0.0 {
+                    return Some(0);
+                }
+                // Rounded up, without `ceil` which needs std.
+                let whole = len as usize;
+                Some(if (whole as Self) < len { whole + 1 } else { whole })
+            }
+
+            fn arange_nth(start: Self, step: Self, i: usize) -> Self {
+                start + i as Self * step
+            }
+        }
+    )*};
+}
+
+impl_arange_signed!(i8, i16, i32, i64, i128);
+impl_arange_unsigned!(u8, u16, u32, u64, u128);
+impl_arange_float!(f32, f64);
+
+#[cfg(feature = "half")]
+macro_rules! impl_arange_half {
+    ($($t:ty),*) => {$(
+        impl ArangeElement for $t {
+            fn arange_len(start: Self, stop: Self, step: Self) -> Option<usize> {
+                f32::arange_len(start.to_f32(), stop.to_f32(), step.to_f32())
+            }
+
+            fn arange_nth(start: Self, step: Self, i: usize) -> Self {
+                Self::from_f32(f32::arange_nth(start.to_f32(), step.to_f32(), i))
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "half")]
+impl_arange_half!(half::f16, half::bf16);
+
+/// Native-endian encoding of one in `dtype`, with all lanes.
+fn one_bytes(dtype: DataType) -> Option<Vec<u8>> {
+    let scalar = |code: DataTypeCode, bits: u8| -> Option<Vec<u8>> {
+        Some(match (code, bits) {
+            (DataTypeCode::Float, 16) => 0x3c00u16.to_ne_bytes().to_vec(),
+            (DataTypeCode::Float, 32) => 1f32.to_ne_bytes().to_vec(),
+            (DataTypeCode::Float, 64) => 1f64.to_ne_bytes().to_vec(),
+            (DataTypeCode::Bfloat, 16) => 0x3f80u16.to_ne_bytes().to_vec(),
+            (
+                DataTypeCode::Int | DataTypeCode::UInt | DataTypeCode::Bool,
+                8 | 16 | 32 | 64 | 128,
+            ) => {
+                let mut bytes = vec![0; bits as usize / 8];
+                if cfg!(target_endian = "little") {
+                    bytes[0] = 1;
+                } else {
+                    *bytes.last_mut()? = 1;
+                }
+                bytes
+            }
+            _ => return None,
+        })
+    };
+    let lane = match dtype.code {
+        // Real part of one, imaginary part of zero.
+        DataTypeCode::Complex => {
+            let mut bytes = scalar(DataTypeCode::Float, dtype.bits / 2)?;
+            bytes.resize(bytes.len() * 2, 0);
+            bytes
+        }
+        code => scalar(code, dtype.bits)?,
+    };
+    Some(lane.repeat(dtype.lanes as usize))
+}
+
+impl Drop for OwnedTensor {
+    fn drop(&mut self) {
+        if self.layout.size() != 0 {
+            unsafe { self.allocator.deallocate(self.ptr, self.layout) };
+        }
+    }
+}
+
+impl fmt::Debug for OwnedTensor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedTensor")
+            .field("ptr", &self.ptr)
+            .field("len", &self.len)
+            .field("dtype", &self.dtype)
+            .field("shape", &self.shape)
+            .finish()
+    }
+}
+
+impl Clone for OwnedTensor {
+    fn clone(&self) -> Self {
+        Self::from_bytes(self.as_bytes(), &self.shape, self.dtype).unwrap()
+    }
+}
+
+impl ToTensor for OwnedTensor {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.ptr.as_ptr().cast()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn from_bytes() {
+        let bytes: Vec<u8> = (0..24).collect();
+        let tensor = OwnedTensor::from_bytes(&bytes, &[2, 3], DataType::U32).unwrap();
+        assert_eq!(tensor.as_bytes(), &bytes[..]);
+        assert_eq!(
+            tensor.as_bytes().as_ptr() as usize % OWNED_TENSOR_ALIGNMENT,
+            0
+        );
+        assert!(OwnedTensor::from_bytes(&bytes, &[2, 2], DataType::U32).is_none());
+
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.dtype(), DataType::U32);
+        assert_eq!(
+            tensor.as_slice::<u32>()[0],
+            u32::from_ne_bytes([0, 1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn factories() {
+        let tensor = ManagedTensor::from(ManagerCtx::zeros(&[2, 3], DataType::F32).unwrap());
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.as_slice::<f32>(), [0.0; 6]);
+
+        let tensor = ManagedTensor::from(ManagerCtx::ones(&[2, 2], DataType::F64).unwrap());
+        assert_eq!(tensor.as_slice::<f64>(), [1.0; 4]);
+        let tensor = ManagedTensor::from(ManagerCtx::ones(&[3], DataType::I16).unwrap());
+        assert_eq!(tensor.as_slice::<i16>(), [1; 3]);
+        let complex = DataType {
+            code: DataTypeCode::Complex,
+            bits: 64,
+            lanes: 1,
+        };
+        let tensor = ManagedTensor::from(ManagerCtx::ones(&[2], complex).unwrap());
+        assert_eq!(tensor.as_slice::<[f32; 2]>(), [[1.0, 0.0]; 2]);
+        let handle = DataType {
+            code: DataTypeCode::OpaqueHandle,
+            bits: 64,
+            lanes: 1,
+        };
+        assert!(ManagerCtx::ones(&[2], handle).is_none());
+
+        let tensor = ManagedTensor::from(ManagerCtx::full(&[1, 3], 7u8).unwrap());
+        assert_eq!(tensor.dtype(), DataType::U8);
+        assert_eq!(tensor.as_slice::<u8>(), [7; 3]);
+
+        let tensor = ManagedTensor::from(ManagerCtx::arange(0i64, 5, 2).unwrap());
+        assert_eq!(tensor.as_slice::<i64>(), [0, 2, 4]);
+        let tensor = ManagedTensor::from(ManagerCtx::arange(1.0f32, 0.0, -0.25).unwrap());
+        assert_eq!(tensor.as_slice::<f32>(), [1.0, 0.75, 0.5, 0.25]);
+        assert!(ManagerCtx::arange(0, 1, 0).is_none());
+        let tensor = ManagedTensor::from(ManagerCtx::arange(250u8, 255, 10).unwrap());
+        assert_eq!(tensor.as_slice::<u8>(), [250]);
+        let tensor = ManagedTensor::from(ManagerCtx::arange(-128i8, 127, 100).unwrap());
+        assert_eq!(tensor.as_slice::<i8>(), [-128, -28, 72]);
+        // Adding the step doesn't change the start, yet it ends.
+        let tensor = ManagedTensor::from(ManagerCtx::arange(1e16f64, 1e16 + 8.0, 1.0).unwrap());
+        assert_eq!(tensor.num_elements(), 8);
+        assert!(ManagerCtx::arange(0.0f32, f32::INFINITY, 1.0).is_none());
+        assert!(ManagerCtx::arange(0.0f32, 1.0, f32::NAN).is_none());
+        assert!(ManagerCtx::zeros(&[-1], DataType::F32).is_none());
+    }
+
+    #[test]
+    fn empty() {
+        let tensor = OwnedTensor::new_zeroed(&[0, 3], DataType::F32).unwrap();
+        assert!(tensor.as_bytes().is_empty());
+        assert!(OwnedTensor::new_zeroed(&[-1], DataType::F32).is_none());
+    }
+}
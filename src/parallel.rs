@@ -0,0 +1,178 @@
+use std::{
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rayon::{prelude::*, slice};
+
+use crate::{
+    ffi::DeviceType, tensor::traits::TensorView, utils::copy_strided_bytes, ManagedTensor,
+};
+
+static PARALLEL_COPY_THRESHOLD: AtomicUsize = AtomicUsize::new(16 << 20);
+
+/// Size in bytes from which [`ManagedTensor::to_contiguous`],
+/// [`ManagedTensor::into_vec`] and the like gather strided tensors in
+/// parallel, 16 MiB unless set.
+pub fn parallel_copy_threshold() -> usize {
+    PARALLEL_COPY_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Set [`parallel_copy_threshold`] for the process, `usize::MAX` keeping
+/// every copy on the calling thread.
+pub fn set_parallel_copy_threshold(bytes: usize) {
+    PARALLEL_COPY_THRESHOLD.store(bytes, Ordering::Relaxed);
+}
+
+/// Raw pointers are not `Send`, wrap the source of a parallel copy instead of
+/// passing its address, which would lose its provenance.
+#[derive(Clone, Copy)]
+struct SendPtr<A>(*const A);
+
+unsafe impl<A> Send for SendPtr<A> {}
+unsafe impl<A> Sync for SendPtr<A> {}
+
+impl<A> SendPtr<A> {
+    fn get(self) -> *const A {
+        self.0
+    }
+}
+
+/// [`copy_strided_bytes`] with one task per index of the outermost axis.
+///
+/// # Safety
+/// Same as [`copy_strided_bytes`].
+pub(crate) unsafe fn par_copy_strided_bytes(
+    src: *const u8,
+    shape: &[i64],
+    strides: &[i64],
+    itemsize: usize,
+    dst: &mut [MaybeUninit<u8>],
+) {
+    if shape.len() < 2 || dst.is_empty() {
+        return copy_strided_bytes(src, shape, strides, itemsize, dst);
+    }
+    let base = SendPtr(src);
+    let chunk_len = dst.len() / shape[0] as usize;
+    dst.par_chunks_mut(chunk_len)
+        .enumerate()
+        .for_each(|(i, dst)| unsafe {
+            let src = base
+                .get()
+                .offset(i as isize * strides[0] as isize * itemsize as isize);
+            copy_strided_bytes(src, &shape[1..], &strides[1..], itemsize, dst);
+        });
+}
+
+impl ManagedTensor {
+    /// Parallel iterator over the elements of a contiguous CPU tensor.
+    pub fn par_iter<A: Sync>(&self) -> slice::Iter<'_, A> {
+        self.as_contiguous_slice().par_iter()
+    }
+
+    /// Parallel iterator over the sub-tensors obtained by fixing the indices
+    /// of axes `0..=axis`, e.g. `par_chunks(0)` on a NCHW tensor yields one
+    /// CHW slice per image.
+    pub fn par_chunks<A: Sync>(&self, axis: usize) -> slice::Chunks<'_, A> {
+        assert!(axis < self.ndim(), "axis out of range");
+        let slice = self.as_contiguous_slice();
+        // Only overflows if another dim is zero, in which case the tensor is
+        // empty anyway, as it is when rayon is given a chunk size of 1 instead
+        // of zero.
+        let chunk_len = self.shape()[axis + 1..]
+            .iter()
+            .try_fold(1usize, |acc, &dim| acc.checked_mul(dim as usize))
+            .unwrap_or(0);
+        slice.par_chunks(chunk_len.max(1))
+    }
+
+    /// Same as [`ManagedTensor::to_contiguous`], but gathers the outermost
+    /// dimension in parallel.
+    pub fn par_to_contiguous<A: Copy + Send + Sync>(&self) -> Vec<A> {
+        let strides = match self.strides() {
+            Some(strides) if !self.is_contiguous() && self.ndim() > 1 => strides,
+            _ => return self.to_contiguous(),
+        };
+        assert_eq!(
+            std::mem::size_of::<A>(),
+            self.dtype().size(),
+            "dtype and A size mismatch"
+        );
+        assert_eq!(
+            self.device().device_type,
+            DeviceType::Cpu,
+            "tensor should be on cpu"
+        );
+        let num_elements = self.num_elements();
+        let itemsize = std::mem::size_of::<A>();
+        let mut buf: Vec<A> = Vec::with_capacity(num_elements);
+        unsafe {
+            let dst = std::slice::from_raw_parts_mut(
+                buf.as_mut_ptr().cast::<MaybeUninit<u8>>(),
+                num_elements * itemsize,
+            );
+            par_copy_strided_bytes(self.first_byte(), self.shape(), strides, itemsize, dst);
+            buf.set_len(num_elements);
+        }
+        buf
+    }
+
+    fn as_contiguous_slice<A>(&self) -> &[A] {
+        assert!(self.is_contiguous(), "tensor should be contiguous");
+        assert_eq!(
+            self.device().device_type,
+            DeviceType::Cpu,
+            "tensor should be on cpu"
+        );
+        self.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::Strided, prelude::*};
+
+    #[test]
+    fn par_iter_sum() {
+        let v: Vec<i64> = (0..1000).collect();
+        let tensor = ManagedTensor::from_dlpack(v.into_dlpack());
+        assert_eq!(tensor.par_iter::<i64>().sum::<i64>(), 999 * 1000 / 2);
+    }
+
+    #[test]
+    fn par_chunks_rows() {
+        let v: Vec<f32> = (0..6).map(|x| x as f32).collect();
+        let tensor = ManagedTensor::from_dlpack(v.into_dlpack());
+        let chunks: Vec<&[f32]> = tensor.par_chunks(0).collect();
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(chunks[5], &[5.0]);
+    }
+
+    /// A [2, 3, 4] tensor permuted to [4, 3, 2].
+    fn permuted(v: Vec<u16>) -> Strided<u16> {
+        Strided::new(v, &[4, 3, 2], &[1, 4, 12])
+    }
+
+    #[test]
+    fn par_to_contiguous_matches_sequential() {
+        let v: Vec<u16> = (0..24).collect();
+        let tensor = ManagedTensor::from_dlpack(permuted(v).into_dlpack());
+        let expected = tensor.to_contiguous::<u16>();
+        assert_eq!(&expected[..4], &[0, 12, 4, 16]);
+        assert_eq!(tensor.par_to_contiguous::<u16>(), expected);
+    }
+
+    #[test]
+    fn parallel_compaction() {
+        let v: Vec<u16> = (0..24).collect();
+        let tensor = ManagedTensor::from_dlpack(permuted(v).into_dlpack());
+        let expected = tensor.to_contiguous::<u16>();
+        let threshold = parallel_copy_threshold();
+        // Other tests may compact concurrently, with either threshold.
+        set_parallel_copy_threshold(0);
+        assert_eq!(tensor.to_contiguous::<u16>(), expected);
+        assert_eq!(&tensor.to_contiguous_bytes()[2..4], &12u16.to_ne_bytes());
+        set_parallel_copy_threshold(threshold);
+    }
+}
@@ -0,0 +1,166 @@
+//! Page-locked host memory, which CUDA copies to and from the device with
+//! DMA, exported as `CUDAHost` tensors. Enabled by the `cudarc` feature.
+
+use std::{ffi::c_void, io, ptr};
+
+use cudarc::driver::sys::{self, CUdevice, CU_MEMHOSTALLOC_PORTABLE};
+
+use crate::{
+    cuda_ipc::{check, with_device},
+    ffi::{DataType, Device, DeviceType},
+    tensor::traits::ToTensor,
+    ShapeAndStrides,
+};
+
+/// Contiguous tensor in page-locked host memory, owning its data.
+#[derive(Debug)]
+pub struct PinnedTensor {
+    /// NULL if the tensor is empty, which allocates nothing.
+    ptr: *mut c_void,
+    len: usize,
+    dtype: DataType,
+    shape: Vec<i64>,
+    /// Device whose primary context is kept alive while the memory is.
+    device: Option<(i32, CUdevice)>,
+}
+
+// The buffer is uniquely owned.
+unsafe impl Send for PinnedTensor {}
+unsafe impl Sync for PinnedTensor {}
+
+impl PinnedTensor {
+    /// Allocate zero-filled page-locked memory through the primary context of
+    /// `device_id`, usable from every context.
+    pub fn new_zeroed(device_id: i32, shape: &[i64], dtype: DataType) -> io::Result<Self> {
+        let len = shape
+            .iter()
+            .try_fold(dtype.size(), |acc, &dim| {
+                acc.checked_mul(usize::try_from(dim).ok()?)
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid shape"))?;
+        let mut tensor = Self {
+            ptr: ptr::null_mut(),
+            len,
+            dtype,
+            shape: shape.to_vec(),
+            device: None,
+        };
+        if len == 0 {
+            return Ok(tensor);
+        }
+        unsafe {
+            with_device(device_id, |device| {
+                let mut ptr = ptr::null_mut();
+                check(sys::cuMemHostAlloc(&mut ptr, len, CU_MEMHOSTALLOC_PORTABLE))?;
+                ptr.cast::<u8>().write_bytes(0, len);
+                // Keep the context alive for as long as the memory.
+                let mut ctx = ptr::null_mut();
+                if let Err(err) = check(sys::cuDevicePrimaryCtxRetain(&mut ctx, device)) {
+                    sys::cuMemFreeHost(ptr);
+                    return Err(err);
+                }
+                tensor.ptr = ptr;
+                tensor.device = Some((device_id, device));
+                Ok(())
+            })?;
+        }
+        Ok(tensor)
+    }
+
+    /// Copy row-major `bytes` into new page-locked memory.
+    pub fn from_bytes(
+        device_id: i32,
+        bytes: &[u8],
+        shape: &[i64],
+        dtype: DataType,
+    ) -> io::Result<Self> {
+        let mut tensor = Self::new_zeroed(device_id, shape, dtype)?;
+        if tensor.len != bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "length of bytes doesn't match shape and dtype",
+            ));
+        }
+        tensor.as_bytes_mut().copy_from_slice(bytes);
+        Ok(tensor)
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr.cast(), self.len) }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        if self.len == 0 {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.cast(), self.len) }
+    }
+}
+
+impl Drop for PinnedTensor {
+    fn drop(&mut self) {
+        if let Some((device_id, device)) = self.device {
+            unsafe {
+                let _ = with_device(device_id, |_| check(sys::cuMemFreeHost(self.ptr)));
+                sys::cuDevicePrimaryCtxRelease_v2(device);
+            }
+        }
+    }
+}
+
+impl ToTensor for PinnedTensor {
+    fn data_ptr(&self) -> *mut c_void {
+        self.ptr
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device {
+            device_type: DeviceType::CudaHost,
+            device_id: 0,
+        }
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn pinned_export() {
+        // Empty tensors don't need a driver.
+        let tensor = PinnedTensor::new_zeroed(0, &[0, 3], DataType::F32).unwrap();
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.device().device_type, DeviceType::CudaHost);
+        assert_eq!(tensor.shape(), &[0, 3]);
+        assert!(PinnedTensor::new_zeroed(0, &[-1], DataType::F32).is_err());
+
+        match PinnedTensor::from_bytes(0, &[1, 2, 3], &[3], DataType::U8) {
+            Ok(tensor) => assert_eq!(tensor.as_bytes(), [1, 2, 3]),
+            Err(err) => eprintln!("skipping pinned allocation: {err}"),
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! Memory management of device backends that are compiled separately, even
+//! in C. A backend registers an [`Allocator`] vtable for its device type at
+//! load time, then [`DeviceTensor`]s of that device type are allocated,
+//! copied and freed through it, and can be exported with [`ManagerCtx`] like
+//! any other tensor. Backends which can tell how much memory a device has
+//! left answer [`DeviceMemoryInfo::memory_info`], to decide before exporting
+//! large tensors. Services exporting tensors of the same sizes on every
+//! request can allocate them from a [`DevicePool`] instead, which keeps the
+//! blocks of deleted tensors for the next ones.
+//!
+//! [`ManagerCtx`]: crate::ManagerCtx
+
+use std::{
+    collections::BTreeMap,
+    ffi::c_void,
+    io,
+    ptr::{self, NonNull},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use crate::{
+    error::invariant_violated,
+    ffi::{DataType, Device, DeviceType},
+    Error, OwnedTensor, Result, ShapeAndStrides, ToTensor,
+};
+
+/// Direction of [`Allocator::copy`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CopyKind {
+    HostToDevice   = 0,
+    DeviceToHost   = 1,
+    DeviceToDevice = 2,
+}
+
+/// C-compatible vtable of a device backend. Every callback gets `ctx` as its
+/// first argument and must be safe to call from any thread. Only
+/// `memory_info` may be NULL.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Allocator {
+    pub ctx: *mut c_void,
+    /// Allocate `size` bytes aligned to `align` on device `device_id`.
+    /// Returns NULL on failure.
+    pub alloc: Option<
+        unsafe extern "C" fn(
+            ctx: *mut c_void,
+            device_id: i32,
+            size: usize,
+            align: usize,
+        ) -> *mut c_void,
+    >,
+    /// Free a block returned by `alloc` with the same arguments.
+    pub free: Option<
+        unsafe extern "C" fn(
+            ctx: *mut c_void,
+            device_id: i32,
+            ptr: *mut c_void,
+            size: usize,
+            align: usize,
+        ),
+    >,
+    /// Copy `size` bytes from `src` to `dst`, where the device side is on
+    /// device `device_id`. Returns 0 on success.
+    pub copy: Option<
+        unsafe extern "C" fn(
+            ctx: *mut c_void,
+            device_id: i32,
+            dst: *mut c_void,
+            src: *const c_void,
+            size: usize,
+            kind: CopyKind,
+        ) -> i32,
+    >,
+    /// Write the free and total bytes of device `device_id`. Returns 0 on
+    /// success. May be NULL if the backend can't tell.
+    pub memory_info: Option<
+        unsafe extern "C" fn(
+            ctx: *mut c_void,
+            device_id: i32,
+            free: *mut usize,
+            total: *mut usize,
+        ) -> i32,
+    >,
+}
+
+// See the invariants of `register_allocator`.
+unsafe impl Send for Allocator {}
+unsafe impl Sync for Allocator {}
+
+/// Memory of a device in bytes, from [`Allocator::memory_info`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub free: usize,
+    pub total: usize,
+}
+
+impl Allocator {
+    /// Whether `alloc`, `free` and `copy` are all set.
+    pub fn is_complete(&self) -> bool {
+        self.alloc.is_some() && self.free.is_some() && self.copy.is_some()
+    }
+
+    /// Memory of device `device_id`, or `None` if the backend can't tell or
+    /// the query fails.
+    pub fn memory_info(&self, device_id: i32) -> Option<MemoryInfo> {
+        let query = self.memory_info?;
+        let (mut free, mut total) = (0, 0);
+        match unsafe { query(self.ctx, device_id, &mut free, &mut total) } {
+            0 => Some(MemoryInfo { free, total }),
+            _ => None,
+        }
+    }
+
+    pub fn total_memory(&self, device_id: i32) -> Option<usize> {
+        self.memory_info(device_id).map(|info| info.total)
+    }
+
+    pub fn free_memory(&self, device_id: i32) -> Option<usize> {
+        self.memory_info(device_id).map(|info| info.free)
+    }
+}
+
+static ALLOCATORS: RwLock<BTreeMap<i32, Allocator>> = RwLock::new(BTreeMap::new());
+
+/// Register `allocator` for every device of `device_type`, returning the one
+/// it replaces. Tensors allocated before keep using the allocator they were
+/// allocated with.
+///
+/// # Safety
+/// The callbacks must behave as documented on [`Allocator`], and `ctx` must
+/// stay valid as long as tensors allocated with it are alive. `allocator`
+/// must be [complete](Allocator::is_complete).
+pub unsafe fn register_allocator(
+    device_type: DeviceType,
+    allocator: Allocator,
+) -> Option<Allocator> {
+    ALLOCATORS
+        .write()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(device_type as i32, allocator)
+}
+
+/// The allocator registered for `device_type`.
+pub fn allocator(device_type: DeviceType) -> Option<Allocator> {
+    ALLOCATORS
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(&(device_type as i32))
+        .copied()
+}
+
+/// Memory queries of [`Device`]s, answered by their registered
+/// [`Allocator`].
+pub trait DeviceMemoryInfo {
+    /// Memory of the device, or `None` if no allocator is registered for its
+    /// type or the allocator can't tell.
+    fn memory_info(&self) -> Option<MemoryInfo>;
+}
+
+impl DeviceMemoryInfo for Device {
+    fn memory_info(&self) -> Option<MemoryInfo> {
+        allocator(self.device_type)?.memory_info(self.device_id)
+    }
+}
+
+/// Alignment requested for the data of [`DeviceTensor`]s.
+pub const DEVICE_TENSOR_ALIGNMENT: usize = 256;
+
+/// Contiguous tensor owning data allocated by a registered [`Allocator`].
+#[derive(Debug)]
+pub struct DeviceTensor {
+    ptr: NonNull<c_void>,
+    len: usize,
+    device: Device,
+    dtype: DataType,
+    shape: Vec<i64>,
+    allocator: Allocator,
+    /// Pool the block goes back to when dropped, instead of being freed.
+    pool: Option<Arc<DevicePool>>,
+}
+
+// The buffer is uniquely owned, and the allocator callbacks are thread-safe.
+unsafe impl Send for DeviceTensor {}
+unsafe impl Sync for DeviceTensor {}
+
+impl DeviceTensor {
+    /// Allocate an uninitialized tensor on `device`.
+    pub fn new(device: Device, shape: &[i64], dtype: DataType) -> Result<Self> {
+        Self::allocate(device, shape, dtype, None)
+    }
+
+    fn allocate(
+        device: Device,
+        shape: &[i64],
+        dtype: DataType,
+        pool: Option<Arc<DevicePool>>,
+    ) -> Result<Self> {
+        let len = shape
+            .iter()
+            .try_fold(dtype.size(), |acc, &dim| {
+                acc.checked_mul(usize::try_from(dim).ok()?)
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid shape"))?;
+        if let Some((block, allocator)) = pool.as_ref().and_then(|pool| pool.take(device, len)) {
+            return Ok(Self {
+                ptr: block.0,
+                len,
+                device,
+                dtype,
+                shape: shape.to_vec(),
+                allocator,
+                pool,
+            });
+        }
+        let allocator = allocator(device.device_type)
+            .filter(Allocator::is_complete)
+            .ok_or(Error::NoAllocator(device))?;
+        let ptr = if len == 0 {
+            NonNull::dangling()
+        } else {
+            let alloc = allocator.alloc.ok_or(Error::NoAllocator(device))?;
+            let ptr = unsafe {
+                alloc(
+                    allocator.ctx,
+                    device.device_id,
+                    len,
+                    DEVICE_TENSOR_ALIGNMENT,
+                )
+            };
+            NonNull::new(ptr).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::OutOfMemory, "device allocation failed")
+            })?
+        };
+        Ok(Self {
+            ptr,
+            len,
+            device,
+            dtype,
+            shape: shape.to_vec(),
+            allocator,
+            pool,
+        })
+    }
+
+    /// Copy a host tensor to `device`.
+    pub fn from_host(tensor: &OwnedTensor, device: Device) -> Result<Self> {
+        let out = Self::new(device, tensor.shape(), tensor.dtype())?;
+        out.copy(
+            out.ptr.as_ptr(),
+            tensor.as_bytes().as_ptr().cast(),
+            CopyKind::HostToDevice,
+        )?;
+        Ok(out)
+    }
+
+    /// Copy the tensor back to the host.
+    pub fn to_host(&self) -> Result<OwnedTensor> {
+        let mut out = OwnedTensor::new_zeroed(&self.shape, self.dtype)
+            .ok_or_else(|| invariant_violated("invalid shape of a device tensor"))?;
+        self.copy(
+            out.as_bytes_mut().as_mut_ptr().cast(),
+            self.ptr.as_ptr(),
+            CopyKind::DeviceToHost,
+        )?;
+        Ok(out)
+    }
+
+    /// Copy the tensor to a new tensor on the same device.
+    pub fn try_clone(&self) -> Result<Self> {
+        let out = Self::new(self.device, &self.shape, self.dtype)?;
+        self.copy(
+            out.ptr.as_ptr(),
+            self.ptr.as_ptr(),
+            CopyKind::DeviceToDevice,
+        )?;
+        Ok(out)
+    }
+
+    fn copy(&self, dst: *mut c_void, src: *const c_void, kind: CopyKind) -> Result<()> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let copy = self
+            .allocator
+            .copy
+            .ok_or_else(|| invariant_violated("device tensor allocated without a copy callback"))?;
+        let status = unsafe {
+            copy(
+                self.allocator.ctx,
+                self.device.device_id,
+                dst,
+                src,
+                self.len,
+                kind,
+            )
+        };
+        if status != 0 {
+            return Err(
+                io::Error::other(format!("device copy failed with status {status}")).into(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    pub fn device(&self) -> Device {
+        self.device
+    }
+}
+
+impl Drop for DeviceTensor {
+    fn drop(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        let block = Block(self.ptr);
+        let block = match &self.pool {
+            Some(pool) => pool.put(self.device, self.len, block, self.allocator),
+            None => Some(block),
+        };
+        if let Some(block) = block {
+            unsafe { free_block(block, self.device, self.len, self.allocator) };
+        }
+    }
+}
+
+/// Device allocation of [`DEVICE_TENSOR_ALIGNMENT`], owned by a
+/// [`DeviceTensor`] or cached by a [`DevicePool`].
+struct Block(NonNull<c_void>);
+
+// Blocks are uniquely owned.
+unsafe impl Send for Block {}
+
+unsafe fn free_block(block: Block, device: Device, len: usize, allocator: Allocator) {
+    // Blocks only come from complete allocators.
+    if let Some(free) = allocator.free {
+        free(
+            allocator.ctx,
+            device.device_id,
+            block.0.as_ptr(),
+            len,
+            DEVICE_TENSOR_ALIGNMENT,
+        );
+    }
+}
+
+/// Blocks cached by a [`DevicePool`], keyed by device type, device id and
+/// size.
+type Blocks = BTreeMap<(i32, i32, usize), Vec<(Block, Allocator)>>;
+
+/// Pool of device blocks, keyed by device and size, which recycles the
+/// blocks of [`DeviceTensor`]s allocated from it once they are dropped, e.g.
+/// when the deleter of their export runs. Tensors share ownership of their
+/// pool, so it lives until the last of them and frees its cached blocks when
+/// dropped.
+pub struct DevicePool {
+    max_blocks: usize,
+    blocks: Mutex<Blocks>,
+}
+
+impl DevicePool {
+    /// Empty pool caching up to `max_blocks` blocks of every device and size,
+    /// freeing the others.
+    pub fn new(max_blocks: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max_blocks,
+            blocks: Mutex::new(BTreeMap::new()),
+        })
+    }
+
+    /// Same as [`DeviceTensor::new`], reusing a cached block of the same
+    /// size on `device` if there is one.
+    pub fn new_tensor(
+        self: &Arc<Self>,
+        device: Device,
+        shape: &[i64],
+        dtype: DataType,
+    ) -> Result<DeviceTensor> {
+        DeviceTensor::allocate(device, shape, dtype, Some(self.clone()))
+    }
+
+    /// Number of blocks cached for reuse.
+    pub fn cached_blocks(&self) -> usize {
+        self.lock().values().map(Vec::len).sum()
+    }
+
+    /// Free every cached block.
+    pub fn clear(&self) {
+        let blocks = core::mem::take(&mut *self.lock());
+        for ((device_type, device_id, len), blocks) in blocks {
+            for (block, allocator) in blocks {
+                let device = Device {
+                    // Only keys of existing devices are inserted.
+                    device_type: DeviceType::try_from(device_type).unwrap(),
+                    device_id,
+                };
+                unsafe { free_block(block, device, len, allocator) };
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Blocks> {
+        self.blocks.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn take(&self, device: Device, len: usize) -> Option<(Block, Allocator)> {
+        let key = (device.device_type as i32, device.device_id, len);
+        self.lock().get_mut(&key)?.pop()
+    }
+
+    /// Cache `block`, or give it back if the pool is full.
+    fn put(&self, device: Device, len: usize, block: Block, allocator: Allocator) -> Option<Block> {
+        let key = (device.device_type as i32, device.device_id, len);
+        let mut blocks = self.lock();
+        let blocks = blocks.entry(key).or_default();
+        if blocks.len() >= self.max_blocks {
+            return Some(block);
+        }
+        blocks.push((block, allocator));
+        None
+    }
+}
+
+impl Drop for DevicePool {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl core::fmt::Debug for DevicePool {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DevicePool")
+            .field("max_blocks", &self.max_blocks)
+            .field("cached_blocks", &self.cached_blocks())
+            .finish()
+    }
+}
+
+impl ToTensor for DeviceTensor {
+    fn data_ptr(&self) -> *mut c_void {
+        if self.len == 0 {
+            ptr::null_mut()
+        } else {
+            self.ptr.as_ptr()
+        }
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        self.device
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        alloc::{self, Layout},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{prelude::*, ManagedTensor};
+
+    static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn host_alloc(
+        _ctx: *mut c_void,
+        _device_id: i32,
+        size: usize,
+        align: usize,
+    ) -> *mut c_void {
+        LIVE_BYTES.fetch_add(size, Ordering::SeqCst);
+        alloc::alloc(Layout::from_size_align_unchecked(size, align)).cast()
+    }
+
+    unsafe extern "C" fn host_free(
+        _ctx: *mut c_void,
+        _device_id: i32,
+        ptr: *mut c_void,
+        size: usize,
+        align: usize,
+    ) {
+        LIVE_BYTES.fetch_sub(size, Ordering::SeqCst);
+        alloc::dealloc(ptr.cast(), Layout::from_size_align_unchecked(size, align));
+    }
+
+    unsafe extern "C" fn host_copy(
+        _ctx: *mut c_void,
+        _device_id: i32,
+        dst: *mut c_void,
+        src: *const c_void,
+        size: usize,
+        _kind: CopyKind,
+    ) -> i32 {
+        ptr::copy_nonoverlapping(src.cast::<u8>(), dst.cast::<u8>(), size);
+        0
+    }
+
+    unsafe extern "C" fn host_memory_info(
+        _ctx: *mut c_void,
+        _device_id: i32,
+        free: *mut usize,
+        total: *mut usize,
+    ) -> i32 {
+        *total = 1024;
+        *free = 1024 - LIVE_BYTES.load(Ordering::SeqCst);
+        0
+    }
+
+    #[test]
+    fn plugin_allocator() {
+        // A device type no other test uses.
+        let device = Device {
+            device_type: DeviceType::Hexagon,
+            device_id: 0,
+        };
+        assert!(matches!(
+            DeviceTensor::new(device, &[2], DataType::F32),
+            Err(Error::NoAllocator(_))
+        ));
+        assert_eq!(device.memory_info(), None);
+        unsafe {
+            register_allocator(
+                DeviceType::Hexagon,
+                Allocator {
+                    ctx: ptr::null_mut(),
+                    alloc: Some(host_alloc),
+                    free: Some(host_free),
+                    copy: Some(host_copy),
+                    memory_info: Some(host_memory_info),
+                },
+            );
+        }
+
+        let host = OwnedTensor::from_bytes(&[1, 2, 3, 4, 5, 6], &[2, 3], DataType::U8).unwrap();
+        let tensor = DeviceTensor::from_host(&host, device).unwrap();
+        assert_eq!(LIVE_BYTES.load(Ordering::SeqCst), 6);
+        assert_eq!(
+            device.memory_info(),
+            Some(MemoryInfo {
+                free: 1018,
+                total: 1024
+            })
+        );
+        let allocator = allocator(DeviceType::Hexagon).unwrap();
+        assert_eq!(allocator.total_memory(0), Some(1024));
+        let copy = tensor.try_clone().unwrap();
+        assert_eq!(copy.to_host().unwrap().as_bytes(), host.as_bytes());
+        drop(copy);
+
+        let managed = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(managed.device(), device);
+        assert_eq!(managed.shape(), &[2, 3]);
+        assert_eq!(LIVE_BYTES.load(Ordering::SeqCst), 6);
+        drop(managed);
+        assert_eq!(LIVE_BYTES.load(Ordering::SeqCst), 0);
+
+        let pool = DevicePool::new(1);
+        let tensor = pool.new_tensor(device, &[2, 3], DataType::U8).unwrap();
+        let data = tensor.data_ptr();
+        let other = pool.new_tensor(device, &[6], DataType::U8).unwrap();
+        drop(ManagedTensor::from_dlpack(tensor.into_dlpack()));
+        drop(other);
+        // The second block didn't fit in the pool.
+        assert_eq!(pool.cached_blocks(), 1);
+        assert_eq!(LIVE_BYTES.load(Ordering::SeqCst), 6);
+        let tensor = pool.new_tensor(device, &[3, 2], DataType::U8).unwrap();
+        assert_eq!(tensor.data_ptr(), data);
+        assert_eq!(pool.cached_blocks(), 0);
+        drop(pool);
+        drop(tensor);
+        assert_eq!(LIVE_BYTES.load(Ordering::SeqCst), 0);
+    }
+}
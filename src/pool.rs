@@ -0,0 +1,127 @@
+//! Thread-local pool of the allocations backing exported tensors.
+//!
+//! Exporting many small tensors, e.g. to hand them to Python one by one,
+//! allocates and frees a block of the same few sizes over and over. Freed
+//! blocks are kept here, per layout, and handed out again by the next export
+//! on the same thread instead of going back to the allocator.
+//!
+//! Without `std` there are no thread-locals, and blocks always go straight
+//! to the allocator.
+
+use alloc::alloc::{self as global, Layout};
+use core::ptr::NonNull;
+#[cfg(feature = "std")]
+use std::{cell::RefCell, collections::HashMap};
+
+/// Largest block that is pooled, bigger ones go straight to the allocator.
+pub const MAX_POOLED_SIZE: usize = 512;
+
+/// Most blocks of a single layout kept per thread.
+pub const MAX_POOLED_BLOCKS: usize = 256;
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Pool(HashMap<Layout, Vec<NonNull<u8>>>);
+
+#[cfg(feature = "std")]
+impl Drop for Pool {
+    fn drop(&mut self) {
+        for (layout, blocks) in self.0.drain() {
+            for block in blocks {
+                unsafe { global::dealloc(block.as_ptr(), layout) };
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static POOL: RefCell<Pool> = RefCell::default();
+}
+
+#[cfg(feature = "std")]
+fn pooled(layout: Layout) -> bool {
+    layout.size() <= MAX_POOLED_SIZE
+}
+
+fn allocate(layout: Layout) -> NonNull<u8> {
+    NonNull::new(unsafe { global::alloc(layout) })
+        .unwrap_or_else(|| global::handle_alloc_error(layout))
+}
+
+/// Allocate a block for `layout`, reusing a pooled one if possible.
+#[cfg(feature = "std")]
+pub(crate) fn alloc(layout: Layout) -> NonNull<u8> {
+    let reused = if pooled(layout) {
+        POOL.try_with(|pool| pool.borrow_mut().0.get_mut(&layout)?.pop())
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+    reused.unwrap_or_else(|| allocate(layout))
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn alloc(layout: Layout) -> NonNull<u8> {
+    allocate(layout)
+}
+
+/// Give a block back to the pool of the current thread, or free it if that
+/// pool is full or already gone.
+///
+/// # Safety
+/// `block` must have been returned by [`alloc`] with the same `layout`, and
+/// must not be used afterwards.
+#[cfg(feature = "std")]
+pub(crate) unsafe fn dealloc(block: NonNull<u8>, layout: Layout) {
+    let kept = pooled(layout)
+        && POOL
+            .try_with(|pool| {
+                let mut pool = pool.borrow_mut();
+                let blocks = pool.0.entry(layout).or_default();
+                let keep = blocks.len() < MAX_POOLED_BLOCKS;
+                if keep {
+                    blocks.push(block);
+                }
+                keep
+            })
+            .unwrap_or(false);
+    if !kept {
+        global::dealloc(block.as_ptr(), layout);
+    }
+}
+
+/// # Safety
+/// Same as the `std` version.
+#[cfg(not(feature = "std"))]
+pub(crate) unsafe fn dealloc(block: NonNull<u8>, layout: Layout) {
+    global::dealloc(block.as_ptr(), layout);
+}
+
+/// Free every block pooled by the current thread.
+pub fn clear() {
+    #[cfg(feature = "std")]
+    let _ = POOL.try_with(|pool| core::mem::take(&mut *pool.borrow_mut()));
+}
+
+// The pool only exists with `std`.
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_blocks() {
+        let layout = Layout::new::<[u64; 8]>();
+        let block = alloc(layout);
+        unsafe { dealloc(block, layout) };
+        assert_eq!(alloc(layout), block);
+        unsafe { dealloc(block, layout) };
+        clear();
+
+        let large = Layout::array::<u8>(MAX_POOLED_SIZE + 1).unwrap();
+        let block = alloc(large);
+        unsafe { dealloc(block, large) };
+        POOL.with(|pool| assert!(!pool.borrow().0.contains_key(&large)));
+    }
+}
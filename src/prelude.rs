@@ -1,5 +1,7 @@
+#[cfg(feature = "dlpack-1-0")]
+pub use crate::ffi::PackVersion;
 pub use crate::{
-    ffi::{DataType, Device, PackVersion},
+    ffi::{DataType, Device},
     tensor::traits::{DLPack, FromDLPack, InferDtype, IntoDLPack, TensorView, ToTensor},
     ManagedTensor, ManagerCtx, ShapeAndStrides,
 };
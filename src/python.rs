@@ -1,18 +1,42 @@
-use std::ptr::NonNull;
+//! DLPack capsules for Python, following the
+//! [python spec](https://dmlc.github.io/dlpack/latest/python_spec.html).
+//!
+//! Nothing here keeps state across calls, neither Python objects nor
+//! interpreter pointers, and the capsule destructor only touches the thread
+//! state it is called with, so capsules may be created and consumed in
+//! sub-interpreters, including ones with their own GIL on Python 3.12+. The
+//! deleters of exported tensors don't call into Python either. What remains
+//! is acquiring the GIL: `Python::with_gil` attaches to the main interpreter,
+//! so code running in a sub-interpreter has to pass down the `Python` token
+//! it was called with, e.g. to [`ManagerCtx::export_batch_py_with`]. Tensors
+//! holding Python objects themselves must release them in the interpreter
+//! they came from, which their `Drop` has to take care of.
+
+use std::{
+    ffi::{c_int, c_void},
+    ptr::NonNull,
+};
 
 use pyo3::{
-    ffi::{PyCapsule_GetPointer, PyCapsule_New, PyCapsule_SetName, PyErr_Occurred, PyErr_Restore},
+    exceptions::{PyBufferError, PyValueError},
+    ffi::{
+        PyCapsule_GetPointer, PyCapsule_IsValid, PyCapsule_New, PyCapsule_SetName, PyErr_Occurred,
+        PyErr_Restore,
+    },
     prelude::*,
+    types::{PyBytes, PyDict, PyList, PyMemoryView},
     IntoPy, Python,
 };
 
 use crate::{
-    ffi,
+    ffi::{self, DataType, Device, DeviceType},
     manager_ctx::ManagerCtx,
     tensor::{
-        traits::{IntoDLPack, ToTensor},
-        ManagedTensor,
+        traits::{IntoDLPack, TensorView, ToTensor},
+        ManagedTensor, Ownership,
     },
+    utils::catch_ffi_panic,
+    Error, ShapeAndStrides, TensorDict,
 };
 
 /// The producer must set the PyCapsule name to "dltensor" so that it can be
@@ -46,6 +70,21 @@ fn py_capsule_to_dlpack(capsule: *mut pyo3::ffi::PyObject) -> NonNull<ffi::DLMan
     }
 }
 
+/// Same as [`py_capsule_to_dlpack`], but checks that `capsule` is an unused
+/// DLPack capsule holding a valid tensor, leaving it untouched otherwise.
+fn try_py_capsule_to_managed(capsule: *mut pyo3::ffi::PyObject) -> PyResult<ManagedTensor> {
+    unsafe {
+        if PyCapsule_IsValid(capsule, DLPACK_CAPSULE_NAME.as_ptr().cast()) != 1 {
+            return Err(Error::Capsule("expected an unused dltensor capsule").into());
+        }
+        let ptr = PyCapsule_GetPointer(capsule, DLPACK_CAPSULE_NAME.as_ptr().cast());
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::new_unchecked(ptr.cast()))
+            .map_err(Error::from)?;
+        PyCapsule_SetName(capsule, DLPACK_CAPSULE_USED_NAME.as_ptr().cast());
+        Ok(tensor)
+    }
+}
+
 /// Refer to [dlpack python_spec](https://dmlc.github.io/dlpack/latest/python_spec.html#implementation)
 unsafe extern "C" fn dlpack_capsule_deleter(capsule: *mut pyo3::ffi::PyObject) {
     if pyo3::ffi::PyCapsule_IsValid(capsule, DLPACK_CAPSULE_USED_NAME.as_ptr() as *const _) == 1 {
@@ -57,19 +96,20 @@ unsafe extern "C" fn dlpack_capsule_deleter(capsule: *mut pyo3::ffi::PyObject) {
     let mut exc_trace = std::ptr::null_mut();
     pyo3::ffi::PyErr_Fetch(&mut exc_type, &mut exc_value, &mut exc_trace);
 
-    let managed = PyCapsule_GetPointer(capsule, DLPACK_CAPSULE_NAME.as_ptr() as *const _)
-        as *mut ffi::DLManagedTensor;
+    catch_ffi_panic("dltensor capsule destructor", || {
+        let managed = PyCapsule_GetPointer(capsule, DLPACK_CAPSULE_NAME.as_ptr() as *const _)
+            as *mut ffi::DLManagedTensor;
 
-    if managed.is_null() {
-        pyo3::ffi::PyErr_WriteUnraisable(capsule);
-        PyErr_Restore(exc_type, exc_value, exc_trace);
-        return;
-    }
+        if managed.is_null() {
+            pyo3::ffi::PyErr_WriteUnraisable(capsule);
+            return;
+        }
 
-    if let Some(del_fn) = (*managed).deleter {
-        del_fn(managed);
-        assert!(PyErr_Occurred().is_null());
-    }
+        if let Some(del_fn) = (*managed).deleter {
+            del_fn(managed);
+            assert!(PyErr_Occurred().is_null());
+        }
+    });
 
     PyErr_Restore(exc_type, exc_value, exc_trace);
 }
@@ -85,18 +125,50 @@ where
     }
 }
 
+impl<T> ManagerCtx<T>
+where
+    T: ToTensor,
+{
+    /// Export every tensor of `iter` as a Python list of capsules. Tensors are
+    /// exported before the GIL of the main interpreter is acquired, which
+    /// happens only once.
+    pub fn export_batch_py<I>(iter: I) -> Py<PyList>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let dlpacks = Self::export_batch(iter);
+        Python::with_gil(|py| capsule_list(py, dlpacks).unbind())
+    }
+
+    /// Same as [`ManagerCtx::export_batch_py`], but with the GIL the caller
+    /// holds already, which works in sub-interpreters too.
+    pub fn export_batch_py_with<'py, I>(py: Python<'py>, iter: I) -> Bound<'py, PyList>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        capsule_list(py, Self::export_batch(iter))
+    }
+}
+
+fn capsule_list(py: Python<'_>, dlpacks: Vec<NonNull<ffi::DLManagedTensor>>) -> Bound<'_, PyList> {
+    let capsules = dlpacks
+        .into_iter()
+        .map(|dlpack| unsafe { PyObject::from_owned_ptr(py, dlpack_to_py_capsule(dlpack)) });
+    PyList::new_bound(py, capsules)
+}
+
 impl ManagedTensor {
     /// Check this [pytorch src](https://github.com/pytorch/pytorch/blob/main/torch/csrc/utils/tensor_new.cpp#L1583)
     /// # Safety
     /// We use pyo3 ffi here.
     pub fn from_py_ptr(capsule: *mut pyo3::ffi::PyObject) -> Self {
-        Self::new(py_capsule_to_dlpack(capsule))
+        Self::new(py_capsule_to_dlpack(capsule), Ownership::Owned)
     }
 }
 
 impl<'source> FromPyObject<'source> for ManagedTensor {
     fn extract(ob: &'source PyAny) -> PyResult<Self> {
-        Ok(ManagedTensor::from_py_ptr(ob.into_ptr()))
+        try_py_capsule_to_managed(ob.as_ptr())
     }
 }
 
@@ -106,3 +178,206 @@ impl IntoPy<PyObject> for ManagedTensor {
         unsafe { PyObject::from_owned_ptr(py, capsule) }
     }
 }
+
+impl<'source> FromPyObject<'source> for TensorDict {
+    /// From a dict of DLPack capsules or objects implementing the DLPack
+    /// protocol, such as PyTorch tensors.
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        let dict: &PyDict = ob.downcast()?;
+        let mut tensors = Self::new();
+        for (name, value) in dict.iter() {
+            let value = if value.hasattr("__dlpack__")? {
+                value.call_method0("__dlpack__")?
+            } else {
+                value
+            };
+            tensors.insert(name.extract()?, value.extract()?);
+        }
+        Ok(tensors)
+    }
+}
+
+impl IntoPy<PyObject> for TensorDict {
+    /// Into a dict of DLPack capsules.
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        let dict = PyDict::new_bound(py);
+        for (name, tensor) in self {
+            // Only fails for unhashable keys.
+            dict.set_item(name, tensor.into_py(py)).unwrap();
+        }
+        dict.into_py(py)
+    }
+}
+
+/// Read-only Python buffer over the data of a contiguous CPU tensor, which it
+/// keeps alive. Made by [`ManagedTensor::into_py_bytes`].
+#[pyclass(unsendable, module = "dlpark")]
+pub struct TensorBuffer(ManagedTensor);
+
+#[pymethods]
+impl TensorBuffer {
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut pyo3::ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if flags & pyo3::ffi::PyBUF_WRITABLE != 0 {
+            return Err(PyBufferError::new_err("tensor buffer is read-only"));
+        }
+        let tensor = &slf.borrow().0;
+        let len = tensor.data_size();
+        // The data pointer of an empty tensor may be NULL, which buffers
+        // can't have.
+        let data = if len == 0 {
+            NonNull::<u8>::dangling().as_ptr()
+        } else {
+            tensor.first_byte()
+        };
+        // Also sets the owner of the view to this object.
+        if pyo3::ffi::PyBuffer_FillInfo(view, slf.as_ptr(), data.cast(), len as isize, 1, flags)
+            == -1
+        {
+            return Err(PyErr::fetch(slf.py()));
+        }
+        Ok(())
+    }
+}
+
+impl ManagedTensor {
+    /// Hand the raw bytes of this CPU tensor to Python. Python `bytes` can't
+    /// borrow memory, so a contiguous tensor becomes a read-only
+    /// `memoryview` of a [`TensorBuffer`] without copying, and only a strided
+    /// one is copied into `bytes`.
+    pub fn into_py_bytes(self, py: Python<'_>) -> PyResult<Bound<'_, PyAny>> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: Device::CPU,
+                found: self.device(),
+            }
+            .into());
+        }
+        self.check_supported().map_err(Error::from)?;
+        if !self.is_contiguous() {
+            return Ok(PyBytes::new_bound(py, &self.to_contiguous_bytes()).into_any());
+        }
+        let buffer = Bound::new(py, TensorBuffer(self))?;
+        Ok(PyMemoryView::from_bound(&buffer)?.into_any())
+    }
+
+    /// Borrow the data of `bytes` as a 1-d u8 tensor, keeping the object
+    /// alive. The data must not be written to, `bytes` are immutable.
+    pub fn from_py_bytes(bytes: Bound<'_, PyBytes>) -> Self {
+        ManagerCtx::new(PyBytesTensor::new(bytes)).into()
+    }
+}
+
+/// CPU tensor over the data of a Python `bytes` object, keeping the object
+/// alive. Made by [`ManagedTensor::from_py_bytes`].
+pub struct PyBytesTensor {
+    bytes: Py<PyBytes>,
+    data: *mut u8,
+    len: usize,
+}
+
+impl PyBytesTensor {
+    pub fn new(bytes: Bound<'_, PyBytes>) -> Self {
+        let data = bytes.as_bytes();
+        Self {
+            data: data.as_ptr().cast_mut(),
+            len: data.len(),
+            bytes: bytes.unbind(),
+        }
+    }
+
+    pub fn bytes(&self) -> &Py<PyBytes> {
+        &self.bytes
+    }
+}
+
+impl ToTensor for PyBytesTensor {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.data.cast()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        DataType::U8
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_1d(self.len)
+    }
+}
+
+/// Stream on which the consumer will use a tensor, passed to `__dlpack__` so
+/// the producer can make it wait for pending work. See the
+/// [python spec](https://dmlc.github.io/dlpack/latest/python_spec.html#the-dlpack-method).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Leave synchronizing to the consumer.
+    NoSync,
+    /// The legacy default stream on CUDA, the default stream on ROCm.
+    Default,
+    /// The per-thread default stream, CUDA only.
+    PerThreadDefault,
+    /// A `cudaStream_t` or `hipStream_t`.
+    Handle(*mut c_void),
+}
+
+impl Stream {
+    /// The `stream` argument of `__dlpack__` for a producer on `device`.
+    pub fn to_py_arg(self, device: Device) -> PyResult<Option<isize>> {
+        let arg = match (device.device_type, self) {
+            (DeviceType::Cpu, Self::NoSync | Self::Default) => None,
+            (_, Self::NoSync) => Some(-1),
+            (DeviceType::Cuda | DeviceType::CudaManaged, Self::Default) => Some(1),
+            (DeviceType::Cuda | DeviceType::CudaManaged, Self::PerThreadDefault) => Some(2),
+            (DeviceType::Rocm, Self::Default) => Some(0),
+            // 0 to 2 stand for the streams above.
+            (DeviceType::Cuda | DeviceType::CudaManaged | DeviceType::Rocm, Self::Handle(ptr))
+                if ptr as isize > 2 =>
+            {
+                Some(ptr as isize)
+            }
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "{self:?} is not a stream of {:?}",
+                    device.device_type
+                )))
+            }
+        };
+        Ok(arg)
+    }
+}
+
+/// The device of an object implementing the DLPack protocol, which decides
+/// what [`Stream`] it can synchronize with.
+pub fn dlpack_device(obj: &Bound<'_, PyAny>) -> PyResult<Device> {
+    let (device_type, device_id): (i32, i32) = obj.call_method0("__dlpack_device__")?.extract()?;
+    let device_type = DeviceType::try_from(device_type)
+        .map_err(|_| PyValueError::new_err(format!("unknown device type {device_type}")))?;
+    Ok(Device {
+        device_type,
+        device_id,
+    })
+}
+
+impl ManagedTensor {
+    /// Import an object implementing the DLPack protocol, e.g. a PyTorch
+    /// tensor, to be used on `stream`. The producer orders its pending work
+    /// before the stream, so no device-wide synchronization is needed.
+    pub fn from_py_on_stream(obj: &Bound<'_, PyAny>, stream: Stream) -> PyResult<Self> {
+        let device = dlpack_device(obj)?;
+        let kwargs = PyDict::new_bound(obj.py());
+        kwargs.set_item("stream", stream.to_py_arg(device)?)?;
+        let capsule = obj.call_method("__dlpack__", (), Some(&kwargs))?;
+        try_py_capsule_to_managed(capsule.as_ptr())
+    }
+}
@@ -0,0 +1,85 @@
+//! Random tensors for benchmarks and test fixtures. Enabled by the `rand`
+//! feature.
+//!
+//! The caller passes the RNG, so a seeded one, e.g.
+//! `StdRng::seed_from_u64(0)`, makes the tensors reproducible.
+
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+use crate::{ffi::DataType, tensor::traits::InferDtype, ManagerCtx, OwnedTensor};
+
+/// Return a tensor filled by `sample` if `dtype` is one of the types.
+macro_rules! sample_as {
+    ($shape:expr, $dtype:expr, $($ty:ty)|+ => $sample:expr) => {
+        $(
+            if $dtype == <$ty>::infer_dtype() {
+                return OwnedTensor::new_with($shape, |elements: &mut [$ty]| {
+                    elements.iter_mut().for_each(|x| *x = $sample)
+                })
+                .map(ManagerCtx::new);
+            }
+        )+
+    };
+}
+
+/// Random CPU tensors, ready to export. They return `None` if `shape` has
+/// negative dims or its size overflows, or if `dtype` isn't supported.
+impl ManagerCtx<OwnedTensor> {
+    /// Floats uniform in `[0, 1)`, integers and bools uniform over all their
+    /// values.
+    pub fn rand_uniform<R: Rng + ?Sized>(
+        shape: &[i64],
+        dtype: DataType,
+        rng: &mut R,
+    ) -> Option<Self> {
+        sample_as!(shape, dtype, f32 | f64 => rng.random());
+        sample_as!(shape, dtype, i8 | i16 | i32 | i64 | i128 => rng.random());
+        sample_as!(shape, dtype, u8 | u16 | u32 | u64 | u128 | bool => rng.random());
+        None
+    }
+
+    /// Floats normally distributed with `mean` and `std_dev`. Also `None` if
+    /// `std_dev` is not finite.
+    pub fn rand_normal<R: Rng + ?Sized>(
+        shape: &[i64],
+        dtype: DataType,
+        mean: f64,
+        std_dev: f64,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let normal = Normal::new(mean, std_dev).ok()?;
+        sample_as!(shape, dtype, f64 => normal.sample(rng));
+        sample_as!(shape, dtype, f32 => normal.sample(rng) as f32);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::prelude::*;
+
+    #[test]
+    fn seeded() {
+        let uniform = |seed| {
+            let ctx =
+                ManagerCtx::rand_uniform(&[4, 8], DataType::F32, &mut StdRng::seed_from_u64(seed));
+            ManagedTensor::from(ctx.unwrap()).to_contiguous::<f32>()
+        };
+        assert_eq!(uniform(0), uniform(0));
+        assert_ne!(uniform(0), uniform(1));
+        assert!(uniform(0).iter().all(|x| (0.0..1.0).contains(x)));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let ctx = ManagerCtx::rand_uniform(&[3], DataType::BOOL, &mut rng).unwrap();
+        assert_eq!(ManagedTensor::from(ctx).dtype(), DataType::BOOL);
+        let ctx = ManagerCtx::rand_normal(&[1000], DataType::F64, 5.0, 0.1, &mut rng).unwrap();
+        let values = ManagedTensor::from(ctx).to_contiguous::<f64>();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!((mean - 5.0).abs() < 0.05);
+        assert!(ManagerCtx::rand_normal(&[2], DataType::I32, 0.0, 1.0, &mut rng).is_none());
+        assert!(ManagerCtx::rand_normal(&[2], DataType::F32, 0.0, f64::NAN, &mut rng).is_none());
+    }
+}
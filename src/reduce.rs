@@ -0,0 +1,222 @@
+//! Reductions over CPU tensors, following strides, for sanity checks and
+//! simple statistics on received tensors.
+//!
+//! Like NumPy, `min` and `max` return NaN if any element is NaN.
+
+use alloc::vec::Vec;
+use core::ops::Add;
+
+use crate::{
+    error::invariant_violated,
+    tensor::traits::{InferDtype, TensorView},
+    utils::for_each_offset,
+    LayoutError, ManagedTensor, OwnedTensor, Result,
+};
+
+impl ManagedTensor {
+    pub fn sum<A>(&self) -> Result<A>
+    where
+        A: InferDtype + Copy + Add<Output = A> + Default,
+    {
+        let (_, sums) = self.reduce(None, |a| a, |acc: A, a| acc + a)?;
+        Ok(sums.first().copied().unwrap_or_default())
+    }
+
+    /// Smallest element, or `None` if the tensor is empty.
+    pub fn min<A: InferDtype + Copy + PartialOrd>(&self) -> Result<Option<A>> {
+        let (_, mins) = self.reduce(None, |a| a, min)?;
+        Ok(mins.first().copied())
+    }
+
+    /// Largest element, or `None` if the tensor is empty.
+    pub fn max<A: InferDtype + Copy + PartialOrd>(&self) -> Result<Option<A>> {
+        let (_, maxs) = self.reduce(None, |a| a, max)?;
+        Ok(maxs.first().copied())
+    }
+
+    /// Mean of all elements, NaN if the tensor is empty.
+    pub fn mean<A: InferDtype + Copy + Into<f64>>(&self) -> Result<f64> {
+        let (_, sums) = self.reduce(None, Into::into, |acc, a: A| acc + a.into())?;
+        Ok(sums.first().copied().unwrap_or_default() / self.num_elements() as f64)
+    }
+
+    /// Sums along `axis`, which is removed from the shape.
+    pub fn sum_axis<A>(&self, axis: usize) -> Result<OwnedTensor>
+    where
+        A: InferDtype + Copy + Add<Output = A> + Default,
+    {
+        self.reduce_axis(axis, Some(A::default()), |a| a, |acc: A, a| acc + a)
+    }
+
+    /// Minima along `axis`, which must not be empty.
+    pub fn min_axis<A: InferDtype + Copy + PartialOrd>(&self, axis: usize) -> Result<OwnedTensor> {
+        self.reduce_axis(axis, None, |a: A| a, min)
+    }
+
+    /// Maxima along `axis`, which must not be empty.
+    pub fn max_axis<A: InferDtype + Copy + PartialOrd>(&self, axis: usize) -> Result<OwnedTensor> {
+        self.reduce_axis(axis, None, |a: A| a, max)
+    }
+
+    /// Means along `axis` as `f64`, NaN if it is empty.
+    pub fn mean_axis<A: InferDtype + Copy + Into<f64>>(&self, axis: usize) -> Result<OwnedTensor> {
+        let len = self.shape().get(axis).copied().unwrap_or_default() as f64;
+        self.reduce_axis(axis, Some(0.0), Into::into, |acc, a: A| acc + a.into())
+            .map(|mut tensor| {
+                let means = tensor.as_bytes_mut().chunks_exact_mut(8);
+                for mean in means {
+                    let sum = f64::from_ne_bytes(mean.try_into().unwrap());
+                    mean.copy_from_slice(&(sum / len).to_ne_bytes());
+                }
+                tensor
+            })
+    }
+
+    /// Like [`ManagedTensor::reduce`], into a tensor, with `empty` as the
+    /// result of empty lanes.
+    fn reduce_axis<A, B>(
+        &self,
+        axis: usize,
+        empty: Option<B>,
+        first: impl FnMut(A) -> B,
+        next: impl FnMut(B, A) -> B,
+    ) -> Result<OwnedTensor>
+    where
+        A: InferDtype + Copy,
+        B: InferDtype + Copy,
+    {
+        let (shape, values) = self.reduce(Some(axis), first, next)?;
+        let len = shape.iter().product::<i64>() as usize;
+        let fill = match (values.len() == len, empty) {
+            (true, _) => None,
+            (false, Some(empty)) => Some(empty),
+            (false, None) => return Err(LayoutError::EmptyAxis { axis }.into()),
+        };
+        let tensor = OwnedTensor::new_with(&shape, |elements: &mut [B]| match fill {
+            Some(empty) => elements.fill(empty),
+            None => elements.copy_from_slice(&values),
+        });
+        tensor.ok_or_else(|| invariant_violated("invalid shape of a reduced tensor"))
+    }
+
+    /// Fold the lanes along `axis`, or all elements, in row-major order. The
+    /// result is empty if the lanes are, with the shape it would have.
+    fn reduce<A, B>(
+        &self,
+        axis: Option<usize>,
+        mut first: impl FnMut(A) -> B,
+        mut next: impl FnMut(B, A) -> B,
+    ) -> Result<(Vec<i64>, Vec<B>)>
+    where
+        A: InferDtype + Copy,
+        B: Copy,
+    {
+        self.check_cpu_dtype::<A>()?;
+        let mut shape = self.shape().to_vec();
+        let mut strides = self.strides_or_contiguous().into_owned();
+        // Move the reduced axis last, so that every lane is visited in turn.
+        let lane = match axis {
+            Some(axis) if axis >= shape.len() => {
+                return Err(LayoutError::InvalidAxis {
+                    axis,
+                    ndim: shape.len(),
+                }
+                .into())
+            }
+            Some(axis) => {
+                let (dim, stride) = (shape.remove(axis), strides.remove(axis));
+                shape.push(dim);
+                strides.push(stride);
+                dim as usize
+            }
+            None => self.num_elements(),
+        };
+        let mut values = Vec::new();
+        let first_element = self.first_byte().cast::<A>().cast_const();
+        let mut i = 0;
+        for_each_offset(&shape, &strides, |offset| {
+            let element = unsafe { first_element.offset(offset).read_unaligned() };
+            if i % lane == 0 {
+                values.push(first(element));
+            } else {
+                let acc = values.last_mut().unwrap();
+                *acc = next(*acc, element);
+            }
+            i += 1;
+        });
+        if axis.is_some() {
+            shape.pop();
+        } else {
+            shape.clear();
+        }
+        Ok((shape, values))
+    }
+}
+
+fn is_nan<A: PartialOrd>(a: &A) -> bool {
+    a.partial_cmp(a).is_none()
+}
+
+fn min<A: PartialOrd>(acc: A, a: A) -> A {
+    if is_nan(&acc) || !(is_nan(&a) || a < acc) {
+        acc
+    } else {
+        a
+    }
+}
+
+fn max<A: PartialOrd>(acc: A, a: A) -> A {
+    if is_nan(&acc) || !(is_nan(&a) || a > acc) {
+        acc
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, Error, LayoutError};
+
+    #[test]
+    fn reductions() {
+        // [[0, 3], [1, 4], [2, 5]], transposed.
+        let tensor = ManagedTensor::from_dlpack(vec![0i32, 1, 2, 3, 4, 5].into_dlpack())
+            .reshape(&[2, 3])
+            .unwrap()
+            .permute(&[1, 0])
+            .unwrap();
+        assert_eq!(tensor.sum::<i32>().unwrap(), 15);
+        assert_eq!(tensor.min::<i32>().unwrap(), Some(0));
+        assert_eq!(tensor.max::<i32>().unwrap(), Some(5));
+        assert_eq!(tensor.mean::<i32>().unwrap(), 2.5);
+
+        let sums = ManagedTensor::from_dlpack(tensor.sum_axis::<i32>(0).unwrap().into_dlpack());
+        assert_eq!(sums.shape(), &[2]);
+        assert_eq!(sums.as_slice::<i32>(), [3, 12]);
+        let maxs = ManagedTensor::from_dlpack(tensor.max_axis::<i32>(1).unwrap().into_dlpack());
+        assert_eq!(maxs.as_slice::<i32>(), [3, 4, 5]);
+        let mins = ManagedTensor::from_dlpack(tensor.min_axis::<i32>(1).unwrap().into_dlpack());
+        assert_eq!(mins.as_slice::<i32>(), [0, 1, 2]);
+        let means = ManagedTensor::from_dlpack(tensor.mean_axis::<i32>(1).unwrap().into_dlpack());
+        assert_eq!(means.as_slice::<f64>(), [1.5, 2.5, 3.5]);
+        assert!(matches!(
+            tensor.sum_axis::<i32>(2),
+            Err(Error::Layout(LayoutError::InvalidAxis { axis: 2, ndim: 2 }))
+        ));
+
+        let tensor = ManagedTensor::from_dlpack(vec![1.0f32, f32::NAN, 0.0].into_dlpack());
+        assert!(tensor.max::<f32>().unwrap().unwrap().is_nan());
+        assert!(tensor.min::<f32>().unwrap().unwrap().is_nan());
+
+        let empty = ManagedTensor::from(ManagerCtx::zeros(&[2, 0], DataType::F32).unwrap());
+        assert_eq!(empty.sum::<f32>().unwrap(), 0.0);
+        assert_eq!(empty.max::<f32>().unwrap(), None);
+        let sums = ManagedTensor::from_dlpack(empty.sum_axis::<f32>(1).unwrap().into_dlpack());
+        assert_eq!(sums.as_slice::<f32>(), [0.0; 2]);
+        assert!(matches!(
+            empty.min_axis::<f32>(1),
+            Err(Error::Layout(LayoutError::EmptyAxis { axis: 1 }))
+        ));
+        assert_eq!(empty.min_axis::<f32>(0).unwrap().shape(), &[0]);
+    }
+}
@@ -0,0 +1,303 @@
+//! Loading and saving the [safetensors](https://github.com/huggingface/safetensors) format.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io,
+    path::Path,
+    sync::Arc,
+};
+
+use ::safetensors::{
+    tensor::TensorView as SafeTensorView, Dtype, SafeTensorError, SafeTensors, View,
+};
+use memmap2::Mmap;
+
+use crate::{
+    endian::{convert_byte_order, swap_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, Device, DeviceType},
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView, ToTensor},
+    ManagedTensor, OwnedTensor, ShapeAndStrides,
+};
+
+fn to_io_error(err: SafeTensorError) -> io::Error {
+    match err {
+        SafeTensorError::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+fn unsupported(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, msg)
+}
+
+/// Map a safetensors dtype to a DLPack dtype, if there is one.
+pub fn from_safetensors_dtype(dtype: Dtype) -> Option<DataType> {
+    let dtype = match dtype {
+        Dtype::BOOL => DataType::BOOL,
+        Dtype::U8 => DataType::U8,
+        Dtype::I8 => DataType::I8,
+        Dtype::I16 => DataType::I16,
+        Dtype::U16 => DataType::U16,
+        Dtype::F16 => DataType::F16,
+        Dtype::BF16 => DataType::BF16,
+        Dtype::I32 => DataType::I32,
+        Dtype::U32 => DataType::U32,
+        Dtype::F32 => DataType::F32,
+        Dtype::C64 => (DataTypeCode::Complex, 64, 1).into(),
+        Dtype::F64 => DataType::F64,
+        Dtype::I64 => DataType::I64,
+        Dtype::U64 => DataType::U64,
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+/// Map a DLPack dtype to a safetensors dtype, if there is one.
+pub fn to_safetensors_dtype(dtype: DataType) -> Option<Dtype> {
+    if dtype.lanes != 1 {
+        return None;
+    }
+    let dtype = match (dtype.code, dtype.bits) {
+        (DataTypeCode::Bool, 8) => Dtype::BOOL,
+        (DataTypeCode::UInt, 8) => Dtype::U8,
+        (DataTypeCode::Int, 8) => Dtype::I8,
+        (DataTypeCode::Int, 16) => Dtype::I16,
+        (DataTypeCode::UInt, 16) => Dtype::U16,
+        (DataTypeCode::Float, 16) => Dtype::F16,
+        (DataTypeCode::Bfloat, 16) => Dtype::BF16,
+        (DataTypeCode::Int, 32) => Dtype::I32,
+        (DataTypeCode::UInt, 32) => Dtype::U32,
+        (DataTypeCode::Float, 32) => Dtype::F32,
+        (DataTypeCode::Complex, 64) => Dtype::C64,
+        (DataTypeCode::Float, 64) => Dtype::F64,
+        (DataTypeCode::Int, 64) => Dtype::I64,
+        (DataTypeCode::UInt, 64) => Dtype::U64,
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+fn dtype_and_shape(view: &SafeTensorView<'_>) -> io::Result<(DataType, Vec<i64>)> {
+    let dtype = from_safetensors_dtype(view.dtype())
+        .ok_or_else(|| unsupported(format!("unsupported safetensors dtype {:?}", view.dtype())))?;
+    let shape = view.shape().iter().map(|&dim| dim as i64).collect();
+    Ok((dtype, shape))
+}
+
+/// Copy every tensor of a serialized safetensors buffer into new CPU tensors.
+pub fn read_safetensors(buffer: &[u8]) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    let safetensors = SafeTensors::deserialize(buffer).map_err(to_io_error)?;
+    let mut tensors = BTreeMap::new();
+    for (name, view) in safetensors.iter() {
+        let (dtype, shape) = dtype_and_shape(&view)?;
+        let mut tensor = OwnedTensor::from_bytes(view.data(), &shape, dtype).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "invalid safetensors shape")
+        })?;
+        convert_byte_order(tensor.as_bytes_mut(), dtype, Endian::Little, Endian::NATIVE);
+        tensors.insert(
+            name.to_string(),
+            ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        );
+    }
+    Ok(tensors)
+}
+
+/// Load every tensor of a safetensors file into new CPU tensors.
+pub fn load_safetensors<P: AsRef<Path>>(path: P) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    read_safetensors(&std::fs::read(path)?)
+}
+
+/// A tensor borrowing its data from a memory-mapped file, keeping the mapping
+/// alive.
+struct MappedTensor {
+    mmap: Arc<Mmap>,
+    offset: usize,
+    shape: Vec<i64>,
+    dtype: DataType,
+}
+
+impl ToTensor for MappedTensor {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.mmap.as_ptr() as *mut std::ffi::c_void
+    }
+
+    fn byte_offset(&self) -> u64 {
+        self.offset as u64
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        crate::ffi::DLPACK_FLAG_BITMASK_READ_ONLY
+    }
+}
+
+/// Memory-map a safetensors file and expose every tensor without copying.
+/// The mapping is released once all returned tensors are dropped.
+///
+/// The tensors are backed by read-only pages, consumers must not write to
+/// them. Since safetensors data is little-endian, this fails with
+/// [`io::ErrorKind::Unsupported`] on big-endian hosts.
+///
+/// # Safety
+/// The file must not be modified or truncated while the tensors are alive,
+/// see [`Mmap::map`].
+pub unsafe fn mmap_safetensors<P: AsRef<Path>>(
+    path: P,
+) -> io::Result<BTreeMap<String, ManagedTensor>> {
+    if !Endian::Little.is_native() {
+        return Err(unsupported(
+            "zero-copy loading requires a little-endian host".to_string(),
+        ));
+    }
+    let mmap = Arc::new(Mmap::map(&File::open(path)?)?);
+    let safetensors = SafeTensors::deserialize(&mmap).map_err(to_io_error)?;
+    let mut tensors = BTreeMap::new();
+    for (name, view) in safetensors.iter() {
+        let (dtype, shape) = dtype_and_shape(&view)?;
+        let tensor = MappedTensor {
+            mmap: mmap.clone(),
+            offset: view.data().as_ptr() as usize - mmap.as_ptr() as usize,
+            shape,
+            dtype,
+        };
+        tensors.insert(
+            name.to_string(),
+            ManagedTensor::from_dlpack(tensor.into_dlpack()),
+        );
+    }
+    Ok(tensors)
+}
+
+/// A [`View`] over a CPU tensor with its shape converted to `usize`.
+struct SaveView<'a> {
+    tensor: &'a ManagedTensor,
+    dtype: Dtype,
+    shape: Vec<usize>,
+}
+
+impl View for SaveView<'_> {
+    fn dtype(&self) -> Dtype {
+        self.dtype
+    }
+
+    fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    fn data(&self) -> Cow<'_, [u8]> {
+        let mut data = self.tensor.to_contiguous_bytes();
+        if !Endian::Little.is_native() {
+            swap_byte_order(data.to_mut(), self.tensor.dtype());
+        }
+        data
+    }
+
+    fn data_len(&self) -> usize {
+        self.tensor.data_size()
+    }
+}
+
+fn save_views<'a, I>(tensors: I) -> io::Result<Vec<(&'a str, SaveView<'a>)>>
+where
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    tensors
+        .into_iter()
+        .map(|(name, tensor)| {
+            // `SaveView::data` can't fail, and copies from the CPU.
+            if tensor.device().device_type != DeviceType::Cpu {
+                return Err(unsupported(format!("tensor {name:?} is not on the cpu")));
+            }
+            let dtype = to_safetensors_dtype(tensor.dtype()).ok_or_else(|| {
+                unsupported(format!(
+                    "dtype {:?} has no safetensors equivalent",
+                    tensor.dtype()
+                ))
+            })?;
+            let shape = tensor.shape().iter().map(|&dim| dim as usize).collect();
+            Ok((
+                name,
+                SaveView {
+                    tensor,
+                    dtype,
+                    shape,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Serialize named CPU tensors and optional metadata into a safetensors buffer.
+pub fn serialize_safetensors<'a, I>(
+    tensors: I,
+    metadata: Option<HashMap<String, String>>,
+) -> io::Result<Vec<u8>>
+where
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    ::safetensors::serialize(save_views(tensors)?, metadata).map_err(to_io_error)
+}
+
+/// Save named CPU tensors and optional metadata as a safetensors file.
+pub fn save_safetensors<'a, P, I>(
+    path: P,
+    tensors: I,
+    metadata: Option<HashMap<String, String>>,
+) -> io::Result<()>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = (&'a str, &'a ManagedTensor)>,
+{
+    ::safetensors::serialize_to_file(save_views(tensors)?, metadata, path.as_ref())
+        .map_err(to_io_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::on_gpu;
+
+    #[test]
+    fn round_trip() {
+        let a = ManagedTensor::from_dlpack(vec![1.0f32, 2.0, 3.0].into_dlpack());
+        let b = ManagedTensor::from_dlpack(vec![7i64; 4].into_dlpack());
+        let buf = serialize_safetensors([("a", &a), ("b", &b)], None).unwrap();
+        let tensors = read_safetensors(&buf).unwrap();
+        assert_eq!(tensors["a"].as_slice::<f32>(), &[1.0, 2.0, 3.0]);
+        assert_eq!(tensors["b"].dtype(), DataType::I64);
+        assert_eq!(tensors["b"].as_slice::<i64>(), &[7; 4]);
+    }
+
+    #[test]
+    fn mmap() {
+        let a = ManagedTensor::from_dlpack(vec![5u16; 8].into_dlpack());
+        let path = std::env::temp_dir().join(format!("dlpark-{}.safetensors", std::process::id()));
+        save_safetensors(&path, [("a", &a)], None).unwrap();
+        let tensors = unsafe { mmap_safetensors(&path) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_ne!(tensors["a"].byte_offset(), 0);
+        assert_eq!(tensors["a"].as_slice::<u16>(), &[5; 8]);
+    }
+
+    #[test]
+    fn gpu_tensor() {
+        let a = ManagedTensor::from_dlpack(vec![1u8].into_dlpack());
+        let b = ManagedTensor::from_dlpack(on_gpu(vec![1.0f32, 2.0]).into_dlpack());
+        let err = serialize_safetensors([("a", &a), ("b", &b)], None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
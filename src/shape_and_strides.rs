@@ -1,20 +1,151 @@
-use std::ptr::NonNull;
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, ptr::NonNull};
 
-use crate::utils::is_contiguous;
+use crate::utils::{is_contiguous, make_contiguous_strides};
+
+/// Highest rank whose shape and strides are stored inline.
+pub const MAX_INLINE_NDIM: usize = 4;
 
 /// If the shape or strides of Tensor is vec of i64, then it should be borrowed
 /// to avoid copy. The lifetime should be 'static since we don't managed its
 /// memory. Otherwise, we should copy the data and convert its type to i64 and
-/// managed it ourselves.
+/// managed it ourselves. Up to [`MAX_INLINE_NDIM`] dimensions are copied
+/// inline without allocating.
 #[derive(Debug)]
 pub enum ShapeAndStrides {
     Contiguous(Box<[i64]>),  // Shape only
     WithStrides(Box<[i64]>), // [Shape | Strides]
+    /// Pointers derived from whole slices, keeping their provenance.
     Borrowed {
         shape: NonNull<i64>,
         strides: Option<NonNull<i64>>,
         len: usize,
     },
+    Inline {
+        shape: [i64; MAX_INLINE_NDIM],
+        strides: Option<[i64; MAX_INLINE_NDIM]>,
+        len: usize,
+    },
+}
+
+/// Invalid combination of shape and strides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutError {
+    StridesLength {
+        ndim: usize,
+        strides: usize,
+    },
+    /// The operation needs a row-major contiguous tensor.
+    NotContiguous,
+    NdimMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// The axis is out of range, or repeated.
+    InvalidAxis {
+        axis: usize,
+        ndim: usize,
+    },
+    OutOfRange {
+        axis: usize,
+        start: i64,
+        end: i64,
+        dim: i64,
+    },
+    /// The byte offset of a view would be negative, which DLPack can't
+    /// express.
+    NegativeOffset,
+    /// The new shape doesn't have this many elements.
+    ReshapeMismatch {
+        num_elements: usize,
+    },
+    /// Tensors joined together differ in a dim other than the joined one.
+    ShapeMismatch {
+        axis: usize,
+        expected: i64,
+        found: i64,
+    },
+    /// There are no tensors to join.
+    NoTensors,
+    /// There is no minimum or maximum along an empty axis.
+    EmptyAxis {
+        axis: usize,
+    },
+    /// A view would reach bytes outside of the tensor it is derived from, or
+    /// has a negative dim.
+    OutsideParent,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StridesLength { ndim, strides } => write!(
+                f,
+                "shape and strides should have same length, got {ndim} dims and {strides} strides"
+            ),
+            Self::NotContiguous => write!(f, "tensor is not contiguous"),
+            Self::NdimMismatch { expected, found } => {
+                write!(f, "expected {expected} dims, found {found}")
+            }
+            Self::InvalidAxis { axis, ndim } => {
+                write!(f, "invalid or repeated axis {axis} of {ndim} dims")
+            }
+            Self::OutOfRange {
+                axis,
+                start,
+                end,
+                dim,
+            } => write!(
+                f,
+                "range {start}..{end} out of bounds of axis {axis} of size {dim}"
+            ),
+            Self::NegativeOffset => write!(f, "view would start before the data pointer"),
+            Self::ReshapeMismatch { num_elements } => {
+                write!(f, "new shape should have {num_elements} elements")
+            }
+            Self::ShapeMismatch {
+                axis,
+                expected,
+                found,
+            } => write!(f, "expected size {expected} of axis {axis}, found {found}"),
+            Self::NoTensors => write!(f, "no tensors to join"),
+            Self::EmptyAxis { axis } => write!(f, "cannot reduce empty axis {axis}"),
+            Self::OutsideParent => write!(f, "view reaches outside of its parent tensor"),
+        }
+    }
+}
+
+impl core::error::Error for LayoutError {}
+
+/// Dimensions stored inline with their count, or spilled into a `Vec`.
+type Dims = Result<([i64; MAX_INLINE_NDIM], usize), Vec<i64>>;
+
+/// Collect up to [`MAX_INLINE_NDIM`] dimensions inline, or all of them into
+/// a `Vec` if there are more.
+fn collect_dims<'a, I>(dims: I) -> Dims
+where
+    I: IntoIterator<Item = &'a i64>,
+{
+    let mut iter = dims.into_iter();
+    let mut buf = [0; MAX_INLINE_NDIM];
+    for (len, dim) in buf.iter_mut().enumerate() {
+        match iter.next() {
+            Some(&d) => *dim = d,
+            None => return Ok((buf, len)),
+        }
+    }
+    match iter.next() {
+        None => Ok((buf, MAX_INLINE_NDIM)),
+        Some(&d) => Err(buf.into_iter().chain([d]).chain(iter.copied()).collect()),
+    }
+}
+
+fn contiguous_strides(shape: &[i64], strides: &mut [i64]) {
+    let mut stride = 1;
+    for i in (0..shape.len()).rev() {
+        strides[i] = stride;
+        stride *= shape[i];
+    }
 }
 
 impl ShapeAndStrides {
@@ -22,66 +153,143 @@ impl ShapeAndStrides {
     where
         I: IntoIterator<Item = &'a i64>,
     {
-        let buf: Vec<i64> = shape.into_iter().copied().collect();
-        Self::Contiguous(buf.into_boxed_slice())
+        match collect_dims(shape) {
+            Ok((shape, len)) => Self::Inline {
+                shape,
+                strides: None,
+                len,
+            },
+            Err(buf) => Self::Contiguous(buf.into_boxed_slice()),
+        }
+    }
+
+    /// Shape of a contiguous vector of `len` elements, exported without
+    /// strides.
+    pub fn new_1d(len: usize) -> Self {
+        let mut shape = [0; MAX_INLINE_NDIM];
+        shape[0] = len as i64;
+        Self::Inline {
+            shape,
+            strides: None,
+            len: 1,
+        }
     }
 
+    /// # Panics
+    /// If `shape` and `strides` have different lengths, see
+    /// [`ShapeAndStrides::try_new_with_strides`].
     pub fn new_with_strides<'a, I>(shape: I, strides: I) -> Self
     where
         I: IntoIterator<Item = &'a i64>,
     {
-        let shape: Vec<&i64> = shape.into_iter().collect();
-        let strides: Vec<&i64> = strides.into_iter().collect();
-        assert_eq!(
-            shape.len(),
-            strides.len(),
-            "shape and strides should have same length"
-        );
-        let mut buf: Vec<i64> = Vec::with_capacity(shape.len() + strides.len());
-        buf.extend(shape);
-        buf.extend(strides);
-        Self::WithStrides(buf.into_boxed_slice())
+        Self::try_new_with_strides(shape, strides).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Same as [`ShapeAndStrides::new_with_strides`], but returns an error
+    /// instead of panicking.
+    pub fn try_new_with_strides<'a, I>(shape: I, strides: I) -> Result<Self, LayoutError>
+    where
+        I: IntoIterator<Item = &'a i64>,
+    {
+        match (collect_dims(shape), collect_dims(strides)) {
+            (Ok((shape, len)), Ok((strides, strides_len))) => {
+                if len != strides_len {
+                    return Err(LayoutError::StridesLength {
+                        ndim: len,
+                        strides: strides_len,
+                    });
+                }
+                Ok(Self::Inline {
+                    shape,
+                    strides: Some(strides),
+                    len,
+                })
+            }
+            (shape, strides) => {
+                let to_vec = |dims: Dims| match dims {
+                    Ok((buf, len)) => <[i64]>::to_vec(&buf[..len]),
+                    Err(buf) => buf,
+                };
+                let mut buf = to_vec(shape);
+                let strides = to_vec(strides);
+                if buf.len() != strides.len() {
+                    return Err(LayoutError::StridesLength {
+                        ndim: buf.len(),
+                        strides: strides.len(),
+                    });
+                }
+                buf.extend(strides);
+                Ok(Self::WithStrides(buf.into_boxed_slice()))
+            }
+        }
     }
 
     pub fn new_contiguous_with_strides<'a, I>(shape: I) -> Self
     where
         I: IntoIterator<Item = &'a i64>,
     {
-        let shape: Vec<&i64> = shape.into_iter().collect();
-        let len = shape.len();
-        let mut buf: Vec<i64> = vec![0; len * 2];
-        let mut stride = 1;
-        for i in (0..len).rev() {
-            buf[i] = *shape[i];
-            buf[i + len] = stride;
-            stride *= buf[i];
+        match collect_dims(shape) {
+            Ok((shape, len)) => {
+                let mut strides = [0; MAX_INLINE_NDIM];
+                contiguous_strides(&shape[..len], &mut strides);
+                Self::Inline {
+                    shape,
+                    strides: Some(strides),
+                    len,
+                }
+            }
+            Err(mut buf) => {
+                let len = buf.len();
+                buf.resize(len * 2, 0);
+                let (shape, strides) = buf.split_at_mut(len);
+                contiguous_strides(shape, strides);
+                Self::WithStrides(buf.into_boxed_slice())
+            }
+        }
+    }
+
+    /// Drop strides that only describe the row-major layout, so they are
+    /// exported as NULL and only computed by consumers that ask for them.
+    /// Halves the dimensions stored for contiguous tensors.
+    pub fn into_lazy(self) -> Self {
+        match self.strides() {
+            Some(strides) if is_contiguous(self.shape(), strides) => {
+                Self::new_contiguous(self.shape())
+            }
+            _ => self,
         }
-        Self::WithStrides(buf.into_boxed_slice())
     }
 
     pub fn new_borrowed(shape: &[i64], strides: Option<&[i64]>) -> Self {
+        Self::try_new_borrowed(shape, strides).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Same as [`ShapeAndStrides::new_borrowed`], but returns an error
+    /// instead of panicking.
+    pub fn try_new_borrowed(shape: &[i64], strides: Option<&[i64]>) -> Result<Self, LayoutError> {
         if let Some(strides) = strides {
-            assert_eq!(
-                shape.len(),
-                strides.len(),
-                "shape and strides should have same length"
-            );
+            if shape.len() != strides.len() {
+                return Err(LayoutError::StridesLength {
+                    ndim: shape.len(),
+                    strides: strides.len(),
+                });
+            }
         }
         let len = shape.len();
-        let shape = NonNull::from(&shape[0]);
-        let strides = strides.map(|s| NonNull::from(&s[0]));
-        Self::Borrowed {
+        let shape = NonNull::from(shape).cast();
+        let strides = strides.map(|s| NonNull::from(s).cast());
+        Ok(Self::Borrowed {
             shape,
             strides,
             len,
-        }
+        })
     }
 
     pub fn len(&self) -> usize {
         match self {
             Self::Contiguous(ref v) => v.len(),
             Self::WithStrides(ref v) => v.len() / 2,
-            Self::Borrowed { len, .. } => *len,
+            Self::Borrowed { len, .. } | Self::Inline { len, .. } => *len,
         }
     }
 
@@ -98,16 +306,9 @@ impl ShapeAndStrides {
             Self::Contiguous(ref v) => v.as_ref(),
             Self::WithStrides(ref v) => &v[0..self.len()],
             Self::Borrowed { shape, .. } => unsafe {
-                std::slice::from_raw_parts(shape.as_ptr(), self.len())
+                core::slice::from_raw_parts(shape.as_ptr(), self.len())
             },
-        }
-    }
-
-    pub(crate) fn shape_ptr(&self) -> *mut i64 {
-        match self {
-            Self::Contiguous(ref v) => v.as_ptr() as *mut i64,
-            Self::WithStrides(ref v) => v.as_ptr() as *mut i64,
-            Self::Borrowed { shape, .. } => shape.as_ptr(),
+            Self::Inline { shape, len, .. } => &shape[..*len],
         }
     }
 
@@ -116,33 +317,43 @@ impl ShapeAndStrides {
             Self::Contiguous(_) => None,
             Self::WithStrides(ref v) => Some(&v[self.len()..]),
             Self::Borrowed { strides, .. } => {
-                strides.map(|s| unsafe { std::slice::from_raw_parts(s.as_ptr(), self.len()) })
+                strides.map(|s| unsafe { core::slice::from_raw_parts(s.as_ptr(), self.len()) })
             }
-        }
-    }
-
-    /// Return nullptr if strides is None.
-    pub(crate) fn strides_ptr(&self) -> *mut i64 {
-        match self {
-            Self::Contiguous(_) => std::ptr::null_mut(),
-            Self::WithStrides(ref v) => &v[self.len()] as *const i64 as *mut i64,
-            Self::Borrowed { strides, .. } => match strides {
-                Some(strides) => strides.as_ptr(),
-                None => std::ptr::null_mut(),
-            },
+            Self::Inline { strides, len, .. } => strides.as_ref().map(|s| &s[..*len]),
         }
     }
 
     pub fn is_contiguous(&self) -> bool {
         match self {
             Self::Contiguous(_) => true,
-            Self::Borrowed { strides: None, .. } => true,
+            Self::Borrowed { strides: None, .. } | Self::Inline { strides: None, .. } => true,
             Self::WithStrides { .. }
             | Self::Borrowed {
                 strides: Some(_), ..
+            }
+            | Self::Inline {
+                strides: Some(_), ..
             } => is_contiguous(self.shape(), self.strides().unwrap()),
         }
     }
+
+    /// Reverse `axis` by negating its stride, like numpy's `[::-1]`. Also
+    /// returns by how many elements the first element moves, to be added to
+    /// the byte offset times the element size.
+    pub fn flip(&self, axis: usize) -> Result<(Self, i64), LayoutError> {
+        let shape = self.shape();
+        let ndim = shape.len();
+        if axis >= ndim {
+            return Err(LayoutError::InvalidAxis { axis, ndim });
+        }
+        let mut strides = match self.strides() {
+            Some(strides) => strides.to_vec(),
+            None => make_contiguous_strides(shape),
+        };
+        let offset = (shape[axis] - 1).max(0) * strides[axis];
+        strides[axis] = -strides[axis];
+        Ok((Self::new_with_strides(shape, &strides), offset))
+    }
 }
 
 // Generated by copilot.
@@ -157,7 +368,7 @@ mod tests {
         assert_eq!(shape.ndim(), 3);
         assert_eq!(shape.shape(), &[1, 2, 3]);
         assert_eq!(shape.strides(), None);
-        assert_eq!(shape.is_contiguous(), true);
+        assert!(shape.is_contiguous());
     }
 
     #[test]
@@ -167,7 +378,7 @@ mod tests {
         assert_eq!(shape.ndim(), 3);
         assert_eq!(shape.shape(), &[1, 2, 3]);
         assert_eq!(shape.strides(), Some([6, 3, 1].as_slice()));
-        assert_eq!(shape.is_contiguous(), true);
+        assert!(shape.is_contiguous());
     }
 
     #[test]
@@ -178,13 +389,80 @@ mod tests {
         assert_eq!(shape.ndim(), 3);
         assert_eq!(shape.shape(), &[1, 2, 3]);
         assert_eq!(shape.strides(), Some([6, 3, 1].as_slice()));
-        assert_eq!(shape.is_contiguous(), true);
+        assert!(shape.is_contiguous());
 
         // Make a not contiguous shape.
         let shape = ShapeAndStrides::new_with_strides(&[1, 2, 3], &[1, 2, 3]);
         assert!(!shape.is_contiguous());
     }
 
+    #[test]
+    fn test_inline() {
+        let shape = ShapeAndStrides::new_contiguous_with_strides(&[2, 3, 4, 5]);
+        assert!(matches!(shape, ShapeAndStrides::Inline { .. }));
+        assert_eq!(shape.shape(), &[2, 3, 4, 5]);
+        assert_eq!(shape.strides(), Some([60, 20, 5, 1].as_slice()));
+
+        let shape = ShapeAndStrides::new_contiguous_with_strides(&[1, 2, 3, 4, 5]);
+        assert!(matches!(shape, ShapeAndStrides::WithStrides(_)));
+        assert_eq!(shape.shape(), &[1, 2, 3, 4, 5]);
+        assert_eq!(shape.strides(), Some([120, 60, 20, 5, 1].as_slice()));
+
+        let shape = ShapeAndStrides::new_with_strides(&[1, 2, 3, 4, 5], &[1, 1, 2, 6, 24]);
+        assert_eq!(shape.strides(), Some([1, 1, 2, 6, 24].as_slice()));
+        assert!(!shape.is_contiguous());
+    }
+
+    #[test]
+    fn test_new_1d() {
+        let shape = ShapeAndStrides::new_1d(5);
+        assert_eq!(shape.ndim(), 1);
+        assert_eq!(shape.shape(), &[5]);
+        assert_eq!(shape.strides(), None);
+        assert!(shape.is_contiguous());
+    }
+
+    #[test]
+    fn test_into_lazy() {
+        let shape = ShapeAndStrides::new_contiguous_with_strides(&[1, 2, 3, 4, 5]).into_lazy();
+        assert!(matches!(shape, ShapeAndStrides::Contiguous(_)));
+        assert_eq!(shape.shape(), &[1, 2, 3, 4, 5]);
+        assert_eq!(shape.strides(), None);
+
+        let shape = ShapeAndStrides::new_with_strides(&[3, 2], &[1, 3]).into_lazy();
+        assert_eq!(shape.strides(), Some([1, 3].as_slice()));
+    }
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(
+            ShapeAndStrides::try_new_with_strides(&[1, 2, 3][..], &[1, 2]).unwrap_err(),
+            LayoutError::StridesLength {
+                ndim: 3,
+                strides: 2
+            }
+        );
+        assert!(ShapeAndStrides::try_new_with_strides(&[1, 2, 3, 4, 5][..], &[1]).is_err());
+        assert!(ShapeAndStrides::try_new_borrowed(&[1, 2], Some(&[1])).is_err());
+        let scalar = ShapeAndStrides::try_new_borrowed(&[], Some(&[])).unwrap();
+        assert_eq!(scalar.shape(), &[] as &[i64]);
+    }
+
+    #[test]
+    fn test_flip() {
+        let shape = ShapeAndStrides::new_contiguous(&[2, 3]);
+        let (flipped, offset) = shape.flip(1).unwrap();
+        assert_eq!(flipped.strides(), Some([3, -1].as_slice()));
+        assert_eq!(offset, 2);
+        let (flipped, offset) = flipped.flip(0).unwrap();
+        assert_eq!(flipped.strides(), Some([-3, -1].as_slice()));
+        assert_eq!(offset, 3);
+        assert_eq!(
+            shape.flip(2).unwrap_err(),
+            LayoutError::InvalidAxis { axis: 2, ndim: 2 }
+        );
+    }
+
     #[test]
     fn test_new_borrowed() {
         let shape = vec![1, 2, 3];
@@ -193,6 +471,6 @@ mod tests {
         assert_eq!(shape.ndim(), 3);
         assert_eq!(shape.shape(), &[1, 2, 3]);
         assert_eq!(shape.strides(), Some([6, 3, 1].as_slice()));
-        assert_eq!(shape.is_contiguous(), true);
+        assert!(shape.is_contiguous());
     }
 }
@@ -0,0 +1,344 @@
+//! Exchanging CPU tensors between processes through POSIX shared memory.
+//!
+//! The producer copies a tensor into a named segment with
+//! [`ShmTensor::create`] and sends the small [`ShmHandle`] to another process,
+//! which maps the same pages with [`ShmTensor::open`]. Both sides can export
+//! their [`ShmTensor`] through DLPack without copying; the mapping is released
+//! by the deleter.
+//!
+//! Exactly one side owns the segment name and unlinks it when dropped. The
+//! creator owns it until [`ShmTensor::release_ownership`] is called, in which
+//! case the receiver should take it over with [`ShmTensor::open_owned`].
+
+use std::{
+    ffi::CString,
+    io::{self, Read, Write},
+    ptr::NonNull,
+};
+
+use crate::{
+    endian::Endian,
+    ffi::{DataType, Device, DeviceType},
+    owned_tensor::byte_len,
+    tensor::traits::{TensorView, ToTensor},
+    wire::WireHeader,
+    ManagedTensor, ShapeAndStrides,
+};
+
+/// Longest segment name accepted from a peer, `NAME_MAX` on Linux.
+const MAX_NAME_LEN: usize = 255;
+
+/// Everything another process needs to map a tensor placed in shared memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShmHandle {
+    /// Name of the segment, starting with `/`.
+    pub name: String,
+    pub dtype: DataType,
+    pub shape: Vec<i64>,
+    /// Size of the data in bytes.
+    pub len: usize,
+}
+
+impl ShmHandle {
+    /// Encode the handle as the segment name followed by a
+    /// [wire](crate::wire) header without payload.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.name.len() as u32).to_le_bytes())?;
+        writer.write_all(self.name.as_bytes())?;
+        WireHeader {
+            flags: 0,
+            dtype: self.dtype,
+            device: Device::CPU,
+            shape: self.shape.clone(),
+            strides: None,
+            axis_names: None,
+            endian: Endian::NATIVE,
+            compressed: false,
+            checksum: false,
+            payload_len: self.len as u64,
+        }
+        .write_to(writer)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_NAME_LEN {
+            return Err(invalid_data("segment name is too long"));
+        }
+        let mut name = vec![0u8; len];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid_data("invalid segment name"))?;
+        let header = WireHeader::read_from(reader)?;
+        let handle = Self {
+            name,
+            dtype: header.dtype,
+            shape: header.shape,
+            len: usize::try_from(header.payload_len)
+                .map_err(|_| invalid_data("tensor is too large"))?,
+        };
+        handle.check_len()?;
+        Ok(handle)
+    }
+
+    /// Check that `len` is the size of a contiguous tensor of `shape` and
+    /// `dtype`, which may come from another process.
+    fn check_len(&self) -> io::Result<()> {
+        if byte_len(&self.shape, self.dtype) != Some(self.len) {
+            return Err(invalid_data("handle length doesn't match its shape"));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn segment_name(name: &str) -> io::Result<CString> {
+    let name = if name.starts_with('/') {
+        name.to_string()
+    } else {
+        format!("/{name}")
+    };
+    CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// Map `len` bytes of the segment referred to by `fd`, closing `fd`.
+unsafe fn map_fd(fd: libc::c_int, len: usize) -> io::Result<NonNull<u8>> {
+    // mmap rejects empty mappings.
+    let ptr = libc::mmap(
+        std::ptr::null_mut(),
+        len.max(1),
+        libc::PROT_READ | libc::PROT_WRITE,
+        libc::MAP_SHARED,
+        fd,
+        0,
+    );
+    let err = io::Error::last_os_error();
+    libc::close(fd);
+    if ptr == libc::MAP_FAILED {
+        return Err(err);
+    }
+    Ok(NonNull::new_unchecked(ptr.cast()))
+}
+
+/// A CPU tensor living in a mapped POSIX shared-memory segment.
+#[derive(Debug)]
+pub struct ShmTensor {
+    ptr: NonNull<u8>,
+    handle: ShmHandle,
+    owner: bool,
+}
+
+unsafe impl Send for ShmTensor {}
+unsafe impl Sync for ShmTensor {}
+
+impl ShmTensor {
+    /// Create a new segment named `name` and copy a CPU tensor into it. The
+    /// returned tensor owns the name.
+    pub fn create(name: &str, tensor: &ManagedTensor) -> io::Result<Self> {
+        if tensor.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be placed in shared memory",
+            ));
+        }
+        let cname = segment_name(name)?;
+        let data = tensor.to_contiguous_bytes();
+        let len = data.len();
+        let ptr = unsafe {
+            let fd = check(libc::shm_open(
+                cname.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+                0o600,
+            ))?;
+            let mapped = match check(libc::ftruncate(fd, len.max(1) as libc::off_t)) {
+                Ok(_) => map_fd(fd, len),
+                Err(err) => {
+                    libc::close(fd);
+                    Err(err)
+                }
+            };
+            match mapped {
+                Ok(ptr) => ptr,
+                Err(err) => {
+                    libc::shm_unlink(cname.as_ptr());
+                    return Err(err);
+                }
+            }
+        };
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), ptr.as_ptr(), len) };
+        Ok(Self {
+            ptr,
+            handle: ShmHandle {
+                name: cname.into_string().unwrap(),
+                dtype: tensor.dtype(),
+                shape: tensor.shape().to_vec(),
+                len,
+            },
+            owner: true,
+        })
+    }
+
+    fn open_impl(handle: &ShmHandle, owner: bool) -> io::Result<Self> {
+        handle.check_len()?;
+        let cname = segment_name(&handle.name)?;
+        let ptr = unsafe {
+            let fd = check(libc::shm_open(cname.as_ptr(), libc::O_RDWR, 0))?;
+            let mut stat: libc::stat = std::mem::zeroed();
+            if let Err(err) = check(libc::fstat(fd, &mut stat)) {
+                libc::close(fd);
+                return Err(err);
+            }
+            if (stat.st_size as u64) < handle.len as u64 {
+                libc::close(fd);
+                return Err(invalid_data(
+                    "shared memory segment is smaller than the tensor",
+                ));
+            }
+            map_fd(fd, handle.len)?
+        };
+        Ok(Self {
+            ptr,
+            handle: handle.clone(),
+            owner,
+        })
+    }
+
+    /// Map a segment created by another process, leaving the name to its
+    /// owner.
+    pub fn open(handle: &ShmHandle) -> io::Result<Self> {
+        Self::open_impl(handle, false)
+    }
+
+    /// Map a segment and take over its name, unlinking it when dropped.
+    pub fn open_owned(handle: &ShmHandle) -> io::Result<Self> {
+        Self::open_impl(handle, true)
+    }
+
+    pub fn handle(&self) -> &ShmHandle {
+        &self.handle
+    }
+
+    /// Stop unlinking the name when dropped, e.g. after handing ownership to
+    /// a receiver that opens it with [`ShmTensor::open_owned`].
+    pub fn release_ownership(&mut self) {
+        self.owner = false;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.handle.len) }
+    }
+}
+
+impl Drop for ShmTensor {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.as_ptr().cast(), self.handle.len.max(1));
+            if self.owner {
+                if let Ok(cname) = segment_name(&self.handle.name) {
+                    libc::shm_unlink(cname.as_ptr());
+                }
+            }
+        }
+    }
+}
+
+impl ToTensor for ShmTensor {
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.ptr.as_ptr().cast()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        self.handle.dtype
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.handle.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn create_and_open() {
+        let v: Vec<f32> = (0..16).map(|x| x as f32).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let name = format!("dlpark-test-{}", std::process::id());
+        let mut producer = ShmTensor::create(&name, &tensor).unwrap();
+        producer.release_ownership();
+
+        let mut buf = Vec::new();
+        producer.handle().write_to(&mut buf).unwrap();
+        let handle = ShmHandle::read_from(&mut buf.as_slice()).unwrap();
+        assert_eq!(&handle, producer.handle());
+
+        let consumer = ShmTensor::open_owned(&handle).unwrap();
+        let consumer = ManagedTensor::from_dlpack(consumer.into_dlpack());
+        assert_eq!(consumer.shape(), &[16]);
+        assert_eq!(consumer.as_slice::<f32>(), &v[..]);
+
+        drop(producer);
+        drop(consumer);
+        // The receiver unlinked the name.
+        assert!(ShmTensor::open(&handle).is_err());
+    }
+
+    #[test]
+    fn forged_handles() {
+        let handle = ShmHandle {
+            name: "/dlpark-forged".to_string(),
+            dtype: DataType::F32,
+            shape: vec![4],
+            len: 16,
+        };
+        let mut buf = Vec::new();
+        handle.write_to(&mut buf).unwrap();
+        assert_eq!(ShmHandle::read_from(&mut buf.as_slice()).unwrap(), handle);
+
+        // Mapping more than the tensor or a negative dim.
+        for forged in [
+            ShmHandle {
+                len: 1 << 30,
+                ..handle.clone()
+            },
+            ShmHandle {
+                shape: vec![-4],
+                ..handle.clone()
+            },
+        ] {
+            let mut buf = Vec::new();
+            forged.write_to(&mut buf).unwrap();
+            let err = ShmHandle::read_from(&mut buf.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let err = ShmTensor::open(&forged).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let mut buf = u32::MAX.to_le_bytes().to_vec();
+        buf.extend(b"/dlpark");
+        let err = ShmHandle::read_from(&mut buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,78 @@
+//! Exports of constant data, e.g. lookup tables baked into the binary.
+
+use crate::{
+    ffi::{DataType, Device},
+    tensor::traits::{InferDtype, ToTensor},
+    ManagerCtx, ShapeAndStrides,
+};
+
+/// Row-major CPU tensor over `'static` data, made by
+/// [`ManagerCtx::from_static`]. It has nothing to drop, and its shape is
+/// borrowed, so exporting it only takes a block from the
+/// [`pool`](crate::pool), which is reused once the tensor is deleted.
+///
+/// Consumers must not write to the data, which may be in read-only memory.
+#[derive(Debug, Clone, Copy)]
+pub struct StaticTensor<T: 'static> {
+    data: &'static [T],
+    shape: &'static [i64],
+}
+
+impl<T: InferDtype> ManagerCtx<StaticTensor<T>> {
+    /// Export `data` with `shape`, or `None` if `shape` has negative dims or
+    /// doesn't have as many elements as `data`.
+    pub fn from_static(data: &'static [T], shape: &'static [i64]) -> Option<Self> {
+        let len = shape.iter().try_fold(1usize, |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        });
+        (len == Some(data.len())).then(|| Self::new(StaticTensor { data, shape }))
+    }
+}
+
+impl<T: InferDtype> ToTensor for StaticTensor<T> {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.data.as_ptr().cast_mut().cast()
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        T::infer_dtype()
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_borrowed(self.shape, None)
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        crate::ffi::DLPACK_FLAG_BITMASK_READ_ONLY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    static TABLE: [u8; 6] = [1, 2, 3, 4, 5, 6];
+
+    #[test]
+    fn static_export() {
+        for _ in 0..2 {
+            let ctx = ManagerCtx::from_static(&TABLE, &[2, 3]).unwrap();
+            let tensor = ManagedTensor::from_dlpack(ctx.into_dlpack());
+            assert_eq!(tensor.shape(), &[2, 3]);
+            assert_eq!(tensor.dtype(), DataType::U8);
+            assert_eq!(tensor.data_ptr(), TABLE.as_ptr().cast_mut().cast());
+            assert_eq!(tensor.as_slice::<u8>(), TABLE);
+        }
+        assert!(ManagerCtx::from_static(&TABLE, &[4]).is_none());
+        assert!(ManagerCtx::from_static(&TABLE, &[-2, -3]).is_none());
+    }
+}
@@ -1,19 +1,51 @@
 pub mod impls;
+pub mod sync;
 pub mod traits;
+pub mod typed;
 
-use std::ptr::NonNull;
+use alloc::{borrow::Cow, boxed::Box, string::String, vec::Vec};
+use core::{cell::OnceCell, mem::ManuallyDrop, ptr::NonNull};
 
-use self::traits::{FromDLPack, IntoDLPack, TensorView, ToTensor};
-use crate::{ffi, manager_ctx::ManagerCtx};
+use self::traits::{FromDLPack, InferDtype, IntoDLPack, TensorView, ToTensor};
+use crate::{
+    ffi,
+    manager_ctx::{reclaim, ManagerCtx},
+    utils::{copy_strided, gather_strided_bytes, make_contiguous_strides},
+    Error,
+};
+
+/// Layout properties of a [`ManagedTensor`], computed on first use.
+#[derive(Debug, Default)]
+struct LayoutCache {
+    num_elements: OnceCell<usize>,
+    is_contiguous: OnceCell<bool>,
+    strides: OnceCell<Box<[i64]>>,
+}
+
+/// Whether a [`ManagedTensor`] is responsible for deleting its
+/// DLManagedTensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ownership {
+    /// The deleter is called when the tensor is dropped.
+    Owned,
+    /// The producer keeps ownership, as a NULL deleter means in DLPack, and
+    /// the tensor is left alone when dropped.
+    Borrowed,
+}
 
 /// Safe wrapper for DLManagedTensor.
-/// Will call deleter when dropped.
-#[derive(Debug, Clone)]
-#[repr(transparent)]
-pub struct ManagedTensor(NonNull<ffi::DLManagedTensor>);
+/// Will call deleter when dropped if [`Ownership::Owned`], so it can't be
+/// cloned, only copied with [`ManagedTensor::try_clone`].
+#[derive(Debug)]
+pub struct ManagedTensor(NonNull<ffi::DLManagedTensor>, LayoutCache, Ownership);
 
 impl Drop for ManagedTensor {
     fn drop(&mut self) {
+        #[cfg(feature = "tracing")]
+        crate::trace::released(self.0.as_ptr(), unsafe { &self.0.as_ref().dl_tensor });
+        if self.2 == Ownership::Borrowed {
+            return;
+        }
         // TODO: we should add a flag for buggy numpy dlpack deleter
         unsafe {
             if let Some(deleter) = self.0.as_ref().deleter {
@@ -24,20 +56,264 @@ impl Drop for ManagedTensor {
 }
 
 impl ManagedTensor {
-    pub fn new(src: NonNull<ffi::DLManagedTensor>) -> Self {
-        Self(src)
+    /// Take ownership of `src` with [`Ownership::Owned`], or borrow it if its
+    /// deleter is NULL. With [`Ownership::Borrowed`] the deleter is never
+    /// called, for callbacks whose caller keeps ownership for the duration
+    /// of the call. With the `defensive` feature every field is validated
+    /// first, panicking on an invalid tensor without calling its deleter; use
+    /// [`ManagedTensor::try_from_dlpack`] to get an error instead.
+    pub fn new(src: NonNull<ffi::DLManagedTensor>, ownership: Ownership) -> Self {
+        #[cfg(feature = "defensive")]
+        if let Err(err) = unsafe { crate::validate::validate_managed(src) } {
+            panic!("invalid DLManagedTensor: {err}");
+        }
+        let mut tensor = Self::new_unchecked(src);
+        if ownership == Ownership::Borrowed {
+            tensor.2 = Ownership::Borrowed;
+        }
+        tensor
+    }
+
+    /// Same as [`ManagedTensor::new`], but never validates `src`, for
+    /// callers which just did.
+    pub(crate) fn new_unchecked(src: NonNull<ffi::DLManagedTensor>) -> Self {
+        #[cfg(feature = "tracing")]
+        crate::trace::imported(src.as_ptr(), unsafe { &src.as_ref().dl_tensor });
+        let ownership = match unsafe { src.as_ref().deleter } {
+            Some(_) => Ownership::Owned,
+            None => Ownership::Borrowed,
+        };
+        Self(src, LayoutCache::default(), ownership)
+    }
+
+    pub fn ownership(&self) -> Ownership {
+        self.2
+    }
+
+    /// Labels of the axes, if the tensor was exported by a [`ManagerCtx`]
+    /// given some with [`ManagerCtx::with_axis_names`].
+    pub fn axis_names(&self) -> Option<&[String]> {
+        unsafe { crate::manager_ctx::axis_names(self.0) }
+    }
+
+    /// Override the ownership derived from the deleter, e.g. to borrow a
+    /// tensor whose producer calls the deleter itself.
+    ///
+    /// # Safety
+    /// For [`Ownership::Owned`], the producer must have handed the tensor
+    /// over, so that its deleter may be called on drop.
+    pub unsafe fn with_ownership(mut self, ownership: Ownership) -> Self {
+        self.2 = ownership;
+        self
+    }
+
+    /// Copy of this tensor. A borrowed tensor gives another borrowed handle
+    /// to the same data, an owned one a new owned contiguous copy of its
+    /// data, which must be on the CPU.
+    pub fn try_clone(&self) -> crate::Result<Self> {
+        if self.2 == Ownership::Borrowed {
+            return Ok(unsafe { Self::new_unchecked(self.0).with_ownership(Ownership::Borrowed) });
+        }
+        if self.device().device_type != ffi::DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: ffi::Device::CPU,
+                found: self.device(),
+            });
+        }
+        self.check_supported()?;
+        let copy =
+            crate::OwnedTensor::from_bytes(&self.to_contiguous_bytes(), self.shape(), self.dtype())
+                .ok_or_else(|| crate::error::invariant_violated("invalid shape of a tensor"))?;
+        Ok(Self::new_unchecked(copy.into_dlpack()))
+    }
+
+    /// Owned row-major copy of this CPU tensor, whether borrowed or not,
+    /// whose data starts at a multiple of `alignment` bytes, e.g. a cache
+    /// line or a page, for SIMD kernels or DMA engines which require it.
+    pub fn clone_into_aligned(&self, alignment: usize) -> crate::Result<Self> {
+        if !alignment.is_power_of_two() {
+            return Err(Error::InvalidAlignment(alignment));
+        }
+        if self.device().device_type != ffi::DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: ffi::Device::CPU,
+                found: self.device(),
+            });
+        }
+        self.check_supported()?;
+        let copy = crate::OwnedTensor::from_bytes_aligned(
+            &self.to_contiguous_bytes(),
+            self.shape(),
+            self.dtype(),
+            alignment,
+        )
+        .ok_or_else(|| crate::error::invariant_violated("invalid shape of a tensor"))?;
+        Ok(Self::new_unchecked(copy.into_dlpack()))
     }
 
     /// Access inner data as 1d array.
+    ///
+    /// # Panics
+    /// If `A` doesn't have the size of the dtype or the data isn't aligned
+    /// for it, see [`ManagedTensor::try_as_slice`].
     pub fn as_slice<A>(&self) -> &[A] {
         assert_eq!(
-            std::mem::size_of::<A>(),
+            core::mem::size_of::<A>(),
             self.dtype().size(),
             "dtype and A size mismatch"
         );
+        assert!(
+            self.is_aligned_for::<A>(),
+            "data is not aligned for A, copy it with to_contiguous instead"
+        );
+        let len = self.num_elements();
+        if len == 0 {
+            // The data pointer of an empty tensor may be NULL.
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.first_byte().cast(), len) }
+    }
+
+    /// Same as [`ManagedTensor::as_slice`], but returns an error instead of
+    /// panicking if the dtype or device doesn't match or the data isn't
+    /// aligned for `A`.
+    pub fn try_as_slice<A: InferDtype>(&self) -> crate::Result<&[A]> {
+        if self.dtype() != A::infer_dtype() {
+            return Err(Error::DtypeMismatch {
+                expected: A::infer_dtype(),
+                found: self.dtype(),
+            });
+        }
+        if self.device().device_type != ffi::DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: ffi::Device::CPU,
+                found: self.device(),
+            });
+        }
+        if !self.is_aligned_for::<A>() {
+            return Err(Error::Misaligned {
+                align: core::mem::align_of::<A>(),
+            });
+        }
+        Ok(self.as_slice())
+    }
+
+    /// Borrow inner data in row-major order if it is contiguous and aligned
+    /// for `A`, copy it otherwise.
+    pub fn as_slice_or_copy<A: InferDtype + Copy>(&self) -> crate::Result<Cow<'_, [A]>> {
+        match self.try_as_slice() {
+            Ok(slice) if self.is_contiguous() => Ok(Cow::Borrowed(slice)),
+            Ok(_) | Err(Error::Misaligned { .. }) => Ok(Cow::Owned(self.to_contiguous())),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether the first element is aligned for `A`, which typed accessors
+    /// require. Producers like NumPy may hand out byte offsets that aren't.
+    pub fn is_aligned_for<A>(&self) -> bool {
+        self.num_elements() == 0 || self.first_byte().cast::<A>().is_aligned()
+    }
+
+    /// Pointer to the first element, derived from the data pointer so that it
+    /// keeps its provenance.
+    pub(crate) fn first_byte(&self) -> *mut u8 {
+        self.data_ptr()
+            .cast::<u8>()
+            .wrapping_add(self.byte_offset() as usize)
+    }
+
+    /// Take back the `Vec<A>` this tensor was exported from by this crate,
+    /// e.g. after a round trip through Python, without copying. Returns the
+    /// tensor unchanged if it didn't come from a `Vec<A>` or is borrowed.
+    pub fn try_into_vec<A: InferDtype>(self) -> Result<Vec<A>, Self> {
+        if self.dtype() != A::infer_dtype() || self.2 == Ownership::Borrowed {
+            return Err(self);
+        }
+        let this = ManuallyDrop::new(self);
+        match unsafe { reclaim::<Vec<A>>(this.0) } {
+            Some(vec) => Ok(vec),
+            None => Err(ManuallyDrop::into_inner(this)),
+        }
+    }
+
+    /// Convert into a `Vec<A>` in row-major order, reclaiming the original
+    /// `Vec` without copying if this tensor was exported from one and is
+    /// owned.
+    pub fn into_vec<A: InferDtype + Copy>(self) -> Vec<A> {
+        self.try_into_vec()
+            .unwrap_or_else(|tensor| tensor.to_contiguous())
+    }
+
+    /// Copy inner data into a new row-major contiguous buffer, following
+    /// strides. The data doesn't have to be aligned for `A`.
+    ///
+    /// # Panics
+    /// If `A` doesn't have the size of the dtype or the tensor isn't on the
+    /// CPU, see [`ManagedTensor::as_slice_or_copy`].
+    pub fn to_contiguous<A: Copy>(&self) -> Vec<A> {
+        assert_eq!(
+            core::mem::size_of::<A>(),
+            self.dtype().size(),
+            "dtype and A size mismatch"
+        );
+        assert_eq!(
+            self.device().device_type,
+            ffi::DeviceType::Cpu,
+            "tensor should be on cpu"
+        );
+        if self.is_contiguous() && self.is_aligned_for::<A>() {
+            return self.as_slice().to_vec();
+        }
+        let strides = self.strides_or_contiguous();
+        let mut buf = Vec::with_capacity(self.num_elements());
         unsafe {
-            let ptr = self.data_ptr().add(self.byte_offset() as usize);
-            std::slice::from_raw_parts(ptr.cast(), self.num_elements())
+            copy_strided(
+                self.first_byte().cast::<A>(),
+                self.shape(),
+                &strides,
+                buf.spare_capacity_mut(),
+            );
+            buf.set_len(self.num_elements());
+        }
+        buf
+    }
+
+    /// Raw bytes of inner data in row-major order. Borrowed when the tensor is
+    /// already contiguous, copied following strides otherwise.
+    ///
+    /// # Panics
+    /// If the tensor isn't on the CPU or fails
+    /// [`ManagedTensor::check_supported`].
+    pub fn to_contiguous_bytes(&self) -> Cow<'_, [u8]> {
+        assert_eq!(
+            self.device().device_type,
+            ffi::DeviceType::Cpu,
+            "tensor should be on cpu"
+        );
+        if let Err(err) = self.check_supported() {
+            panic!("{err}");
+        }
+        let len = self.data_size();
+        if len == 0 {
+            return Cow::Borrowed(&[]);
+        }
+        let ptr = self.first_byte();
+        match self.strides() {
+            Some(strides) if !self.is_contiguous() => {
+                let mut buf = Vec::with_capacity(len);
+                unsafe {
+                    gather_strided_bytes(
+                        ptr,
+                        self.shape(),
+                        strides,
+                        self.dtype().size(),
+                        buf.spare_capacity_mut(),
+                    );
+                    buf.set_len(len);
+                }
+                Cow::Owned(buf)
+            }
+            _ => Cow::Borrowed(unsafe { core::slice::from_raw_parts(ptr, len) }),
         }
     }
 
@@ -48,18 +324,21 @@ impl ManagedTensor {
         self.0.as_ptr()
     }
 
-    /// Get DLPack ptr.
+    /// Get DLPack ptr. The caller becomes responsible for calling its
+    /// deleter.
     pub fn into_inner(self) -> NonNull<ffi::DLManagedTensor> {
-        self.0
+        ManuallyDrop::new(self).0
     }
 
     pub(crate) fn dl_tensor(&self) -> &ffi::DLTensor {
+        #[cfg(feature = "debug-guards")]
+        crate::guards::check_alive(self.0.as_ptr());
         unsafe { &self.0.as_ref().dl_tensor }
     }
 }
 
 impl TensorView for ManagedTensor {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
         self.dl_tensor().data_ptr()
     }
 
@@ -86,6 +365,37 @@ impl TensorView for ManagedTensor {
     fn ndim(&self) -> usize {
         self.dl_tensor().ndim()
     }
+
+    fn strides_or_contiguous(&self) -> Cow<'_, [i64]> {
+        match self.strides() {
+            Some(strides) => Cow::Borrowed(strides),
+            None if self.ndim() == 0 => Cow::Borrowed(&[]),
+            None => Cow::Borrowed(
+                self.1
+                    .strides
+                    .get_or_init(|| make_contiguous_strides(self.shape()).into()),
+            ),
+        }
+    }
+
+    fn num_elements(&self) -> usize {
+        *self
+            .1
+            .num_elements
+            .get_or_init(|| self.dl_tensor().num_elements())
+    }
+
+    fn is_contiguous(&self) -> bool {
+        *self
+            .1
+            .is_contiguous
+            .get_or_init(|| self.dl_tensor().is_contiguous())
+    }
+
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        unsafe { crate::manager_ctx::flags(self.0) }
+    }
 }
 
 impl<T> From<ManagerCtx<T>> for ManagedTensor
@@ -93,19 +403,19 @@ where
     T: ToTensor,
 {
     fn from(value: ManagerCtx<T>) -> Self {
-        Self(value.into_dlpack())
+        Self::new(value.into_dlpack(), Ownership::Owned)
     }
 }
 
 impl FromDLPack for ManagedTensor {
     fn from_dlpack(src: NonNull<ffi::DLManagedTensor>) -> Self {
-        Self(src)
+        Self::new(src, Ownership::Owned)
     }
 }
 
 impl IntoDLPack for ManagedTensor {
     fn into_dlpack(self) -> NonNull<ffi::DLManagedTensor> {
-        self.0
+        self.into_inner()
     }
 }
 
@@ -114,7 +424,7 @@ mod tests {
     use std::sync::Arc;
 
     use super::*;
-    use crate::{prelude::*, utils::make_contiguous_strides};
+    use crate::{fixtures::transposed, prelude::*};
 
     #[test]
     fn from_vec_f32() {
@@ -150,4 +460,248 @@ mod tests {
         let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
         assert_eq!(tensor.as_slice::<f32>(), &v[..]);
     }
+
+    #[test]
+    fn test_to_contiguous() {
+        let v: Vec<i32> = (0..6).collect();
+        let tensor = ManagedTensor::from_dlpack(transposed(v).into_dlpack());
+        assert!(!tensor.is_contiguous());
+        assert_eq!(tensor.to_contiguous::<i32>(), vec![0, 3, 1, 4, 2, 5]);
+        let bytes = tensor.to_contiguous_bytes();
+        assert_eq!(&bytes[4..8], &3i32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_into_vec() {
+        let v: Vec<f32> = vec![1.0, 2.0, 3.0];
+        let ptr = v.as_ptr();
+        let tensor = ManagedTensor::from_dlpack(v.into_dlpack());
+        let tensor = tensor.try_into_vec::<i32>().unwrap_err();
+        let v = tensor.try_into_vec::<f32>().unwrap();
+        assert_eq!(v.as_ptr(), ptr);
+        assert_eq!(v, [1.0, 2.0, 3.0]);
+
+        // Other tensors are copied, even with the same dtype and layout.
+        let boxed: Box<[f32]> = v.into_boxed_slice();
+        let tensor = ManagedTensor::from_dlpack(boxed.into_dlpack());
+        let tensor = tensor.try_into_vec::<f32>().unwrap_err();
+        assert_eq!(tensor.into_vec::<f32>(), [1.0, 2.0, 3.0]);
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        let tensor = tensor.try_into_vec::<i32>().unwrap_err();
+        assert_eq!(tensor.into_vec::<i32>(), [0, 3, 1, 4, 2, 5]);
+    }
+
+    #[test]
+    fn test_clone_into_aligned() {
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        let copy = tensor.clone_into_aligned(4096).unwrap();
+        assert_eq!(copy.data_ptr() as usize % 4096, 0);
+        assert!(copy.is_contiguous());
+        assert_eq!(copy.shape(), tensor.shape());
+        assert_eq!(copy.as_slice::<i32>(), [0, 3, 1, 4, 2, 5]);
+        assert!(matches!(
+            tensor.clone_into_aligned(48),
+            Err(Error::InvalidAlignment(48))
+        ));
+    }
+
+    #[test]
+    fn test_ownership() {
+        let tensor = ManagedTensor::from_dlpack(vec![1.0f32, 2.0].into_dlpack());
+        assert_eq!(tensor.ownership(), Ownership::Owned);
+        let copy = tensor.try_clone().unwrap();
+        assert_eq!(copy.ownership(), Ownership::Owned);
+        assert_ne!(copy.data_ptr(), tensor.data_ptr());
+        assert_eq!(copy.as_slice::<f32>(), [1.0, 2.0]);
+
+        // Dropping a borrowed tensor doesn't delete it.
+        let handle = tensor.into_inner();
+        drop(unsafe {
+            ManagedTensor::new(handle, Ownership::Owned).with_ownership(Ownership::Borrowed)
+        });
+        let borrowed = ManagedTensor::new(handle, Ownership::Borrowed);
+        assert_eq!(borrowed.ownership(), Ownership::Borrowed);
+        assert_eq!(borrowed.as_slice::<f32>(), [1.0, 2.0]);
+        drop(borrowed);
+        let v = ManagedTensor::new(handle, Ownership::Owned)
+            .try_into_vec::<f32>()
+            .unwrap();
+        assert_eq!(v, [1.0, 2.0]);
+
+        let mut data = [1i32, 2, 3];
+        let mut shape = [3];
+        let mut managed = ffi::DLManagedTensor {
+            dl_tensor: ffi::DLTensor {
+                data: data.as_mut_ptr().cast(),
+                device: Device::CPU,
+                ndim: 1,
+                dtype: DataType::I32,
+                shape: shape.as_mut_ptr(),
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: std::ptr::null_mut(),
+            deleter: None,
+        };
+        let tensor = ManagedTensor::new(NonNull::from(&mut managed), Ownership::Owned);
+        assert_eq!(tensor.ownership(), Ownership::Borrowed);
+        let view = tensor.try_clone().unwrap();
+        assert_eq!(view.ownership(), Ownership::Borrowed);
+        assert_eq!(view.as_ptr(), tensor.as_ptr());
+        let tensor = tensor.try_into_vec::<i32>().unwrap_err();
+        assert_eq!(tensor.into_vec::<i32>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut shape = [32, 3, 224, 224];
+        let tensor = ffi::DLTensor {
+            data: std::ptr::null_mut(),
+            device: Device::cuda(0),
+            ndim: 4,
+            dtype: DataType::F32,
+            shape: shape.as_mut_ptr(),
+            strides: std::ptr::null_mut(),
+            byte_offset: 0,
+        };
+        assert_eq!(
+            tensor.summary(),
+            "f32[32, 3, 224, 224] @ cuda:0 (contiguous)"
+        );
+
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        assert_eq!(tensor.summary(), "i32[3, 2] @ cpu:0 (strided)");
+        let tensor = ManagedTensor::from_dlpack(vec![true].into_dlpack());
+        assert_eq!(tensor.summary(), "bool[1] @ cpu:0 (contiguous)");
+    }
+
+    #[test]
+    fn test_cached_layout() {
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        assert_eq!(tensor.num_elements(), 6);
+        assert!(!tensor.is_contiguous());
+        assert_eq!(tensor.1.num_elements.get(), Some(&6));
+        assert_eq!(tensor.1.is_contiguous.get(), Some(&false));
+        assert_eq!(tensor.strides_or_contiguous(), [1, 3].as_slice());
+        assert!(tensor.1.strides.get().is_none());
+
+        // Handing the tensor on keeps it alive.
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.to_contiguous::<i32>(), [0, 3, 1, 4, 2, 5]);
+    }
+
+    #[test]
+    fn test_checked_sizes() {
+        let mut shape = [i64::MAX, 4];
+        let mut tensor = ffi::DLTensor {
+            data: std::ptr::null_mut(),
+            device: Device::CPU,
+            ndim: 2,
+            dtype: DataType::F64,
+            shape: shape.as_mut_ptr(),
+            strides: std::ptr::null_mut(),
+            byte_offset: 0,
+        };
+        assert_eq!(tensor.checked_num_elements(), None);
+        assert_eq!(tensor.checked_data_size(), None);
+
+        let mut shape = [i64::MAX / 4, 0];
+        tensor.shape = shape.as_mut_ptr();
+        assert_eq!(tensor.checked_data_size(), Some(0));
+        let mut shape = [i64::MAX / 2, 1];
+        tensor.shape = shape.as_mut_ptr();
+        assert_eq!(tensor.checked_num_elements(), Some(i64::MAX as usize / 2));
+        assert_eq!(tensor.checked_data_size(), None);
+        let mut shape = [-2, -3];
+        tensor.shape = shape.as_mut_ptr();
+        assert_eq!(tensor.checked_num_elements(), None);
+    }
+
+    #[test]
+    fn test_sizes() {
+        let ctx = ManagerCtx::new(vec![1u16, 2, 3]);
+        assert_eq!(ctx.itemsize(), 2);
+        assert_eq!(ctx.nbytes(), 6);
+        assert!(!ctx.is_empty());
+        let tensor = ManagedTensor::from(ctx);
+        assert_eq!((tensor.itemsize(), tensor.nbytes()), (2, 6));
+
+        let tensor = ManagedTensor::from_dlpack(Vec::<f64>::new().into_dlpack());
+        assert!(tensor.is_empty());
+        assert_eq!((tensor.itemsize(), tensor.nbytes()), (8, 0));
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        assert!(!tensor.is_empty() && !tensor.is_contiguous());
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let mut managed = ffi::DLManagedTensor {
+            dl_tensor: ffi::DLTensor {
+                data: std::ptr::null_mut(),
+                device: Device::CPU,
+                ndim: 0,
+                dtype: DataType::F32,
+                shape: std::ptr::null_mut(),
+                strides: std::ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: std::ptr::null_mut(),
+            deleter: None,
+        };
+        assert_eq!(managed.dl_tensor.shape(), &[] as &[i64]);
+        let mut shape = [0];
+        managed.dl_tensor.ndim = 1;
+        managed.dl_tensor.shape = shape.as_mut_ptr();
+        let tensor = ManagedTensor::new(NonNull::from(&mut managed), Ownership::Owned);
+        assert_eq!(tensor.as_slice::<f32>(), &[] as &[f32]);
+    }
+
+    /// Four i32 stored one byte into a buffer.
+    struct Misaligned(Vec<u8>);
+
+    impl ToTensor for Misaligned {
+        fn data_ptr(&self) -> *mut std::ffi::c_void {
+            self.0.as_ptr() as *mut std::ffi::c_void
+        }
+
+        fn byte_offset(&self) -> u64 {
+            1
+        }
+
+        fn device(&self) -> Device {
+            Device::CPU
+        }
+
+        fn dtype(&self) -> DataType {
+            DataType::I32
+        }
+
+        fn shape_and_strides(&self) -> ShapeAndStrides {
+            ShapeAndStrides::new_contiguous(&[4])
+        }
+    }
+
+    #[test]
+    fn test_misaligned() {
+        let mut buf = vec![0u8];
+        buf.extend([1i32, 2, 3, 4].iter().flat_map(|v| v.to_ne_bytes()));
+        let tensor = ManagedTensor::from_dlpack(Misaligned(buf).into_dlpack());
+        // The allocator is all but certain to align the buffer to 4 bytes.
+        if tensor.is_aligned_for::<i32>() {
+            return;
+        }
+        assert!(matches!(
+            tensor.try_as_slice::<i32>(),
+            Err(Error::Misaligned { align: 4 })
+        ));
+        assert!(matches!(
+            tensor.try_as_slice::<f32>(),
+            Err(Error::DtypeMismatch { .. })
+        ));
+        assert_eq!(tensor.to_contiguous::<i32>(), [1, 2, 3, 4]);
+        assert!(matches!(
+            tensor.as_slice_or_copy::<i32>().unwrap(),
+            Cow::Owned(_)
+        ));
+    }
 }
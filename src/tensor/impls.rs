@@ -1,13 +1,15 @@
-use std::{ptr::NonNull, sync::Arc};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::ptr::NonNull;
 
 use super::{
     ffi,
-    traits::{InferDtype, IntoDLPack, ToTensor},
+    traits::{InferDtype, IntoDLPack, TensorView, ToTensor},
+    ManagedTensor,
 };
 use crate::{
-    ffi::{DataType, Device},
+    ffi::{DataType, Device, DeviceType},
     manager_ctx::ManagerCtx,
-    ShapeAndStrides,
+    Error, LayoutError, ShapeAndStrides,
 };
 
 macro_rules! impl_for_rust_type {
@@ -19,8 +21,8 @@ macro_rules! impl_for_rust_type {
         }
 
         impl ToTensor for $rust_type {
-            fn data_ptr(&self) -> *mut std::ffi::c_void {
-                self as *const Self as *mut std::ffi::c_void
+            fn data_ptr(&self) -> *mut core::ffi::c_void {
+                self as *const Self as *mut core::ffi::c_void
             }
 
             fn byte_offset(&self) -> u64 {
@@ -68,8 +70,8 @@ impl<T> ToTensor for Vec<T>
 where
     T: InferDtype,
 {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
-        self.as_ptr() as *mut T as *mut std::ffi::c_void
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.as_ptr() as *mut T as *mut core::ffi::c_void
     }
 
     fn byte_offset(&self) -> u64 {
@@ -85,7 +87,7 @@ where
     }
 
     fn shape_and_strides(&self) -> ShapeAndStrides {
-        ShapeAndStrides::new_with_strides(&[self.len() as i64], &[1])
+        ShapeAndStrides::new_1d(self.len())
     }
 }
 
@@ -93,8 +95,8 @@ impl<T> ToTensor for Box<[T]>
 where
     T: InferDtype,
 {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
-        self.as_ptr() as *mut T as *mut std::ffi::c_void
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.as_ptr() as *mut T as *mut core::ffi::c_void
     }
 
     fn byte_offset(&self) -> u64 {
@@ -110,7 +112,7 @@ where
     }
 
     fn shape_and_strides(&self) -> ShapeAndStrides {
-        ShapeAndStrides::new_with_strides(&[self.len() as i64], &[1])
+        ShapeAndStrides::new_1d(self.len())
     }
 }
 
@@ -118,8 +120,8 @@ impl<T> ToTensor for Arc<[T]>
 where
     T: InferDtype,
 {
-    fn data_ptr(&self) -> *mut std::ffi::c_void {
-        self.as_ptr() as *mut T as *mut std::ffi::c_void
+    fn data_ptr(&self) -> *mut core::ffi::c_void {
+        self.as_ptr() as *mut T as *mut core::ffi::c_void
     }
 
     fn byte_offset(&self) -> u64 {
@@ -135,7 +137,7 @@ where
     }
 
     fn shape_and_strides(&self) -> ShapeAndStrides {
-        ShapeAndStrides::new_with_strides(&[self.len() as i64], &[1])
+        ShapeAndStrides::new_1d(self.len())
     }
 }
 
@@ -148,3 +150,102 @@ where
         ctx.into_dl_managed_tensor()
     }
 }
+
+/// Check that the data of `tensor` can be taken as a row-major sequence of
+/// `T` on the CPU.
+fn check_contiguous_cpu<T: InferDtype>(tensor: &ManagedTensor) -> crate::Result<()> {
+    if tensor.dtype() != T::infer_dtype() {
+        return Err(Error::DtypeMismatch {
+            expected: T::infer_dtype(),
+            found: tensor.dtype(),
+        });
+    }
+    if tensor.device().device_type != DeviceType::Cpu {
+        return Err(Error::DeviceMismatch {
+            expected: Device::CPU,
+            found: tensor.device(),
+        });
+    }
+    if !tensor.is_contiguous() {
+        return Err(LayoutError::NotContiguous.into());
+    }
+    Ok(())
+}
+
+/// Takes back the `Vec` the tensor was exported from without copying, if it
+/// was.
+impl<T> TryFrom<ManagedTensor> for Vec<T>
+where
+    T: InferDtype + Copy,
+{
+    type Error = Error;
+
+    fn try_from(tensor: ManagedTensor) -> crate::Result<Self> {
+        check_contiguous_cpu::<T>(&tensor)?;
+        Ok(tensor.into_vec())
+    }
+}
+
+impl<T> TryFrom<ManagedTensor> for Box<[T]>
+where
+    T: InferDtype + Copy,
+{
+    type Error = Error;
+
+    fn try_from(tensor: ManagedTensor) -> crate::Result<Self> {
+        Vec::try_from(tensor).map(Vec::into_boxed_slice)
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T> TryFrom<ManagedTensor> for ndarray::ArrayD<T>
+where
+    T: InferDtype + Copy,
+{
+    type Error = Error;
+
+    fn try_from(tensor: ManagedTensor) -> crate::Result<Self> {
+        let shape: Vec<usize> = tensor.shape().iter().map(|&dim| dim as usize).collect();
+        let data = Vec::try_from(tensor)?;
+        ndarray::ArrayD::from_shape_vec(shape, data)
+            .map_err(|_| crate::error::invariant_violated("shape doesn't match the data"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prelude::*, OwnedTensor};
+
+    #[test]
+    fn try_from_tensor() {
+        let dlpack = vec![1.0f32, 2.0, 3.0].into_dlpack();
+        let data = unsafe { dlpack.as_ref().dl_tensor.data };
+        let vec = Vec::<f32>::try_from(ManagedTensor::from_dlpack(dlpack)).unwrap();
+        assert_eq!(vec, [1.0, 2.0, 3.0]);
+        assert_eq!(vec.as_ptr() as *mut core::ffi::c_void, data);
+
+        let tensor = ManagedTensor::from_dlpack(vec![1u8, 2].into_dlpack());
+        assert!(matches!(
+            Box::<[i8]>::try_from(tensor),
+            Err(Error::DtypeMismatch { .. })
+        ));
+
+        let bytes: Vec<u8> = (0i32..6).flat_map(i32::to_ne_bytes).collect();
+        let tensor = OwnedTensor::from_bytes(&bytes, &[2, 3], DataType::I32).unwrap();
+        let boxed = Box::<[i32]>::try_from(ManagedTensor::from_dlpack(tensor.into_dlpack()));
+        assert_eq!(&*boxed.unwrap(), &[0, 1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn try_into_array() {
+        let bytes: Vec<u8> = (0i32..6).flat_map(i32::to_ne_bytes).collect();
+        let tensor = OwnedTensor::from_bytes(&bytes, &[2, 3], DataType::I32).unwrap();
+        let array =
+            ndarray::ArrayD::<i32>::try_from(ManagedTensor::from_dlpack(tensor.into_dlpack()))
+                .unwrap();
+        assert_eq!(array.shape(), &[2, 3]);
+        assert_eq!(array[[1, 2]], 5);
+    }
+}
@@ -0,0 +1,100 @@
+//! Wrappers for moving imported tensors to, and sharing them between, other
+//! threads. A [`ManagedTensor`] is neither `Send` nor `Sync` since nothing is
+//! known about its producer, these wrappers let the caller vouch for it once.
+
+use core::ops::Deref;
+
+use super::{traits::TensorView, ManagedTensor};
+
+/// A [`ManagedTensor`] that can be moved to another thread, e.g. into a
+/// thread pool.
+#[derive(Debug)]
+pub struct SendTensor(ManagedTensor);
+
+// See the invariants of `SendTensor::new`.
+unsafe impl Send for SendTensor {}
+
+impl SendTensor {
+    /// # Safety
+    /// The deleter of `tensor` must be safe to call from any thread, and no
+    /// other thread may write its data while this tensor is alive.
+    pub unsafe fn new(tensor: ManagedTensor) -> Self {
+        Self(tensor)
+    }
+
+    pub fn into_inner(self) -> ManagedTensor {
+        self.0
+    }
+}
+
+impl Deref for SendTensor {
+    type Target = ManagedTensor;
+
+    fn deref(&self) -> &ManagedTensor {
+        &self.0
+    }
+}
+
+/// A [`ManagedTensor`] that can be shared between threads, e.g. through an
+/// `Arc`. Its data can only be read.
+#[derive(Debug)]
+pub struct SyncTensorView(ManagedTensor);
+
+// The data is only read, and the layout caches are filled before the tensor
+// is shared so they are only read as well.
+unsafe impl Send for SyncTensorView {}
+unsafe impl Sync for SyncTensorView {}
+
+impl SyncTensorView {
+    /// Same invariants as [`SendTensor::new`], which `tensor` already
+    /// guarantees.
+    pub fn new(tensor: SendTensor) -> Self {
+        let tensor = tensor.into_inner();
+        tensor.num_elements();
+        tensor.is_contiguous();
+        tensor.strides_or_contiguous();
+        Self(tensor)
+    }
+
+    pub fn into_inner(self) -> SendTensor {
+        SendTensor(self.0)
+    }
+}
+
+impl Deref for SyncTensorView {
+    type Target = ManagedTensor;
+
+    fn deref(&self) -> &ManagedTensor {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn share_between_threads() {
+        let tensor = ManagedTensor::from_dlpack(vec![1i32, 2, 3].into_dlpack());
+        let tensor = unsafe { SendTensor::new(tensor) };
+        let sum = thread::spawn(move || tensor.as_slice::<i32>().iter().sum::<i32>())
+            .join()
+            .unwrap();
+        assert_eq!(sum, 6);
+
+        let tensor = ManagedTensor::from_dlpack(vec![1i32, 2, 3].into_dlpack());
+        let view = Arc::new(SyncTensorView::new(unsafe { SendTensor::new(tensor) }));
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let view = view.clone();
+                thread::spawn(move || view.num_elements())
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 3);
+        }
+    }
+}
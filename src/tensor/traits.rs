@@ -1,8 +1,10 @@
-use std::ptr::NonNull;
+use alloc::{borrow::Cow, string::String};
+use core::{fmt::Write, ptr::NonNull};
 
 use crate::{
-    ffi::{self, DataType, Device},
-    utils::is_contiguous,
+    device_names::DisplayDevice,
+    ffi::{self, DataType, DataTypeCode, Device},
+    utils::{is_contiguous, make_contiguous_strides},
     ShapeAndStrides,
 };
 
@@ -18,7 +20,7 @@ pub trait InferDtype {
 /// Access Tensor data.
 pub trait TensorView {
     /// Get untyped data ptr
-    fn data_ptr(&self) -> *mut std::ffi::c_void;
+    fn data_ptr(&self) -> *mut core::ffi::c_void;
     /// Get shape as slice.
     fn shape(&self) -> &[i64];
     /// Get strides as slice. If strides is None, Tensor is assumed to be
@@ -29,9 +31,37 @@ pub trait TensorView {
     fn dtype(&self) -> DataType;
     fn byte_offset(&self) -> u64;
 
+    /// Get strides, computing the row-major ones if they were left out.
+    fn strides_or_contiguous(&self) -> Cow<'_, [i64]> {
+        match self.strides() {
+            Some(strides) => Cow::Borrowed(strides),
+            None if self.ndim() == 0 => Cow::Borrowed(&[]),
+            None => Cow::Owned(make_contiguous_strides(self.shape())),
+        }
+    }
+
     // Get num elements in Tensor.
     fn num_elements(&self) -> usize {
-        self.shape().iter().product::<i64>() as usize
+        self.checked_num_elements()
+            .expect("negative dims or number of elements overflows")
+    }
+
+    /// Get num elements in Tensor, or `None` if a dim is negative or the
+    /// product overflows.
+    fn checked_num_elements(&self) -> Option<usize> {
+        self.shape().iter().try_fold(1usize, |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        })
+    }
+
+    /// Whether the tensor has no elements, i.e. some dim is zero.
+    fn is_empty(&self) -> bool {
+        self.shape().contains(&0)
+    }
+
+    /// Size of an element in bytes, lanes included.
+    fn itemsize(&self) -> usize {
+        self.dtype().size()
     }
 
     /// For given DLTensor, the size of memory required to store the contents of
@@ -48,7 +78,18 @@ pub trait TensorView {
     /// }
     /// ```
     fn data_size(&self) -> usize {
-        self.num_elements() * self.dtype().size()
+        self.checked_data_size().expect("data size overflows")
+    }
+
+    /// Same as [`TensorView::data_size`], or `None` on negative dims or
+    /// overflow.
+    fn checked_data_size(&self) -> Option<usize> {
+        self.checked_num_elements()?.checked_mul(self.itemsize())
+    }
+
+    /// Same as [`TensorView::data_size`], named after NumPy's `nbytes`.
+    fn nbytes(&self) -> usize {
+        self.data_size()
     }
 
     /// Return true if tensor is contiguous in memory in the order specified by
@@ -59,16 +100,100 @@ pub trait TensorView {
             None => true,
         }
     }
+
+    /// `DLPACK_FLAG_BITMASK_*` bits of the tensor, 0 where it can't tell.
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        0
+    }
+
+    /// Whether the data must not be written to, as far as the tensor can
+    /// tell, see [`TensorView::flags`].
+    #[cfg(feature = "dlpack-1-0")]
+    fn is_read_only(&self) -> bool {
+        self.flags() & ffi::DLPACK_FLAG_BITMASK_READ_ONLY != 0
+    }
+
+    /// One-line description for logging, without the data, e.g.
+    /// `f32[32, 3, 224, 224] @ cuda:0 (contiguous)`.
+    fn summary(&self) -> String {
+        let dtype = self.dtype();
+        let device = self.device();
+        let mut summary = String::new();
+        match dtype_name(dtype.code) {
+            (name, true) => {
+                let _ = write!(summary, "{name}{}", dtype.bits);
+            }
+            (name, false) => summary.push_str(name),
+        }
+        if dtype.lanes != 1 {
+            let _ = write!(summary, "x{}", dtype.lanes);
+        }
+        let _ = write!(
+            summary,
+            "{:?} @ {} ({})",
+            self.shape(),
+            DisplayDevice(device),
+            if self.is_contiguous() {
+                "contiguous"
+            } else {
+                "strided"
+            }
+        );
+        summary
+    }
+}
+
+/// Name of the scalar type, and whether the bits have to be appended.
+fn dtype_name(code: DataTypeCode) -> (&'static str, bool) {
+    match code {
+        DataTypeCode::Int => ("i", true),
+        DataTypeCode::UInt => ("u", true),
+        DataTypeCode::Float => ("f", true),
+        DataTypeCode::OpaqueHandle => ("handle", true),
+        DataTypeCode::Bfloat => ("bf", true),
+        DataTypeCode::Complex => ("c", true),
+        DataTypeCode::Bool => ("bool", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E3M4 => ("f8e3m4", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E4M3 => ("f8e4m3", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E4M3B11Fnuz => ("f8e4m3b11fnuz", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E4M3Fn => ("f8e4m3fn", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E4M3Fnuz => ("f8e4m3fnuz", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E5M2 => ("f8e5m2", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E5M2Fnuz => ("f8e5m2fnuz", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float8E8M0Fnu => ("f8e8m0fnu", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float6E2M3Fn => ("f6e2m3fn", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float6E3M2Fn => ("f6e3m2fn", false),
+        #[cfg(feature = "dlpack-1-1")]
+        DataTypeCode::Float4E2M1Fn => ("f4e2m1fn", false),
+    }
 }
 
 /// User should implement this trait for their tensor.
 pub trait ToTensor {
-    fn data_ptr(&self) -> *mut std::ffi::c_void;
+    fn data_ptr(&self) -> *mut core::ffi::c_void;
     /// If return None, tensor must be contiguous.
     fn shape_and_strides(&self) -> ShapeAndStrides;
     fn device(&self) -> Device;
     fn dtype(&self) -> DataType;
     fn byte_offset(&self) -> u64;
+    /// `DLPACK_FLAG_BITMASK_*` bits to export the tensor with, e.g.
+    /// [`DLPACK_FLAG_BITMASK_READ_ONLY`](ffi::DLPACK_FLAG_BITMASK_READ_ONLY)
+    /// for data in read-only memory.
+    #[cfg(feature = "dlpack-1-0")]
+    fn flags(&self) -> u64 {
+        0
+    }
 }
 
 // TODO: we should add `try_to_dlpack` fn
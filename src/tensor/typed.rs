@@ -0,0 +1,454 @@
+//! [`ManagedTensor`]s whose element type, and optionally rank, were checked
+//! once, with typed accessors that don't need to check them again.
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Deref, Range},
+};
+
+use super::{
+    traits::{InferDtype, TensorView},
+    ManagedTensor,
+};
+use crate::{Error, LayoutError, Result};
+
+/// CPU tensor of `A`, made by [`ManagedTensor::into_typed`].
+#[derive(Debug)]
+pub struct TypedTensor<A> {
+    tensor: ManagedTensor,
+    _marker: PhantomData<A>,
+}
+
+impl ManagedTensor {
+    /// Check once that this is a CPU tensor of `A` aligned for it. Strided
+    /// tensors are accessed following their strides.
+    pub fn into_typed<A: InferDtype>(self) -> Result<TypedTensor<A>> {
+        self.check_cpu_dtype::<A>()?;
+        if !self.is_aligned_for::<A>() {
+            return Err(Error::Misaligned {
+                align: core::mem::align_of::<A>(),
+            });
+        }
+        Ok(TypedTensor {
+            tensor: self,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<A> TypedTensor<A> {
+    /// All elements in row-major order, or `None` if the tensor isn't
+    /// contiguous, see [`TypedTensor::iter`].
+    pub fn slice(&self) -> Option<&[A]> {
+        self.tensor.is_contiguous().then(|| self.tensor.as_slice())
+    }
+
+    /// Element at `index`, or `None` if it has the wrong number of dims or is
+    /// out of bounds.
+    pub fn get(&self, index: &[i64]) -> Option<&A> {
+        if index.len() != self.tensor.ndim() {
+            return None;
+        }
+        let strides = self.tensor.strides_or_contiguous();
+        let mut offset = 0isize;
+        for ((&i, &dim), &stride) in index.iter().zip(self.tensor.shape()).zip(&*strides) {
+            if !(0..dim).contains(&i) {
+                return None;
+            }
+            offset += (i * stride) as isize;
+        }
+        Some(unsafe { &*self.first().offset(offset) })
+    }
+
+    /// Elements in row-major order, following strides.
+    pub fn iter(&self) -> TypedIter<'_, A> {
+        TypedIter {
+            first: self.first(),
+            offsets: self.offsets(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> ManagedTensor {
+        self.tensor
+    }
+
+    /// Check once that the tensor has `R` dims.
+    pub fn into_rank<const R: usize>(self) -> Result<Tensor<A, R>> {
+        let ndim = self.tensor.ndim();
+        if ndim != R {
+            return Err(LayoutError::NdimMismatch {
+                expected: R,
+                found: ndim,
+            }
+            .into());
+        }
+        Ok(Tensor(self))
+    }
+
+    fn first(&self) -> *const A {
+        self.tensor.first_byte().cast_const().cast()
+    }
+
+    fn offsets(&self) -> Offsets {
+        let len = self.tensor.num_elements();
+        if self.tensor.is_contiguous() {
+            return Offsets::Contiguous(0..len);
+        }
+        let shape = self.tensor.shape();
+        let strides = self.tensor.strides_or_contiguous();
+        let last = shape
+            .iter()
+            .map(|&dim| (dim - 1).max(0))
+            .collect::<Vec<_>>();
+        let back = last
+            .iter()
+            .zip(&*strides)
+            .map(|(i, stride)| i * stride)
+            .sum();
+        Offsets::Strided(Box::new(StridedOffsets {
+            shape: shape.into(),
+            strides: strides.into(),
+            front: (vec![0; shape.len()], 0),
+            back: (last, back),
+            len,
+        }))
+    }
+}
+
+/// Offsets of the elements of a tensor from its first one, in elements and
+/// in row-major order.
+#[derive(Debug)]
+enum Offsets {
+    Contiguous(Range<usize>),
+    Strided(Box<StridedOffsets>),
+}
+
+/// Indices and offsets of the next elements from both ends of a strided
+/// tensor, moved one axis at a time instead of recomputed.
+#[derive(Debug)]
+struct StridedOffsets {
+    shape: Box<[i64]>,
+    strides: Box<[i64]>,
+    front: (Vec<i64>, i64),
+    back: (Vec<i64>, i64),
+    /// Elements left between `front` and `back`, both included.
+    len: usize,
+}
+
+impl Iterator for Offsets {
+    type Item = isize;
+
+    fn next(&mut self) -> Option<isize> {
+        let offsets = match self {
+            Self::Contiguous(range) => return range.next().map(|i| i as isize),
+            Self::Strided(offsets) => offsets,
+        };
+        if offsets.len == 0 {
+            return None;
+        }
+        offsets.len -= 1;
+        let (index, offset) = &mut offsets.front;
+        let current = *offset;
+        for axis in (0..index.len()).rev() {
+            index[axis] += 1;
+            *offset += offsets.strides[axis];
+            if index[axis] < offsets.shape[axis] {
+                break;
+            }
+            *offset -= offsets.strides[axis] * offsets.shape[axis];
+            index[axis] = 0;
+        }
+        Some(current as isize)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match self {
+            Self::Contiguous(range) => range.len(),
+            Self::Strided(offsets) => offsets.len,
+        };
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Offsets {
+    fn next_back(&mut self) -> Option<isize> {
+        let offsets = match self {
+            Self::Contiguous(range) => return range.next_back().map(|i| i as isize),
+            Self::Strided(offsets) => offsets,
+        };
+        if offsets.len == 0 {
+            return None;
+        }
+        offsets.len -= 1;
+        let (index, offset) = &mut offsets.back;
+        let current = *offset;
+        for axis in (0..index.len()).rev() {
+            if index[axis] > 0 {
+                index[axis] -= 1;
+                *offset -= offsets.strides[axis];
+                break;
+            }
+            index[axis] = offsets.shape[axis] - 1;
+            *offset += offsets.strides[axis] * index[axis];
+        }
+        Some(current as isize)
+    }
+}
+
+/// Borrowing iterator over the elements of a [`TypedTensor`] in row-major
+/// order, made by [`TypedTensor::iter`].
+#[derive(Debug)]
+pub struct TypedIter<'a, A> {
+    first: *const A,
+    offsets: Offsets,
+    _marker: PhantomData<&'a A>,
+}
+
+impl<'a, A> Iterator for TypedIter<'a, A> {
+    type Item = &'a A;
+
+    fn next(&mut self) -> Option<&'a A> {
+        let offset = self.offsets.next()?;
+        Some(unsafe { &*self.first.offset(offset) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+
+impl<A> DoubleEndedIterator for TypedIter<'_, A> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let offset = self.offsets.next_back()?;
+        Some(unsafe { &*self.first.offset(offset) })
+    }
+}
+
+impl<A> ExactSizeIterator for TypedIter<'_, A> {}
+
+impl<A> FusedIterator for TypedIter<'_, A> {}
+
+impl<A> Deref for TypedTensor<A> {
+    type Target = ManagedTensor;
+
+    fn deref(&self) -> &ManagedTensor {
+        &self.tensor
+    }
+}
+
+impl<'a, A> IntoIterator for &'a TypedTensor<A> {
+    type IntoIter = TypedIter<'a, A>;
+    type Item = &'a A;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<A: Copy> IntoIterator for TypedTensor<A> {
+    type IntoIter = TypedIntoIter<A>;
+    type Item = A;
+
+    fn into_iter(self) -> TypedIntoIter<A> {
+        TypedIntoIter {
+            first: self.first(),
+            offsets: self.offsets(),
+            _tensor: self,
+        }
+    }
+}
+
+/// Owning iterator over the elements of a [`TypedTensor`] in row-major order,
+/// keeping the tensor alive until it is dropped.
+#[derive(Debug)]
+pub struct TypedIntoIter<A> {
+    first: *const A,
+    offsets: Offsets,
+    _tensor: TypedTensor<A>,
+}
+
+impl<A: Copy> Iterator for TypedIntoIter<A> {
+    type Item = A;
+
+    fn next(&mut self) -> Option<A> {
+        let offset = self.offsets.next()?;
+        Some(unsafe { *self.first.offset(offset) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.offsets.size_hint()
+    }
+}
+
+impl<A: Copy> DoubleEndedIterator for TypedIntoIter<A> {
+    fn next_back(&mut self) -> Option<A> {
+        let offset = self.offsets.next_back()?;
+        Some(unsafe { *self.first.offset(offset) })
+    }
+}
+
+impl<A: Copy> ExactSizeIterator for TypedIntoIter<A> {}
+
+impl<A: Copy> FusedIterator for TypedIntoIter<A> {}
+
+/// [`TypedTensor`] with `R` dims, made by [`TypedTensor::into_rank`], e.g.
+/// `Tensor<f32, 4>` for NCHW images.
+#[derive(Debug)]
+pub struct Tensor<A, const R: usize>(TypedTensor<A>);
+
+impl<A, const R: usize> Tensor<A, R> {
+    pub fn shape(&self) -> [i64; R] {
+        self.0.shape().try_into().expect("rank was checked")
+    }
+
+    /// Element at `index`, or `None` if it is out of bounds.
+    pub fn get(&self, index: [i64; R]) -> Option<&A> {
+        self.0.get(&index)
+    }
+
+    pub fn into_inner(self) -> TypedTensor<A> {
+        self.0
+    }
+}
+
+impl<A, const R: usize> Deref for Tensor<A, R> {
+    type Target = TypedTensor<A>;
+
+    fn deref(&self) -> &TypedTensor<A> {
+        &self.0
+    }
+}
+
+impl<A: Copy, const R: usize> IntoIterator for Tensor<A, R> {
+    type IntoIter = TypedIntoIter<A>;
+    type Item = A;
+
+    fn into_iter(self) -> TypedIntoIter<A> {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, A, const R: usize> IntoIterator for &'a Tensor<A, R> {
+    type IntoIter = TypedIter<'a, A>;
+    type Item = &'a A;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{transposed, Strided},
+        prelude::*,
+        Error, OwnedTensor,
+    };
+
+    #[test]
+    fn typed_accessors() {
+        let bytes: Vec<u8> = (1i32..=6).flat_map(i32::to_ne_bytes).collect();
+        let tensor = OwnedTensor::from_bytes(&bytes, &[2, 3], DataType::I32).unwrap();
+        let typed = ManagedTensor::from_dlpack(tensor.into_dlpack())
+            .into_typed::<i32>()
+            .unwrap();
+        assert_eq!(typed.slice(), Some(&[1, 2, 3, 4, 5, 6][..]));
+        assert_eq!(typed.get(&[1, 0]), Some(&4));
+        assert_eq!(typed.get(&[0, 3]), None);
+        assert_eq!(typed.get(&[1]), None);
+        assert_eq!(typed.iter().sum::<i32>(), 21);
+        assert_eq!(typed.shape(), &[2, 3]);
+
+        let tensor = typed.into_rank::<2>().unwrap();
+        assert_eq!(tensor.shape(), [2, 3]);
+        assert_eq!(tensor.get([1, 2]), Some(&6));
+        assert_eq!(tensor.get([2, 0]), None);
+        assert!(matches!(
+            tensor.into_inner().into_rank::<4>(),
+            Err(Error::Layout(LayoutError::NdimMismatch {
+                expected: 4,
+                found: 2
+            }))
+        ));
+
+        let tensor = ManagedTensor::from_dlpack(vec![1i32, 2].into_dlpack());
+        assert!(matches!(
+            tensor.into_typed::<f32>(),
+            Err(Error::DtypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn iterators() {
+        let tensor = ManagedTensor::from_dlpack(vec![1i32, 2, 3, 4].into_dlpack());
+        let typed = tensor.into_typed::<i32>().unwrap();
+        let mut sum = 0;
+        for x in &typed {
+            sum += x;
+        }
+        assert_eq!(sum, 10);
+
+        let mut iter = typed.into_iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.map(|x| x * 2).collect::<Vec<_>>(), [2, 4, 6]);
+
+        let tensor = ManagedTensor::from_dlpack(vec![5u8, 6].into_dlpack());
+        let tensor = tensor.into_typed::<u8>().unwrap().into_rank::<1>().unwrap();
+        assert_eq!((&tensor).into_iter().max(), Some(&6));
+        assert_eq!(tensor.into_iter().rev().collect::<Vec<_>>(), [6, 5]);
+    }
+
+    #[test]
+    fn strided() {
+        let tensor = ManagedTensor::from_dlpack(transposed((0..6).collect()).into_dlpack());
+        let typed = tensor.into_typed::<i32>().unwrap();
+        assert_eq!(typed.slice(), None);
+        assert_eq!(typed.get(&[2, 1]), Some(&5));
+        assert_eq!(typed.get(&[1, 2]), None);
+        assert_eq!(
+            typed.iter().copied().collect::<Vec<_>>(),
+            [0, 3, 1, 4, 2, 5]
+        );
+        assert_eq!(
+            typed.iter().rev().copied().collect::<Vec<_>>(),
+            [5, 2, 4, 1, 3, 0]
+        );
+
+        let mut iter = typed.into_iter();
+        assert_eq!(iter.len(), 6);
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next_back(), Some(5));
+        assert_eq!(iter.next_back(), Some(2));
+        assert_eq!(iter.collect::<Vec<_>>(), [3, 1, 4]);
+
+        let tensor = ManagedTensor::from_dlpack(
+            Strided::new((0..24u16).collect(), &[4, 3, 2], &[1, 4, 12]).into_dlpack(),
+        );
+        let expected = tensor.to_contiguous::<u16>();
+        let typed = tensor.into_typed::<u16>().unwrap();
+        assert_eq!(typed.iter().copied().collect::<Vec<_>>(), expected);
+        let mut reversed = typed.into_iter().rev().collect::<Vec<_>>();
+        reversed.reverse();
+        assert_eq!(reversed, expected);
+
+        // Broadcast and empty views.
+        let tensor =
+            ManagedTensor::from_dlpack(Strided::new(vec![7u8, 8], &[3, 2], &[0, 1]).into_dlpack());
+        let typed = tensor.into_typed::<u8>().unwrap();
+        assert_eq!(
+            typed.iter().copied().collect::<Vec<_>>(),
+            [7, 8, 7, 8, 7, 8]
+        );
+        let tensor = ManagedTensor::from_dlpack(
+            Strided::new(Vec::<u8>::new(), &[0, 2], &[1, 0]).into_dlpack(),
+        );
+        let typed = tensor.into_typed::<u8>().unwrap();
+        assert_eq!(typed.iter().len(), 0);
+        assert_eq!(typed.into_iter().next_back(), None);
+    }
+}
@@ -0,0 +1,155 @@
+//! Named collections of tensors, as model inputs and outputs nearly always
+//! are.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{wire::EncodeOptions, ManagedTensor};
+
+/// Tensors by name, in name order. Dereferences to its map.
+///
+/// It is encoded as a `u32` count of tensors followed, for each tensor in
+/// name order, by a `u32` name length, the UTF-8 name and a
+/// [wire](crate::wire) message, all integers little-endian.
+#[derive(Debug, Default)]
+pub struct TensorDict(BTreeMap<String, ManagedTensor>);
+
+impl TensorDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_inner(self) -> BTreeMap<String, ManagedTensor> {
+        self.0
+    }
+
+    /// Encode every tensor, which must be on the CPU, as described in the
+    /// [`TensorDict`] docs.
+    pub fn encode_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.encode_with(writer, EncodeOptions::default())
+    }
+
+    /// Same as [`TensorDict::encode_to`], with every tensor encoded with
+    /// `options`.
+    pub fn encode_with<W: Write>(&self, mut writer: W, options: EncodeOptions) -> io::Result<()> {
+        let count = u32::try_from(self.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many tensors"))?;
+        writer.write_all(&count.to_le_bytes())?;
+        for (name, tensor) in &self.0 {
+            let len = u32::try_from(name.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "name is too long"))?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            tensor.encode_with(&mut writer, options)?;
+        }
+        Ok(())
+    }
+
+    /// Decode a dict encoded by [`TensorDict::encode_to`] into CPU tensors.
+    pub fn decode_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let count = read_u32(&mut reader)?;
+        let mut dict = Self::new();
+        for _ in 0..count {
+            let len = read_u32(&mut reader)?;
+            let mut name = Vec::new();
+            reader.by_ref().take(len.into()).read_to_end(&mut name)?;
+            if name.len() != len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let name = String::from_utf8(name)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let tensor = ManagedTensor::decode_from(&mut reader)?;
+            if dict.insert(name, tensor).is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "duplicate tensor name",
+                ));
+            }
+        }
+        Ok(dict)
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+impl Deref for TensorDict {
+    type Target = BTreeMap<String, ManagedTensor>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for TensorDict {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<BTreeMap<String, ManagedTensor>> for TensorDict {
+    fn from(tensors: BTreeMap<String, ManagedTensor>) -> Self {
+        Self(tensors)
+    }
+}
+
+impl<S: Into<String>> FromIterator<(S, ManagedTensor)> for TensorDict {
+    fn from_iter<I: IntoIterator<Item = (S, ManagedTensor)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(name, tensor)| (name.into(), tensor))
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for TensorDict {
+    type IntoIter = std::collections::btree_map::IntoIter<String, ManagedTensor>;
+    type Item = (String, ManagedTensor);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn wire() {
+        let dict: TensorDict = [
+            (
+                "pixels",
+                ManagedTensor::from_dlpack(vec![1u8, 2, 3].into_dlpack()),
+            ),
+            (
+                "logits",
+                ManagedTensor::from_dlpack(vec![0.5f32, -1.0].into_dlpack()),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let mut buf = Vec::new();
+        dict.encode_to(&mut buf).unwrap();
+        // Followed by another message, which is left alone.
+        buf.extend([0xff]);
+
+        let mut reader = buf.as_slice();
+        let decoded = TensorDict::decode_from(&mut reader).unwrap();
+        assert_eq!(reader, [0xff]);
+        assert_eq!(decoded.keys().collect::<Vec<_>>(), ["logits", "pixels"]);
+        assert_eq!(decoded["logits"].as_slice::<f32>(), &[0.5, -1.0]);
+        assert_eq!(decoded["pixels"].as_slice::<u8>(), &[1, 2, 3]);
+
+        let err = TensorDict::decode_from(&buf[..buf.len() - 2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,158 @@
+//! Generators of tensor metadata, for downstream crates to fuzz and property
+//! test their own DLPack handling. Enabled by the `test-support` feature.
+//!
+//! The `Arbitrary` impls of the raw types, in `dlpark-sys`, only produce
+//! valid values. The proptest strategies come in valid and invalid flavors,
+//! invalid ones being rejected by
+//! [`ManagedTensor::validate`](crate::ManagedTensor::validate) or
+//! [`ShapeAndStrides::try_new_with_strides`](crate::ShapeAndStrides::try_new_with_strides).
+
+use proptest::{collection::vec, prelude::*, sample::select};
+
+use crate::ffi::{DataType, DataTypeCode, Device, DeviceType};
+
+const DTYPE_CODES: [DataTypeCode; 7] = [
+    DataTypeCode::Int,
+    DataTypeCode::UInt,
+    DataTypeCode::Float,
+    DataTypeCode::OpaqueHandle,
+    DataTypeCode::Bfloat,
+    DataTypeCode::Complex,
+    DataTypeCode::Bool,
+];
+
+const DEVICE_TYPES: [DeviceType; 14] = [
+    DeviceType::Cpu,
+    DeviceType::Cuda,
+    DeviceType::CudaHost,
+    DeviceType::OpenCl,
+    DeviceType::Vulkan,
+    DeviceType::Metal,
+    DeviceType::Vpi,
+    DeviceType::Rocm,
+    DeviceType::RocmHost,
+    DeviceType::ExtDev,
+    DeviceType::CudaManaged,
+    DeviceType::OneApi,
+    DeviceType::WebGpu,
+    DeviceType::Hexagon,
+];
+
+const BITS: [u8; 4] = [8, 16, 32, 64];
+
+pub fn data_type() -> impl Strategy<Value = DataType> {
+    (select(&DTYPE_CODES[..]), select(&BITS[..]), 1u16..=4)
+        .prop_map(|(code, bits, lanes)| DataType { code, bits, lanes })
+}
+
+/// Data types with zero bits or lanes.
+pub fn invalid_data_type() -> impl Strategy<Value = DataType> {
+    (select(&DTYPE_CODES[..]), 0u8..=64, 0u16..=4)
+        .prop_filter("bits or lanes must be zero", |&(_, bits, lanes)| {
+            bits == 0 || lanes == 0
+        })
+        .prop_map(|(code, bits, lanes)| DataType { code, bits, lanes })
+}
+
+pub fn device() -> impl Strategy<Value = Device> {
+    (select(&DEVICE_TYPES[..]), 0i32..8).prop_map(|(device_type, device_id)| Device {
+        device_type,
+        device_id,
+    })
+}
+
+/// Shapes of up to `max_ndim` dims, each at most `max_dim`, including empty
+/// ones.
+pub fn shape(max_ndim: usize, max_dim: i64) -> impl Strategy<Value = Vec<i64>> {
+    vec(0..=max_dim, 0..=max_ndim)
+}
+
+/// Shapes with at least one negative dim.
+pub fn invalid_shape(max_ndim: usize, max_dim: i64) -> impl Strategy<Value = Vec<i64>> {
+    (
+        vec(0..=max_dim, 1..=max_ndim.max(1)),
+        any::<prop::sample::Index>(),
+        -max_dim.max(1)..0,
+    )
+        .prop_map(|(mut shape, axis, dim)| {
+            let axis = axis.index(shape.len());
+            shape[axis] = dim;
+            shape
+        })
+}
+
+/// Shapes with strides, in elements, of non-overlapping layouts: the axes in
+/// any order, each possibly skipping elements.
+pub fn shape_and_strides(
+    max_ndim: usize,
+    max_dim: i64,
+) -> impl Strategy<Value = (Vec<i64>, Vec<i64>)> {
+    shape(max_ndim, max_dim).prop_flat_map(|shape| {
+        let ndim = shape.len();
+        (
+            Just((0..ndim).collect::<Vec<_>>()).prop_shuffle(),
+            vec(1i64..=3, ndim),
+        )
+            .prop_map(move |(order, steps)| {
+                let mut strides = vec![0; ndim];
+                let mut extent = 1;
+                for &axis in order.iter().rev() {
+                    strides[axis] = extent * steps[axis];
+                    extent = strides[axis] * shape[axis].max(1);
+                }
+                (shape.clone(), strides)
+            })
+    })
+}
+
+/// Shapes with strides of a different number of dims.
+pub fn invalid_shape_and_strides(
+    max_ndim: usize,
+    max_dim: i64,
+) -> impl Strategy<Value = (Vec<i64>, Vec<i64>)> {
+    (
+        shape(max_ndim, max_dim),
+        vec(1..=max_dim.max(1), 0..=max_ndim + 1),
+    )
+        .prop_filter("lengths must differ", |(shape, strides)| {
+            shape.len() != strides.len()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use super::*;
+    use crate::{utils::byte_span, ShapeAndStrides};
+
+    proptest! {
+        #[test]
+        fn valid_layouts((shape, strides) in shape_and_strides(5, 6)) {
+            prop_assert!(ShapeAndStrides::try_new_with_strides(&shape, &strides).is_ok());
+            prop_assert!(byte_span(&shape, Some(&strides), 4).is_some());
+        }
+
+        #[test]
+        fn invalid_layouts(
+            (shape, strides) in invalid_shape_and_strides(5, 6),
+            negative in invalid_shape(5, 6),
+            dtype in invalid_data_type(),
+        ) {
+            prop_assert!(ShapeAndStrides::try_new_with_strides(&shape, &strides).is_err());
+            prop_assert!(byte_span(&negative, None, 4).is_none());
+            prop_assert!(dtype.bits == 0 || dtype.lanes == 0);
+        }
+    }
+
+    #[test]
+    fn arbitrary_metadata() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        let mut u = Unstructured::new(&bytes);
+        for _ in 0..16 {
+            let dtype = DataType::arbitrary(&mut u).unwrap();
+            assert!(dtype.bits > 0 && dtype.lanes > 0);
+            Device::arbitrary(&mut u).unwrap();
+        }
+    }
+}
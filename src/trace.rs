@@ -0,0 +1,113 @@
+//! Events of the lifecycle of tensors crossing the FFI boundary, emitted with
+//! `tracing` at the debug level under the `dlpark` target. Enabled by the
+//! `tracing` feature.
+
+use crate::{device_names::DisplayDevice, ffi, tensor::traits::TensorView};
+
+fn event(action: &str, managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    tracing::debug!(
+        target: "dlpark",
+        ptr = ?managed,
+        shape = ?tensor.shape(),
+        dtype = ?tensor.dtype,
+        device = %DisplayDevice(tensor.device),
+        "{action} tensor",
+    );
+}
+
+/// A tensor was exported by [`ManagerCtx`](crate::ManagerCtx).
+pub(crate) fn exported(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    event("exported", managed, tensor);
+}
+
+/// The deleter of a tensor exported by this crate is running.
+pub(crate) fn deleted(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    event("deleted", managed, tensor);
+}
+
+/// A tensor exported by this crate was taken back without running its
+/// deleter.
+pub(crate) fn reclaimed(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    event("reclaimed", managed, tensor);
+}
+
+/// A tensor from any producer was wrapped in a [`ManagedTensor`].
+///
+/// [`ManagedTensor`]: crate::ManagedTensor
+pub(crate) fn imported(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    event("imported", managed, tensor);
+}
+
+/// A [`ManagedTensor`] is dropped, calling the deleter of its producer.
+///
+/// [`ManagedTensor`]: crate::ManagedTensor
+pub(crate) fn released(managed: *const ffi::DLManagedTensor, tensor: &ffi::DLTensor) {
+    event("released", managed, tensor);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    use crate::{prelude::*, ManagedTensor};
+
+    /// Collects the messages of the events of this crate.
+    #[derive(Default, Clone)]
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Messages {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{value:?}"));
+            }
+        }
+    }
+
+    impl Subscriber for Messages {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "dlpark"
+        }
+
+        fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    #[test]
+    fn lifecycle_events() {
+        let messages = Messages::default();
+        tracing::subscriber::with_default(messages.clone(), || {
+            let tensor = ManagedTensor::from_dlpack(vec![1f32, 2.0].into_dlpack());
+            drop(tensor);
+        });
+        assert_eq!(
+            *messages.0.lock().unwrap(),
+            [
+                "exported tensor",
+                "imported tensor",
+                "released tensor",
+                "deleted tensor"
+            ]
+        );
+    }
+}
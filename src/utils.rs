@@ -1,8 +1,17 @@
+use alloc::{vec, vec::Vec};
+use core::{mem::MaybeUninit, ops::Range};
+#[cfg(feature = "std")]
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+};
+
 pub fn make_contiguous_strides(shape: &[i64]) -> Vec<i64> {
     let rank = shape.len();
     let mut strides = vec![1; rank];
-    for i in (0..rank - 1).rev() {
-        strides[i] = strides[i + 1] * shape[i + 1];
+    // Nothing to do for scalars, whose rank is zero.
+    for i in (1..rank).rev() {
+        strides[i - 1] = strides[i] * shape[i];
     }
     strides
 }
@@ -23,6 +32,177 @@ pub fn is_contiguous(shape: &[i64], strides: &[i64]) -> bool {
     true
 }
 
+/// Run `f`, which is called from an `extern "C"` function, reporting a panic
+/// on stderr instead of letting it unwind into the foreign caller. Without
+/// `std` panics can't be caught, and are expected to abort.
+#[cfg(feature = "std")]
+pub(crate) fn catch_ffi_panic(context: &str, f: impl FnOnce()) {
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+        eprintln!("dlpark: panic in {context}: {}", panic_message(&*payload));
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn catch_ffi_panic(_context: &str, f: impl FnOnce()) {
+    f()
+}
+
+#[cfg(feature = "std")]
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(msg) => msg,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+/// Range of bytes, relative to the first element, spanned by the elements
+/// reachable through `shape` and `strides`. Empty for empty tensors, `None` if
+/// a dim is negative or the range overflows.
+pub fn byte_span(shape: &[i64], strides: Option<&[i64]>, itemsize: usize) -> Option<Range<i64>> {
+    if shape.iter().any(|&dim| dim < 0) {
+        return None;
+    }
+    if shape.contains(&0) {
+        return Some(0..0);
+    }
+    let itemsize = i64::try_from(itemsize).ok()?;
+    // Offsets of the first and last reachable elements.
+    let (mut first, mut last) = (0i64, 0i64);
+    match strides {
+        Some(strides) => {
+            for (&dim, &stride) in shape.iter().zip(strides) {
+                let span = (dim - 1).checked_mul(stride)?;
+                if span < 0 {
+                    first = first.checked_add(span)?;
+                } else {
+                    last = last.checked_add(span)?;
+                }
+            }
+        }
+        None => {
+            last = shape
+                .iter()
+                .try_fold(1i64, |acc, &dim| acc.checked_mul(dim))?
+                - 1
+        }
+    }
+    Some(first.checked_mul(itemsize)?..last.checked_add(1)?.checked_mul(itemsize)?)
+}
+
+/// Copy elements of a strided tensor at `src` into `dst` in row-major order.
+/// `src` doesn't have to be aligned, and large copies may be parallel, see
+/// [`gather_strided_bytes`].
+///
+/// # Safety
+/// Every offset reachable from `src` through `shape` and `strides` must be
+/// valid for reads, and `dst` must hold exactly `shape.iter().product()`
+/// elements.
+pub(crate) unsafe fn copy_strided<A: Copy>(
+    src: *const A,
+    shape: &[i64],
+    strides: &[i64],
+    dst: &mut [MaybeUninit<A>],
+) {
+    let itemsize = core::mem::size_of::<A>();
+    let dst = core::slice::from_raw_parts_mut(dst.as_mut_ptr().cast(), dst.len() * itemsize);
+    gather_strided_bytes(src.cast(), shape, strides, itemsize, dst);
+}
+
+/// [`copy_strided_bytes`], spread over the rayon thread pool along the
+/// outermost axis with the `rayon` feature if `dst` holds at least
+/// [`crate::parallel_copy_threshold`] bytes.
+///
+/// # Safety
+/// Same as [`copy_strided_bytes`].
+pub(crate) unsafe fn gather_strided_bytes(
+    src: *const u8,
+    shape: &[i64],
+    strides: &[i64],
+    itemsize: usize,
+    dst: &mut [MaybeUninit<u8>],
+) {
+    #[cfg(feature = "rayon")]
+    if dst.len() >= crate::parallel::parallel_copy_threshold() {
+        return crate::parallel::par_copy_strided_bytes(src, shape, strides, itemsize, dst);
+    }
+    copy_strided_bytes(src, shape, strides, itemsize, dst);
+}
+
+/// Untyped version of [`copy_strided`] for elements of `itemsize` bytes.
+///
+/// Innermost axes that are laid out contiguously, e.g. the rows of a tensor
+/// sliced along its columns, are copied a whole run at a time.
+///
+/// # Safety
+/// Same as [`copy_strided`], with `dst` holding `itemsize` bytes per element.
+pub(crate) unsafe fn copy_strided_bytes(
+    src: *const u8,
+    shape: &[i64],
+    strides: &[i64],
+    itemsize: usize,
+    dst: &mut [MaybeUninit<u8>],
+) {
+    let mut ndim = shape.len();
+    let mut run = 1;
+    while ndim > 0 && (shape[ndim - 1] == 1 || strides[ndim - 1] == run) {
+        run *= shape[ndim - 1];
+        ndim -= 1;
+    }
+    let run_size = run as usize * itemsize;
+    if run_size == 0 {
+        return;
+    }
+    let mut index = vec![0i64; ndim];
+    let mut offset = 0isize;
+    for chunk in dst.chunks_exact_mut(run_size) {
+        core::ptr::copy_nonoverlapping(
+            src.offset(offset * itemsize as isize),
+            chunk.as_mut_ptr().cast::<u8>(),
+            run_size,
+        );
+        // Increase the multi-index like an odometer, keeping offset in sync.
+        for axis in (0..ndim).rev() {
+            index[axis] += 1;
+            offset += strides[axis] as isize;
+            if index[axis] < shape[axis] {
+                break;
+            }
+            offset -= (strides[axis] * shape[axis]) as isize;
+            index[axis] = 0;
+        }
+    }
+}
+
+/// Call `f` with the offset, in elements, of every element of a strided
+/// tensor in row-major order.
+pub(crate) fn for_each_offset(shape: &[i64], strides: &[i64], mut f: impl FnMut(isize)) {
+    if shape.iter().any(|&dim| dim <= 0) {
+        return;
+    }
+    let mut index = vec![0i64; shape.len()];
+    let mut offset = 0isize;
+    loop {
+        f(offset);
+        // Increase the multi-index like an odometer, keeping offset in sync.
+        let mut axis = shape.len();
+        loop {
+            if axis == 0 {
+                return;
+            }
+            axis -= 1;
+            index[axis] += 1;
+            offset += strides[axis] as isize;
+            if index[axis] < shape[axis] {
+                break;
+            }
+            offset -= (strides[axis] * shape[axis]) as isize;
+            index[axis] = 0;
+        }
+    }
+}
+
 // Generated by copilot.
 #[cfg(test)]
 mod tests {
@@ -41,6 +221,56 @@ mod tests {
     fn test_is_contiguous() {
         let shape = vec![1, 2, 3];
         let strides = vec![6, 3, 1];
-        assert_eq!(is_contiguous(&shape, &strides), true);
+        assert!(is_contiguous(&shape, &strides));
+    }
+
+    #[test]
+    fn test_byte_span() {
+        assert_eq!(byte_span(&[2, 3], None, 4), Some(0..24));
+        assert_eq!(byte_span(&[3, 2], Some(&[1, 3]), 4), Some(0..24));
+        assert_eq!(byte_span(&[2, 3], Some(&[-3, 1]), 1), Some(-3..3));
+        assert_eq!(byte_span(&[2, 0], Some(&[1, 1]), 4), Some(0..0));
+        assert_eq!(byte_span(&[2, -1], None, 4), None);
+        assert_eq!(byte_span(&[i64::MAX, 3], None, 4), None);
+    }
+
+    #[test]
+    fn test_copy_strided() {
+        // Transposed view of [[0, 1, 2], [3, 4, 5]].
+        let src = [0, 1, 2, 3, 4, 5];
+        let mut dst = Vec::with_capacity(6);
+        unsafe {
+            copy_strided(src.as_ptr(), &[3, 2], &[1, 3], dst.spare_capacity_mut());
+            dst.set_len(6);
+        }
+        assert_eq!(dst, vec![0, 3, 1, 4, 2, 5]);
+    }
+
+    #[test]
+    fn test_copy_strided_runs() {
+        // Columns 1..3 of [[0, 1, 2, 3], [4, 5, 6, 7]], rows are contiguous.
+        let src = [0, 1, 2, 3, 4, 5, 6, 7];
+        let mut dst = Vec::with_capacity(4);
+        unsafe {
+            copy_strided(
+                src[1..].as_ptr(),
+                &[2, 1, 2],
+                &[4, 9, 1],
+                dst.spare_capacity_mut(),
+            );
+            dst.set_len(4);
+        }
+        assert_eq!(dst, vec![1, 2, 5, 6]);
+    }
+
+    #[test]
+    fn test_for_each_offset() {
+        let mut offsets = vec![];
+        for_each_offset(&[3, 2], &[1, 3], |offset| offsets.push(offset));
+        assert_eq!(offsets, vec![0, 3, 1, 4, 2, 5]);
+        offsets.clear();
+        for_each_offset(&[], &[], |offset| offsets.push(offset));
+        assert_eq!(offsets, vec![0]);
+        for_each_offset(&[2, 0], &[1, 1], |_| unreachable!());
     }
 }
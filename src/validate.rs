@@ -0,0 +1,431 @@
+//! Sanity checks of DLManagedTensors received from other producers, before
+//! any of their fields are trusted.
+
+use core::{fmt, ptr::NonNull};
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::{
+    device_names::device_type_name,
+    ffi::{self, DataTypeCode, DeviceType},
+    tensor::traits::TensorView,
+    utils::byte_span,
+    ManagedTensor, Ownership,
+};
+
+/// Why a DLManagedTensor was rejected by [`ManagedTensor::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The DLManagedTensor pointer itself is NULL.
+    NullTensor,
+    NegativeNdim(i32),
+    /// The shape pointer is NULL although the tensor has dimensions.
+    NullShape,
+    NegativeDim {
+        axis: usize,
+        dim: i64,
+    },
+    /// The number of elements doesn't fit in `usize`.
+    TooManyElements,
+    /// The data pointer is NULL although the tensor has elements.
+    NullData,
+    /// The strides pointer is the shape pointer.
+    AliasedStrides,
+    UnknownDtypeCode(u8),
+    /// Zero bits or lanes.
+    InvalidDtype {
+        bits: u8,
+        lanes: u16,
+    },
+    UnknownDeviceType(i32),
+    /// Some element lies outside of the buffer of this size in bytes.
+    OutOfBounds {
+        buffer_len: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NullTensor => write!(f, "null tensor"),
+            Self::NegativeNdim(ndim) => write!(f, "negative ndim {ndim}"),
+            Self::NullShape => write!(f, "null shape"),
+            Self::NegativeDim { axis, dim } => write!(f, "negative dim {dim} on axis {axis}"),
+            Self::TooManyElements => write!(f, "number of elements overflows"),
+            Self::NullData => write!(f, "null data for a non-empty tensor"),
+            Self::AliasedStrides => write!(f, "strides alias shape"),
+            Self::UnknownDtypeCode(code) => write!(f, "unknown dtype code {code}"),
+            Self::InvalidDtype { bits, lanes } => {
+                write!(f, "invalid dtype with {bits} bits and {lanes} lanes")
+            }
+            Self::UnknownDeviceType(device_type) => {
+                write!(f, "unknown device type {device_type}")?;
+                match device_type_name(*device_type) {
+                    Some(name) => write!(f, " ({name})"),
+                    None => Ok(()),
+                }
+            }
+            Self::OutOfBounds { buffer_len } => {
+                write!(f, "elements out of bounds of a {buffer_len} bytes buffer")
+            }
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+#[cfg(feature = "std")]
+impl From<ValidationError> for io::Error {
+    fn from(err: ValidationError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Why a valid tensor was rejected by [`ManagedTensor::check_supported`]:
+/// its data can't be viewed as an array of whole-byte elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UnsupportedLayout {
+    /// Elements narrower than a byte, e.g. bit-packed booleans or packed
+    /// 4-bit types, which can't be addressed one by one.
+    SubByte { dtype: ffi::DataType },
+    /// The data holds handles to memory elsewhere, e.g. of a sparse tensor,
+    /// rather than the elements themselves.
+    OpaqueHandle { bits: u8 },
+}
+
+impl fmt::Display for UnsupportedLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SubByte { dtype } => write!(
+                f,
+                "unsupported {:?} elements of {} bits and {} lanes, not a whole number of bytes",
+                dtype.code, dtype.bits, dtype.lanes
+            ),
+            Self::OpaqueHandle { bits } => {
+                write!(f, "unsupported opaque handles of {bits} bits")
+            }
+        }
+    }
+}
+
+impl core::error::Error for UnsupportedLayout {}
+
+#[cfg(feature = "std")]
+impl From<UnsupportedLayout> for io::Error {
+    fn from(err: UnsupportedLayout) -> Self {
+        io::Error::new(io::ErrorKind::Unsupported, err)
+    }
+}
+
+/// Check the fields of `tensor`, reading enums as their raw values so that
+/// invalid discriminants are caught instead of being undefined behavior.
+///
+/// # Safety
+/// `tensor` must point to readable memory of a DLTensor's size, and its shape
+/// to `ndim` readable dims if it isn't NULL.
+unsafe fn validate_dl_tensor(tensor: *const ffi::DLTensor) -> Result<(), ValidationError> {
+    let raw_device_type = *core::ptr::addr_of!((*tensor).device.device_type).cast::<i32>();
+    DeviceType::try_from(raw_device_type)
+        .map_err(|ffi::Unknown(raw)| ValidationError::UnknownDeviceType(raw))?;
+    let raw_code = *core::ptr::addr_of!((*tensor).dtype.code).cast::<u8>();
+    DataTypeCode::try_from(raw_code)
+        .map_err(|ffi::Unknown(raw)| ValidationError::UnknownDtypeCode(raw))?;
+    let tensor = &*tensor;
+    if tensor.dtype.bits == 0 || tensor.dtype.lanes == 0 {
+        return Err(ValidationError::InvalidDtype {
+            bits: tensor.dtype.bits,
+            lanes: tensor.dtype.lanes,
+        });
+    }
+    let ndim =
+        usize::try_from(tensor.ndim).map_err(|_| ValidationError::NegativeNdim(tensor.ndim))?;
+    if ndim > 0 && tensor.shape.is_null() {
+        return Err(ValidationError::NullShape);
+    }
+    if ndim > 0 && tensor.strides == tensor.shape {
+        return Err(ValidationError::AliasedStrides);
+    }
+    let shape: &[i64] = if ndim == 0 {
+        &[]
+    } else {
+        core::slice::from_raw_parts(tensor.shape, ndim)
+    };
+    let mut num_elements = 1usize;
+    for (axis, &dim) in shape.iter().enumerate() {
+        let dim = usize::try_from(dim).map_err(|_| ValidationError::NegativeDim { axis, dim })?;
+        num_elements = num_elements
+            .checked_mul(dim)
+            .ok_or(ValidationError::TooManyElements)?;
+    }
+    if num_elements > 0 && tensor.data.is_null() {
+        return Err(ValidationError::NullData);
+    }
+    Ok(())
+}
+
+/// Check the DLTensor of `managed`.
+///
+/// # Safety
+/// `managed` must point to readable memory of a DLManagedTensor's size, and
+/// its shape as for [`validate_dl_tensor`].
+pub(crate) unsafe fn validate_managed(
+    managed: NonNull<ffi::DLManagedTensor>,
+) -> Result<(), ValidationError> {
+    validate_dl_tensor(core::ptr::addr_of!((*managed.as_ptr()).dl_tensor))
+}
+
+impl ManagedTensor {
+    /// Check that the DLTensor describes a sane tensor. Accessors of a
+    /// tensor from an untrusted producer are only safe to use after this
+    /// succeeded.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        unsafe { validate_dl_tensor(core::ptr::addr_of!((*self.as_ptr()).dl_tensor)) }
+    }
+
+    /// Check that the data can be read as an array of whole-byte elements,
+    /// which all views and copies of this crate assume. Should be called
+    /// after [`ManagedTensor::validate`].
+    pub fn check_supported(&self) -> Result<(), UnsupportedLayout> {
+        let dtype = self.dtype();
+        if dtype.code == ffi::DataTypeCode::OpaqueHandle {
+            return Err(UnsupportedLayout::OpaqueHandle { bits: dtype.bits });
+        }
+        if !(dtype.bits as u32 * dtype.lanes as u32).is_multiple_of(8) {
+            return Err(UnsupportedLayout::SubByte { dtype });
+        }
+        Ok(())
+    }
+
+    /// Check that every element reachable through the shape, strides and byte
+    /// offset lies within the `buffer_len` bytes starting at the data pointer,
+    /// e.g. when the size of the allocation is known from elsewhere. Should be
+    /// called after [`ManagedTensor::validate`].
+    pub fn check_bounds(&self, buffer_len: usize) -> Result<(), ValidationError> {
+        let out_of_bounds = ValidationError::OutOfBounds { buffer_len };
+        let span = byte_span(self.shape(), self.strides(), self.dtype().size())
+            .ok_or(ValidationError::TooManyElements)?;
+        if span.is_empty() {
+            return Ok(());
+        }
+        let offset = i64::try_from(self.byte_offset()).map_err(|_| out_of_bounds)?;
+        let start = offset.checked_add(span.start).ok_or(out_of_bounds)?;
+        let end = offset.checked_add(span.end).ok_or(out_of_bounds)?;
+        match (usize::try_from(start), usize::try_from(end)) {
+            (Ok(_), Ok(end)) if end <= buffer_len => Ok(()),
+            _ => Err(out_of_bounds),
+        }
+    }
+
+    /// Same as [`FromDLPack::from_dlpack`](crate::FromDLPack), but validates
+    /// `src` first. On error `src` stays owned by the caller, its deleter is
+    /// not called.
+    pub fn try_from_dlpack(src: NonNull<ffi::DLManagedTensor>) -> Result<Self, ValidationError> {
+        unsafe { validate_managed(src)? };
+        Ok(Self::new_unchecked(src))
+    }
+
+    /// Wrap a DLManagedTensor handed over through a C API of the caller,
+    /// checking that `ptr` isn't NULL and validating it first. On error the
+    /// tensor stays with the caller, its deleter is not called.
+    ///
+    /// # Safety
+    /// A non-NULL `ptr` must point to a DLManagedTensor as for
+    /// [`ManagedTensor::validate`]. With [`Ownership::Owned`] the caller must
+    /// hand it over, with [`Ownership::Borrowed`] keep it alive as long as
+    /// the returned tensor.
+    pub unsafe fn from_c_ptr(
+        ptr: *mut ffi::DLManagedTensor,
+        ownership: Ownership,
+    ) -> Result<Self, ValidationError> {
+        let src = NonNull::new(ptr).ok_or(ValidationError::NullTensor)?;
+        validate_managed(src)?;
+        Ok(Self::new_unchecked(src).with_ownership(ownership))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+    use crate::ffi::{DataType, Device};
+
+    fn managed(data: *mut std::ffi::c_void, shape: &mut [i64]) -> ffi::DLManagedTensor {
+        ffi::DLManagedTensor {
+            dl_tensor: ffi::DLTensor {
+                data,
+                device: Device::CPU,
+                ndim: shape.len() as i32,
+                dtype: DataType::F32,
+                shape: shape.as_mut_ptr(),
+                strides: ptr::null_mut(),
+                byte_offset: 0,
+            },
+            manager_ctx: ptr::null_mut(),
+            deleter: None,
+        }
+    }
+
+    fn check(managed: &mut ffi::DLManagedTensor) -> Result<(), ValidationError> {
+        ManagedTensor::try_from_dlpack(NonNull::from(managed)).map(drop)
+    }
+
+    #[test]
+    fn bounds() {
+        let mut data = [0f32; 8];
+        let data = data.as_mut_ptr().cast();
+        let mut shape = [2, 3];
+        let mut strides = [4, 1];
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.strides = strides.as_mut_ptr();
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::from(&mut tensor)).unwrap();
+        assert_eq!(tensor.check_bounds(28), Ok(()));
+        assert_eq!(
+            tensor.check_bounds(27),
+            Err(ValidationError::OutOfBounds { buffer_len: 27 })
+        );
+
+        let mut strides = [-4, 1];
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.strides = strides.as_mut_ptr();
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::from(&mut tensor)).unwrap();
+        assert!(tensor.check_bounds(32).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let mut data = [0f32; 6];
+        let data = data.as_mut_ptr().cast();
+        let mut shape = [2, 3];
+        assert_eq!(check(&mut managed(data, &mut shape)), Ok(()));
+        assert_eq!(
+            check(&mut managed(ptr::null_mut(), &mut shape)),
+            Err(ValidationError::NullData)
+        );
+        assert_eq!(check(&mut managed(ptr::null_mut(), &mut [2, 0])), Ok(()));
+        assert_eq!(
+            check(&mut managed(data, &mut [2, -3])),
+            Err(ValidationError::NegativeDim { axis: 1, dim: -3 })
+        );
+        assert_eq!(
+            check(&mut managed(data, &mut [i64::MAX, i64::MAX])),
+            Err(ValidationError::TooManyElements)
+        );
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.ndim = -1;
+        assert_eq!(check(&mut tensor), Err(ValidationError::NegativeNdim(-1)));
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.shape = ptr::null_mut();
+        assert_eq!(check(&mut tensor), Err(ValidationError::NullShape));
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.strides = tensor.dl_tensor.shape;
+        assert_eq!(check(&mut tensor), Err(ValidationError::AliasedStrides));
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.dtype.lanes = 0;
+        assert_eq!(
+            check(&mut tensor),
+            Err(ValidationError::InvalidDtype { bits: 32, lanes: 0 })
+        );
+
+        let mut tensor = managed(data, &mut shape);
+        unsafe {
+            ptr::addr_of_mut!(tensor.dl_tensor.device.device_type)
+                .cast::<i32>()
+                .write(5);
+        }
+        assert_eq!(
+            check(&mut tensor),
+            Err(ValidationError::UnknownDeviceType(5))
+        );
+
+        let mut tensor = managed(data, &mut shape);
+        unsafe {
+            ptr::addr_of_mut!(tensor.dl_tensor.dtype.code)
+                .cast::<u8>()
+                .write(42);
+        }
+        assert_eq!(
+            check(&mut tensor),
+            Err(ValidationError::UnknownDtypeCode(42))
+        );
+    }
+
+    #[cfg(feature = "defensive")]
+    #[test]
+    #[should_panic(expected = "invalid DLManagedTensor: negative dim -3 on axis 1")]
+    fn defensive() {
+        let mut data = [0f32; 6];
+        let mut shape = [2, -3];
+        let mut tensor = managed(data.as_mut_ptr().cast(), &mut shape);
+        ManagedTensor::new(NonNull::from(&mut tensor), Ownership::Owned);
+    }
+
+    #[test]
+    fn c_pointers() {
+        unsafe extern "C" fn deleter(managed: *mut ffi::DLManagedTensor) {
+            (*managed).manager_ctx = ptr::dangling_mut();
+        }
+
+        let mut data = [0f32; 6];
+        let mut shape = [2, 3];
+        let mut tensor = managed(data.as_mut_ptr().cast(), &mut shape);
+        tensor.deleter = Some(deleter);
+        let borrowed = unsafe { ManagedTensor::from_c_ptr(&mut tensor, Ownership::Borrowed) };
+        assert_eq!(borrowed.unwrap().ownership(), Ownership::Borrowed);
+        assert!(tensor.manager_ctx.is_null());
+        let owned = unsafe { ManagedTensor::from_c_ptr(&mut tensor, Ownership::Owned) };
+        drop(owned.unwrap());
+        assert!(!tensor.manager_ctx.is_null());
+
+        let null = unsafe { ManagedTensor::from_c_ptr(ptr::null_mut(), Ownership::Owned) };
+        assert_eq!(null.unwrap_err(), ValidationError::NullTensor);
+        let mut tensor = managed(data.as_mut_ptr().cast(), &mut shape);
+        tensor.dl_tensor.ndim = -2;
+        let invalid = unsafe { ManagedTensor::from_c_ptr(&mut tensor, Ownership::Owned) };
+        assert_eq!(invalid.unwrap_err(), ValidationError::NegativeNdim(-2));
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut data = [0u8; 6];
+        let data = data.as_mut_ptr().cast();
+        let mut shape = [2, 3];
+        let mut tensor = managed(data, &mut shape);
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::from(&mut tensor)).unwrap();
+        assert_eq!(tensor.check_supported(), Ok(()));
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.dtype = DataType {
+            code: ffi::DataTypeCode::Bool,
+            bits: 1,
+            lanes: 1,
+        };
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::from(&mut tensor)).unwrap();
+        assert!(matches!(
+            tensor.check_supported(),
+            Err(UnsupportedLayout::SubByte { dtype }) if dtype.bits == 1
+        ));
+
+        let mut tensor = managed(data, &mut shape);
+        tensor.dl_tensor.dtype.code = ffi::DataTypeCode::OpaqueHandle;
+        tensor.dl_tensor.dtype.bits = 64;
+        let tensor = ManagedTensor::try_from_dlpack(NonNull::from(&mut tensor)).unwrap();
+        assert_eq!(
+            tensor.check_supported(),
+            Err(UnsupportedLayout::OpaqueHandle { bits: 64 })
+        );
+        #[cfg(feature = "std")]
+        {
+            let mut buf = Vec::new();
+            assert_eq!(
+                tensor.write_npy(&mut buf).unwrap_err().kind(),
+                io::ErrorKind::Unsupported
+            );
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Conversions between CPU tensors and JS typed arrays, for exchanging
+//! tensors with ML runtimes in the browser. Enabled by the `wasm-bindgen`
+//! feature, and only usable on `wasm32-unknown-unknown`.
+//!
+//! Views share the linear memory of the wasm module, so growing the memory,
+//! which any allocation may do, detaches them. Copy instead when the array
+//! outlives the current call.
+
+use std::io;
+
+use js_sys::{Float32Array, Uint8Array};
+
+use crate::{
+    ffi::{DataType, DeviceType},
+    tensor::traits::TensorView,
+    Error, LayoutError, ManagedTensor, OwnedTensor, Result,
+};
+
+impl ManagedTensor {
+    /// Copy into a new `Float32Array` in row-major order.
+    pub fn to_float32_array(&self) -> Result<Float32Array> {
+        Ok(Float32Array::from(&*self.as_slice_or_copy::<f32>()?))
+    }
+
+    /// Copy the raw bytes into a new `Uint8Array` in row-major order.
+    pub fn to_uint8_array(&self) -> Result<Uint8Array> {
+        self.check_cpu()?;
+        self.check_supported()?;
+        Ok(Uint8Array::from(&*self.to_contiguous_bytes()))
+    }
+
+    /// `Float32Array` over the data of this tensor, without copying.
+    ///
+    /// # Safety
+    /// The view must not be used after this tensor is dropped or the wasm
+    /// memory grows.
+    pub unsafe fn float32_view(&self) -> Result<Float32Array> {
+        let data = self.try_as_slice::<f32>()?;
+        self.check_contiguous()?;
+        Ok(Float32Array::view(data))
+    }
+
+    /// `Uint8Array` over the raw bytes of this tensor, without copying.
+    ///
+    /// # Safety
+    /// Same as [`ManagedTensor::float32_view`].
+    pub unsafe fn uint8_view(&self) -> Result<Uint8Array> {
+        self.check_cpu()?;
+        self.check_supported()?;
+        self.check_contiguous()?;
+        // Borrowed since the tensor is contiguous.
+        Ok(Uint8Array::view(&self.to_contiguous_bytes()))
+    }
+
+    fn check_cpu(&self) -> Result<()> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(Error::DeviceMismatch {
+                expected: crate::ffi::Device::CPU,
+                found: self.device(),
+            });
+        }
+        Ok(())
+    }
+
+    fn check_contiguous(&self) -> Result<()> {
+        if !self.is_contiguous() {
+            return Err(LayoutError::NotContiguous.into());
+        }
+        Ok(())
+    }
+}
+
+impl OwnedTensor {
+    /// Copy a `Float32Array` holding row-major data of `shape` into a new
+    /// tensor.
+    pub fn from_float32_array(array: &Float32Array, shape: &[i64]) -> Result<Self> {
+        let bytes = Uint8Array::new_with_byte_offset_and_length(
+            &array.buffer(),
+            array.byte_offset(),
+            array.byte_length(),
+        );
+        Self::from_uint8_array(&bytes, shape, DataType::F32)
+    }
+
+    /// Copy a `Uint8Array` holding row-major data of `shape` and `dtype`
+    /// into a new tensor.
+    pub fn from_uint8_array(array: &Uint8Array, shape: &[i64], dtype: DataType) -> Result<Self> {
+        let mut tensor = Self::new_zeroed(shape, dtype)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid shape"))?;
+        if tensor.as_bytes().len() != array.length() as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "array length doesn't match shape and dtype",
+            )
+            .into());
+        }
+        array.copy_to(tensor.as_bytes_mut());
+        Ok(tensor)
+    }
+}
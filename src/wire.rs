@@ -0,0 +1,671 @@
+//! A compact binary envelope for sending tensors over sockets and pipes.
+//!
+//! All integers are little-endian. The layout of version 1 is:
+//!
+//! | field          | size            |                                    |
+//! |----------------|-----------------|------------------------------------|
+//! | magic          | 4               | `b"DLPK"`                          |
+//! | version        | 2               |                                    |
+//! | header flags   | 2               | `WIRE_FLAG_*` values               |
+//! | dlpack flags   | 8               | `DLPACK_FLAG_BITMASK_*` values     |
+//! | dtype          | 4               | code `u8`, bits `u8`, lanes `u16`  |
+//! | device         | 8               | device type `i32`, device id `i32` |
+//! | ndim           | 4               |                                    |
+//! | shape          | 8 * ndim        |                                    |
+//! | strides        | 8 * ndim        | only with [`WIRE_FLAG_STRIDES`]    |
+//! | axis names     | variable        | only with [`WIRE_FLAG_AXIS_NAMES`] |
+//! | payload length | 8               |                                    |
+//! | payload        | payload length  |                                    |
+//! | checksum       | 4               | only with [`WIRE_FLAG_CRC32`]      |
+//!
+//! Without strides the payload is the row-major data of the tensor, in the
+//! byte order given by [`WIRE_FLAG_BIG_ENDIAN`]. The
+//! device records where the tensor came from; decoded tensors always live in
+//! host memory. Header flags unknown to the decoder are rejected, and strided
+//! tensors are bounded by [`WIRE_MAX_EXPANSION`].
+//!
+//! With [`WIRE_FLAG_ZSTD`] the payload length still counts uncompressed
+//! bytes, but the payload is stored as a sequence of blocks, each a `u32`
+//! compressed length followed by a zstd frame of at most
+//! [`WIRE_ZSTD_BLOCK_SIZE`] uncompressed bytes. Blocks are encoded and decoded
+//! one at a time, so neither side needs to buffer the whole compressed
+//! payload.
+//!
+//! With [`WIRE_FLAG_CRC32`] the payload is followed by the CRC32 of its
+//! uncompressed bytes, which the decoder verifies.
+//!
+//! With [`WIRE_FLAG_AXIS_NAMES`] every axis has a name, written as a `u16`
+//! length followed by UTF-8 bytes, see [`ManagerCtx::with_axis_names`].
+//!
+//! [`ManagerCtx::with_axis_names`]: crate::ManagerCtx::with_axis_names
+
+use std::{
+    io::{self, Read, Write},
+    mem::MaybeUninit,
+};
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode, Device, DeviceType},
+    owned_tensor::byte_len,
+    tensor::traits::{FromDLPack, IntoDLPack, TensorView},
+    utils::{byte_span, copy_strided_bytes},
+    ManagedTensor, ManagerCtx, OwnedTensor,
+};
+
+pub const WIRE_MAGIC: &[u8; 4] = b"DLPK";
+pub const WIRE_VERSION: u16 = 1;
+/// Set when the header carries strides describing the payload layout.
+pub const WIRE_FLAG_STRIDES: u16 = 1 << 0;
+/// Set when the payload is big-endian, little-endian otherwise.
+pub const WIRE_FLAG_BIG_ENDIAN: u16 = 1 << 1;
+/// Set when the payload is stored as zstd-compressed blocks.
+pub const WIRE_FLAG_ZSTD: u16 = 1 << 2;
+/// Set when the payload is followed by its CRC32.
+pub const WIRE_FLAG_CRC32: u16 = 1 << 3;
+/// Set when the header carries the names of the axes.
+pub const WIRE_FLAG_AXIS_NAMES: u16 = 1 << 4;
+/// Maximum number of uncompressed bytes in a zstd block.
+pub const WIRE_ZSTD_BLOCK_SIZE: usize = 1 << 20;
+/// Upper bound on `ndim` accepted by the decoder, to bound the header size.
+pub const WIRE_MAX_NDIM: u32 = 1024;
+/// Upper bound on how many times larger than its payload, counted as at
+/// least 4 KiB, a strided tensor may decode to. Zero strides let a few bytes
+/// describe any shape, this bounds what a short message can allocate.
+pub const WIRE_MAX_EXPANSION: u64 = 64;
+
+const WIRE_KNOWN_FLAGS: u16 = WIRE_FLAG_STRIDES
+    | WIRE_FLAG_BIG_ENDIAN
+    | WIRE_FLAG_ZSTD
+    | WIRE_FLAG_CRC32
+    | WIRE_FLAG_AXIS_NAMES;
+
+/// The metadata preceding the payload of an encoded tensor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WireHeader {
+    pub flags: u64,
+    pub dtype: DataType,
+    pub device: Device,
+    pub shape: Vec<i64>,
+    pub strides: Option<Vec<i64>>,
+    /// One name per axis, if any.
+    pub axis_names: Option<Vec<String>>,
+    /// Byte order of the payload.
+    pub endian: Endian,
+    /// Whether the payload is stored as zstd-compressed blocks.
+    pub compressed: bool,
+    /// Whether the payload is followed by its CRC32.
+    pub checksum: bool,
+    /// Length of the uncompressed payload.
+    pub payload_len: u64,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_i64s<R: Read>(reader: &mut R, len: usize) -> io::Result<Vec<i64>> {
+    (0..len)
+        .map(|_| read_array(reader).map(i64::from_le_bytes))
+        .collect()
+}
+
+impl WireHeader {
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        if &read_array::<_, 4>(reader)? != WIRE_MAGIC {
+            return Err(invalid_data("not a dlpark wire message"));
+        }
+        let version = u16::from_le_bytes(read_array(reader)?);
+        if version != WIRE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("unsupported wire format version {version}"),
+            ));
+        }
+        let header_flags = u16::from_le_bytes(read_array(reader)?);
+        if header_flags & !WIRE_KNOWN_FLAGS != 0 {
+            return Err(invalid_data("unknown header flags"));
+        }
+        let flags = u64::from_le_bytes(read_array(reader)?);
+
+        let [code, bits, lanes @ ..] = read_array::<_, 4>(reader)?;
+        let code = DataTypeCode::try_from(code).map_err(|_| invalid_data("unknown dtype code"))?;
+        let dtype = DataType::builder()
+            .code(code)
+            .bits(bits)
+            .lanes(u16::from_le_bytes(lanes))
+            .build()
+            .map_err(|err| invalid_data(&format!("invalid dtype: {err}")))?;
+        let device_type = DeviceType::try_from(i32::from_le_bytes(read_array(reader)?))
+            .map_err(|_| invalid_data("unknown device type"))?;
+        let device_id = i32::from_le_bytes(read_array(reader)?);
+
+        let ndim = u32::from_le_bytes(read_array(reader)?);
+        if ndim > WIRE_MAX_NDIM {
+            return Err(invalid_data("too many dimensions"));
+        }
+        let shape = read_i64s(reader, ndim as usize)?;
+        let strides = if header_flags & WIRE_FLAG_STRIDES != 0 {
+            Some(read_i64s(reader, ndim as usize)?)
+        } else {
+            None
+        };
+        let axis_names = if header_flags & WIRE_FLAG_AXIS_NAMES != 0 {
+            let names = (0..ndim).map(|_| {
+                let len = u16::from_le_bytes(read_array(reader)?);
+                let mut name = vec![0; len.into()];
+                reader.read_exact(&mut name)?;
+                String::from_utf8(name).map_err(|_| invalid_data("axis name is not UTF-8"))
+            });
+            Some(names.collect::<io::Result<_>>()?)
+        } else {
+            None
+        };
+        let endian = if header_flags & WIRE_FLAG_BIG_ENDIAN != 0 {
+            Endian::Big
+        } else {
+            Endian::Little
+        };
+        let compressed = header_flags & WIRE_FLAG_ZSTD != 0;
+        let checksum = header_flags & WIRE_FLAG_CRC32 != 0;
+        let payload_len = u64::from_le_bytes(read_array(reader)?);
+
+        Ok(Self {
+            flags,
+            dtype,
+            device: Device {
+                device_type,
+                device_id,
+            },
+            shape,
+            strides,
+            axis_names,
+            endian,
+            compressed,
+            checksum,
+            payload_len,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut header_flags = 0;
+        if self.strides.is_some() {
+            header_flags |= WIRE_FLAG_STRIDES;
+        }
+        if self.endian == Endian::Big {
+            header_flags |= WIRE_FLAG_BIG_ENDIAN;
+        }
+        if self.compressed {
+            header_flags |= WIRE_FLAG_ZSTD;
+        }
+        if self.checksum {
+            header_flags |= WIRE_FLAG_CRC32;
+        }
+        if let Some(names) = &self.axis_names {
+            if names.len() != self.shape.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "axis names don't match the number of axes",
+                ));
+            }
+            if names.iter().any(|name| name.len() > u16::MAX.into()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "axis name is too long",
+                ));
+            }
+            header_flags |= WIRE_FLAG_AXIS_NAMES;
+        }
+        writer.write_all(WIRE_MAGIC)?;
+        writer.write_all(&WIRE_VERSION.to_le_bytes())?;
+        writer.write_all(&header_flags.to_le_bytes())?;
+        writer.write_all(&self.flags.to_le_bytes())?;
+        writer.write_all(&[self.dtype.code as u8, self.dtype.bits])?;
+        writer.write_all(&self.dtype.lanes.to_le_bytes())?;
+        writer.write_all(&(self.device.device_type as i32).to_le_bytes())?;
+        writer.write_all(&self.device.device_id.to_le_bytes())?;
+        writer.write_all(&(self.shape.len() as u32).to_le_bytes())?;
+        for dim in self.shape.iter().chain(self.strides.iter().flatten()) {
+            writer.write_all(&dim.to_le_bytes())?;
+        }
+        for name in self.axis_names.iter().flatten() {
+            writer.write_all(&(name.len() as u16).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+        }
+        writer.write_all(&self.payload_len.to_le_bytes())
+    }
+
+    /// Number of payload bytes spanned by `shape` and `strides`, or `None` if
+    /// they are negative or overflow.
+    fn required_payload_len(&self) -> Option<u64> {
+        let span = byte_span(&self.shape, self.strides.as_deref(), self.dtype.size())?;
+        if span.start < 0 {
+            return None;
+        }
+        u64::try_from(span.end).ok()
+    }
+}
+
+/// Options for [`ManagedTensor::encode_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Compress the payload with zstd at this level. Requires the `zstd`
+    /// feature.
+    pub zstd_level: Option<i32>,
+    /// Append the CRC32 of the payload.
+    pub checksum: bool,
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "compressed payloads require the zstd feature",
+    )
+}
+
+/// Hashes everything read through it, if enabled.
+struct Crc32Reader<R> {
+    reader: R,
+    hasher: Option<crc32fast::Hasher>,
+}
+
+impl<R: Read> Read for Crc32Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+/// Reads the uncompressed payload out of a sequence of zstd blocks.
+#[cfg(feature = "zstd")]
+struct ZstdBlocks<R> {
+    reader: R,
+    remaining: u64,
+    block: Vec<u8>,
+    pos: usize,
+    decompressor: zstd::bulk::Decompressor<'static>,
+}
+
+#[cfg(feature = "zstd")]
+impl<R: Read> ZstdBlocks<R> {
+    fn new(reader: R, payload_len: u64) -> io::Result<Self> {
+        Ok(Self {
+            reader,
+            remaining: payload_len,
+            block: Vec::new(),
+            pos: 0,
+            decompressor: zstd::bulk::Decompressor::new()?,
+        })
+    }
+
+    fn next_block(&mut self) -> io::Result<()> {
+        let len = u32::from_le_bytes(read_array(&mut self.reader)?) as usize;
+        if len > zstd::zstd_safe::compress_bound(WIRE_ZSTD_BLOCK_SIZE) {
+            return Err(invalid_data("zstd block is too large"));
+        }
+        let mut compressed = vec![0u8; len];
+        self.reader.read_exact(&mut compressed)?;
+        let capacity = self.remaining.min(WIRE_ZSTD_BLOCK_SIZE as u64) as usize;
+        self.block = self.decompressor.decompress(&compressed, capacity)?;
+        if self.block.is_empty() {
+            return Err(invalid_data("empty zstd block"));
+        }
+        self.remaining -= self.block.len() as u64;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl<R: Read> Read for ZstdBlocks<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.block.len() {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            self.next_block()?;
+        }
+        let n = buf.len().min(self.block.len() - self.pos);
+        buf[..n].copy_from_slice(&self.block[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl ManagedTensor {
+    fn check_encodable(&self) -> io::Result<WireHeader> {
+        if self.device().device_type != DeviceType::Cpu {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only cpu tensors can be encoded",
+            ));
+        }
+        self.check_supported()?;
+        Ok(WireHeader {
+            flags: 0,
+            dtype: self.dtype(),
+            device: self.device(),
+            shape: self.shape().to_vec(),
+            strides: None,
+            axis_names: self.axis_names().map(<[String]>::to_vec),
+            endian: Endian::NATIVE,
+            compressed: false,
+            checksum: false,
+            payload_len: self.data_size() as u64,
+        })
+    }
+
+    /// Encode a CPU tensor as a [wire](crate::wire) message. The payload is
+    /// always written in row-major order.
+    pub fn encode_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.encode_with(writer, EncodeOptions::default())
+    }
+
+    /// Encode a CPU tensor as a [wire](crate::wire) message with a
+    /// zstd-compressed payload, using the given compression `level`.
+    #[cfg(feature = "zstd")]
+    pub fn encode_compressed_to<W: Write>(&self, writer: W, level: i32) -> io::Result<()> {
+        self.encode_with(
+            writer,
+            EncodeOptions {
+                zstd_level: Some(level),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Encode a CPU tensor as a [wire](crate::wire) message.
+    pub fn encode_with<W: Write>(&self, mut writer: W, options: EncodeOptions) -> io::Result<()> {
+        let header = WireHeader {
+            compressed: options.zstd_level.is_some(),
+            checksum: options.checksum,
+            ..self.check_encodable()?
+        };
+        #[cfg(not(feature = "zstd"))]
+        if header.compressed {
+            return Err(zstd_unsupported());
+        }
+        let data = self.to_contiguous_bytes();
+        header.write_to(&mut writer)?;
+        match options.zstd_level {
+            None => writer.write_all(&data)?,
+            #[cfg(feature = "zstd")]
+            Some(level) => {
+                let mut compressor = zstd::bulk::Compressor::new(level)?;
+                for block in data.chunks(WIRE_ZSTD_BLOCK_SIZE) {
+                    let block = compressor.compress(block)?;
+                    writer.write_all(&(block.len() as u32).to_le_bytes())?;
+                    writer.write_all(&block)?;
+                }
+            }
+            #[cfg(not(feature = "zstd"))]
+            Some(_) => unreachable!(),
+        }
+        if options.checksum {
+            writer.write_all(&crc32fast::hash(&data).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Decode a [wire](crate::wire) message into a new CPU tensor.
+    pub fn decode_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let header = WireHeader::read_from(&mut reader)?;
+        let required = header
+            .required_payload_len()
+            .ok_or_else(|| invalid_data("invalid shape or strides"))?;
+        if required > header.payload_len {
+            return Err(invalid_data("payload is too short for shape and strides"));
+        }
+        let payload: Box<dyn Read + '_> = if header.compressed {
+            #[cfg(feature = "zstd")]
+            {
+                Box::new(ZstdBlocks::new(&mut reader, header.payload_len)?)
+            }
+            #[cfg(not(feature = "zstd"))]
+            return Err(zstd_unsupported());
+        } else {
+            Box::new(reader.by_ref().take(header.payload_len))
+        };
+        let mut payload = Crc32Reader {
+            reader: payload,
+            hasher: header.checksum.then(crc32fast::Hasher::new),
+        };
+        let mut tensor = match &header.strides {
+            None => {
+                let tensor = OwnedTensor::read_from(&mut payload, &header.shape, header.dtype)?;
+                // Skip any padding so the stream ends up after the message.
+                io::copy(&mut payload, &mut io::sink())?;
+                tensor
+            }
+            Some(strides) => {
+                let len = byte_len(&header.shape, header.dtype)
+                    .ok_or_else(|| invalid_data("invalid shape"))?;
+                if len as u64
+                    > header
+                        .payload_len
+                        .max(4096)
+                        .saturating_mul(WIRE_MAX_EXPANSION)
+                {
+                    return Err(invalid_data("strided tensor is too large for its payload"));
+                }
+                // Read first: with zero strides the shape may still be larger
+                // than the payload.
+                let mut data = Vec::new();
+                payload.read_to_end(&mut data)?;
+                if data.len() as u64 != header.payload_len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                let mut tensor = OwnedTensor::try_new_zeroed(&header.shape, header.dtype)?;
+                let itemsize = header.dtype.size();
+                let dst = tensor.as_bytes_mut();
+                // Safe since every reachable offset is within the payload, and
+                // initialized bytes are valid `MaybeUninit`s.
+                unsafe {
+                    let dst = &mut *(dst as *mut [u8] as *mut [MaybeUninit<u8>]);
+                    copy_strided_bytes(data.as_ptr(), &header.shape, strides, itemsize, dst);
+                }
+                tensor
+            }
+        };
+        if let Some(hasher) = payload.hasher.take() {
+            drop(payload);
+            let expected = u32::from_le_bytes(read_array(&mut reader)?);
+            if hasher.finalize() != expected {
+                return Err(invalid_data("payload checksum mismatch"));
+            }
+        }
+        convert_byte_order(
+            tensor.as_bytes_mut(),
+            header.dtype,
+            header.endian,
+            Endian::NATIVE,
+        );
+        let ctx = ManagerCtx::new(tensor);
+        let ctx = match header.axis_names {
+            // One name per axis, as read.
+            Some(names) => ctx.with_axis_names(names)?,
+            None => ctx,
+        };
+        Ok(Self::from_dlpack(ctx.into_dlpack()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let v: Vec<f64> = (0..6).map(|x| x as f64).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let mut buf = Vec::new();
+        tensor.encode_to(&mut buf).unwrap();
+        assert_eq!(buf.len(), 32 + 8 + 8 + 6 * 8);
+
+        let tensor = ManagedTensor::decode_from(buf.as_slice()).unwrap();
+        assert_eq!(tensor.shape(), &[6]);
+        assert_eq!(tensor.dtype(), DataType::F64);
+        assert_eq!(tensor.as_slice::<f64>(), &v[..]);
+    }
+
+    #[test]
+    fn axis_names() {
+        let ctx = ManagerCtx::new(vec![0u8; 6])
+            .with_axis_names(["time"])
+            .unwrap();
+        let tensor = ManagedTensor::from_dlpack(ctx.into_dlpack());
+        let mut buf = Vec::new();
+        tensor.encode_to(&mut buf).unwrap();
+        let tensor = ManagedTensor::decode_from(buf.as_slice()).unwrap();
+        assert_eq!(tensor.axis_names(), Some(["time".to_string()].as_slice()));
+        assert_eq!(tensor.as_slice::<u8>(), &[0; 6]);
+    }
+
+    #[test]
+    fn strided_payload() {
+        let header = WireHeader {
+            flags: 0,
+            dtype: DataType::U8,
+            device: Device::CPU,
+            shape: vec![2, 2],
+            strides: Some(vec![1, 2]),
+            axis_names: None,
+            endian: Endian::NATIVE,
+            compressed: false,
+            checksum: false,
+            payload_len: 4,
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        buf.extend([0, 1, 2, 3]);
+        let tensor = ManagedTensor::decode_from(buf.as_slice()).unwrap();
+        assert_eq!(tensor.as_slice::<u8>(), &[0, 2, 1, 3]);
+
+        // Strides reaching beyond the payload are rejected.
+        let header = WireHeader {
+            strides: Some(vec![4, 1]),
+            ..header
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        buf.extend([0, 1, 2, 3]);
+        assert!(ManagedTensor::decode_from(buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn big_endian_payload() {
+        let header = WireHeader {
+            flags: 0,
+            dtype: DataType::U32,
+            device: Device::CPU,
+            shape: vec![1],
+            strides: None,
+            axis_names: None,
+            endian: Endian::Big,
+            compressed: false,
+            checksum: false,
+            payload_len: 4,
+        };
+        let mut buf = Vec::new();
+        header.write_to(&mut buf).unwrap();
+        buf.extend(7u32.to_be_bytes());
+        let tensor = ManagedTensor::decode_from(buf.as_slice()).unwrap();
+        assert_eq!(tensor.as_slice::<u32>(), &[7]);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn compressed_round_trip() {
+        let v: Vec<f32> = (0..WIRE_ZSTD_BLOCK_SIZE / 2)
+            .map(|x| (x % 7) as f32)
+            .collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        let mut buf = Vec::new();
+        tensor.encode_compressed_to(&mut buf, 3).unwrap();
+        assert!(buf.len() < tensor.data_size() / 10);
+
+        // Trailing data after the message is left unread.
+        buf.push(42);
+        let mut reader = buf.as_slice();
+        let decoded = ManagedTensor::decode_from(&mut reader).unwrap();
+        assert_eq!(decoded.as_slice::<f32>(), &v[..]);
+        assert_eq!(reader, &[42]);
+    }
+
+    #[test]
+    fn checksum() {
+        let tensor = ManagedTensor::from_dlpack(vec![1u16, 2, 3].into_dlpack());
+        let options = EncodeOptions {
+            checksum: true,
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        tensor.encode_with(&mut buf, options).unwrap();
+        let decoded = ManagedTensor::decode_from(buf.as_slice()).unwrap();
+        assert_eq!(decoded.as_slice::<u16>(), &[1, 2, 3]);
+
+        // Flip a payload bit.
+        let payload_start = buf.len() - 4 - 6;
+        buf[payload_start] ^= 1;
+        let err = ManagedTensor::decode_from(buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn bad_magic() {
+        assert!(ManagedTensor::decode_from(&b"NOPE"[..]).is_err());
+    }
+
+    #[test]
+    fn forged_shape() {
+        let mut buf = Vec::new();
+        ManagedTensor::from_dlpack(vec![1u8].into_dlpack())
+            .encode_to(&mut buf)
+            .unwrap();
+        let mut header = WireHeader::read_from(&mut buf.as_slice()).unwrap();
+
+        // Fails reading instead of allocating the claimed size.
+        header.shape = vec![1 << 40];
+        header.payload_len = 1 << 40;
+        let mut forged = Vec::new();
+        header.write_to(&mut forged).unwrap();
+        forged.push(1);
+        let err = ManagedTensor::decode_from(forged.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // Broadcasting one byte is fine, up to a bound.
+        header.strides = Some(vec![0]);
+        header.payload_len = 1;
+        for (len, ok) in [(1000, true), (1 << 30, false), (1 << 60, false)] {
+            header.shape = vec![len];
+            let mut forged = Vec::new();
+            header.write_to(&mut forged).unwrap();
+            forged.push(1);
+            match ManagedTensor::decode_from(forged.as_slice()) {
+                Ok(tensor) => assert!(ok && tensor.as_slice::<u8>().iter().all(|&x| x == 1)),
+                Err(err) => assert!(!ok && err.kind() == io::ErrorKind::InvalidData),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_flags() {
+        let mut buf = Vec::new();
+        ManagedTensor::from_dlpack(vec![1u8].into_dlpack())
+            .encode_to(&mut buf)
+            .unwrap();
+        buf[7] |= 0x80;
+        let err = ManagedTensor::decode_from(buf.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
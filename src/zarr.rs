@@ -0,0 +1,645 @@
+//! Reading [Zarr](https://zarr.dev) v2 and v3 arrays from local directory
+//! stores.
+//!
+//! [`ZarrArray::open`] only parses the array metadata. Chunks are read on
+//! demand with [`ZarrArray::read_chunk`], or all at once with
+//! [`ZarrArray::read`]. Chunks missing from the store are filled with the
+//! fill value of the array. Uncompressed chunks are always supported, zstd
+//! compressed chunks need the `zstd` feature.
+
+#[cfg(feature = "zstd")]
+use std::io::Read;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::{
+    endian::{convert_byte_order, Endian},
+    ffi::{DataType, DataTypeCode},
+    npy::{descr_to_dtype, fortran_to_c},
+    owned_tensor::byte_len,
+    tensor::traits::{FromDLPack, IntoDLPack},
+    ManagedTensor, OwnedTensor,
+};
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn unsupported(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, msg.into())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compressor {
+    None,
+    Zstd,
+}
+
+/// How chunk indices are turned into keys of the store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChunkKeys {
+    /// `Some("c")` for the default v3 encoding.
+    prefix: Option<&'static str>,
+    separator: char,
+}
+
+impl ChunkKeys {
+    fn key(&self, index: &[u64]) -> String {
+        let mut parts: Vec<String> = self.prefix.iter().map(|p| p.to_string()).collect();
+        parts.extend(index.iter().map(u64::to_string));
+        if parts.is_empty() {
+            // A zero-dimensional array stored with the v2 encoding.
+            parts.push("0".to_string());
+        }
+        parts.join(&self.separator.to_string())
+    }
+}
+
+/// A Zarr array in a local directory store.
+#[derive(Debug, Clone)]
+pub struct ZarrArray {
+    path: PathBuf,
+    shape: Vec<i64>,
+    chunk_shape: Vec<i64>,
+    /// Size in bytes of a whole chunk.
+    chunk_len: usize,
+    dtype: DataType,
+    endian: Endian,
+    fortran_order: bool,
+    compressor: Compressor,
+    keys: ChunkKeys,
+    /// One element of the fill value in native byte order.
+    fill: Vec<u8>,
+}
+
+fn get<'a>(meta: &'a Value, key: &str) -> io::Result<&'a Value> {
+    meta.get(key)
+        .ok_or_else(|| invalid_data(format!("missing `{key}` in zarr metadata")))
+}
+
+fn dims(value: &Value) -> io::Result<Vec<i64>> {
+    value
+        .as_array()
+        .and_then(|dims| {
+            dims.iter()
+                .map(|dim| dim.as_u64().and_then(|dim| i64::try_from(dim).ok()))
+                .collect()
+        })
+        .ok_or_else(|| invalid_data("invalid dimensions in zarr metadata"))
+}
+
+fn v3_dtype(name: &str) -> Option<DataType> {
+    let dtype = match name {
+        "bool" => DataType::BOOL,
+        "int8" => DataType::I8,
+        "int16" => DataType::I16,
+        "int32" => DataType::I32,
+        "int64" => DataType::I64,
+        "uint8" => DataType::U8,
+        "uint16" => DataType::U16,
+        "uint32" => DataType::U32,
+        "uint64" => DataType::U64,
+        "float16" => DataType::F16,
+        "bfloat16" => DataType::BF16,
+        "float32" => DataType::F32,
+        "float64" => DataType::F64,
+        "complex64" => (DataTypeCode::Complex, 64, 1).into(),
+        "complex128" => (DataTypeCode::Complex, 128, 1).into(),
+        _ => return None,
+    };
+    Some(dtype)
+}
+
+/// Encode a single real fill value in native byte order.
+fn scalar_bytes(value: &Value, code: DataTypeCode, bits: u8) -> Option<Vec<u8>> {
+    let float = || -> Option<f64> {
+        match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => match s.as_str() {
+                "NaN" => Some(f64::NAN),
+                "Infinity" => Some(f64::INFINITY),
+                "-Infinity" => Some(f64::NEG_INFINITY),
+                _ => None,
+            },
+            _ => None,
+        }
+    };
+    // Hex strings give the raw bits of floats in v3.
+    if let Some(hex) = value.as_str().and_then(|s| s.strip_prefix("0x")) {
+        let raw = u64::from_str_radix(hex, 16).ok()?;
+        let size = bits as usize / 8;
+        if size > 8 {
+            return None;
+        }
+        let mut bytes = raw.to_le_bytes()[..size].to_vec();
+        convert_byte_order(
+            &mut bytes,
+            (code, bits, 1).into(),
+            Endian::Little,
+            Endian::NATIVE,
+        );
+        return Some(bytes);
+    }
+    let bytes = match (code, bits) {
+        (DataTypeCode::Bool, 8) => vec![value.as_bool()? as u8],
+        (DataTypeCode::Int, 8) => (value.as_i64()? as i8).to_ne_bytes().to_vec(),
+        (DataTypeCode::Int, 16) => (value.as_i64()? as i16).to_ne_bytes().to_vec(),
+        (DataTypeCode::Int, 32) => (value.as_i64()? as i32).to_ne_bytes().to_vec(),
+        (DataTypeCode::Int, 64) => value.as_i64()?.to_ne_bytes().to_vec(),
+        (DataTypeCode::UInt, 8) => (value.as_u64()? as u8).to_ne_bytes().to_vec(),
+        (DataTypeCode::UInt, 16) => (value.as_u64()? as u16).to_ne_bytes().to_vec(),
+        (DataTypeCode::UInt, 32) => (value.as_u64()? as u32).to_ne_bytes().to_vec(),
+        (DataTypeCode::UInt, 64) => value.as_u64()?.to_ne_bytes().to_vec(),
+        (DataTypeCode::Float, 32) => (float()? as f32).to_ne_bytes().to_vec(),
+        (DataTypeCode::Float, 64) => float()?.to_ne_bytes().to_vec(),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Encode the fill value of an array, falling back to zeros for values that
+/// can't be represented.
+fn fill_bytes(value: &Value, dtype: DataType) -> Vec<u8> {
+    let fill = match (dtype.code, value) {
+        (_, Value::Null) => None,
+        (DataTypeCode::Complex, Value::Array(parts)) if parts.len() == 2 => {
+            let re = scalar_bytes(&parts[0], DataTypeCode::Float, dtype.bits / 2);
+            let im = scalar_bytes(&parts[1], DataTypeCode::Float, dtype.bits / 2);
+            re.zip(im).map(|(re, im)| [re, im].concat())
+        }
+        (code, value) => scalar_bytes(value, code, dtype.bits),
+    };
+    fill.filter(|fill| fill.len() == dtype.size())
+        .unwrap_or_else(|| vec![0; dtype.size()])
+}
+
+impl ZarrArray {
+    /// Open the array stored in the directory `path`, using `zarr.json` for
+    /// v3 and `.zarray` for v2.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        match fs::read(path.join("zarr.json")) {
+            Ok(meta) => Self::from_v3(path, &serde_json::from_slice(&meta)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let meta = fs::read(path.join(".zarray"))?;
+                Self::from_v2(path, &serde_json::from_slice(&meta)?)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn from_v2(path: &Path, meta: &Value) -> io::Result<Self> {
+        if get(meta, "zarr_format")?.as_u64() != Some(2) {
+            return Err(unsupported("unsupported zarr format"));
+        }
+        let descr = get(meta, "dtype")?
+            .as_str()
+            .ok_or_else(|| unsupported("structured zarr dtypes are not supported"))?;
+        let (dtype, endian) = descr_to_dtype(descr)
+            .ok_or_else(|| unsupported(format!("unsupported zarr dtype {descr}")))?;
+        let compressor = match get(meta, "compressor")? {
+            Value::Null => Compressor::None,
+            c if c.get("id").and_then(Value::as_str) == Some("zstd") => Compressor::Zstd,
+            c => return Err(unsupported(format!("unsupported zarr compressor {c}"))),
+        };
+        if !meta.get("filters").is_none_or(Value::is_null) {
+            return Err(unsupported("zarr filters are not supported"));
+        }
+        let fortran_order = match get(meta, "order")?.as_str() {
+            Some("C") => false,
+            Some("F") => true,
+            _ => return Err(invalid_data("invalid order in zarr metadata")),
+        };
+        let separator = match meta.get("dimension_separator").and_then(Value::as_str) {
+            None | Some(".") => '.',
+            Some("/") => '/',
+            Some(_) => return Err(invalid_data("invalid zarr dimension separator")),
+        };
+        Self::new(
+            path,
+            dims(get(meta, "shape")?)?,
+            dims(get(meta, "chunks")?)?,
+            dtype,
+            endian,
+            fortran_order,
+            compressor,
+            ChunkKeys {
+                prefix: None,
+                separator,
+            },
+            get(meta, "fill_value")?,
+        )
+    }
+
+    fn from_v3(path: &Path, meta: &Value) -> io::Result<Self> {
+        if get(meta, "zarr_format")?.as_u64() != Some(3) {
+            return Err(unsupported("unsupported zarr format"));
+        }
+        if get(meta, "node_type")?.as_str() != Some("array") {
+            return Err(invalid_data("zarr node is not an array"));
+        }
+        let name = get(meta, "data_type")?
+            .as_str()
+            .ok_or_else(|| unsupported("unsupported zarr data type"))?;
+        let dtype =
+            v3_dtype(name).ok_or_else(|| unsupported(format!("unsupported zarr dtype {name}")))?;
+        let shape = dims(get(meta, "shape")?)?;
+
+        let grid = get(meta, "chunk_grid")?;
+        if grid.get("name").and_then(Value::as_str) != Some("regular") {
+            return Err(unsupported("only regular zarr chunk grids are supported"));
+        }
+        let chunk_shape = dims(get(get(grid, "configuration")?, "chunk_shape")?)?;
+
+        let encoding = get(meta, "chunk_key_encoding")?;
+        let separator = encoding
+            .get("configuration")
+            .and_then(|c| c.get("separator"))
+            .and_then(Value::as_str);
+        let keys = match encoding.get("name").and_then(Value::as_str) {
+            Some("default") => ChunkKeys {
+                prefix: Some("c"),
+                separator: if separator == Some(".") { '.' } else { '/' },
+            },
+            Some("v2") => ChunkKeys {
+                prefix: None,
+                separator: if separator == Some("/") { '/' } else { '.' },
+            },
+            _ => return Err(unsupported("unsupported zarr chunk key encoding")),
+        };
+
+        let mut endian = Endian::Little;
+        let mut fortran_order = false;
+        let mut compressor = Compressor::None;
+        let codecs = get(meta, "codecs")?
+            .as_array()
+            .ok_or_else(|| invalid_data("invalid codecs in zarr metadata"))?;
+        for codec in codecs {
+            let config = codec.get("configuration");
+            match codec.get("name").and_then(Value::as_str) {
+                Some("bytes") => {
+                    if config.and_then(|c| c.get("endian")).and_then(Value::as_str) == Some("big") {
+                        endian = Endian::Big;
+                    }
+                }
+                Some("transpose") => {
+                    let order = config
+                        .and_then(|c| c.get("order"))
+                        .map(dims)
+                        .transpose()?
+                        .unwrap_or_default();
+                    let reversed: Vec<i64> = (0..shape.len() as i64).rev().collect();
+                    if order != reversed {
+                        return Err(unsupported("only reversing zarr transposes are supported"));
+                    }
+                    fortran_order = true;
+                }
+                Some("zstd") => compressor = Compressor::Zstd,
+                _ => return Err(unsupported(format!("unsupported zarr codec {codec}"))),
+            }
+        }
+
+        Self::new(
+            path,
+            shape,
+            chunk_shape,
+            dtype,
+            endian,
+            fortran_order,
+            compressor,
+            keys,
+            get(meta, "fill_value")?,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        path: &Path,
+        shape: Vec<i64>,
+        chunk_shape: Vec<i64>,
+        dtype: DataType,
+        endian: Endian,
+        fortran_order: bool,
+        compressor: Compressor,
+        keys: ChunkKeys,
+        fill_value: &Value,
+    ) -> io::Result<Self> {
+        if chunk_shape.len() != shape.len() || chunk_shape.contains(&0) {
+            return Err(invalid_data("invalid zarr chunk shape"));
+        }
+        // Both come from the metadata, they must at least describe buffers
+        // that could be allocated.
+        if byte_len(&shape, dtype).is_none() {
+            return Err(invalid_data("zarr array is too large"));
+        }
+        let chunk_len = byte_len(&chunk_shape, dtype)
+            .ok_or_else(|| invalid_data("zarr chunks are too large"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            shape,
+            chunk_shape,
+            chunk_len,
+            dtype,
+            endian,
+            fortran_order,
+            compressor,
+            keys,
+            fill: fill_bytes(fill_value, dtype),
+        })
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn chunk_shape(&self) -> &[i64] {
+        &self.chunk_shape
+    }
+
+    pub fn dtype(&self) -> DataType {
+        self.dtype
+    }
+
+    /// Number of chunks along each dimension.
+    pub fn grid_shape(&self) -> Vec<u64> {
+        self.shape
+            .iter()
+            .zip(&self.chunk_shape)
+            .map(|(&dim, &chunk)| (dim as u64).div_ceil(chunk as u64))
+            .collect()
+    }
+
+    /// Indices of all chunks of the array, in row-major order.
+    pub fn chunk_indices(&self) -> impl Iterator<Item = Vec<u64>> {
+        let grid = self.grid_shape();
+        let count = grid.iter().product::<u64>();
+        (0..count).map(move |mut i| {
+            let mut index = vec![0; grid.len()];
+            for (idx, &dim) in index.iter_mut().zip(&grid).rev() {
+                *idx = i % dim;
+                i /= dim;
+            }
+            index
+        })
+    }
+
+    /// Read a whole chunk as stored, in row-major order and native byte
+    /// order.
+    fn read_raw_chunk(&self, index: &[u64]) -> io::Result<OwnedTensor> {
+        let data = match fs::read(self.path.join(self.keys.key(index))) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let mut chunk = OwnedTensor::try_new_zeroed(&self.chunk_shape, self.dtype)?;
+                for element in chunk.as_bytes_mut().chunks_exact_mut(self.fill.len()) {
+                    element.copy_from_slice(&self.fill);
+                }
+                return Ok(chunk);
+            }
+            Err(err) => return Err(err),
+        };
+        let data = match self.compressor {
+            Compressor::None => data,
+            #[cfg(feature = "zstd")]
+            Compressor::Zstd => {
+                // Stops right after the expected size, which it mustn't exceed.
+                let mut decoded = Vec::new();
+                zstd::stream::read::Decoder::new(data.as_slice())?
+                    .take(self.chunk_len as u64 + 1)
+                    .read_to_end(&mut decoded)?;
+                decoded
+            }
+            #[cfg(not(feature = "zstd"))]
+            Compressor::Zstd => {
+                return Err(unsupported(
+                    "zstd compressed chunks require the zstd feature",
+                ))
+            }
+        };
+        if data.len() != self.chunk_len {
+            return Err(invalid_data("zarr chunk has the wrong size"));
+        }
+        let data = if self.fortran_order {
+            fortran_to_c(&data, &self.chunk_shape, self.dtype.size())
+        } else {
+            data
+        };
+        let mut chunk = OwnedTensor::try_new_zeroed(&self.chunk_shape, self.dtype)?;
+        chunk.as_bytes_mut().copy_from_slice(&data);
+        convert_byte_order(
+            chunk.as_bytes_mut(),
+            self.dtype,
+            self.endian,
+            Endian::NATIVE,
+        );
+        Ok(chunk)
+    }
+
+    /// Origin and extent of a chunk, clipped to the bounds of the array.
+    fn chunk_region(&self, index: &[u64]) -> io::Result<(Vec<i64>, Vec<i64>)> {
+        if index.len() != self.shape.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk index has the wrong number of dimensions",
+            ));
+        }
+        let mut origin = Vec::with_capacity(index.len());
+        let mut extent = Vec::with_capacity(index.len());
+        for ((&i, &dim), &chunk) in index.iter().zip(&self.shape).zip(&self.chunk_shape) {
+            let start = i64::try_from(i)
+                .ok()
+                .and_then(|i| i.checked_mul(chunk))
+                .filter(|&start| start < dim)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "chunk index out of bounds")
+                })?;
+            origin.push(start);
+            extent.push(chunk.min(dim - start));
+        }
+        Ok((origin, extent))
+    }
+
+    /// Read one chunk into a new CPU tensor. Chunks at the upper edges of the
+    /// array are clipped to its bounds.
+    pub fn read_chunk(&self, index: &[u64]) -> io::Result<ManagedTensor> {
+        let (_, extent) = self.chunk_region(index)?;
+        let raw = self.read_raw_chunk(index)?;
+        let mut tensor = OwnedTensor::try_new_zeroed(&extent, self.dtype)?;
+        let zeros = vec![0; extent.len()];
+        copy_region(
+            raw.as_bytes(),
+            &self.chunk_shape,
+            tensor.as_bytes_mut(),
+            &extent,
+            &zeros,
+            &extent,
+            self.dtype.size(),
+        );
+        Ok(ManagedTensor::from_dlpack(tensor.into_dlpack()))
+    }
+
+    /// Read the whole array into a new CPU tensor.
+    pub fn read(&self) -> io::Result<ManagedTensor> {
+        let mut tensor = OwnedTensor::try_new_zeroed(&self.shape, self.dtype)?;
+        for index in self.chunk_indices() {
+            let (origin, extent) = self.chunk_region(&index)?;
+            let raw = self.read_raw_chunk(&index)?;
+            copy_region(
+                raw.as_bytes(),
+                &self.chunk_shape,
+                tensor.as_bytes_mut(),
+                &self.shape,
+                &origin,
+                &extent,
+                self.dtype.size(),
+            );
+        }
+        Ok(ManagedTensor::from_dlpack(tensor.into_dlpack()))
+    }
+}
+
+/// Copy the leading `extent` elements of the row-major array `src` to
+/// `origin` in the row-major array `dst`.
+fn copy_region(
+    src: &[u8],
+    src_shape: &[i64],
+    dst: &mut [u8],
+    dst_shape: &[i64],
+    origin: &[i64],
+    extent: &[i64],
+    itemsize: usize,
+) {
+    if extent.contains(&0) {
+        return;
+    }
+    let Some((&row, outer)) = extent.split_last() else {
+        dst[..itemsize].copy_from_slice(&src[..itemsize]);
+        return;
+    };
+    let row_bytes = row as usize * itemsize;
+    let mut index = vec![0i64; outer.len()];
+    loop {
+        let (mut src_offset, mut dst_offset) = (0, 0);
+        for d in 0..extent.len() {
+            let i = index.get(d).copied().unwrap_or(0);
+            src_offset = src_offset * src_shape[d] as usize + i as usize;
+            dst_offset = dst_offset * dst_shape[d] as usize + (origin[d] + i) as usize;
+        }
+        let (src_offset, dst_offset) = (src_offset * itemsize, dst_offset * itemsize);
+        dst[dst_offset..dst_offset + row_bytes]
+            .copy_from_slice(&src[src_offset..src_offset + row_bytes]);
+
+        // Advance the outer index like an odometer.
+        let mut d = outer.len();
+        loop {
+            if d == 0 {
+                return;
+            }
+            d -= 1;
+            index[d] += 1;
+            if index[d] < outer[d] {
+                break;
+            }
+            index[d] = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::traits::TensorView;
+
+    fn temp_store(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("dlpark-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn chunk(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn v2_edge_and_missing_chunks() {
+        let path = temp_store("zarr-v2");
+        fs::write(
+            path.join(".zarray"),
+            r#"{"zarr_format": 2, "shape": [3, 3], "chunks": [2, 2], "dtype": "<i4",
+                "compressor": null, "fill_value": -1, "order": "C", "filters": null}"#,
+        )
+        .unwrap();
+        fs::write(path.join("0.0"), chunk(&[0, 1, 3, 4])).unwrap();
+        fs::write(path.join("0.1"), chunk(&[2, 0, 5, 0])).unwrap();
+        fs::write(path.join("1.0"), chunk(&[6, 7, 0, 0])).unwrap();
+
+        let array = ZarrArray::open(&path).unwrap();
+        assert_eq!(array.grid_shape(), &[2, 2]);
+        assert_eq!(array.chunk_indices().count(), 4);
+        let edge = array.read_chunk(&[0, 1]).unwrap();
+        assert_eq!(edge.shape(), &[2, 1]);
+        assert_eq!(edge.as_slice::<i32>(), &[2, 5]);
+
+        let tensor = array.read().unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(tensor.shape(), &[3, 3]);
+        assert_eq!(tensor.as_slice::<i32>(), &[0, 1, 2, 3, 4, 5, 6, 7, -1]);
+    }
+
+    #[test]
+    fn v3_transposed_chunks() {
+        let path = temp_store("zarr-v3");
+        fs::write(
+            path.join("zarr.json"),
+            r#"{"zarr_format": 3, "node_type": "array", "shape": [2, 2],
+                "data_type": "int32", "fill_value": 0,
+                "chunk_grid": {"name": "regular", "configuration": {"chunk_shape": [2, 2]}},
+                "chunk_key_encoding": {"name": "default"},
+                "codecs": [{"name": "transpose", "configuration": {"order": [1, 0]}},
+                           {"name": "bytes", "configuration": {"endian": "little"}}]}"#,
+        )
+        .unwrap();
+        fs::create_dir_all(path.join("c/0")).unwrap();
+        fs::write(path.join("c/0/0"), chunk(&[0, 2, 1, 3])).unwrap();
+
+        let tensor = ZarrArray::open(&path).unwrap().read().unwrap();
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(tensor.as_slice::<i32>(), &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn oversized_metadata() {
+        let path = temp_store("zarr-oversized");
+        let write_meta = |shape: &str, chunks: &str, compressor: &str| {
+            let meta = format!(
+                r#"{{"zarr_format": 2, "shape": {shape}, "chunks": {chunks}, "dtype": "<i4",
+                    "compressor": {compressor}, "fill_value": 0, "order": "C",
+                    "filters": null}}"#
+            );
+            fs::write(path.join(".zarray"), meta).unwrap();
+        };
+        write_meta("[4294967296, 4294967296]", "[1, 1]", "null");
+        let err = ZarrArray::open(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Missing chunks are allocated from the metadata alone.
+        write_meta("[1]", "[1152921504606846976]", "null");
+        let err = ZarrArray::open(&path).unwrap().read().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::OutOfMemory);
+
+        // A chunk decompressing to more than its shape.
+        #[cfg(feature = "zstd")]
+        {
+            write_meta("[2]", "[2]", r#"{"id": "zstd"}"#);
+            let bomb = zstd::encode_all(&[0u8; 1 << 20][..], 0).unwrap();
+            fs::write(path.join("0"), bomb).unwrap();
+            let err = ZarrArray::open(&path).unwrap().read().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use zerocopy::{FromBytes, Immutable, IntoBytes};
+
+use crate::{
+    ffi::{DataType, Device, DeviceType},
+    tensor::traits::{InferDtype, TensorView, ToTensor},
+    ManagedTensor, ShapeAndStrides,
+};
+
+impl ManagedTensor {
+    /// Access inner data as 1d array without trusting the producer.
+    ///
+    /// Unlike [`ManagedTensor::as_slice`], this returns `None` instead of
+    /// building a slice when the tensor is not a contiguous CPU tensor, the
+    /// size of `A` doesn't match the dtype, or the data is misaligned for `A`.
+    pub fn as_slice_checked<A>(&self) -> Option<&[A]>
+    where
+        A: FromBytes + Immutable,
+    {
+        if self.device().device_type != DeviceType::Cpu
+            || !self.is_contiguous()
+            || std::mem::size_of::<A>() != self.dtype().size()
+        {
+            return None;
+        }
+        let len = self.data_size();
+        if len == 0 {
+            return Some(&[]);
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(self.first_byte(), len) };
+        <[A]>::ref_from_bytes(bytes).ok()
+    }
+}
+
+/// Typed CPU tensor decoded from a raw byte payload, e.g. one received over
+/// the network.
+///
+/// The payload is copied into a buffer properly aligned for `T`, so it is
+/// always sound to access the data as `&[T]`.
+pub struct BytesTensor<T> {
+    data: Vec<T>,
+    shape: Vec<i64>,
+}
+
+impl<T> BytesTensor<T>
+where
+    T: FromBytes + IntoBytes + Immutable,
+{
+    /// Returns `None` if the length of `bytes` doesn't match `shape`.
+    pub fn from_bytes(bytes: &[u8], shape: &[i64]) -> Option<Self> {
+        let num_elements = shape.iter().try_fold(1usize, |acc, &dim| {
+            acc.checked_mul(usize::try_from(dim).ok()?)
+        })?;
+        if num_elements.checked_mul(std::mem::size_of::<T>())? != bytes.len() {
+            return None;
+        }
+        let mut data = T::new_vec_zeroed(num_elements).ok()?;
+        data.as_mut_bytes().copy_from_slice(bytes);
+        Some(Self {
+            data,
+            shape: shape.to_vec(),
+        })
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+}
+
+impl<T> ToTensor for BytesTensor<T>
+where
+    T: InferDtype,
+{
+    fn data_ptr(&self) -> *mut std::ffi::c_void {
+        self.data.as_ptr() as *mut T as *mut std::ffi::c_void
+    }
+
+    fn byte_offset(&self) -> u64 {
+        0
+    }
+
+    fn device(&self) -> Device {
+        Device::CPU
+    }
+
+    fn dtype(&self) -> DataType {
+        T::infer_dtype()
+    }
+
+    fn shape_and_strides(&self) -> ShapeAndStrides {
+        ShapeAndStrides::new_contiguous_with_strides(&self.shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn as_slice_checked() {
+        let v: Vec<f32> = (0..10).map(|x| x as f32).collect();
+        let tensor = ManagedTensor::from_dlpack(v.clone().into_dlpack());
+        assert_eq!(tensor.as_slice_checked::<f32>(), Some(&v[..]));
+        assert_eq!(tensor.as_slice_checked::<f64>(), None);
+        assert_eq!(tensor.as_slice_checked::<u8>(), None);
+    }
+
+    #[test]
+    fn bytes_tensor_round_trip() {
+        let v: Vec<i32> = (0..6).collect();
+        let tensor = BytesTensor::<i32>::from_bytes(v.as_bytes(), &[2, 3]).unwrap();
+        assert_eq!(tensor.as_slice(), &v[..]);
+
+        let tensor = ManagedTensor::from_dlpack(tensor.into_dlpack());
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.dtype(), DataType::I32);
+        assert_eq!(tensor.as_slice_checked::<i32>(), Some(&v[..]));
+    }
+
+    #[test]
+    fn bytes_tensor_length_mismatch() {
+        assert!(BytesTensor::<f32>::from_bytes(&[0; 7], &[2]).is_none());
+        assert!(BytesTensor::<f32>::from_bytes(&[0; 8], &[-1, -2]).is_none());
+    }
+}